@@ -0,0 +1,52 @@
+/*
+ * C interface of phonetisaurus-g2p-py, built with the `capi` feature:
+ *
+ *     cargo build --release --no-default-features --features capi
+ *
+ * Models and results are opaque and owned by the caller, who releases them with the
+ * matching _free function. Functions that fail return NULL; phonetisaurus_last_error()
+ * then describes the failure. Strings are UTF-8 and NUL-terminated.
+ */
+
+#ifndef PHONETISAURUS_H
+#define PHONETISAURUS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct PhonetisaurusModel PhonetisaurusModel;
+typedef struct PhonetisaurusResult PhonetisaurusResult;
+
+/* Message of the last error on this thread, or NULL. Valid until the next failing call. */
+const char *phonetisaurus_last_error(void);
+
+/* Load a model from a file. Returns NULL on failure. */
+PhonetisaurusModel *phonetisaurus_model_load(const char *path);
+
+/* Load a model from the len bytes at data. Returns NULL on failure. */
+PhonetisaurusModel *phonetisaurus_model_load_from_memory(const uint8_t *data, size_t len);
+
+/* Release a model. NULL is ignored. */
+void phonetisaurus_model_free(PhonetisaurusModel *model);
+
+/* Phonemize a word. Returns NULL on failure. Models can be shared between threads. */
+PhonetisaurusResult *phonetisaurus_phonemize(const PhonetisaurusModel *model, const char *word);
+
+/* Phonemes of a result, separated by spaces. Valid until the result is freed. */
+const char *phonetisaurus_result_phonemes(const PhonetisaurusResult *result);
+
+/* Negative log likelihood of a result, lower is better. */
+float phonetisaurus_result_score(const PhonetisaurusResult *result);
+
+/* Release a result. NULL is ignored. */
+void phonetisaurus_result_free(PhonetisaurusResult *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PHONETISAURUS_H */
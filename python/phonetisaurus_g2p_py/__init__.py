@@ -4,5 +4,13 @@ phonetisaurus_g2p_py: A Python wrapper for Phonetisaurus FST-based grapheme-to-p
 
 from .phonetisaurus_g2p_py import PyPhonetisaurusModel as PhonetisaurusModel
 from .phonetisaurus_g2p_py import PyPhonetizationResult as PhonetizationResult
+from .phonetisaurus_g2p_py import PhonetisaurusError, ModelLoadError, UnknownSymbolError, DecodeError
 
-__all__ = ["PhonetisaurusModel", "PhonetizationResult"]
+__all__ = [
+    "PhonetisaurusModel",
+    "PhonetizationResult",
+    "PhonetisaurusError",
+    "ModelLoadError",
+    "UnknownSymbolError",
+    "DecodeError",
+]
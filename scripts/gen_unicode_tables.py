@@ -0,0 +1,118 @@
+#!/usr/bin/env python3
+"""
+Generate the Unicode tables of the crate from the Unicode Character Database shipped with Python.
+
+Usage: python scripts/gen_unicode_tables.py
+Writes src/normalize/tables.rs and src/segmentation/tables.rs, relative to the repository root.
+"""
+
+import os
+import unicodedata
+
+ROOT = os.path.join(os.path.dirname(os.path.abspath(__file__)), "..")
+HEADER = "// Generated from the Unicode Character Database, version %s, by scripts/gen_unicode_tables.py.\n// Do not edit by hand." % unicodedata.unidata_version
+
+
+def ch(cp):
+    return "'\\u{%x}'" % cp
+
+
+def ranges(predicate):
+    """Inclusive (first, last) ranges of the code points satisfying predicate."""
+    result = []
+    for cp in range(0x110000):
+        if predicate(cp):
+            if result and result[-1][1] == cp - 1:
+                result[-1] = (result[-1][0], cp)
+            else:
+                result.append((cp, cp))
+    return result
+
+
+def normalization_tables():
+    decomp = []  # (cp, first, second or 0)
+    comps = []  # (first, second, composite)
+    ccc = []
+    for cp in range(0x110000):
+        if 0xAC00 <= cp <= 0xD7A3:
+            continue  # Hangul syllables are algorithmic
+        c = chr(cp)
+        d = unicodedata.decomposition(c)
+        if d and not d.startswith('<'):
+            parts = [int(x, 16) for x in d.split()]
+            assert len(parts) in (1, 2)
+            decomp.append((cp, parts[0], parts[1] if len(parts) == 2 else 0))
+            if len(parts) == 2 and unicodedata.normalize('NFC', c) == c and unicodedata.combining(chr(parts[0])) == 0:
+                comps.append((parts[0], parts[1], cp))
+        k = unicodedata.combining(c)
+        if k:
+            if ccc and ccc[-1][1] == cp - 1 and ccc[-1][2] == k:
+                ccc[-1] = (ccc[-1][0], cp, k)
+            else:
+                ccc.append((cp, cp, k))
+    comps.sort()
+
+    out = [HEADER, ""]
+    out.append("/// Canonical decompositions as `(char, first, second)`, sorted by char. `second` is")
+    out.append("/// `'\\0'` for singleton decompositions. Hangul syllables are decomposed algorithmically.")
+    out.append("pub(super) static CANONICAL_DECOMPOSITIONS: &[(char, char, char)] = &[")
+    for cp, a, b in decomp:
+        out.append("    (%s, %s, %s)," % (ch(cp), ch(a), ch(b)))
+    out.append("];")
+    out.append("")
+    out.append("/// Primary composites as `(first, second, composite)`, sorted by `(first, second)`.")
+    out.append("pub(super) static CANONICAL_COMPOSITIONS: &[(char, char, char)] = &[")
+    for a, b, cp in comps:
+        out.append("    (%s, %s, %s)," % (ch(a), ch(b), ch(cp)))
+    out.append("];")
+    out.append("")
+    out.append("/// Non-zero canonical combining classes as inclusive `(first, last, class)` ranges, sorted.")
+    out.append("pub(super) static COMBINING_CLASSES: &[(char, char, u8)] = &[")
+    for a, b, k in ccc:
+        out.append("    (%s, %s, %d)," % (ch(a), ch(b), k))
+    out.append("];")
+    return out
+
+
+# Format characters that extend or prefix a cluster instead of being controls
+ZWNJ, ZWJ = 0x200C, 0x200D
+PREPENDED_FORMAT = {0x600, 0x601, 0x602, 0x603, 0x604, 0x605, 0x6DD, 0x70F, 0x890, 0x891, 0x8E2, 0x110BD, 0x110CD}
+
+
+def is_extend(cp):
+    category = unicodedata.category(chr(cp))
+    return (category in ("Mn", "Me", "Mc")
+            or cp == ZWNJ
+            or 0xFF9E <= cp <= 0xFF9F  # halfwidth katakana sound marks
+            or 0x1F3FB <= cp <= 0x1F3FF  # emoji skin tone modifiers
+            or 0xE0020 <= cp <= 0xE007F)  # tags
+
+
+def is_control(cp):
+    category = unicodedata.category(chr(cp))
+    if category == "Cf":
+        return cp not in (ZWNJ, ZWJ) and cp not in PREPENDED_FORMAT and not 0xE0020 <= cp <= 0xE007F
+    return category in ("Cc", "Zl", "Zp")
+
+
+def segmentation_tables():
+    out = [HEADER, ""]
+    out.append("/// Marks and modifiers that extend the preceding grapheme cluster, as inclusive ranges.")
+    out.append("pub(super) static EXTEND: &[(char, char)] = &[")
+    for a, b in ranges(is_extend):
+        out.append("    (%s, %s)," % (ch(a), ch(b)))
+    out.append("];")
+    out.append("")
+    out.append("/// Control and format characters that always form a cluster of their own, as inclusive ranges.")
+    out.append("pub(super) static CONTROL: &[(char, char)] = &[")
+    for a, b in ranges(is_control):
+        out.append("    (%s, %s)," % (ch(a), ch(b)))
+    out.append("];")
+    return out
+
+
+for path, lines in (("src/normalize/tables.rs", normalization_tables()),
+                    ("src/segmentation/tables.rs", segmentation_tables())):
+    os.makedirs(os.path.dirname(os.path.join(ROOT, path)), exist_ok=True)
+    with open(os.path.join(ROOT, path), "w") as f:
+        f.write("\n".join(lines) + "\n")
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+
+#[derive(Clone, Debug)]
+/// Spells acronyms like "NASA" or "HTML" letter by letter with a table of letter names, for a
+/// [`G2pPipeline`](crate::G2pPipeline) to keep them away from the model.
+///
+/// A word is taken for an acronym if it has at least [`Self::with_min_len`] chars, no
+/// lowercase letter, at least one uppercase letter, and a name for each of its chars. Names
+/// of digits and symbols can be added to spell tokens like "MP3".
+pub struct AcronymSpeller {
+    /// Phonemes of each letter's name, by uppercase letter.
+    names: HashMap<char, Vec<String>>,
+    /// Shortest acronym, in chars.
+    min_len: usize,
+}
+
+impl Default for AcronymSpeller {
+    fn default() -> Self {
+        AcronymSpeller {
+            names: HashMap::new(),
+            min_len: 2,
+        }
+    }
+}
+
+impl AcronymSpeller {
+    /// Create a speller without letter names.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read letter names in CMUdict format, one letter followed by the phonemes of its name
+    /// per line, e.g. `H EY CH`. Letters are case-insensitive. Empty lines and lines starting
+    /// with `;;;` or `#` are ignored, so names of these symbols are added with [`Self::insert`].
+    pub fn from_reader(reader: impl BufRead) -> io::Result<Self> {
+        let mut speller = AcronymSpeller::new();
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            let Some(letter) = fields.next() else {
+                continue;
+            };
+            if letter.starts_with(";;;") || letter.starts_with('#') {
+                continue;
+            }
+            let mut chars = letter.chars();
+            let phonemes: Vec<String> = fields.map(str::to_string).collect();
+            match (chars.next(), chars.next()) {
+                (Some(letter), None) if !phonemes.is_empty() => speller.insert(letter, phonemes),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Letter name line {} is not a letter with phonemes: {}", line_number + 1, line),
+                    ));
+                }
+            }
+        }
+        Ok(speller)
+    }
+
+    /// Set the phonemes of a letter's name, for both cases of the letter.
+    pub fn insert(&mut self, letter: char, phonemes: Vec<String>) {
+        self.names.insert(uppercase(letter), phonemes);
+    }
+
+    /// Only spell words of at least this many chars, 2 by default, so that words like "A"
+    /// and "I" are left to the model.
+    pub fn with_min_len(mut self, min_len: usize) -> Self {
+        self.min_len = min_len;
+        self
+    }
+
+    /// Whether a word is an acronym this speller can spell.
+    pub fn is_acronym(&self, word: &str) -> bool {
+        word.chars().count() >= self.min_len
+            && word.chars().any(char::is_uppercase)
+            && word.chars().all(|c| !c.is_lowercase() && self.names.contains_key(&c))
+    }
+
+    /// The phonemes of the letter names of an acronym, or `None` if the word is not one.
+    pub fn spell(&self, word: &str) -> Option<Vec<String>> {
+        if !self.is_acronym(word) {
+            return None;
+        }
+        Some(word.chars().flat_map(|c| self.names[&c].iter().cloned()).collect())
+    }
+}
+
+/// The uppercase form of a letter, if it is a single char.
+fn uppercase(letter: char) -> char {
+    let mut upper = letter.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(upper), None) => upper,
+        _ => letter,
+    }
+}
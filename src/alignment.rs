@@ -0,0 +1,93 @@
+use crate::error::{PhonetisaurusError, Result};
+use crate::options::PhonemeSeparators;
+use rustfst::prelude::*;
+use std::ops::Range;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Graphemes of the input word together with the phonemes produced for them.
+pub struct AlignedSegment {
+    /// Positions of the graphemes in the input word, counted in chars, after case folding and
+    /// normalization.
+    pub graphemes: Range<usize>,
+    /// Phoneme tokens produced for the graphemes, empty if they are silent.
+    pub phonemes: Vec<String>,
+}
+
+/// Collect the `(ilabel, olabel)` pairs and the total weight of a single-path FST.
+pub(crate) fn linear_path(fst: &VectorFst<TropicalWeight>) -> Result<(Vec<(Label, Label)>, TropicalWeight)> {
+    let mut state = fst.start().ok_or(PhonetisaurusError::NoPathFound)?;
+    let mut arcs = Vec::new();
+    let mut weight = TropicalWeight::one();
+
+    loop {
+        let trs = fst.get_trs(state).map_err(PhonetisaurusError::FstError)?;
+        let Some(tr) = trs.trs().first() else {
+            break;
+        };
+        arcs.push((tr.ilabel, tr.olabel));
+        weight.times_assign(tr.weight).map_err(PhonetisaurusError::FstError)?;
+        state = tr.nextstate;
+    }
+    let final_weight = fst
+        .final_weight(state)
+        .map_err(PhonetisaurusError::FstError)?
+        .ok_or(PhonetisaurusError::NoPathFound)?;
+    weight.times_assign(final_weight).map_err(PhonetisaurusError::FstError)?;
+
+    Ok((arcs, weight))
+}
+
+/// Align the arcs of a path through the composed FST to the graphemes they consumed.
+///
+/// Every arc with a non-epsilon input label consumes the next entry of `spans`. Phonemes
+/// inserted by arcs with an epsilon input label are attached to the preceding segment, or to
+/// the first one if nothing precedes them.
+pub(crate) fn align(
+    arcs: &[(Label, Label)],
+    spans: &[Range<usize>],
+    osyms: &SymbolTable,
+    separators: &PhonemeSeparators,
+) -> Result<Vec<AlignedSegment>> {
+    let mut segments: Vec<AlignedSegment> = Vec::with_capacity(spans.len());
+    let mut leading_phonemes: Vec<String> = Vec::new();
+    let mut spans = spans.iter();
+
+    for &(ilabel, olabel) in arcs {
+        let phoneme = decode_label(olabel, osyms, separators)?;
+        if ilabel != EPS_LABEL {
+            let graphemes = spans.next().cloned().ok_or(PhonetisaurusError::NoPathFound)?;
+            let mut phonemes = std::mem::take(&mut leading_phonemes);
+            phonemes.extend(phoneme);
+            segments.push(AlignedSegment { graphemes, phonemes });
+        } else if let Some(phoneme) = phoneme {
+            match segments.last_mut() {
+                Some(segment) => segment.phonemes.push(phoneme),
+                None => leading_phonemes.push(phoneme),
+            }
+        }
+    }
+    if !leading_phonemes.is_empty() {
+        // only insertions, no graphemes were consumed
+        segments.push(AlignedSegment {
+            graphemes: 0..0,
+            phonemes: leading_phonemes,
+        });
+    }
+
+    Ok(segments)
+}
+
+/// Turn an output label into a phoneme token, `None` for epsilon and skipped symbols.
+///
+/// Skip symbols ("_") need to be skipped, ties ("|") in symbols need to be removed.
+fn decode_label(label: Label, osyms: &SymbolTable, separators: &PhonemeSeparators) -> Result<Option<String>> {
+    if label == EPS_LABEL {
+        return Ok(None);
+    }
+    match osyms.get_symbol(label) {
+        Some(symbol) if separators.is_skip(symbol) => Ok(None),
+        Some(symbol) => Ok(Some(separators.token(symbol))),
+        None => Err(PhonetisaurusError::UnknownLabel(label)),
+    }
+}
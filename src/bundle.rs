@@ -0,0 +1,227 @@
+//! Model bundles: a tar archive holding the model as `model.fst` and a `manifest.json` that
+//! says how the model is meant to be used.
+//!
+//! A bundle is loaded like a plain model file, and the manifest is then available from
+//! [`PhonetisaurusModel::manifest`](crate::PhonetisaurusModel::manifest). Bundles can be made
+//! with [`write_bundle`], the `pack` command, or `tar` itself.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::error::{PhonetisaurusError, Result};
+use crate::flat_json::{parse_string_object, write_string_object};
+use crate::normalize::{CaseFold, Normalization};
+use crate::options::PhonemizeOptions;
+
+/// Name of the model in a bundle.
+pub(crate) const MODEL_ENTRY: &str = "model.fst";
+/// Name of the manifest in a bundle.
+pub(crate) const MANIFEST_ENTRY: &str = "manifest.json";
+
+/// Size of the blocks of a tar archive.
+const BLOCK: usize = 512;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+/// Conventions of a model, stored next to it in a bundle.
+///
+/// In `manifest.json`, all members are strings, e.g.
+/// `{"language": "en-US", "phoneme_set": "arpabet", "case_fold": "lower", "version": "1.2"}`.
+/// `case_fold` and `normalize` take the values of the server options.
+pub struct ModelManifest {
+    /// Language tag of the words the model was trained on.
+    pub language: Option<String>,
+    /// Name of the phoneme set of the pronunciations, like `arpabet`, `nofabet` or `ipa`.
+    pub phoneme_set: Option<String>,
+    /// Case of the words the model was trained on.
+    pub case_fold: Option<CaseFold>,
+    /// Unicode normalization form of the words the model was trained on.
+    pub normalize: Option<Normalization>,
+    /// How the model was trained, e.g. the lexicon and the alignment settings.
+    pub training: Option<String>,
+    /// Version of the model.
+    pub version: Option<String>,
+    /// Other members of the manifest, kept as they are.
+    pub extra: BTreeMap<String, String>,
+}
+
+impl ModelManifest {
+    /// Read a manifest from its JSON.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let invalid = |message: String| {
+            PhonetisaurusError::ModelLoadError(anyhow::anyhow!("Invalid bundle manifest: {}", message))
+        };
+        let mut manifest = ModelManifest::default();
+        for (key, value) in parse_string_object(json).map_err(invalid)? {
+            match key.as_str() {
+                "language" => manifest.language = Some(value),
+                "phoneme_set" => manifest.phoneme_set = Some(value),
+                "case_fold" => {
+                    let case_fold = named(CASE_FOLDS, &value);
+                    manifest.case_fold = Some(case_fold.ok_or_else(|| invalid(format!("unknown case_fold \"{}\"", value)))?);
+                }
+                "normalize" => {
+                    let normalize = named(NORMALIZATIONS, &value);
+                    manifest.normalize = Some(normalize.ok_or_else(|| invalid(format!("unknown normalize \"{}\"", value)))?);
+                }
+                "training" => manifest.training = Some(value),
+                "version" => manifest.version = Some(value),
+                _ => {
+                    manifest.extra.insert(key, value);
+                }
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// The JSON of the manifest, with the members that are set.
+    pub fn to_json(&self) -> String {
+        write_string_object(self.members())
+    }
+
+    /// The members that are set, as they are named and written in `manifest.json`.
+    pub fn members(&self) -> impl Iterator<Item = (&str, &str)> {
+        let members = [
+            ("language", self.language.as_deref()),
+            ("phoneme_set", self.phoneme_set.as_deref()),
+            ("case_fold", self.case_fold.and_then(|case_fold| name_of(CASE_FOLDS, case_fold))),
+            ("normalize", self.normalize.and_then(|normalize| name_of(NORMALIZATIONS, normalize))),
+            ("training", self.training.as_deref()),
+            ("version", self.version.as_deref()),
+        ];
+        members
+            .into_iter()
+            .filter_map(|(key, value)| Some((key, value?)))
+            .chain(self.extra.iter().map(|(key, value)| (key.as_str(), value.as_str())))
+    }
+
+    /// Options with the case folding and normalization of the manifest, to phonemize words
+    /// the way the model expects them.
+    pub fn options(&self) -> PhonemizeOptions {
+        let defaults = PhonemizeOptions::default();
+        PhonemizeOptions {
+            case_fold: self.case_fold.unwrap_or(defaults.case_fold),
+            normalize: self.normalize.unwrap_or(defaults.normalize),
+            ..defaults
+        }
+    }
+}
+
+/// Names of the case mappings in manifests.
+const CASE_FOLDS: &[(&str, CaseFold)] = &[
+    ("none", CaseFold::None),
+    ("lower", CaseFold::Lower),
+    ("upper", CaseFold::Upper),
+    ("turkic_lower", CaseFold::TurkicLower),
+    ("turkic_upper", CaseFold::TurkicUpper),
+];
+
+/// Names of the normalization forms in manifests.
+const NORMALIZATIONS: &[(&str, Normalization)] = &[
+    ("none", Normalization::None),
+    ("nfc", Normalization::Nfc),
+    ("nfd", Normalization::Nfd),
+];
+
+/// The value with a name in a table of names.
+fn named<T: Copy>(names: &[(&str, T)], name: &str) -> Option<T> {
+    names.iter().find(|(n, _)| *n == name).map(|&(_, value)| value)
+}
+
+/// The name of a value in a table of names.
+fn name_of<T: PartialEq>(names: &[(&'static str, T)], value: T) -> Option<&'static str> {
+    names.iter().find(|(_, v)| *v == value).map(|&(name, _)| name)
+}
+
+/// Write a bundle of a binary model and its manifest as a tar archive.
+pub fn write_bundle(mut writer: impl Write, model_binary: &[u8], manifest: &ModelManifest) -> io::Result<()> {
+    write_entry(&mut writer, MANIFEST_ENTRY, manifest.to_json().as_bytes())?;
+    write_entry(&mut writer, MODEL_ENTRY, model_binary)?;
+    // the end of the archive is marked by two empty blocks
+    writer.write_all(&[0; 2 * BLOCK])?;
+    writer.flush()
+}
+
+/// Whether data is a tar archive, recognized by the ustar magic of its first header.
+pub(crate) fn is_bundle(data: &[u8]) -> bool {
+    data.get(257..262) == Some(b"ustar")
+}
+
+/// The model and the manifest of a bundle.
+pub(crate) fn read_bundle(data: &[u8]) -> Result<(&[u8], Option<ModelManifest>)> {
+    let invalid = |message: &str| PhonetisaurusError::ModelLoadError(anyhow::anyhow!("Invalid model bundle: {}", message));
+    let mut model = None;
+    let mut manifest = None;
+    let mut offset = 0;
+    while let Some(header) = data.get(offset..offset + BLOCK) {
+        if header.iter().all(|&byte| byte == 0) {
+            break;
+        }
+        let size = octal(&header[124..136]).ok_or_else(|| invalid("malformed entry size"))?;
+        let content = data
+            .get(offset + BLOCK..offset + BLOCK + size)
+            .ok_or_else(|| invalid("truncated entry"))?;
+        // regular files only, "0" or NUL in old archives
+        if matches!(header[156], b'0' | 0) {
+            // only POSIX archives have a prefix, GNU ones keep other data there
+            let prefix = match &header[257..263] {
+                b"ustar\0" => field(&header[345..500]),
+                _ => String::new(),
+            };
+            let name = [prefix, field(&header[0..100])]
+                .into_iter()
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("/");
+            match name.trim_start_matches("./") {
+                MODEL_ENTRY => model = Some(content),
+                MANIFEST_ENTRY => {
+                    let json = std::str::from_utf8(content).map_err(|_| invalid("manifest is not UTF-8"))?;
+                    manifest = Some(ModelManifest::from_json(json)?);
+                }
+                _ => {}
+            }
+        }
+        offset += BLOCK + size.div_ceil(BLOCK) * BLOCK;
+    }
+    let model = model.ok_or_else(|| invalid(&format!("no {} in the archive", MODEL_ENTRY)))?;
+    Ok((model, manifest))
+}
+
+fn write_entry(writer: &mut impl Write, name: &str, content: &[u8]) -> io::Result<()> {
+    let mut header = [0u8; BLOCK];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[108..116].copy_from_slice(b"0000000\0");
+    header[116..124].copy_from_slice(b"0000000\0");
+    header[124..136].copy_from_slice(format!("{:011o}\0", content.len()).as_bytes());
+    header[136..148].copy_from_slice(b"00000000000\0");
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    // the checksum is computed with its own field filled with spaces
+    header[148..156].copy_from_slice(b"        ");
+    let checksum: u32 = header.iter().map(|&byte| u32::from(byte)).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+
+    writer.write_all(&header)?;
+    writer.write_all(content)?;
+    writer.write_all(&vec![0; content.len().div_ceil(BLOCK) * BLOCK - content.len()])
+}
+
+/// A NUL-terminated text field of a tar header.
+fn field(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// An octal number field of a tar header.
+fn octal(bytes: &[u8]) -> Option<usize> {
+    let digits = field(bytes);
+    let digits = digits.trim_matches(|c: char| c == ' ' || c == '\0');
+    if digits.is_empty() {
+        return Some(0);
+    }
+    usize::from_str_radix(digits, 8).ok()
+}
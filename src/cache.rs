@@ -0,0 +1,151 @@
+use crate::error::Result;
+use crate::lru::LruCache;
+use crate::parallel::{default_threads, par_map};
+use crate::phonetisaurus::{PhonetisaurusModel, PhonetizationResult};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Phonemizer that memoizes the results of the most recently phonemized words.
+///
+/// Natural text repeats a small set of words very often, so even a few thousand entries
+/// avoid most decoding work. Failed phonemizations are not cached. The cache is shared
+/// between threads behind a lock, which is only held for lookups and insertions.
+pub struct CachedPhonetisaurusModel {
+    model: PhonetisaurusModel,
+    cache: Mutex<LruCache<String, PhonetizationResult>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// Usage of a [`CachedPhonetisaurusModel`] at one point in time.
+pub struct CacheStats {
+    /// Lookups answered from the cache.
+    pub hits: u64,
+    /// Lookups that had to decode the word.
+    pub misses: u64,
+    /// Words currently cached.
+    pub entries: usize,
+    /// Maximum number of cached words.
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    /// Fraction of the lookups that were hits, 0 if there was none.
+    pub fn hit_ratio(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 { 0.0 } else { self.hits as f64 / lookups as f64 }
+    }
+}
+
+impl CachedPhonetisaurusModel {
+    /// Wrap a model with a cache holding up to `capacity` words.
+    pub fn new(model: PhonetisaurusModel, capacity: usize) -> Self {
+        CachedPhonetisaurusModel {
+            model,
+            cache: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Phonemize a word, answering from the cache if it was phonemized recently.
+    pub fn phonemize_word(&self, word: &str) -> Result<PhonetizationResult> {
+        if let Some(result) = self.lock().get(word) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(result.clone());
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        // decode without holding the lock, so other threads are not blocked meanwhile
+        let result = self.model.phonemize_word(word)?;
+        self.lock().put(word.to_string(), result.clone());
+
+        Ok(result)
+    }
+
+    /// The wrapped model, for calls that bypass the cache.
+    pub fn model(&self) -> &PhonetisaurusModel {
+        &self.model
+    }
+
+    /// Number of words currently cached.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no word is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Maximum number of words kept in the cache.
+    pub fn capacity(&self) -> usize {
+        self.lock().capacity()
+    }
+
+    /// Number of lookups answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of lookups that had to decode the word.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// The hit and miss counters and the size of the cache.
+    pub fn stats(&self) -> CacheStats {
+        let cache = self.lock();
+        CacheStats {
+            hits: self.hits(),
+            misses: self.misses(),
+            entries: cache.len(),
+            capacity: cache.capacity(),
+        }
+    }
+
+    /// Set the hit and miss counters back to 0, e.g. after [`Self::warm_up`] or to measure
+    /// a period of time.
+    pub fn reset_stats(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+
+    /// Page in the model and decode `words` in parallel into the cache, so that the first
+    /// lookups of common words are as fast as later ones. Returns the number of words cached.
+    ///
+    /// Words already cached are not decoded again, words that fail are skipped, and the hit
+    /// and miss counters are not changed. Only the last `capacity` words stay cached.
+    pub fn warm_up(&self, words: &[&str]) -> usize {
+        self.model.warm_up();
+        let missing: Vec<&str> = {
+            let cache = self.lock();
+            words.iter().copied().filter(|word| !cache.contains(*word)).collect()
+        };
+        let results = par_map(&missing, default_threads(), |word| self.model.phonemize_word(word));
+        let mut cache = self.lock();
+        for (word, result) in missing.iter().zip(results) {
+            if let Ok(result) = result {
+                cache.put(word.to_string(), result);
+            }
+        }
+        words.iter().filter(|word| cache.contains(**word)).count()
+    }
+
+    /// Forget all cached words.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<String, PhonetizationResult>> {
+        // the cache operations never panic while holding the lock, so a poisoned cache is intact
+        self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl PhonetisaurusModel {
+    /// Wrap the model with an LRU cache of up to `capacity` phonemized words.
+    pub fn with_cache(self, capacity: usize) -> CachedPhonetisaurusModel {
+        CachedPhonetisaurusModel::new(self, capacity)
+    }
+}
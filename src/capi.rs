@@ -0,0 +1,182 @@
+//! C interface, declared in `include/phonetisaurus.h`.
+//!
+//! Models and results are opaque pointers owned by the caller, who releases them with the
+//! matching `_free` function. Functions that fail return null and leave a message that
+//! [`phonetisaurus_last_error`] returns on the same thread.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::path::Path;
+
+use crate::{PhonetisaurusModel, PhonetizationResult};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl ToString) {
+    // interior NULs cannot be represented, the message is cut there
+    let message = message.to_string();
+    let message = message.split('\0').next().unwrap_or_default();
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// Result of [`phonetisaurus_phonemize`].
+pub struct PhonetisaurusResult {
+    phonemes: CString,
+    neg_log_score: f32,
+}
+
+impl From<PhonetizationResult> for PhonetisaurusResult {
+    fn from(result: PhonetizationResult) -> Self {
+        PhonetisaurusResult {
+            // phoneme symbols come from a text symbol table and contain no NUL
+            phonemes: CString::new(result.phonemes).unwrap_or_default(),
+            neg_log_score: result.neg_log_score,
+        }
+    }
+}
+
+/// Message of the last error on this thread, or null if there was none.
+///
+/// The string stays valid until the next failing call on this thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn phonetisaurus_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Load a model from a file. Returns null on failure.
+///
+/// # Safety
+///
+/// `path` must be a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn phonetisaurus_model_load(path: *const c_char) -> *mut PhonetisaurusModel {
+    if path.is_null() {
+        set_last_error("path is null");
+        return std::ptr::null_mut();
+    }
+    let path = match unsafe { CStr::from_ptr(path) }.to_str() {
+        Ok(path) => path,
+        Err(e) => {
+            set_last_error(format!("path is not UTF-8: {}", e));
+            return std::ptr::null_mut();
+        }
+    };
+    match PhonetisaurusModel::try_from(Path::new(path)) {
+        Ok(model) => Box::into_raw(Box::new(model)),
+        Err(e) => {
+            set_last_error(e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Load a model from the `len` bytes at `data`. Returns null on failure.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn phonetisaurus_model_load_from_memory(
+    data: *const u8,
+    len: usize,
+) -> *mut PhonetisaurusModel {
+    if data.is_null() {
+        set_last_error("data is null");
+        return std::ptr::null_mut();
+    }
+    let data = unsafe { std::slice::from_raw_parts(data, len) };
+    match PhonetisaurusModel::try_from(data) {
+        Ok(model) => Box::into_raw(Box::new(model)),
+        Err(e) => {
+            set_last_error(e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Release a model. Null is ignored.
+///
+/// # Safety
+///
+/// `model` must come from one of the load functions and must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn phonetisaurus_model_free(model: *mut PhonetisaurusModel) {
+    if !model.is_null() {
+        drop(unsafe { Box::from_raw(model) });
+    }
+}
+
+/// Phonemize a UTF-8 word. Returns null on failure.
+///
+/// Models can be used from several threads at once.
+///
+/// # Safety
+///
+/// `model` must be a live model and `word` a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn phonetisaurus_phonemize(
+    model: *const PhonetisaurusModel,
+    word: *const c_char,
+) -> *mut PhonetisaurusResult {
+    if model.is_null() || word.is_null() {
+        set_last_error("model or word is null");
+        return std::ptr::null_mut();
+    }
+    let model = unsafe { &*model };
+    let word = match unsafe { CStr::from_ptr(word) }.to_str() {
+        Ok(word) => word,
+        Err(e) => {
+            set_last_error(format!("word is not UTF-8: {}", e));
+            return std::ptr::null_mut();
+        }
+    };
+    match model.phonemize_word(word) {
+        Ok(result) => Box::into_raw(Box::new(result.into())),
+        Err(e) => {
+            set_last_error(e);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Phonemes of a result, separated by spaces.
+///
+/// The string is owned by the result and valid until it is freed.
+///
+/// # Safety
+///
+/// `result` must be a live result.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn phonetisaurus_result_phonemes(result: *const PhonetisaurusResult) -> *const c_char {
+    unsafe { &*result }.phonemes.as_ptr()
+}
+
+/// Negative log likelihood of a result, lower is better.
+///
+/// # Safety
+///
+/// `result` must be a live result.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn phonetisaurus_result_score(result: *const PhonetisaurusResult) -> f32 {
+    unsafe { &*result }.neg_log_score
+}
+
+/// Release a result. Null is ignored.
+///
+/// # Safety
+///
+/// `result` must come from [`phonetisaurus_phonemize`] and must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn phonetisaurus_result_free(result: *mut PhonetisaurusResult) {
+    if !result.is_null() {
+        drop(unsafe { Box::from_raw(result) });
+    }
+}
@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use super::http::{HttpError, Request};
+
+/// Who may use the server: the API keys it accepts and the browser origins allowed to call it.
+pub struct AccessPolicy {
+    /// Accepted API keys, none if the server is open.
+    api_keys: Vec<String>,
+    /// Origins sent `Access-Control-Allow-Origin`, `*` for any.
+    cors_origins: Vec<String>,
+}
+
+impl AccessPolicy {
+    pub fn new(api_keys: Vec<String>, cors_origins: Vec<String>) -> Self {
+        AccessPolicy { api_keys, cors_origins }
+    }
+
+    /// Read API keys from a file, one per line. Blank lines and lines starting with `#` are
+    /// skipped.
+    pub fn read_api_keys(path: &Path) -> std::io::Result<Vec<String>> {
+        let keys = std::fs::read_to_string(path)?;
+        Ok(keys
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect())
+    }
+
+    /// Check the API key of a request, sent as `Authorization: Bearer KEY` or `X-API-Key: KEY`.
+    pub fn authorize(&self, request: &Request) -> Result<(), HttpError> {
+        if self.api_keys.is_empty() {
+            return Ok(());
+        }
+        let key = match (request.header("authorization"), request.header("x-api-key")) {
+            (Some(authorization), _) => authorization
+                .split_once(' ')
+                .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+                .map(|(_, key)| key.trim()),
+            (None, key) => key,
+        };
+        let Some(key) = key else {
+            return Err(HttpError::new(401, "Missing API key"));
+        };
+        // every key is compared in full, so the time taken does not reveal matching prefixes
+        let mut matched = false;
+        for accepted in &self.api_keys {
+            matched |= constant_time_eq(accepted.as_bytes(), key.as_bytes());
+        }
+        if matched {
+            Ok(())
+        } else {
+            Err(HttpError::new(401, "Invalid API key"))
+        }
+    }
+
+    /// The CORS headers of a response to a request, if it comes from an allowed origin.
+    pub fn cors_headers(&self, request: &Request) -> Vec<(&'static str, String)> {
+        let mut headers = Vec::new();
+        if self.cors_origins.is_empty() {
+            return headers;
+        }
+        // the allowed origin depends on the request unless any origin is allowed
+        let any = self.cors_origins.iter().any(|origin| origin == "*");
+        if !any {
+            headers.push(("Vary", "Origin".to_string()));
+        }
+        match request.header("origin") {
+            Some(_) if any => headers.push(("Access-Control-Allow-Origin", "*".to_string())),
+            Some(origin) if self.cors_origins.iter().any(|allowed| allowed.eq_ignore_ascii_case(origin)) => {
+                headers.push(("Access-Control-Allow-Origin", origin.to_string()));
+            }
+            _ => {}
+        }
+        headers
+    }
+
+    /// The headers of the answer to a CORS preflight request, on top of [`Self::cors_headers`].
+    pub fn preflight_headers(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Access-Control-Allow-Methods", "GET, POST, OPTIONS".to_string()),
+            ("Access-Control-Allow-Headers", "Authorization, Content-Type, X-API-Key".to_string()),
+            ("Access-Control-Max-Age", "86400".to_string()),
+        ]
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |difference, (x, y)| difference | (x ^ y)) == 0
+}
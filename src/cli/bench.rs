@@ -0,0 +1,202 @@
+use clap::Args;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::json::Value;
+use super::load_model;
+use super::options::parse_options;
+use phonetisaurus_g2p_py::*;
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Path to the Phonetisaurus FST model file [default: $PHONETISAURUS_MODEL, or model_path
+    /// in the config file]
+    model_path: Option<String>,
+
+    /// File with the words to phonemize, one per line [default: stdin]
+    #[arg(long)]
+    wordlist: Option<PathBuf>,
+
+    /// Number of passes over the word list per configuration
+    #[arg(long, default_value_t = 1)]
+    iterations: usize,
+
+    /// Number of distinct pronunciations to produce per word
+    #[arg(long, default_value_t = 1)]
+    nbest: usize,
+
+    /// Decoding options to benchmark, as a JSON object like the "options" of the server API,
+    /// e.g. '{"beam": 5}'. Repeat to compare several configurations [default: {}]
+    #[arg(long = "config", value_name = "JSON")]
+    configs: Vec<String>,
+
+    /// Convert the model with PhonetisaurusModel::optimize before benchmarking
+    #[arg(long)]
+    optimize: bool,
+
+    /// Print the results as a JSON object
+    #[arg(long)]
+    json: bool,
+}
+
+/// Timings of one configuration.
+struct Measurement {
+    config: String,
+    words: usize,
+    failures: usize,
+    elapsed: Duration,
+    /// Time per word, sorted.
+    latencies: Vec<Duration>,
+}
+
+impl Measurement {
+    fn words_per_second(&self) -> f64 {
+        self.words as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+
+    /// Latency below which the fraction `q` of the words were phonemized, in milliseconds.
+    fn quantile_ms(&self, q: f64) -> f64 {
+        let Some(last) = self.latencies.len().checked_sub(1) else {
+            return 0.0;
+        };
+        let index = ((last as f64) * q).round() as usize;
+        self.latencies[index].as_secs_f64() * 1000.0
+    }
+}
+
+/// Phonemize a word list repeatedly, reporting throughput, latency percentiles and peak
+/// memory for each decoding configuration.
+///
+/// Words are phonemized one after another on a single thread, so that the latencies are
+/// not distorted by contention.
+pub fn run(args: BenchArgs) {
+    let configs = if args.configs.is_empty() { vec!["{}".to_string()] } else { args.configs.clone() };
+    let options: Vec<PhonemizeOptions> = configs
+        .iter()
+        .map(|config| {
+            Value::parse(config).and_then(|value| parse_options(&value)).unwrap_or_else(|e| {
+                eprintln!("Invalid --config '{}': {}", config, e);
+                std::process::exit(2);
+            })
+        })
+        .collect();
+    let words = read_words(args.wordlist.as_deref()).unwrap_or_else(|e| {
+        eprintln!("Failed to read word list: {}", e);
+        std::process::exit(1);
+    });
+
+    let load_start = Instant::now();
+    let mut model = load_model(args.model_path.as_deref());
+    if args.optimize {
+        model = model.optimize();
+    }
+    let load_time = load_start.elapsed();
+
+    let measurements: Vec<Measurement> = configs
+        .into_iter()
+        .zip(&options)
+        .map(|(config, options)| measure(&model, &words, args.iterations, args.nbest, config, options))
+        .collect();
+    let peak_rss = peak_rss_bytes();
+
+    if args.json {
+        let results: Vec<Value> = measurements
+            .iter()
+            .map(|m| {
+                Value::object([
+                    ("config", m.config.as_str().into()),
+                    ("words", m.words.into()),
+                    ("failures", m.failures.into()),
+                    ("seconds", (m.elapsed.as_secs_f64() as f32).into()),
+                    ("words_per_second", (m.words_per_second() as f32).into()),
+                    ("p50_ms", (m.quantile_ms(0.5) as f32).into()),
+                    ("p99_ms", (m.quantile_ms(0.99) as f32).into()),
+                    ("max_ms", (m.quantile_ms(1.0) as f32).into()),
+                ])
+            })
+            .collect();
+        let report = Value::object([
+            ("load_seconds", (load_time.as_secs_f64() as f32).into()),
+            ("peak_rss_bytes", peak_rss.map_or(Value::Null, Value::from)),
+            ("results", results.into()),
+        ]);
+        println!("{}", report);
+        return;
+    }
+
+    println!("Model loaded in {:.3} s", load_time.as_secs_f64());
+    println!("{:>12} {:>10} {:>10} {:>10} {:>9}  config", "words/s", "p50 ms", "p99 ms", "max ms", "failures");
+    for m in &measurements {
+        println!(
+            "{:>12.1} {:>10.3} {:>10.3} {:>10.3} {:>9}  {}",
+            m.words_per_second(),
+            m.quantile_ms(0.5),
+            m.quantile_ms(0.99),
+            m.quantile_ms(1.0),
+            m.failures,
+            m.config
+        );
+    }
+    match peak_rss {
+        Some(bytes) => println!("Peak RSS: {:.1} MiB", bytes as f64 / (1024.0 * 1024.0)),
+        None => println!("Peak RSS: unavailable on this platform"),
+    }
+}
+
+fn measure(
+    model: &PhonetisaurusModel,
+    words: &[String],
+    iterations: usize,
+    nbest: usize,
+    config: String,
+    options: &PhonemizeOptions,
+) -> Measurement {
+    let mut latencies = Vec::with_capacity(words.len() * iterations);
+    let mut failures = 0;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        for word in words {
+            let word_start = Instant::now();
+            let result = model.phonemize_word_nbest_with_options(word, nbest, options);
+            latencies.push(word_start.elapsed());
+            failures += usize::from(result.is_err());
+        }
+    }
+    let elapsed = start.elapsed();
+    latencies.sort_unstable();
+
+    Measurement {
+        config,
+        words: latencies.len(),
+        failures,
+        elapsed,
+        latencies,
+    }
+}
+
+/// The non-empty lines of the word list, or of stdin.
+fn read_words(path: Option<&Path>) -> io::Result<Vec<String>> {
+    let reader: Box<dyn BufRead> = match path {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(io::stdin().lock()),
+    };
+    let mut words = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let word = line.trim();
+        if !word.is_empty() {
+            words.push(word.to_string());
+        }
+    }
+    Ok(words)
+}
+
+/// Peak resident set size of the process, read from `/proc` on Linux.
+fn peak_rss_bytes() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kib: usize = line.trim_start_matches("VmHWM:").trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kib * 1024)
+}
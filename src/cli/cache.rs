@@ -0,0 +1,89 @@
+use clap::{Args, Subcommand};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use super::json::Value;
+use phonetisaurus_g2p_py::DiskCache;
+
+#[derive(Args)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    command: CacheCommand,
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Show how many words a cache file holds, and how old they are
+    Stats {
+        /// Path to the cache file, as given to phonemize --cache-file
+        cache_file: PathBuf,
+
+        /// Count the words cached longer ago than this many seconds as expired
+        #[arg(long, value_name = "SECONDS")]
+        ttl: Option<u64>,
+
+        /// Print the statistics as a JSON object
+        #[arg(long)]
+        json: bool,
+    },
+    /// Remove all words from a cache file
+    Clear {
+        /// Path to the cache file, as given to phonemize --cache-file
+        cache_file: PathBuf,
+    },
+}
+
+/// Show the contents of a phonemization cache file, or empty it.
+pub fn run(args: CacheArgs) {
+    let cache_file = match &args.command {
+        CacheCommand::Stats { cache_file, .. } | CacheCommand::Clear { cache_file } => cache_file.clone(),
+    };
+    let cache = match DiskCache::open(&cache_file) {
+        Ok(cache) => cache,
+        Err(e) => {
+            eprintln!("Failed to open cache '{}': {}", cache_file.display(), e);
+            std::process::exit(1);
+        }
+    };
+
+    match args.command {
+        CacheCommand::Stats { ttl, json, .. } => {
+            let cache = match ttl {
+                Some(ttl) => cache.with_ttl(Duration::from_secs(ttl)),
+                None => cache,
+            };
+            let stats = cache.stats();
+            // ages in seconds, which need no calendar to print
+            let age = |time: Option<SystemTime>| {
+                time.map(|time| SystemTime::now().duration_since(time).unwrap_or_default().as_secs() as usize)
+            };
+            if json {
+                let age_json = |time| age(time).map_or(Value::Null, Value::from);
+                let stats = Value::object([
+                    ("entries", stats.entries.into()),
+                    ("expired", stats.expired.into()),
+                    ("file_size", (stats.file_size as usize).into()),
+                    ("oldest_age_seconds", age_json(stats.oldest)),
+                    ("newest_age_seconds", age_json(stats.newest)),
+                ]);
+                println!("{}", stats);
+                return;
+            }
+            println!("Entries: {}", stats.entries);
+            if ttl.is_some() {
+                println!("Expired: {}", stats.expired);
+            }
+            println!("File size: {} bytes", stats.file_size);
+            if let (Some(oldest), Some(newest)) = (age(stats.oldest), age(stats.newest)) {
+                println!("Oldest entry: {} s ago", oldest);
+                println!("Newest entry: {} s ago", newest);
+            }
+        }
+        CacheCommand::Clear { .. } => {
+            if let Err(e) = cache.clear() {
+                eprintln!("Failed to clear cache '{}': {}", cache_file.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
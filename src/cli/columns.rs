@@ -0,0 +1,57 @@
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+/// How the words of a list are read.
+pub enum InputFormat {
+    /// One word per line
+    Lines,
+    /// Rows of tab-separated fields
+    Tsv,
+    /// Rows of comma-separated fields, quoted with `"` where needed
+    Csv,
+}
+
+impl InputFormat {
+    /// Field `column` of a row, counted from 0 and unquoted, `None` if the row has fewer fields.
+    pub fn field(self, row: &str, column: usize) -> Option<String> {
+        match self {
+            InputFormat::Lines => (column == 0).then(|| row.to_string()),
+            InputFormat::Tsv => row.split('\t').nth(column).map(str::to_string),
+            InputFormat::Csv => csv_fields(row).into_iter().nth(column),
+        }
+    }
+
+    /// The row with `value` added as its last field, quoted if needed.
+    pub fn append_field(self, row: &str, value: &str) -> String {
+        match self {
+            InputFormat::Lines | InputFormat::Tsv => format!("{}\t{}", row, value),
+            InputFormat::Csv if value.contains([',', '"', '\n', '\r']) => {
+                format!("{},\"{}\"", row, value.replace('"', "\"\""))
+            }
+            InputFormat::Csv => format!("{},{}", row, value),
+        }
+    }
+}
+
+/// Fields of a CSV row as in RFC 4180: quoted fields may contain commas and `""` for a quote.
+/// Line breaks inside quotes are not supported, as rows are read line by line.
+fn csv_fields(row: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
@@ -0,0 +1,296 @@
+use clap::{Arg, Command};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// File read from the current directory if no `--config` is given.
+pub const DEFAULT_CONFIG: &str = "g2p.toml";
+
+/// Defaults of the command-line arguments, read from a TOML file like
+///
+/// ```toml
+/// model_path = "models/en.fst"
+///
+/// [phonemize]
+/// phoneme_set = "ipa"
+/// nbest = 3
+///
+/// [serve]
+/// port = 9000
+/// api_key_file = "/run/secrets/g2p_keys"
+/// ```
+///
+/// Keys are the long names of the options, with `_` or `-`, and `model_path`. Keys before the
+/// first table apply to every command with that argument, keys in a table like `[serve]` to
+/// that command only. Arguments given on the command line take precedence, and
+/// `PHONETISAURUS_MODEL` over `model_path`.
+///
+/// Only the part of TOML that arguments need is read: strings, numbers, booleans and arrays
+/// of them on one line.
+pub struct Config {
+    settings: Vec<Setting>,
+}
+
+struct Setting {
+    /// The command of the table the key is in, `None` before the first table.
+    command: Option<String>,
+    key: String,
+    values: Vec<String>,
+    line_number: usize,
+}
+
+/// Take `--config PATH` from the start of the arguments, before any subcommand, or find
+/// [`DEFAULT_CONFIG`] in the current directory.
+pub fn take_config_path(args: &mut Vec<OsString>) -> Option<PathBuf> {
+    match args.get(1).and_then(|arg| arg.to_str()) {
+        Some("--config") if args.len() > 2 => {
+            let path = args.remove(2);
+            args.remove(1);
+            Some(PathBuf::from(path))
+        }
+        Some(arg) if arg.starts_with("--config=") => {
+            let path = PathBuf::from(&arg["--config=".len()..]);
+            args.remove(1);
+            Some(path)
+        }
+        _ => Path::new(DEFAULT_CONFIG).is_file().then(|| PathBuf::from(DEFAULT_CONFIG)),
+    }
+}
+
+impl Config {
+    pub fn read(path: &Path) -> Result<Config, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Config::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut settings = Vec::new();
+        let mut command = None;
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let error = |message: &str| format!("Line {}: {}", line_number, message);
+            let mut parser = Parser { text: line, pos: 0 };
+            parser.skip_whitespace();
+            if parser.at_end() {
+                continue;
+            }
+            if parser.eat('[') {
+                let name = parser.key().map_err(|e| error(&e))?;
+                if !parser.eat(']') || !parser.at_end() {
+                    return Err(error("expected ']' after the table name"));
+                }
+                command = Some(name);
+                continue;
+            }
+            let key = parser.key().map_err(|e| error(&e))?;
+            if !parser.eat('=') {
+                return Err(error("expected '=' after the key"));
+            }
+            let values = parser.values().map_err(|e| error(&e))?;
+            if !parser.at_end() {
+                return Err(error("unexpected characters after the value"));
+            }
+            settings.push(Setting {
+                command: command.clone(),
+                key,
+                values,
+                line_number,
+            });
+        }
+
+        Ok(Config { settings })
+    }
+
+    /// Set the values as the defaults of the options of `command` and its subcommands.
+    ///
+    /// The root command takes the settings of `[phonemize]`, as it phonemizes without a
+    /// subcommand. The model path is positional and keeps its place, so it gets no default;
+    /// commands given none take it from [`Config::model_path`] after parsing.
+    pub fn apply(&self, mut command: Command) -> Result<Command, String> {
+        for setting in &self.settings {
+            let subcommands: Vec<String> = command
+                .get_subcommands()
+                .map(|subcommand| subcommand.get_name().to_string())
+                .filter(|name| setting.command.as_ref().is_none_or(|command| command == name))
+                .collect();
+            if subcommands.is_empty() {
+                let command = setting.command.as_deref().unwrap_or_default();
+                return Err(format!("Line {}: there is no command '{}'", setting.line_number, command));
+            }
+
+            let mut applied = false;
+            if setting.command.as_deref().is_none_or(|command| command == "phonemize") {
+                command = setting.apply_to(command, &mut applied);
+            }
+            for name in subcommands {
+                command = command.mut_subcommand(name, |subcommand| setting.apply_to(subcommand, &mut applied));
+            }
+            if !applied {
+                return Err(format!("Line {}: there is no argument '{}'", setting.line_number, setting.key));
+            }
+        }
+
+        Ok(command)
+    }
+
+    /// The model path set for `command`, e.g. `phonemize`.
+    pub fn model_path(&self, command: &str) -> Option<&str> {
+        self.settings
+            .iter()
+            .filter(|setting| setting.key.replace('-', "_") == "model_path")
+            .filter(|setting| setting.command.as_deref().is_none_or(|name| name == command))
+            .filter_map(|setting| setting.values.first())
+            .next_back()
+            .map(String::as_str)
+    }
+}
+
+impl Setting {
+    /// Set the default of the option of `command` named by the key, if it has one. The model
+    /// path is only looked for, see [`Config::model_path`].
+    fn apply_to(&self, command: Command, applied: &mut bool) -> Command {
+        let key = self.key.replace('-', "_");
+        let arg = command.get_arguments().find(|arg| {
+            let long = arg.get_long().map(|long| long.replace('-', "_"));
+            arg.get_id() == key.as_str() || long.as_deref() == Some(&key)
+        });
+        let Some(arg) = arg else {
+            return command;
+        };
+        if arg.is_positional() {
+            *applied |= key == "model_path";
+            return command;
+        }
+        *applied = true;
+        let id = arg.get_id().clone();
+        command.mut_arg(id, |arg| set_defaults(arg, &self.values))
+    }
+}
+
+fn set_defaults(arg: Arg, values: &[String]) -> Arg {
+    match values {
+        [value] => arg.default_value(value.clone()),
+        values => arg.default_values(values.to_vec()),
+    }
+}
+
+/// A line of the file.
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Whether only whitespace and a comment are left.
+    fn at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.rest().is_empty() || self.rest().starts_with('#')
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// A bare or quoted key.
+    fn key(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        if self.rest().starts_with(['"', '\'']) {
+            return self.string();
+        }
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err("expected a key".to_string());
+        }
+        let key = self.rest()[..len].to_string();
+        self.pos += len;
+        Ok(key)
+    }
+
+    /// A value, or the items of an array.
+    fn values(&mut self) -> Result<Vec<String>, String> {
+        if !self.eat('[') {
+            return Ok(vec![self.value()?]);
+        }
+        let mut values = Vec::new();
+        loop {
+            if self.eat(']') {
+                return Ok(values);
+            }
+            values.push(self.value()?);
+            if self.eat(']') {
+                return Ok(values);
+            }
+            if !self.eat(',') {
+                return Err("expected ',' or ']' in the array".to_string());
+            }
+        }
+    }
+
+    /// A string, a number or a boolean, as the text of a command-line argument.
+    fn value(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        if self.rest().starts_with(['"', '\'']) {
+            return self.string();
+        }
+        let len = self
+            .rest()
+            .find(|c: char| c.is_whitespace() || c == ',' || c == ']' || c == '#')
+            .unwrap_or(self.rest().len());
+        let token = &self.rest()[..len];
+        let valid = matches!(token, "true" | "false")
+            || (!token.is_empty() && token.chars().all(|c| c.is_ascii_digit() || "+-._eE".contains(c)));
+        if !valid {
+            return Err(format!("expected a string, a number or a boolean, found '{}'", token));
+        }
+        self.pos += len;
+        Ok(token.replace('_', ""))
+    }
+
+    /// A basic string in double quotes with escapes, or a literal string in single quotes.
+    fn string(&mut self) -> Result<String, String> {
+        let quote = self.rest().chars().next().expect("called at a quote");
+        self.pos += 1;
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                c if c == quote => {
+                    self.pos += i + 1;
+                    return Ok(value);
+                }
+                '\\' if quote == '"' => match chars.next().map(|(_, c)| c) {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('r') => value.push('\r'),
+                    Some('u') => {
+                        let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                        let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                        value.push(c.ok_or_else(|| format!("invalid escape \\u{}", hex))?);
+                    }
+                    _ => return Err("invalid escape in a string".to_string()),
+                },
+                c => value.push(c),
+            }
+        }
+        Err("unterminated string".to_string())
+    }
+}
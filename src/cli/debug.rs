@@ -0,0 +1,61 @@
+use clap::Args;
+use std::path::PathBuf;
+
+use super::load_model;
+use phonetisaurus_g2p_py::*;
+
+#[derive(Args)]
+pub struct DebugArgs {
+    /// Path to the Phonetisaurus FST model file
+    model_path: String,
+
+    /// Word to decode
+    word: String,
+
+    /// Number of distinct pronunciations to produce
+    #[arg(long, default_value_t = 1)]
+    nbest: usize,
+
+    /// Write the FST of every decoding stage to <DIR>/<stage>.dot, for Graphviz
+    #[arg(long, value_name = "DIR")]
+    dot: Option<PathBuf>,
+}
+
+/// Decode a word, printing the decoding stages and the pronunciations, and optionally
+/// drawing the FSTs of the stages.
+pub fn run(args: DebugArgs) {
+    let model = load_model(Some(&args.model_path));
+    let (results, drawings) = model.draw_decoding(&args.word, args.nbest, &PhonemizeOptions::default());
+
+    if let Some(dir) = &args.dot
+        && let Err(e) = std::fs::create_dir_all(dir)
+    {
+        eprintln!("Failed to create directory '{}': {}", dir.display(), e);
+        std::process::exit(1);
+    }
+    for (stage, dot) in &drawings {
+        match &args.dot {
+            Some(dir) => {
+                let path = dir.join(format!("{}.dot", stage));
+                if let Err(e) = std::fs::write(&path, dot) {
+                    eprintln!("Failed to write '{}': {}", path.display(), e);
+                    std::process::exit(1);
+                }
+                println!("{}: {}", stage, path.display());
+            }
+            None => println!("{}", stage),
+        }
+    }
+
+    match results {
+        Ok(results) => {
+            for result in results {
+                println!("{}\t{}", result.phonemes, result.neg_log_score);
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to phonemize '{}': {}", args.word, e);
+            std::process::exit(1);
+        }
+    }
+}
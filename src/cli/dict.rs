@@ -0,0 +1,137 @@
+use clap::Args;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::{load_lexicon, load_model};
+use phonetisaurus_g2p_py::{PhonetisaurusModel, PhonetizationResult};
+
+/// Number of words phonemized in parallel before their entries are written.
+const CHUNK_SIZE: usize = 4096;
+
+#[derive(Args)]
+pub struct DictArgs {
+    /// Path to the Phonetisaurus FST model file [default: $PHONETISAURUS_MODEL, or model_path
+    /// in the config file]
+    model_path: Option<String>,
+
+    /// File with the words to include, one per line [default: stdin]
+    #[arg(long)]
+    wordlist: Option<PathBuf>,
+
+    /// File to write the lexicon to [default: stdout]
+    #[arg(long)]
+    out: Option<PathBuf>,
+
+    /// Number of pronunciations per word, written as WORD, WORD(2), ...
+    #[arg(long, default_value_t = 1)]
+    nbest: usize,
+
+    /// Exception lexicon (CMUdict format) whose pronunciations override the model
+    #[arg(long)]
+    lexicon: Option<PathBuf>,
+}
+
+/// Write a CMUdict/Kaldi-style pronunciation lexicon for a list of words.
+pub fn run(args: DictArgs) {
+    let mut model = load_model(args.model_path.as_deref());
+    if let Some(lexicon_path) = &args.lexicon {
+        model = model.with_lexicon(load_lexicon(lexicon_path));
+    }
+
+    let reader = open_wordlist(args.wordlist.as_deref());
+    let out: Box<dyn Write> = match &args.out {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                eprintln!("Failed to create output file '{}': {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(io::stdout().lock()),
+    };
+
+    match write_dict(&model, reader, BufWriter::new(out), args.nbest) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("Failed to write lexicon: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Open the word list at `path`, or stdin, exiting the process if that fails.
+pub fn open_wordlist(path: Option<&Path>) -> Box<dyn BufRead> {
+    match path {
+        Some(path) => match File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => {
+                eprintln!("Failed to open word list '{}': {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(io::stdin().lock()),
+    }
+}
+
+/// Phonemize the distinct words of `reader` and write their entries in input order.
+///
+/// Failed words are reported on stderr and left out. Returns whether all words succeeded.
+fn write_dict(
+    model: &PhonetisaurusModel,
+    reader: impl BufRead,
+    mut out: impl Write,
+    nbest: usize,
+) -> io::Result<bool> {
+    let all_ok = phonemize_wordlist(model, reader, nbest, |word, results| {
+        for (i, result) in results.iter().enumerate() {
+            match i {
+                0 => writeln!(out, "{} {}", word, result.phonemes)?,
+                _ => writeln!(out, "{}({}) {}", word, i + 1, result.phonemes)?,
+            }
+        }
+        Ok(())
+    })?;
+    out.flush()?;
+
+    Ok(all_ok)
+}
+
+/// Phonemize the distinct words of `reader` in chunks, passing the pronunciations of each to
+/// `entry` in input order.
+///
+/// Failed words are reported on stderr and skipped. Returns whether all words succeeded.
+pub fn phonemize_wordlist(
+    model: &PhonetisaurusModel,
+    reader: impl BufRead,
+    nbest: usize,
+    mut entry: impl FnMut(&str, &[PhonetizationResult]) -> io::Result<()>,
+) -> io::Result<bool> {
+    let mut seen = HashSet::new();
+    let mut words = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let word = line.trim();
+        if !word.is_empty() && seen.insert(word.to_string()) {
+            words.push(word.to_string());
+        }
+    }
+
+    let mut all_ok = true;
+    for chunk in words.chunks(CHUNK_SIZE) {
+        let chunk: Vec<&str> = chunk.iter().map(String::as_str).collect();
+        for (word, results) in chunk.iter().zip(model.phonemize_words_nbest(&chunk, nbest)) {
+            match results {
+                Ok(results) => entry(word, &results)?,
+                Err(e) => {
+                    eprintln!("Failed to phonemize word '{}': {}", word, e);
+                    all_ok = false;
+                }
+            }
+        }
+    }
+
+    Ok(all_ok)
+}
@@ -0,0 +1,150 @@
+use clap::Args;
+use std::path::PathBuf;
+
+use super::json::Value;
+use super::{load_lexicon, load_model};
+
+#[derive(Args)]
+pub struct EvaluateArgs {
+    /// Path to the Phonetisaurus FST model file [default: $PHONETISAURUS_MODEL, or model_path
+    /// in the config file]
+    model_path: Option<String>,
+
+    /// Reference lexicon (CMUdict format) whose headwords are phonemized and compared
+    #[arg(long)]
+    reference: PathBuf,
+
+    /// Number of words with the most phoneme errors to list
+    #[arg(long, default_value_t = 10)]
+    worst: usize,
+
+    /// Print the report as a JSON object
+    #[arg(long)]
+    json: bool,
+}
+
+/// Comparison of the model's pronunciation of a word with its reference pronunciations.
+struct WordScore {
+    word: String,
+    /// Phonemes produced by the model, `None` if the word failed.
+    hypothesis: Option<String>,
+    /// The reference pronunciation closest to the hypothesis.
+    reference: String,
+    /// Edit distance between the hypothesis and that reference, in phonemes.
+    errors: usize,
+    reference_len: usize,
+}
+
+/// Phonemize every headword of a reference lexicon and report phoneme and word error rates.
+///
+/// Words with several reference pronunciations are scored against the closest one. Words
+/// the model fails on count as entirely wrong.
+pub fn run(args: EvaluateArgs) {
+    let model = load_model(args.model_path.as_deref());
+    let reference = load_lexicon(&args.reference);
+
+    let mut entries: Vec<_> = reference.iter().collect();
+    entries.sort_by_key(|&(word, _)| word);
+    let words: Vec<&str> = entries.iter().map(|&(word, _)| word).collect();
+    let results = model.phonemize_words(&words);
+
+    let scores: Vec<WordScore> = entries
+        .iter()
+        .zip(results)
+        .map(|(&(word, pronunciations), result)| {
+            let hypothesis = result.ok().map(|result| result.phoneme_tokens);
+            let (errors, closest) = pronunciations
+                .iter()
+                .map(|pronunciation| {
+                    let errors = match &hypothesis {
+                        Some(hypothesis) => edit_distance(hypothesis, pronunciation),
+                        None => pronunciation.len(),
+                    };
+                    (errors, pronunciation)
+                })
+                .min_by_key(|&(errors, _)| errors)
+                .expect("lexicon entries have at least one pronunciation");
+            WordScore {
+                word: word.to_string(),
+                hypothesis: hypothesis.map(|tokens| tokens.join(" ")),
+                reference: closest.join(" "),
+                errors,
+                reference_len: closest.len(),
+            }
+        })
+        .collect();
+
+    let total_errors: usize = scores.iter().map(|score| score.errors).sum();
+    let total_phonemes: usize = scores.iter().map(|score| score.reference_len).sum();
+    let wrong_words = scores
+        .iter()
+        .filter(|score| score.errors > 0 || score.hypothesis.is_none())
+        .count();
+    let failed_words = scores.iter().filter(|score| score.hypothesis.is_none()).count();
+    let per = ratio(total_errors, total_phonemes);
+    let wer = ratio(wrong_words, scores.len());
+
+    let mut worst: Vec<&WordScore> = scores.iter().filter(|score| score.errors > 0).collect();
+    worst.sort_by(|a, b| b.errors.cmp(&a.errors).then_with(|| a.word.cmp(&b.word)));
+    worst.truncate(args.worst);
+
+    if args.json {
+        let worst = worst
+            .iter()
+            .map(|score| {
+                Value::object([
+                    ("word", score.word.as_str().into()),
+                    ("hypothesis", score.hypothesis.clone().map_or(Value::Null, Value::from)),
+                    ("reference", score.reference.as_str().into()),
+                    ("errors", score.errors.into()),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let report = Value::object([
+            ("words", scores.len().into()),
+            ("failed_words", failed_words.into()),
+            ("phoneme_errors", total_errors.into()),
+            ("reference_phonemes", total_phonemes.into()),
+            ("per", Value::Number(per)),
+            ("wer", Value::Number(wer)),
+            ("worst", worst.into()),
+        ]);
+        println!("{}", report);
+        return;
+    }
+
+    println!("Words: {} ({} failed)", scores.len(), failed_words);
+    println!("PER: {:.2}% ({} errors in {} phonemes)", 100.0 * per, total_errors, total_phonemes);
+    println!("WER: {:.2}% ({} words)", 100.0 * wer, wrong_words);
+    if !worst.is_empty() {
+        println!("Worst words:");
+        for score in worst {
+            println!(
+                "  {}\t{} error(s)\t{}\t(reference: {})",
+                score.word,
+                score.errors,
+                score.hypothesis.as_deref().unwrap_or("<failed>"),
+                score.reference
+            );
+        }
+    }
+}
+
+fn ratio(part: usize, total: usize) -> f64 {
+    if total == 0 { 0.0 } else { part as f64 / total as f64 }
+}
+
+/// Levenshtein distance between two phoneme sequences.
+fn edit_distance(a: &[String], b: &[String]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(x != y);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
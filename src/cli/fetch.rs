@@ -0,0 +1,43 @@
+use clap::Args;
+use std::path::PathBuf;
+
+use phonetisaurus_g2p_py::PhonetisaurusModel;
+use phonetisaurus_g2p_py::fetch::ModelDownload;
+
+#[derive(Args)]
+pub struct FetchArgs {
+    /// HTTPS URL of the model
+    url: String,
+
+    /// Expected SHA-256 checksum of the model, in hex
+    #[arg(long)]
+    sha256: Option<String>,
+
+    /// Directory to cache the model in [default: $XDG_CACHE_HOME/phonetisaurus-g2p]
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+}
+
+/// Download a model into the cache unless it is there already, check that it loads, and print
+/// its path.
+pub fn run(args: FetchArgs) {
+    let mut download = ModelDownload::new(&args.url);
+    if let Some(sha256) = &args.sha256 {
+        download = download.with_sha256(sha256);
+    }
+    if let Some(cache_dir) = args.cache_dir {
+        download = download.with_cache_dir(cache_dir);
+    }
+    let path = match download.fetch() {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("Failed to fetch model: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = PhonetisaurusModel::try_from(path.as_path()) {
+        eprintln!("Downloaded file '{}' is not a valid model: {}", path.display(), e);
+        std::process::exit(1);
+    }
+    println!("{}", path.display());
+}
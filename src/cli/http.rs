@@ -0,0 +1,191 @@
+use std::io::{self, BufRead, Read, Write};
+
+use super::json::Value;
+
+/// Largest request body the server reads, in bytes.
+pub const MAX_BODY_SIZE: usize = 1 << 20;
+
+/// Longest request line or header line the server reads, in bytes.
+const MAX_LINE_SIZE: usize = 8 << 10;
+
+/// Most headers the server reads from one request.
+const MAX_HEADERS: usize = 100;
+
+/// An HTTP/1.1 request, as far as the server cares about it.
+pub struct Request {
+    pub method: String,
+    /// Request target without the query string.
+    pub path: String,
+    /// Header names and values, in the order they were sent.
+    pub headers: Vec<(String, String)>,
+    /// Empty until read with [`read_body`].
+    pub body: Vec<u8>,
+    /// Length of the body announced by the client.
+    content_length: usize,
+}
+
+impl Request {
+    /// The value of a header, whose name is matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Headers added to a response, as `(name, value)`.
+pub type Headers = [(&'static str, String)];
+
+/// An HTTP error status together with a message for the client.
+pub struct HttpError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl HttpError {
+    pub fn new(status: u16, message: impl Into<String>) -> Self {
+        HttpError {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// Read the request line and the headers of one request from a connection.
+///
+/// The body is left to [`read_body`], so that the server can wait for its turn to handle the
+/// request before taking the body into memory. Returns `Ok(None)` if the client closed the
+/// connection before sending anything.
+pub fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Result<Request, HttpError>>> {
+    let mut request_line = String::new();
+    match read_line(reader, &mut request_line)? {
+        Line::End => return Ok(None),
+        Line::TooLong => return Ok(Some(Err(HttpError::new(414, "Request line too long")))),
+        Line::Read => {}
+    }
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(Some(Err(HttpError::new(400, "Malformed request line"))));
+    };
+    let path = target.split('?').next().unwrap_or_default().to_string();
+    let method = method.to_string();
+
+    let mut content_length = 0;
+    let mut headers = Vec::new();
+    loop {
+        let mut header = String::new();
+        match read_line(reader, &mut header)? {
+            Line::End => return Ok(Some(Err(HttpError::new(400, "Unexpected end of headers")))),
+            Line::TooLong => return Ok(Some(Err(HttpError::new(431, "Header line too long")))),
+            Line::Read => {}
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Ok(Some(Err(HttpError::new(400, "Malformed header"))));
+        };
+        if name.eq_ignore_ascii_case("content-length") {
+            match value.trim().parse() {
+                Ok(length) => content_length = length,
+                Err(_) => return Ok(Some(Err(HttpError::new(400, "Invalid Content-Length")))),
+            }
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            return Ok(Some(Err(HttpError::new(501, "Chunked bodies are not supported"))));
+        }
+        if headers.len() == MAX_HEADERS {
+            return Ok(Some(Err(HttpError::new(431, "Too many headers"))));
+        }
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+    if content_length > MAX_BODY_SIZE {
+        return Ok(Some(Err(HttpError::new(413, "Request body too large"))));
+    }
+
+    Ok(Some(Ok(Request {
+        method,
+        path,
+        headers,
+        body: Vec::new(),
+        content_length,
+    })))
+}
+
+/// Read the body of a request whose head was read with [`read_request`].
+pub fn read_body(reader: &mut impl BufRead, request: &mut Request) -> io::Result<()> {
+    request.body = vec![0; request.content_length];
+    reader.read_exact(&mut request.body)
+}
+
+/// How reading a line of the request ended.
+enum Line {
+    Read,
+    /// The connection was closed before the line started.
+    End,
+    /// The line is longer than [`MAX_LINE_SIZE`], and was not read to its end.
+    TooLong,
+}
+
+/// Read a line of at most [`MAX_LINE_SIZE`] bytes, so that a client cannot fill the memory
+/// with a line that never ends.
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> io::Result<Line> {
+    let read = reader.by_ref().take(MAX_LINE_SIZE as u64 + 1).read_line(line)?;
+    Ok(match read {
+        0 => Line::End,
+        _ if read > MAX_LINE_SIZE => Line::TooLong,
+        _ => Line::Read,
+    })
+}
+
+/// Write a JSON response and mark the connection for closing.
+pub fn write_response(out: &mut impl Write, status: u16, headers: &Headers, body: &Value) -> io::Result<()> {
+    write_text_response(out, status, "application/json", headers, &body.to_string())
+}
+
+/// Write a response with a body of the given content type and mark the connection for closing.
+pub fn write_text_response(
+    out: &mut impl Write,
+    status: u16,
+    content_type: &str,
+    headers: &Headers,
+    body: &str,
+) -> io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n",
+        status,
+        reason_phrase(status),
+        content_type,
+        body.len()
+    );
+    match status {
+        401 => head.push_str("WWW-Authenticate: Bearer\r\n"),
+        // clients backing off from a full server retry after a second
+        429 => head.push_str("Retry-After: 1\r\n"),
+        _ => {}
+    }
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    write!(out, "{}Connection: close\r\n\r\n{}", head, body)?;
+    out.flush()
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        414 => "URI Too Long",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
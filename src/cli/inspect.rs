@@ -0,0 +1,51 @@
+use clap::Args;
+
+use super::json::Value;
+use super::load_model;
+
+#[derive(Args)]
+pub struct InspectArgs {
+    /// Path to the Phonetisaurus FST model file [default: $PHONETISAURUS_MODEL, or model_path
+    /// in the config file]
+    model_path: Option<String>,
+
+    /// Print the information as a JSON object
+    #[arg(long)]
+    json: bool,
+}
+
+/// Print the size and the symbol inventories of a model.
+pub fn run(args: InspectArgs) {
+    let model = load_model(args.model_path.as_deref());
+    let input_symbols = model.input_symbols();
+    let output_symbols = model.output_symbols();
+
+    if args.json {
+        let mut info = vec![
+            ("num_states", model.num_states().into()),
+            ("num_arcs", model.num_arcs().into()),
+            ("input_symbols", input_symbols.into()),
+            ("output_symbols", output_symbols.into()),
+        ];
+        if let Some(manifest) = model.manifest() {
+            let manifest = Value::parse(&manifest.to_json()).expect("the manifest is valid JSON");
+            info.push(("manifest", manifest));
+        }
+        println!("{}", Value::object(info));
+        return;
+    }
+
+    if let Some(manifest) = model.manifest() {
+        let Ok(Value::Object(members)) = Value::parse(&manifest.to_json()) else {
+            unreachable!("the manifest is a JSON object");
+        };
+        for (key, value) in members {
+            println!("{}: {}", key, value.as_str().unwrap_or_default());
+        }
+    }
+
+    println!("States: {}", model.num_states());
+    println!("Arcs: {}", model.num_arcs());
+    println!("Input symbols ({}): {}", input_symbols.len(), input_symbols.join(" "));
+    println!("Output symbols ({}): {}", output_symbols.len(), output_symbols.join(" "));
+}
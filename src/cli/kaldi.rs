@@ -0,0 +1,92 @@
+use clap::Args;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use super::dict::{open_wordlist, phonemize_wordlist};
+use super::{load_lexicon, load_model};
+use phonetisaurus_g2p_py::KaldiLexicon;
+
+#[derive(Args)]
+pub struct KaldiArgs {
+    /// Path to the Phonetisaurus FST model file [default: $PHONETISAURUS_MODEL, or model_path
+    /// in the config file]
+    model_path: Option<String>,
+
+    /// File with the words to include, one per line [default: stdin]
+    #[arg(long)]
+    wordlist: Option<PathBuf>,
+
+    /// Directory to write lexicon.txt and the other files to, created if missing
+    #[arg(long, default_value = ".")]
+    dir: PathBuf,
+
+    /// Also write lexicon_disambig.txt, phones.txt, words.txt, L.fst and L_disambig.fst
+    #[arg(long)]
+    fst: bool,
+
+    /// Number of pronunciations per word
+    #[arg(long, default_value_t = 1)]
+    nbest: usize,
+
+    /// Exception lexicon (CMUdict format) whose pronunciations override the model
+    #[arg(long)]
+    lexicon: Option<PathBuf>,
+
+    /// Silence phone optionally inserted between words in the FSTs
+    #[arg(long, value_name = "PHONE", requires = "fst")]
+    silence_phone: Option<String>,
+
+    /// Probability of the silence between words, strictly between 0 and 1
+    #[arg(long, value_name = "P", default_value_t = 0.5, requires = "silence_phone")]
+    silence_prob: f32,
+}
+
+/// Write a Kaldi lexicon for a list of words, and optionally the lexicon FSTs of a lang
+/// directory.
+pub fn run(args: KaldiArgs) {
+    if !(args.silence_prob > 0.0 && args.silence_prob < 1.0) {
+        eprintln!("--silence-prob must be between 0 and 1, got {}", args.silence_prob);
+        std::process::exit(1);
+    }
+    let mut model = load_model(args.model_path.as_deref());
+    if let Some(lexicon_path) = &args.lexicon {
+        model = model.with_lexicon(load_lexicon(lexicon_path));
+    }
+
+    let reader = open_wordlist(args.wordlist.as_deref());
+    let mut lexicon = KaldiLexicon::new();
+    if let Some(phone) = &args.silence_phone {
+        lexicon = lexicon.with_optional_silence(phone, args.silence_prob);
+    }
+    let all_ok = phonemize_wordlist(&model, reader, args.nbest, |word, results| {
+        for result in results {
+            lexicon.push(word, result.phoneme_tokens.clone());
+        }
+        Ok(())
+    })
+    .unwrap_or_else(|e| {
+        eprintln!("Failed to read word list: {}", e);
+        std::process::exit(1);
+    });
+
+    if let Err(e) = fs::create_dir_all(&args.dir) {
+        eprintln!("Failed to create directory '{}': {}", args.dir.display(), e);
+        std::process::exit(1);
+    }
+    let written = match args.fst {
+        true => lexicon.write_lang(&args.dir),
+        false => File::create(args.dir.join("lexicon.txt")).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            lexicon.write_lexicon(&mut out)?;
+            out.flush()
+        }),
+    };
+    if let Err(e) = written {
+        eprintln!("Failed to write lexicon to '{}': {}", args.dir.display(), e);
+        std::process::exit(1);
+    }
+    if !all_ok {
+        std::process::exit(1);
+    }
+}
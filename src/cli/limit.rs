@@ -0,0 +1,72 @@
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+/// Bounds the number of requests decoded at once, and the number waiting for their turn.
+pub struct ConcurrencyLimit {
+    max_concurrency: usize,
+    max_queue: usize,
+    state: Mutex<LimitState>,
+    freed: Condvar,
+}
+
+#[derive(Default)]
+struct LimitState {
+    running: usize,
+    queued: usize,
+}
+
+/// A request's turn, given back when dropped.
+pub struct Permit<'a>(&'a ConcurrencyLimit);
+
+impl ConcurrencyLimit {
+    /// A limit of `max_concurrency` running requests, `None` for no limit, and `max_queue`
+    /// requests waiting for one of them to finish.
+    pub fn new(max_concurrency: Option<usize>, max_queue: usize) -> Self {
+        ConcurrencyLimit {
+            max_concurrency: max_concurrency.unwrap_or(usize::MAX),
+            max_queue,
+            state: Mutex::default(),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Wait for a turn to run a request, or `None` if the queue is full.
+    pub fn acquire(&self) -> Option<Permit<'_>> {
+        let mut state = self.lock();
+        if state.running >= self.max_concurrency {
+            if state.queued >= self.max_queue {
+                return None;
+            }
+            state.queued += 1;
+            while state.running >= self.max_concurrency {
+                state = self.freed.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+            state.queued -= 1;
+        }
+        state.running += 1;
+        Some(Permit(self))
+    }
+
+    /// Whether a request would be turned away by [`Self::acquire`] now.
+    pub fn is_full(&self) -> bool {
+        let state = self.lock();
+        state.running >= self.max_concurrency && state.queued >= self.max_queue
+    }
+
+    /// Number of requests running and waiting.
+    pub fn load(&self) -> (usize, usize) {
+        let state = self.lock();
+        (state.running, state.queued)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LimitState> {
+        // the state is only changed by increments and decrements, so it is intact when poisoned
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.0.lock().running -= 1;
+        self.0.freed.notify_one();
+    }
+}
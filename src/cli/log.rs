@@ -0,0 +1,141 @@
+use clap::{Args, ValueEnum};
+use std::fmt;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::json::Value;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+/// How messages are written to stderr.
+pub enum LogFormat {
+    /// The message alone, as it always was
+    #[default]
+    Text,
+    /// One JSON object per line with a timestamp, the level, the message and its fields
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+/// Importance of a message, most important first.
+pub enum Level {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    /// One message per request of the server
+    Debug,
+    /// One message per word of the server
+    Trace,
+}
+
+#[derive(Args, Clone, Copy, Debug, Default)]
+pub struct LogArgs {
+    /// Format of the messages written to stderr
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Least important messages written to stderr
+    #[arg(long, value_enum, default_value_t = Level::Info)]
+    log_level: Level,
+}
+
+static CONFIG: OnceLock<LogArgs> = OnceLock::new();
+
+impl LogArgs {
+    /// Use these settings for all messages of the process. Only the first call has an effect.
+    pub fn init(self) {
+        let _ = CONFIG.set(self);
+    }
+}
+
+/// Whether messages of this level are written, to skip collecting their fields otherwise.
+#[cfg(feature = "server")]
+pub fn enabled(level: Level) -> bool {
+    level <= CONFIG.get().copied().unwrap_or_default().log_level
+}
+
+/// Write a message to stderr. The text format writes the message alone, so it should contain
+/// what matters of the fields; the JSON format adds the fields as members.
+pub fn log(level: Level, message: &str, fields: impl IntoIterator<Item = (&'static str, Value)>) {
+    let config = CONFIG.get().copied().unwrap_or_default();
+    if level > config.log_level {
+        return;
+    }
+    match config.log_format {
+        LogFormat::Text => eprintln!("{}", message),
+        LogFormat::Json => {
+            let mut members = vec![
+                ("timestamp", Value::from(timestamp(SystemTime::now()))),
+                ("level", level.name().into()),
+                ("message", message.into()),
+            ];
+            members.extend(fields);
+            eprintln!("{}", Value::object(members));
+        }
+    }
+}
+
+pub fn error(message: &str, fields: impl IntoIterator<Item = (&'static str, Value)>) {
+    log(Level::Error, message, fields)
+}
+
+pub fn warn(message: &str, fields: impl IntoIterator<Item = (&'static str, Value)>) {
+    log(Level::Warn, message, fields)
+}
+
+pub fn info(message: &str, fields: impl IntoIterator<Item = (&'static str, Value)>) {
+    log(Level::Info, message, fields)
+}
+
+/// Log `message: error` as an error, with the error as a field.
+pub fn failed(message: &str, error: &dyn fmt::Display) {
+    let error = error.to_string();
+    log(Level::Error, &format!("{}: {}", message, error), [("error", error.into())]);
+}
+
+/// Log `message 'path': error` as an error, with the path and the error as fields.
+pub fn failed_on(message: &str, path: &Path, error: &dyn fmt::Display) {
+    let error = error.to_string();
+    let path = path.display().to_string();
+    log(Level::Error, &format!("{} '{}': {}", message, path, error), [("path", path.into()), ("error", error.into())]);
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+}
+
+/// A time in RFC 3339 format in UTC with milliseconds, like `2024-05-01T12:00:00.000Z`.
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (days, seconds_of_day) = (seconds / 86400, seconds % 86400);
+    // days since 1970-01-01 to the civil date, after Howard Hinnant's days_from_civil inverse
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use phonetisaurus_g2p_py::*;
+
+/// Upper bounds of the request latency buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 12] = [0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+/// Kinds of word errors that are counted separately, the rest count as `other`.
+const ERROR_KINDS: [&str; 6] = ["unknown_symbol", "no_path", "timeout", "input_too_long", "search_space_exceeded", "other"];
+
+/// Name, help text and value of a gauge of each model.
+type ModelGauge = (&'static str, &'static str, fn(&PhonetisaurusModel) -> usize);
+
+/// Name, help text, type and value of a metric of each model cache.
+type CacheMetric = (&'static str, &'static str, &'static str, fn(&CachedPhonetisaurusModel) -> String);
+
+#[derive(Default)]
+struct Histogram {
+    /// Number of observations per bucket, not cumulative, the last one for `+Inf`.
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+}
+
+/// Counters of a running server, rendered in the Prometheus text format by [`Metrics::render`].
+pub struct Metrics {
+    /// Number of requests per endpoint and status.
+    requests: Mutex<BTreeMap<(&'static str, u16), u64>>,
+    latencies: Mutex<BTreeMap<&'static str, Histogram>>,
+    words: AtomicU64,
+    /// Number of failed words per entry of [`ERROR_KINDS`].
+    errors: [AtomicU64; ERROR_KINDS.len()],
+    /// Number of reloads of the models that succeeded and that failed.
+    reloads: [AtomicU64; 2],
+    /// Gauges describing the models, which only change when they are reloaded.
+    model_info: Mutex<String>,
+}
+
+impl Metrics {
+    /// Metrics of a server for the given models, as `(language, path, model)`.
+    pub fn new<'a>(models: impl IntoIterator<Item = (&'a str, &'a str, &'a PhonetisaurusModel)>) -> Self {
+        Metrics {
+            requests: Mutex::default(),
+            latencies: Mutex::default(),
+            words: AtomicU64::new(0),
+            errors: Default::default(),
+            reloads: Default::default(),
+            model_info: Mutex::new(model_info(models)),
+        }
+    }
+
+    /// Describe the models served after they were reloaded.
+    pub fn set_models<'a>(&self, models: impl IntoIterator<Item = (&'a str, &'a str, &'a PhonetisaurusModel)>) {
+        *lock(&self.model_info) = model_info(models);
+    }
+
+    /// Count a reload of the models.
+    pub fn record_reload(&self, succeeded: bool) {
+        self.reloads[usize::from(!succeeded)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a finished request to `endpoint` that took `elapsed`.
+    pub fn record_request(&self, endpoint: &'static str, status: u16, elapsed: Duration) {
+        *lock(&self.requests).entry((endpoint, status)).or_default() += 1;
+
+        let seconds = elapsed.as_secs_f64();
+        let mut latencies = lock(&self.latencies);
+        let histogram = latencies.entry(endpoint).or_default();
+        let bucket = LATENCY_BUCKETS.iter().position(|&bound| seconds <= bound).unwrap_or(LATENCY_BUCKETS.len());
+        histogram.buckets[bucket] += 1;
+        histogram.sum += seconds;
+    }
+
+    /// Count a phonemized word, and its error if it failed.
+    pub fn record_word<T>(&self, result: &Result<T, PhonetisaurusError>) {
+        self.words.fetch_add(1, Ordering::Relaxed);
+        if let Err(e) = result {
+            let kind = match e {
+                PhonetisaurusError::UnknownSymbol { .. } => 0,
+                PhonetisaurusError::NoPathFound => 1,
+                PhonetisaurusError::Timeout => 2,
+                PhonetisaurusError::InputTooLong { .. } => 3,
+                PhonetisaurusError::SearchSpaceExceeded { .. } => 4,
+                _ => 5,
+            };
+            self.errors[kind].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// All metrics in the Prometheus text exposition format, with the number of requests
+    /// `(running, queued)`.
+    pub fn render(&self, caches: &BTreeMap<String, CachedPhonetisaurusModel>, load: (usize, usize)) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "g2p_requests_total", "Requests handled, by endpoint and status.", "counter");
+        for ((endpoint, status), count) in lock(&self.requests).iter() {
+            let _ = writeln!(out, "g2p_requests_total{{endpoint=\"{}\",status=\"{}\"}} {}", endpoint, status, count);
+        }
+
+        header(&mut out, "g2p_request_duration_seconds", "Time to answer a request.", "histogram");
+        for (endpoint, histogram) in lock(&self.latencies).iter() {
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(&histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "g2p_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"{}\"}} {}",
+                    endpoint, bound, cumulative
+                );
+            }
+            cumulative += histogram.buckets[LATENCY_BUCKETS.len()];
+            let _ = writeln!(
+                out,
+                "g2p_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"+Inf\"}} {}",
+                endpoint, cumulative
+            );
+            let _ = writeln!(out, "g2p_request_duration_seconds_sum{{endpoint=\"{}\"}} {}", endpoint, histogram.sum);
+            let _ = writeln!(out, "g2p_request_duration_seconds_count{{endpoint=\"{}\"}} {}", endpoint, cumulative);
+        }
+
+        header(&mut out, "g2p_requests_in_flight", "Phonemize requests being decoded.", "gauge");
+        let _ = writeln!(out, "g2p_requests_in_flight {}", load.0);
+        header(&mut out, "g2p_requests_queued", "Phonemize requests waiting for --max-concurrency.", "gauge");
+        let _ = writeln!(out, "g2p_requests_queued {}", load.1);
+
+        header(&mut out, "g2p_words_total", "Words phonemized, including failed ones.", "counter");
+        let _ = writeln!(out, "g2p_words_total {}", self.words.load(Ordering::Relaxed));
+        header(&mut out, "g2p_word_errors_total", "Words that failed to phonemize, by error.", "counter");
+        for (kind, count) in ERROR_KINDS.iter().zip(&self.errors) {
+            let _ = writeln!(out, "g2p_word_errors_total{{error=\"{}\"}} {}", kind, count.load(Ordering::Relaxed));
+        }
+
+        if !caches.is_empty() {
+            let cache_gauges: [CacheMetric; 5] = [
+                ("g2p_cache_hits_total", "Words answered from the cache.", "counter", |cache| {
+                    cache.hits().to_string()
+                }),
+                ("g2p_cache_misses_total", "Cache lookups that had to decode the word.", "counter", |cache| {
+                    cache.misses().to_string()
+                }),
+                ("g2p_cache_hit_ratio", "Fraction of cache lookups that were hits.", "gauge", |cache| {
+                    cache.stats().hit_ratio().to_string()
+                }),
+                ("g2p_cache_entries", "Words currently cached.", "gauge", |cache| cache.len().to_string()),
+                ("g2p_cache_capacity", "Maximum number of cached words.", "gauge", |cache| {
+                    cache.capacity().to_string()
+                }),
+            ];
+            for (name, help, kind, value) in cache_gauges {
+                header(&mut out, name, help, kind);
+                for (lang, cache) in caches {
+                    let _ = writeln!(out, "{}{{lang=\"{}\"}} {}", name, escape_label(lang), value(cache));
+                }
+            }
+        }
+
+        header(&mut out, "g2p_model_reloads_total", "Reloads of the models, by result.", "counter");
+        for (result, count) in ["success", "failure"].iter().zip(&self.reloads) {
+            let _ = writeln!(out, "g2p_model_reloads_total{{result=\"{}\"}} {}", result, count.load(Ordering::Relaxed));
+        }
+
+        out.push_str(&lock(&self.model_info));
+        out
+    }
+}
+
+/// Gauges describing the models, as `(language, path, model)`.
+fn model_info<'a>(models: impl IntoIterator<Item = (&'a str, &'a str, &'a PhonetisaurusModel)>) -> String {
+    let models: Vec<_> = models.into_iter().collect();
+    let mut model_info = String::new();
+    header(&mut model_info, "g2p_model_info", "Models served, always 1.", "gauge");
+    for (lang, path, model) in &models {
+        let _ = writeln!(
+            model_info,
+            "g2p_model_info{{lang=\"{}\",path=\"{}\",optimized=\"{}\"}} 1",
+            escape_label(lang),
+            escape_label(path),
+            model.is_optimized()
+        );
+    }
+    let gauges: [ModelGauge; 5] = [
+        ("g2p_model_states", "Number of states of the model FST.", PhonetisaurusModel::num_states),
+        ("g2p_model_arcs", "Number of arcs of the model FST.", PhonetisaurusModel::num_arcs),
+        ("g2p_model_input_symbols", "Number of graphemes the model accepts.", |model| {
+            model.input_symbols().len()
+        }),
+        ("g2p_model_output_symbols", "Number of phoneme symbols the model can emit.", |model| {
+            model.output_symbols().len()
+        }),
+        ("g2p_lexicon_entries", "Number of words of the exception lexicon.", |model| {
+            model.lexicon().map_or(0, Lexicon::len)
+        }),
+    ];
+    for (name, help, value) in gauges {
+        header(&mut model_info, name, help, "gauge");
+        for (lang, _, model) in &models {
+            let _ = writeln!(model_info, "{}{{lang=\"{}\"}} {}", name, escape_label(lang), value(model));
+        }
+    }
+    model_info
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escape a label value as the text format requires.
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    // the counters never panic while holding the lock, so a poisoned map is intact
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
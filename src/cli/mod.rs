@@ -0,0 +1,94 @@
+use std::path::Path;
+use std::sync::OnceLock;
+
+use phonetisaurus_g2p_py::{Lexicon, PhonetisaurusModel, locate};
+
+#[cfg(feature = "server")]
+pub mod access;
+pub mod bench;
+pub mod cache;
+pub mod columns;
+pub mod config;
+pub mod debug;
+pub mod dict;
+pub mod evaluate;
+#[cfg(feature = "download")]
+pub mod fetch;
+#[cfg(feature = "server")]
+pub mod http;
+pub mod inspect;
+pub mod json;
+pub mod kaldi;
+#[cfg(feature = "server")]
+pub mod limit;
+pub mod log;
+#[cfg(feature = "server")]
+pub mod metrics;
+pub mod options;
+pub mod output;
+pub mod pack;
+pub mod phonemize;
+#[cfg(feature = "server")]
+pub mod pool;
+pub mod progress;
+pub mod resume;
+pub mod review;
+#[cfg(feature = "server")]
+pub mod serve;
+pub mod ssml;
+pub mod validate;
+
+/// The model path of the config file, for commands given none.
+static CONFIG_MODEL_PATH: OnceLock<String> = OnceLock::new();
+
+/// Use `model_path` from the config file for commands given no model path, unless
+/// `PHONETISAURUS_MODEL` is set.
+pub fn set_config_model_path(model_path: String) {
+    let _ = CONFIG_MODEL_PATH.set(model_path);
+}
+
+/// The model path for commands given none: `PHONETISAURUS_MODEL`, or else `model_path` from
+/// the config file.
+pub fn default_model_path() -> Option<String> {
+    let env = std::env::var(locate::MODEL_ENV).ok().filter(|model_path| !model_path.is_empty());
+    env.or_else(|| CONFIG_MODEL_PATH.get().cloned())
+}
+
+/// The model path given on the command line, or else [`default_model_path`], exiting the
+/// process if there is neither.
+pub fn require_model_path(model_path: Option<&str>) -> String {
+    match model_path.map(str::to_string).or_else(default_model_path) {
+        Some(model_path) => model_path,
+        None => {
+            log::error("No model given: pass its path, or set PHONETISAURUS_MODEL or model_path in the config file", []);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Load the model from the specified path, or from [`default_model_path`] if none is given,
+/// exiting the process if that fails.
+///
+/// Names and relative paths that do not exist are looked up in `PHONETISAURUS_MODEL_DIR`.
+pub fn load_model(model_path: Option<&str>) -> PhonetisaurusModel {
+    let model_path = require_model_path(model_path);
+    let model_path = locate::model_path(Path::new(&model_path));
+    match PhonetisaurusModel::try_from(model_path.as_path()) {
+        Ok(m) => m,
+        Err(e) => {
+            log::failed_on("Failed to load model from", &model_path, &e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Load an exception lexicon from the specified path, exiting the process if that fails.
+pub fn load_lexicon(lexicon_path: &Path) -> Lexicon {
+    match Lexicon::try_from(lexicon_path) {
+        Ok(lexicon) => lexicon,
+        Err(e) => {
+            log::failed_on("Failed to load lexicon from", lexicon_path, &e);
+            std::process::exit(1);
+        }
+    }
+}
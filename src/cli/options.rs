@@ -0,0 +1,176 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::json::Value;
+use phonetisaurus_g2p_py::*;
+
+/// Read [`PhonemizeOptions`] from a JSON object, e.g.
+/// `{"unknown_chars": "skip", "case_fold": "lower", "normalize": "nfc"}`.
+pub fn parse_options(options: &Value) -> Result<PhonemizeOptions, String> {
+    let Value::Object(members) = options else {
+        return Err("\"options\" must be an object".to_string());
+    };
+    let mut parsed = PhonemizeOptions::default();
+    let mut joiner = None;
+    for (key, value) in members {
+        match key.as_str() {
+            "beam" | "max_states" | "max_word_length" | "max_lattice_states" => {
+                let count = value
+                    .as_f64()
+                    .filter(|n| *n >= 1.0 && n.fract() == 0.0)
+                    .ok_or_else(|| format!("Option \"{}\" must be a positive integer", key))?;
+                let count = Some(count as usize);
+                match key.as_str() {
+                    "beam" => parsed.beam = count,
+                    "max_states" => parsed.max_states = count,
+                    "max_word_length" => parsed.max_word_length = count,
+                    _ => parsed.max_lattice_states = count,
+                }
+                continue;
+            }
+            "timeout_ms" => {
+                let millis = value
+                    .as_f64()
+                    .filter(|n| *n >= 0.0 && n.fract() == 0.0)
+                    .ok_or_else(|| format!("Option \"{}\" must be a non-negative integer", key))?;
+                parsed.timeout = Some(Duration::from_millis(millis as u64));
+                continue;
+            }
+            "prune_weight" => {
+                let weight = value
+                    .as_f64()
+                    .filter(|n| *n >= 0.0)
+                    .ok_or_else(|| format!("Option \"{}\" must be a non-negative number", key))?;
+                parsed.prune_weight = Some(weight as f32);
+                continue;
+            }
+            "diagnostics" => {
+                parsed.diagnostics = match value {
+                    Value::Bool(diagnostics) => *diagnostics,
+                    _ => return Err(format!("Option \"{}\" must be a boolean", key)),
+                };
+                continue;
+            }
+            "skip_symbols" => {
+                parsed.separators.skip_symbols = value
+                    .as_array()
+                    .and_then(|symbols| symbols.iter().map(|symbol| symbol.as_str().map(str::to_string)).collect())
+                    .ok_or_else(|| format!("Option \"{}\" must be an array of strings", key))?;
+                continue;
+            }
+            _ => {}
+        }
+        let value = value
+            .as_str()
+            .ok_or_else(|| format!("Option \"{}\" must be a string", key))?;
+        let invalid = || format!("Invalid value \"{}\" for option \"{}\"", value, key);
+        match key.as_str() {
+            "unknown_chars" => {
+                parsed.unknown_char_policy = match value {
+                    "error" => UnknownCharPolicy::Error,
+                    "skip" => UnknownCharPolicy::Skip,
+                    _ => return Err(invalid()),
+                }
+            }
+            "substitute" => {
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => parsed.unknown_char_policy = UnknownCharPolicy::Substitute(ch),
+                    _ => return Err(invalid()),
+                }
+            }
+            "tag_separator" => {
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => parsed.tag_separator = Some(ch),
+                    _ => return Err(invalid()),
+                }
+            }
+            "case_fold" => {
+                parsed.case_fold = match value {
+                    "none" => CaseFold::None,
+                    "lower" => CaseFold::Lower,
+                    "upper" => CaseFold::Upper,
+                    "turkic_lower" => CaseFold::TurkicLower,
+                    "turkic_upper" => CaseFold::TurkicUpper,
+                    _ => return Err(invalid()),
+                }
+            }
+            "normalize" => {
+                parsed.normalize = match value {
+                    "none" => Normalization::None,
+                    "nfc" => Normalization::Nfc,
+                    "nfd" => Normalization::Nfd,
+                    _ => return Err(invalid()),
+                }
+            }
+            "segmentation" => {
+                parsed.segmentation = match value {
+                    "chars" => Segmentation::Chars,
+                    "grapheme_clusters" => Segmentation::GraphemeClusters,
+                    "longest_match" => Segmentation::LongestMatch,
+                    _ => return Err(invalid()),
+                }
+            }
+            "syllabify" => {
+                let alphabet = match value {
+                    "arpabet" => PhonemeAlphabet::Arpabet,
+                    "nofabet" => PhonemeAlphabet::Nofabet,
+                    _ => return Err(invalid()),
+                };
+                parsed.syllabifier = Some(Arc::new(Syllabifier::builtin(alphabet)));
+            }
+            "stress_convention" | "stress_marks" => {
+                let convention = match value {
+                    "arpabet" => Some(StressConvention::Arpabet),
+                    "nofabet" => Some(StressConvention::Nofabet),
+                    "ipa" => Some(StressConvention::Ipa),
+                    "keep" | "strip" if key == "stress_marks" => None,
+                    _ => return Err(invalid()),
+                };
+                match (key.as_str(), convention) {
+                    ("stress_convention", convention) => parsed.stress_convention = convention,
+                    (_, Some(convention)) => parsed.stress_marking = StressMarking::Normalize(convention),
+                    _ if value == "strip" => parsed.stress_marking = StressMarking::Strip,
+                    _ => parsed.stress_marking = StressMarking::Keep,
+                }
+            }
+            "word_punctuation" => {
+                parsed.word_punctuation = match value {
+                    "as_is" => WordPunctuation::AsIs,
+                    "keep" => WordPunctuation::Keep,
+                    "strip" => WordPunctuation::Strip,
+                    "split" => WordPunctuation::Split { joiner: String::new() },
+                    _ => return Err(invalid()),
+                }
+            }
+            "punctuation_joiner" => joiner = Some(value.to_string()),
+            "tie" => parsed.separators.tie = value.to_string(),
+            "phoneme_separator" => parsed.separators.phoneme_separator = value.to_string(),
+            "length_normalization" => {
+                parsed.length_normalization = match value {
+                    "none" => LengthNormalization::None,
+                    "per_phoneme" => LengthNormalization::PerPhoneme,
+                    "per_grapheme" => LengthNormalization::PerGrapheme,
+                    _ => return Err(invalid()),
+                }
+            }
+            "semiring" => {
+                parsed.semiring = match value {
+                    "tropical" => ScoreSemiring::Tropical,
+                    "log" => ScoreSemiring::Log,
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(format!("Unknown option \"{}\"", key)),
+        }
+    }
+    if let Some(joiner) = joiner {
+        match &mut parsed.word_punctuation {
+            WordPunctuation::Split { joiner: split_joiner } => *split_joiner = joiner,
+            _ => return Err("Option \"punctuation_joiner\" requires \"word_punctuation\": \"split\"".to_string()),
+        }
+    }
+
+    Ok(parsed)
+}
@@ -0,0 +1,332 @@
+use clap::ValueEnum;
+use std::io::{self, Write};
+
+use super::columns::InputFormat;
+use super::json::Value;
+use super::log;
+use phonetisaurus_g2p_py::{
+    DecodeDiagnostics, MbrolaPhonemes, PhonemeIdMap, PhonetisaurusError, PhonetizationResult, ResultSource, Stress,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+/// How phonemization results are written.
+pub enum OutputFormat {
+    /// Only the phonemes, one pronunciation per line
+    Plain,
+    /// `word<TAB>phonemes`, one pronunciation per line
+    Tsv,
+    /// A single JSON array with one object per word
+    Json,
+    /// One JSON object per word and line
+    Jsonl,
+    /// MBROLA `.pho` lines of the best pronunciation, after a `; word` comment
+    Pho,
+    /// `word<TAB>score<TAB>phonemes`, one pronunciation per line, as phonetisaurus-apply
+    /// writes them with `--verbose`
+    Phonetisaurus,
+}
+
+/// Writes the results of a sequence of words in one of the [`OutputFormat`]s.
+///
+/// Failed words are reported on stderr for the text formats, and as objects with an
+/// `error` member for the JSON formats.
+pub struct ResultWriter<W: Write> {
+    out: W,
+    format: OutputFormat,
+    written: usize,
+    normalized_scores: bool,
+    phoneme_separator: String,
+    phoneme_ids: Option<PhonemeIdMap>,
+    mbrola: MbrolaPhonemes,
+}
+
+impl<W: Write> ResultWriter<W> {
+    pub fn new(out: W, format: OutputFormat) -> Self {
+        ResultWriter {
+            out,
+            format,
+            written: 0,
+            normalized_scores: false,
+            phoneme_separator: " ".to_string(),
+            phoneme_ids: None,
+            mbrola: MbrolaPhonemes::default(),
+        }
+    }
+
+    /// Include the normalized scores in the JSON formats.
+    pub fn with_normalized_scores(mut self, normalized_scores: bool) -> Self {
+        self.normalized_scores = normalized_scores;
+        self
+    }
+
+    /// Separator the phonemes were joined with, used to write syllabified results.
+    pub fn with_phoneme_separator(mut self, phoneme_separator: &str) -> Self {
+        self.phoneme_separator = phoneme_separator.to_string();
+        self
+    }
+
+    /// Write the phoneme IDs of this table instead of the phonemes in the text formats, and
+    /// add them to the JSON formats. The results must all have IDs in the table.
+    pub fn with_phoneme_ids(mut self, phoneme_ids: Option<PhonemeIdMap>) -> Self {
+        self.phoneme_ids = phoneme_ids;
+        self
+    }
+
+    /// Durations and stress handling of the pho format.
+    pub fn with_mbrola(mut self, mbrola: MbrolaPhonemes) -> Self {
+        self.mbrola = mbrola;
+        self
+    }
+
+    /// Write the pronunciations of one word, best first.
+    pub fn write(
+        &mut self,
+        word: &str,
+        results: &Result<Vec<PhonetizationResult>, PhonetisaurusError>,
+    ) -> io::Result<()> {
+        match self.format {
+            OutputFormat::Plain | OutputFormat::Tsv | OutputFormat::Phonetisaurus => match results {
+                Ok(results) => {
+                    for result in results {
+                        match self.format {
+                            OutputFormat::Tsv => write!(self.out, "{}\t", word)?,
+                            OutputFormat::Phonetisaurus => {
+                                write!(self.out, "{}\t{}\t", word, format_score(result.neg_log_score))?
+                            }
+                            _ => {}
+                        }
+                        match &self.phoneme_ids {
+                            Some(phoneme_ids) => writeln!(self.out, "{}", ids_text(result, phoneme_ids))?,
+                            None => writeln!(self.out, "{}", phonemes_text(result, &self.phoneme_separator))?,
+                        }
+                    }
+                }
+                Err(e) => word_failed(word, e),
+            },
+            OutputFormat::Json => {
+                let separator = if self.written == 0 { "[\n" } else { ",\n" };
+                let json = to_json(word, results, self.normalized_scores, self.phoneme_ids.as_ref());
+                write!(self.out, "{}{}", separator, json)?;
+            }
+            OutputFormat::Pho => match results.as_ref().map(|results| results.first()) {
+                Ok(Some(best)) => write!(self.out, "; {}\n{}", word, self.mbrola.result_pho(best))?,
+                Ok(None) => {}
+                Err(e) => word_failed(word, e),
+            },
+            OutputFormat::Jsonl => {
+                let json = to_json(word, results, self.normalized_scores, self.phoneme_ids.as_ref());
+                writeln!(self.out, "{}", json)?;
+            }
+        }
+        self.written += 1;
+
+        Ok(())
+    }
+
+    /// Write a row of delimited input with the best pronunciation of its word added as the last
+    /// field, which is left empty if there is none.
+    pub fn write_row(
+        &mut self,
+        row: &str,
+        input_format: InputFormat,
+        word: &str,
+        results: &Result<Vec<PhonetizationResult>, PhonetisaurusError>,
+    ) -> io::Result<()> {
+        let phonemes = match results {
+            Ok(results) => match (results.first(), &self.phoneme_ids) {
+                (Some(best), Some(phoneme_ids)) => ids_text(best, phoneme_ids),
+                (Some(best), None) => phonemes_text(best, &self.phoneme_separator),
+                (None, _) => String::new(),
+            },
+            Err(e) => {
+                word_failed(word, e);
+                String::new()
+            }
+        };
+        writeln!(self.out, "{}", input_format.append_field(row, &phonemes))?;
+        self.written += 1;
+
+        Ok(())
+    }
+
+    /// Write the header row of delimited input with a `phonemes` field added.
+    pub fn write_header_row(&mut self, row: &str, input_format: InputFormat) -> io::Result<()> {
+        writeln!(self.out, "{}", input_format.append_field(row, "phonemes"))
+    }
+
+    /// Flush what was written so far, e.g. to pass it on down a pipeline.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    /// Terminate the output and flush it.
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.format == OutputFormat::Json {
+            let opening = if self.written == 0 { "[" } else { "" };
+            writeln!(self.out, "{}\n]", opening)?;
+        }
+        self.out.flush()
+    }
+}
+
+/// Report a word that failed in a format without room for errors.
+fn word_failed(word: &str, error: &PhonetisaurusError) {
+    let message = format!("Failed to phonemize word '{}': {}", word, error);
+    log::warn(&message, [("word", word.into()), ("error", error.to_string().into())]);
+}
+
+/// A score as C++ streams write floats by default, like `printf("%g")` with 6 significant
+/// digits: `13.2651`, `0.5`, `1.5e+06`.
+fn format_score(score: f32) -> String {
+    if score == 0.0 || !score.is_finite() {
+        return score.to_string();
+    }
+    let scientific = format!("{:.5e}", score);
+    let (mantissa, exponent) = scientific.split_once('e').expect("formatted with an exponent");
+    let exponent: i32 = exponent.parse().expect("the exponent is an integer");
+    let trim = |number: &str| -> String {
+        match number.contains('.') {
+            true => number.trim_end_matches('0').trim_end_matches('.').to_string(),
+            false => number.to_string(),
+        }
+    };
+    if (-4..6).contains(&exponent) {
+        trim(&format!("{:.*}", (5 - exponent) as usize, score))
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", trim(mantissa), sign, exponent.abs())
+    }
+}
+
+/// Phonemes of a result for the text formats, with " . " between syllables if the result
+/// was syllabified.
+pub fn phonemes_text(result: &PhonetizationResult, phoneme_separator: &str) -> String {
+    if result.syllables.is_empty() {
+        return result.phonemes.clone();
+    }
+    let syllables: Vec<String> = result
+        .syllables
+        .iter()
+        .map(|syllable| syllable.join(phoneme_separator))
+        .collect();
+    syllables.join(" . ")
+}
+
+/// Phoneme IDs of a result for the text formats, separated by spaces.
+pub fn ids_text(result: &PhonetizationResult, phoneme_ids: &PhonemeIdMap) -> String {
+    let ids = phoneme_ids.result_ids(result).unwrap_or_default();
+    ids.iter().map(i64::to_string).collect::<Vec<_>>().join(" ")
+}
+
+/// JSON object of the pronunciations of one word, or of the error it failed with.
+pub fn to_json(
+    word: &str,
+    results: &Result<Vec<PhonetizationResult>, PhonetisaurusError>,
+    normalized_scores: bool,
+    phoneme_ids: Option<&PhonemeIdMap>,
+) -> Value {
+    let results = match results {
+        Ok(results) => results,
+        Err(e) => {
+            return Value::object([("word", word.into()), ("error", e.to_string().into())]);
+        }
+    };
+    let mut members = vec![("word", Value::from(word))];
+    if let Some((best, alternates)) = results.split_first() {
+        members.push(("phonemes", best.phonemes.as_str().into()));
+        members.push(("score", best.neg_log_score.into()));
+        members.push(("posterior", best.posterior.into()));
+        if normalized_scores {
+            members.push(("normalized_score", best.normalized_score.into()));
+        }
+        if !best.syllables.is_empty() {
+            members.push(("syllables", best.syllables.clone().into()));
+        }
+        if !best.stress.is_empty() {
+            members.push(("stress", stress_to_json(&best.stress)));
+        }
+        if !best.phoneme_posteriors.is_empty() {
+            members.push(("phoneme_posteriors", best.phoneme_posteriors.clone().into()));
+        }
+        if let Some(phoneme_ids) = phoneme_ids {
+            members.push(("phoneme_ids", phoneme_ids.result_ids(best).unwrap_or_default().into()));
+        }
+        if let Some(diagnostics) = &best.diagnostics {
+            members.push(("diagnostics", diagnostics_to_json(diagnostics)));
+        }
+        if !alternates.is_empty() {
+            let alternates = alternates
+                .iter()
+                .map(|alternate| {
+                    let mut members = vec![
+                        ("phonemes", alternate.phonemes.as_str().into()),
+                        ("score", alternate.neg_log_score.into()),
+                        ("posterior", alternate.posterior.into()),
+                    ];
+                    if normalized_scores {
+                        members.push(("normalized_score", alternate.normalized_score.into()));
+                    }
+                    if !alternate.syllables.is_empty() {
+                        members.push(("syllables", alternate.syllables.clone().into()));
+                    }
+                    if !alternate.stress.is_empty() {
+                        members.push(("stress", stress_to_json(&alternate.stress)));
+                    }
+                    if !alternate.phoneme_posteriors.is_empty() {
+                        members.push(("phoneme_posteriors", alternate.phoneme_posteriors.clone().into()));
+                    }
+                    if let Some(phoneme_ids) = phoneme_ids {
+                        members.push(("phoneme_ids", phoneme_ids.result_ids(alternate).unwrap_or_default().into()));
+                    }
+                    Value::object(members)
+                })
+                .collect::<Vec<_>>();
+            members.push(("alternates", alternates.into()));
+        }
+    }
+
+    Value::object(members)
+}
+
+/// Diagnostics as `{"source": "model", "skipped_chars": 0, "elapsed_ms": 0.4, "stages": [...]}`,
+/// with the time, states and arcs of each stage.
+fn diagnostics_to_json(diagnostics: &DecodeDiagnostics) -> Value {
+    let source = match diagnostics.source {
+        ResultSource::Model => "model",
+        ResultSource::Lexicon => "lexicon",
+        ResultSource::Cache => "cache",
+    };
+    let stages: Vec<Value> = diagnostics
+        .stages
+        .iter()
+        .map(|stage| {
+            Value::object([
+                ("stage", stage.stage.to_string().into()),
+                ("elapsed_ms", ((stage.elapsed.as_secs_f64() * 1000.0) as f32).into()),
+                ("states", stage.num_states.into()),
+                ("arcs", stage.num_arcs.into()),
+            ])
+        })
+        .collect();
+    Value::object([
+        ("source", source.into()),
+        ("skipped_chars", diagnostics.skipped_chars.into()),
+        ("elapsed_ms", ((diagnostics.elapsed().as_secs_f64() * 1000.0) as f32).into()),
+        ("stages", stages.into()),
+    ])
+}
+
+/// Stressed phonemes as `[{"position": 1, "level": "primary"}, ...]`.
+fn stress_to_json(stress: &[(usize, Stress)]) -> Value {
+    let stress: Vec<Value> = stress
+        .iter()
+        .map(|&(position, level)| {
+            let level = match level {
+                Stress::Primary => "primary",
+                Stress::Secondary => "secondary",
+            };
+            Value::object([("position", position.into()), ("level", level.into())])
+        })
+        .collect();
+    stress.into()
+}
@@ -0,0 +1,143 @@
+use clap::{Args, ValueEnum};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+use phonetisaurus_g2p_py::{CaseFold, ModelManifest, Normalization, PhonetisaurusModel, write_bundle};
+
+#[derive(Args)]
+pub struct PackArgs {
+    /// Path to the Phonetisaurus FST model file [default: $PHONETISAURUS_MODEL, or model_path
+    /// in the config file]
+    model_path: Option<String>,
+
+    /// Path of the bundle to write
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Manifest (JSON) to start from, the other options override its members
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+
+    /// Language tag of the words the model was trained on, e.g. en-US
+    #[arg(long)]
+    language: Option<String>,
+
+    /// Phoneme set of the pronunciations, e.g. arpabet or ipa
+    #[arg(long)]
+    phoneme_set: Option<String>,
+
+    /// Case of the words the model was trained on, applied to the words phonemized with it
+    #[arg(long, value_enum)]
+    case_fold: Option<CaseFoldArg>,
+
+    /// Unicode normalization of the words the model was trained on
+    #[arg(long, value_enum)]
+    normalize: Option<NormalizeArg>,
+
+    /// How the model was trained, e.g. the lexicon and the alignment settings
+    #[arg(long)]
+    training: Option<String>,
+
+    /// Version of the model
+    #[arg(long)]
+    model_version: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+/// Case mappings, named like the server options.
+pub enum CaseFoldArg {
+    None,
+    Lower,
+    Upper,
+    #[value(name = "turkic_lower")]
+    TurkicLower,
+    #[value(name = "turkic_upper")]
+    TurkicUpper,
+}
+
+impl CaseFoldArg {
+    pub fn case_fold(self) -> CaseFold {
+        match self {
+            CaseFoldArg::None => CaseFold::None,
+            CaseFoldArg::Lower => CaseFold::Lower,
+            CaseFoldArg::Upper => CaseFold::Upper,
+            CaseFoldArg::TurkicLower => CaseFold::TurkicLower,
+            CaseFoldArg::TurkicUpper => CaseFold::TurkicUpper,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+/// Unicode normalization forms.
+enum NormalizeArg {
+    None,
+    Nfc,
+    Nfd,
+}
+
+/// Bundle a model with a manifest describing how it is meant to be used.
+pub fn run(args: PackArgs) {
+    let model_path = super::require_model_path(args.model_path.as_deref());
+    let model_binary = match std::fs::read(&model_path) {
+        Ok(model_binary) => model_binary,
+        Err(e) => {
+            eprintln!("Failed to read model '{}': {}", model_path, e);
+            std::process::exit(1);
+        }
+    };
+    let model = match PhonetisaurusModel::try_from(model_binary.as_slice()) {
+        Ok(model) => model,
+        Err(e) => {
+            eprintln!("'{}' is not a valid model: {}", model_path, e);
+            std::process::exit(1);
+        }
+    };
+
+    // a bundle is packed again with its manifest as the starting point
+    let mut manifest = match &args.manifest {
+        Some(path) => {
+            let manifest = std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| ModelManifest::from_json(&json).map_err(|e| e.to_string()));
+            match manifest {
+                Ok(manifest) => manifest,
+                Err(e) => {
+                    eprintln!("Failed to read manifest '{}': {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => model.manifest().cloned().unwrap_or_default(),
+    };
+    if let Some(case_fold) = args.case_fold {
+        manifest.case_fold = Some(case_fold.case_fold());
+    }
+    if let Some(normalize) = args.normalize {
+        manifest.normalize = Some(match normalize {
+            NormalizeArg::None => Normalization::None,
+            NormalizeArg::Nfc => Normalization::Nfc,
+            NormalizeArg::Nfd => Normalization::Nfd,
+        });
+    }
+    manifest.language = args.language.or(manifest.language);
+    manifest.phoneme_set = args.phoneme_set.or(manifest.phoneme_set);
+    manifest.training = args.training.or(manifest.training);
+    manifest.version = args.model_version.or(manifest.version);
+
+    let model_binary = match model.manifest() {
+        Some(_) => match model.to_bytes() {
+            Ok(model_binary) => model_binary,
+            Err(e) => {
+                eprintln!("Failed to serialize model: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => model_binary,
+    };
+    let written = File::create(&args.output).and_then(|file| write_bundle(BufWriter::new(file), &model_binary, &manifest));
+    if let Err(e) = written {
+        eprintln!("Failed to write bundle '{}': {}", args.output.display(), e);
+        std::process::exit(1);
+    }
+}
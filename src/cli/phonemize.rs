@@ -0,0 +1,922 @@
+use clap::{Args, ValueEnum};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::{load_lexicon, load_model};
+use super::columns::InputFormat;
+use super::log::{self, LogArgs};
+use super::output::{OutputFormat, ResultWriter, ids_text, phonemes_text};
+use super::pack::CaseFoldArg;
+use super::progress::Progress;
+use super::resume;
+use phonetisaurus_g2p_py::*;
+
+#[derive(Args)]
+pub struct PhonemizeArgs {
+    /// Path to the Phonetisaurus FST model file [default: $PHONETISAURUS_MODEL, or model_path
+    /// in the config file]
+    // clap leaves the group of arguments with flattened ones empty, but the root command needs it
+    // to tell whether the phonemize arguments are given
+    #[arg(group = "PhonemizeArgs")]
+    model_path: Option<String>,
+    
+    /// Word to phonemize. If omitted, words are read line by line from stdin or --input
+    word: Option<String>,
+
+    /// File to read words from, one per line
+    #[arg(long, conflicts_with = "word")]
+    input: Option<PathBuf>,
+
+    /// File to write the results of a word list to [default: stdout]
+    #[arg(long, conflicts_with = "word")]
+    out: Option<PathBuf>,
+
+    /// How to read the words of a list: one per line, or a column of tab- or comma-separated
+    /// rows, which are written back with the best pronunciation added as a last column
+    #[arg(long, value_enum, default_value_t = InputFormat::Lines, conflicts_with_all = ["word", "format", "compat", "resume"])]
+    input_format: InputFormat,
+
+    /// Column of the words in tsv and csv rows, counted from 1
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    word_column: u64,
+
+    /// The first tsv or csv row is a header, written back with a `phonemes` column added
+    #[arg(long)]
+    header: bool,
+
+    /// Keep the results in the --out file of an interrupted run and skip the words it has,
+    /// appending the others. Needs --format tsv, jsonl or phonetisaurus
+    #[arg(long, requires = "out")]
+    resume: bool,
+
+    /// Output format [default: "Nofabet: <phonemes>" for a single word, tsv otherwise]
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Write the output like another tool, to take its place in existing scripts
+    #[arg(long, value_enum, conflicts_with = "format")]
+    compat: Option<Compat>,
+
+    /// Number of distinct pronunciations to produce per word
+    #[arg(long, default_value_t = 1)]
+    nbest: usize,
+
+    /// Exception lexicon (CMUdict format) whose pronunciations override the model
+    #[arg(long)]
+    lexicon: Option<PathBuf>,
+
+    /// Transducer (binary FST) applied to the pronunciations of the model, e.g. a dialect
+    /// adaptation. Can be given several times, the transducers are applied in order
+    #[arg(long, value_name = "PATH")]
+    post_fst: Vec<PathBuf>,
+
+    /// Add a length-normalized score to the JSON formats
+    #[arg(long, value_enum)]
+    length_normalization: Option<ScoreNormalization>,
+
+    /// Score pronunciations by their best alignment (tropical), or by all of them (log),
+    /// which also adds the posterior of each phoneme to the JSON formats
+    #[arg(long, value_enum, default_value_t = SemiringArg::Tropical)]
+    semiring: SemiringArg,
+
+    /// Keep at most this many lattice states per input character
+    #[arg(long)]
+    beam: Option<usize>,
+
+    /// Keep at most this many lattice states in total
+    #[arg(long)]
+    max_states: Option<usize>,
+
+    /// Drop lattice paths worse than the best one by more than this weight
+    #[arg(long)]
+    prune_weight: Option<f32>,
+
+    /// Give up on a word after this many milliseconds, checked between the decoding stages.
+    /// Without pruning options, also prunes with --prune-weight 20
+    #[arg(long)]
+    timeout_ms: Option<u64>,
+
+    /// Reject words longer than this many characters
+    #[arg(long)]
+    max_word_length: Option<usize>,
+
+    /// Give up on a word whose decoding lattice has more states than this
+    #[arg(long)]
+    max_lattice_states: Option<usize>,
+
+    /// What to do with hyphens and apostrophes inside words, like in "don't"
+    #[arg(long, value_enum)]
+    word_punctuation: Option<WordPunctuationArg>,
+
+    /// Phoneme token put between the parts of a word split at punctuation, for
+    /// --word-punctuation split
+    #[arg(long, default_value = "")]
+    punctuation_joiner: String,
+
+    /// Split words like `read#VBD` at this char into the word and a POS or sense tag, which
+    /// selects `WORD#TAG` lexicon entries and is read by models trained with tags
+    #[arg(long, value_name = "CHAR")]
+    tag_separator: Option<char>,
+
+    /// Output symbols that stand for silent graphemes [default: _]
+    #[arg(long, value_delimiter = ',')]
+    skip_symbols: Option<Vec<String>>,
+
+    /// Tie between the phonemes of multi-phoneme output symbols, removed from the output
+    #[arg(long, default_value = "|")]
+    tie_symbol: String,
+
+    /// Separator between the phonemes of a pronunciation
+    #[arg(long, default_value = " ")]
+    phoneme_separator: String,
+
+    /// Convert the phonemes of the model to this phoneme set
+    #[arg(long, value_enum)]
+    phoneme_set: Option<PhonemeSet>,
+
+    /// Write the pronunciations of --phoneme-set espeak as `[[...]]`, the notation eSpeak-ng
+    /// reads phonemes in
+    #[arg(long)]
+    espeak_brackets: bool,
+
+    /// Split pronunciations into syllables, with the built-in table of --model-phonemes
+    #[arg(long)]
+    syllabify: bool,
+
+    /// Split pronunciations into syllables with a phoneme classification table
+    #[arg(long, conflicts_with = "syllabify")]
+    syllable_classes: Option<PathBuf>,
+
+    /// How the model marks stress, adds the stressed phonemes to the JSON formats
+    /// [default: --model-phonemes if --stress-marks is given]
+    #[arg(long, value_enum)]
+    stress_convention: Option<StressMarks>,
+
+    /// Strip the stress markers, or rewrite them in another convention
+    #[arg(long, value_enum)]
+    stress_marks: Option<StressMarks>,
+
+    /// Phoneme inventory of the model, for --phoneme-set, --syllabify and --stress-marks
+    #[arg(long, value_enum, default_value_t = ModelPhonemes::Nofabet)]
+    model_phonemes: ModelPhonemes,
+
+    /// Case mapping applied to the words before decoding, instead of the one of the model bundle
+    #[arg(long, value_enum)]
+    case_fold: Option<CaseFoldArg>,
+
+    /// Convert phonemes with a mapping table instead (TSV, or JSON if the name ends in .json)
+    #[arg(long, conflicts_with = "phoneme_set")]
+    phoneme_map: Option<PathBuf>,
+
+    /// Write phoneme IDs for a TTS voice instead of the phonemes, with a table of IDs like
+    /// the config of a piper voice (JSON if the name ends in .json, TSV otherwise). Applied
+    /// after --phoneme-set or --phoneme-map
+    #[arg(long, value_name = "PATH")]
+    phoneme_ids: Option<PathBuf>,
+
+    /// Duration of the phonemes in --format pho, in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 80)]
+    pho_duration: u32,
+
+    /// Durations of single phonemes in --format pho, as `phoneme<TAB>milliseconds` lines
+    #[arg(long, value_name = "PATH")]
+    pho_durations: Option<PathBuf>,
+
+    /// Duration of the silence around words in --format pho, in milliseconds, 0 for none
+    #[arg(long, value_name = "MS", default_value_t = 100)]
+    pho_pause: u32,
+
+    /// Keep the pronunciations in this file and reuse them in later runs with the same model
+    /// and options
+    #[arg(long, value_name = "PATH")]
+    cache_file: Option<PathBuf>,
+
+    /// Do not reuse pronunciations cached longer ago than this many seconds
+    #[arg(long, value_name = "SECONDS", requires = "cache_file")]
+    cache_ttl: Option<u64>,
+
+    /// Keep at most this many pronunciations in the cache file, dropping the oldest
+    #[arg(long, value_name = "WORDS", requires = "cache_file")]
+    cache_max_entries: Option<usize>,
+
+    /// Print the model load time and the time and lattice size of every decoding stage of
+    /// every word to stderr, as tab-separated lines
+    #[arg(long)]
+    trace: bool,
+
+    /// Add the decoding stages, the skipped characters and the source of the pronunciations of
+    /// every word to the JSON formats
+    #[arg(long)]
+    diagnostics: bool,
+
+    /// Threads decoding the words of a list, which are written in input order; 0 for one per
+    /// CPU
+    #[arg(long, short = 'j', value_name = "THREADS", default_value_t = 1)]
+    jobs: usize,
+
+    /// Show the words done, their rate and the time left on stderr while phonemizing a list
+    #[arg(long)]
+    progress: bool,
+
+    /// Decode each distinct word of a list once and repeat its pronunciations where it occurs
+    /// again. The pronunciations of all distinct words are kept in memory
+    #[arg(long)]
+    dedup: bool,
+
+    /// Decode a list word by word and flush the results of each before reading the next line,
+    /// to sit in a pipeline processing an endless stream. Ignores --jobs
+    #[arg(long, conflicts_with = "dedup")]
+    unbuffered: bool,
+
+    #[command(flatten)]
+    log: LogArgs,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+/// Tools whose output can be imitated.
+enum Compat {
+    /// phonetisaurus-apply with --verbose, i.e. --format phonetisaurus
+    Phonetisaurus,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+/// Phoneme set the output is converted to.
+enum PhonemeSet {
+    Ipa,
+    XSampa,
+    /// eSpeak-ng phoneme mnemonics, written without separators and with stress like
+    /// `espeak-ng -x`
+    Espeak,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+/// Stress conventions, and what to do with stress markers.
+enum StressMarks {
+    /// Keep the markers (only for --stress-marks)
+    Keep,
+    /// Remove the markers (only for --stress-marks)
+    Strip,
+    /// Digits 0 to 2 after vowels
+    Arpabet,
+    /// Digits 0 to 3 after vowels and syllabic consonants
+    Nofabet,
+    /// ˈ and ˌ before stressed phonemes
+    Ipa,
+}
+
+impl StressMarks {
+    fn convention(self) -> Option<StressConvention> {
+        match self {
+            StressMarks::Keep | StressMarks::Strip => None,
+            StressMarks::Arpabet => Some(StressConvention::Arpabet),
+            StressMarks::Nofabet => Some(StressConvention::Nofabet),
+            StressMarks::Ipa => Some(StressConvention::Ipa),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+/// Phoneme inventory a model was trained with.
+pub enum ModelPhonemes {
+    Arpabet,
+    Nofabet,
+}
+
+impl ModelPhonemes {
+    pub fn alphabet(self) -> PhonemeAlphabet {
+        match self {
+            ModelPhonemes::Arpabet => PhonemeAlphabet::Arpabet,
+            ModelPhonemes::Nofabet => PhonemeAlphabet::Nofabet,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+/// Length the normalized score is divided by.
+enum ScoreNormalization {
+    /// Number of phonemes of the pronunciation
+    PerPhoneme,
+    /// Number of characters of the word
+    PerGrapheme,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+/// How the alignments of a pronunciation are scored.
+enum SemiringArg {
+    /// The best alignment, like phonetisaurus
+    Tropical,
+    /// The sum of all alignments
+    Log,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+/// Handling of hyphens and apostrophes inside words.
+enum WordPunctuationArg {
+    /// Look them up like letters
+    AsIs,
+    /// Keep those the model knows, drop the others
+    Keep,
+    /// Drop them
+    Strip,
+    /// Phonemize the parts between them separately and join them with --punctuation-joiner
+    Split,
+}
+
+/// Phonemize a single word, or a list of words from stdin or a file.
+pub fn run(mut args: PhonemizeArgs) {
+    args.log.init();
+    if let Some(Compat::Phonetisaurus) = args.compat {
+        args.format = Some(OutputFormat::Phonetisaurus);
+    }
+    let load_start = Instant::now();
+    let mut phonemizer = load_model(args.model_path.as_deref());
+    if args.trace {
+        eprintln!("trace\t\tload\t{:.3}\t{}\t{}", millis(load_start.elapsed()), phonemizer.num_states(), phonemizer.num_arcs());
+    }
+    if let Some(lexicon_path) = &args.lexicon {
+        phonemizer = phonemizer.with_lexicon(load_lexicon(lexicon_path));
+    }
+    for post_fst_path in &args.post_fst {
+        phonemizer = match phonemizer.with_post_fst(post_fst_path) {
+            Ok(phonemizer) => phonemizer,
+            Err(e) => {
+                log::failed_on("Failed to load post-transducer from", post_fst_path, &e);
+                std::process::exit(1);
+            }
+        };
+    }
+
+    let alphabet = args.model_phonemes.alphabet();
+    let mapper = match (&args.phoneme_map, args.phoneme_set) {
+        (Some(path), _) => match PhonemeMapper::try_from(path.as_path()) {
+            Ok(mapper) => Some(mapper),
+            Err(e) => {
+                log::failed_on("Failed to load phoneme map from", path, &e);
+                std::process::exit(1);
+            }
+        },
+        (None, Some(PhonemeSet::Espeak)) => None,
+        (None, Some(phoneme_set)) => {
+            Some(match phoneme_set {
+                PhonemeSet::Ipa => PhonemeMapper::to_ipa(alphabet),
+                PhonemeSet::XSampa => PhonemeMapper::to_xsampa(alphabet),
+                PhonemeSet::Espeak => unreachable!("eSpeak-ng mnemonics are converted with the stress"),
+            })
+        }
+        (None, None) => None,
+    };
+    let mapper = mapper.as_ref();
+    let phoneme_ids = args.phoneme_ids.as_ref().map(|path| match PhonemeIdMap::try_from(path.as_path()) {
+        Ok(phoneme_ids) => phoneme_ids,
+        Err(e) => {
+            log::failed_on("Failed to load phoneme ID map from", path, &e);
+            std::process::exit(1);
+        }
+    });
+    let syllabifier = match &args.syllable_classes {
+        Some(path) => match Syllabifier::try_from(path.as_path()) {
+            Ok(syllabifier) => Some(Arc::new(syllabifier)),
+            Err(e) => {
+                log::failed_on("Failed to load syllabification table from", path, &e);
+                std::process::exit(1);
+            }
+        },
+        None if args.syllabify => Some(Arc::new(Syllabifier::builtin(alphabet))),
+        None => None,
+    };
+
+    let stress_convention = match (args.stress_convention, args.stress_marks) {
+        (Some(convention), _) => match convention.convention() {
+            Some(convention) => Some(convention),
+            None => {
+                log::error("--stress-convention must be arpabet, nofabet or ipa", []);
+                std::process::exit(1);
+            }
+        },
+        (None, Some(_)) => Some(match alphabet {
+            PhonemeAlphabet::Arpabet => StressConvention::Arpabet,
+            PhonemeAlphabet::Nofabet => StressConvention::Nofabet,
+        }),
+        (None, None) => None,
+    };
+    let stress_marking = match args.stress_marks {
+        None | Some(StressMarks::Keep) => StressMarking::Keep,
+        Some(StressMarks::Strip) => StressMarking::Strip,
+        Some(marks) => StressMarking::Normalize(marks.convention().unwrap_or(StressConvention::Ipa)),
+    };
+
+    if args.espeak_brackets && args.phoneme_set != Some(PhonemeSet::Espeak) {
+        log::error("--espeak-brackets requires --phoneme-set espeak", []);
+        std::process::exit(1);
+    }
+    // the stress of the tokens as the stress marking leaves them
+    let marked_stress = match stress_marking {
+        StressMarking::Keep => Some(stress_convention.unwrap_or(match alphabet {
+            PhonemeAlphabet::Arpabet => StressConvention::Arpabet,
+            PhonemeAlphabet::Nofabet => StressConvention::Nofabet,
+        })),
+        StressMarking::Strip => None,
+        StressMarking::Normalize(convention) => Some(convention),
+    };
+    let espeak = (args.phoneme_set == Some(PhonemeSet::Espeak) && args.phoneme_map.is_none()).then(|| {
+        EspeakPhonemes::with_mapper(PhonemeMapper::to_espeak(alphabet), marked_stress)
+            .with_brackets(args.espeak_brackets)
+    });
+    let pho_durations = match &args.pho_durations {
+        Some(path) => read_durations(path).unwrap_or_else(|e| {
+            log::failed_on("Failed to read phoneme durations from", path, &e);
+            std::process::exit(1);
+        }),
+        None => Vec::new(),
+    };
+    let mbrola = MbrolaPhonemes::new()
+        // converted tokens only keep the markers of IPA stress
+        .with_stress_convention(marked_stress.filter(|&stress| mapper.is_none() || stress == StressConvention::Ipa))
+        .with_default_duration(args.pho_duration)
+        .with_durations(pho_durations)
+        .with_pause(args.pho_pause);
+    let converter = Converter {
+        mapper,
+        espeak: espeak.as_ref(),
+        phoneme_ids: phoneme_ids.as_ref(),
+        mbrola,
+    };
+
+    let mut options = PhonemizeOptions {
+        length_normalization: match args.length_normalization {
+            None => LengthNormalization::None,
+            Some(ScoreNormalization::PerPhoneme) => LengthNormalization::PerPhoneme,
+            Some(ScoreNormalization::PerGrapheme) => LengthNormalization::PerGrapheme,
+        },
+        semiring: match args.semiring {
+            SemiringArg::Tropical => ScoreSemiring::Tropical,
+            SemiringArg::Log => ScoreSemiring::Log,
+        },
+        beam: args.beam,
+        max_states: args.max_states,
+        prune_weight: args.prune_weight,
+        timeout: args.timeout_ms.map(Duration::from_millis),
+        max_word_length: args.max_word_length,
+        max_lattice_states: args.max_lattice_states,
+        word_punctuation: match args.word_punctuation {
+            None | Some(WordPunctuationArg::AsIs) => WordPunctuation::AsIs,
+            Some(WordPunctuationArg::Keep) => WordPunctuation::Keep,
+            Some(WordPunctuationArg::Strip) => WordPunctuation::Strip,
+            Some(WordPunctuationArg::Split) => WordPunctuation::Split {
+                joiner: args.punctuation_joiner,
+            },
+        },
+        tag_separator: args.tag_separator,
+        separators: PhonemeSeparators {
+            skip_symbols: args.skip_symbols.unwrap_or_else(|| PhonemeSeparators::default().skip_symbols),
+            tie: args.tie_symbol,
+            phoneme_separator: args.phoneme_separator,
+        },
+        syllabifier,
+        stress_convention,
+        stress_marking,
+        diagnostics: args.diagnostics,
+        tracer: args.trace.then(|| {
+            Tracer::new(|trace: &StageTrace<'_>| {
+                eprintln!(
+                    "trace\t{}\t{}\t{:.3}\t{}\t{}",
+                    trace.word,
+                    trace.stage,
+                    millis(trace.elapsed),
+                    trace.num_states,
+                    trace.num_arcs
+                );
+            })
+        }),
+        // the case and normalization the model was trained with, if it comes in a bundle
+        ..phonemizer.manifest().map(ModelManifest::options).unwrap_or_default()
+    };
+    if let Some(case_fold) = args.case_fold {
+        options.case_fold = case_fold.case_fold();
+    }
+
+    let disk_cache = args.cache_file.as_ref().map(|path| {
+        let mut cache = match DiskCache::open(path) {
+            Ok(cache) => cache,
+            Err(e) => {
+                log::failed_on("Failed to open cache", path, &e);
+                std::process::exit(1);
+            }
+        };
+        if let Some(ttl) = args.cache_ttl {
+            cache = cache.with_ttl(Duration::from_secs(ttl));
+        }
+        if let Some(max_entries) = args.cache_max_entries {
+            cache = cache.with_max_entries(max_entries);
+        }
+        match phonemizer.clone().with_disk_cache(cache) {
+            Ok(cached) => cached,
+            Err(e) => {
+                log::failed_on("Failed to open cache", path, &e);
+                std::process::exit(1);
+            }
+        }
+    });
+    let disk_cache = disk_cache.as_ref();
+
+    let Some(word) = args.word else {
+        // Phonemize a list of words
+        let reader: Box<dyn BufRead> = match &args.input {
+            Some(path) => match File::open(path) {
+                Ok(file) => Box::new(BufReader::new(file)),
+                Err(e) => {
+                    log::failed_on("Failed to open input file", path, &e);
+                    std::process::exit(1);
+                }
+            },
+            None => Box::new(io::stdin().lock()),
+        };
+        let format = args.format.unwrap_or(OutputFormat::Tsv);
+        let mut done_words = HashSet::new();
+        let out: Box<dyn Write> = match &args.out {
+            Some(path) if args.resume => match resume::open_resumed(path, format) {
+                Ok((file, words)) => {
+                    let message = format!("Resuming after {} words in '{}'", words.len(), path.display());
+                    log::info(&message, [("path", path.display().to_string().into()), ("words", words.len().into())]);
+                    done_words = words;
+                    Box::new(file)
+                }
+                Err(e) => {
+                    log::failed_on("Failed to resume from", path, &e);
+                    std::process::exit(1);
+                }
+            },
+            Some(path) => match File::create(path) {
+                Ok(file) => Box::new(file),
+                Err(e) => {
+                    log::failed_on("Failed to create output file", path, &e);
+                    std::process::exit(1);
+                }
+            },
+            None => Box::new(io::stdout().lock()),
+        };
+        let processing = ListProcessing {
+            jobs: match args.jobs {
+                0 => std::thread::available_parallelism().map_or(1, usize::from),
+                jobs => jobs,
+            },
+            dedup: args.dedup,
+            unbuffered: args.unbuffered,
+            done_words,
+            columns: (args.input_format != InputFormat::Lines).then_some(Columns {
+                input_format: args.input_format,
+                word_column: args.word_column as usize - 1,
+                header: args.header,
+            }),
+            // counting the words of a file first is quick next to decoding them
+            progress: args.progress.then(|| {
+                let total = args.input.as_deref().and_then(count_words);
+                let header = args.header && args.input_format != InputFormat::Lines;
+                Progress::new(total.map(|total| total.saturating_sub(usize::from(header))))
+            }),
+        };
+        let processed = phonemize_lines(
+            &phonemizer,
+            disk_cache,
+            reader,
+            out,
+            format,
+            args.nbest,
+            &options,
+            &converter,
+            processing,
+        );
+        flush_cache(disk_cache);
+        match processed {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                log::failed("Failed to process words", &e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    };
+
+    let Some(format) = args.format else {
+        // Phonemize the word, printing each pronunciation on its own line, best first
+        let results = phonemize(&phonemizer, disk_cache, &word, args.nbest, &options, &converter);
+        flush_cache(disk_cache);
+        match results {
+            Ok(results) => {
+                if let Some(phoneme_ids) = &phoneme_ids {
+                    for result in &results {
+                        println!("IDs: {}", ids_text(result, phoneme_ids));
+                    }
+                    return;
+                }
+                let label = match (args.phoneme_set, mapper) {
+                    (Some(PhonemeSet::Ipa), _) => "IPA",
+                    (Some(PhonemeSet::XSampa), _) => "X-SAMPA",
+                    (Some(PhonemeSet::Espeak), _) => "eSpeak",
+                    (None, Some(_)) => "Phonemes",
+                    (None, None) => "Nofabet",
+                };
+                for result in &results {
+                    println!("{}: {}", label, phonemes_text(result, &options.separators.phoneme_separator));
+                }
+            }
+            Err(e) => {
+                log::failed("Failed to phonemize word", &e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    };
+
+    let results = phonemize(&phonemizer, disk_cache, &word, args.nbest, &options, &converter);
+    flush_cache(disk_cache);
+    let mut writer = ResultWriter::new(io::stdout().lock(), format)
+        .with_normalized_scores(args.length_normalization.is_some())
+        .with_phoneme_separator(&options.separators.phoneme_separator)
+        .with_phoneme_ids(phoneme_ids.clone())
+        .with_mbrola(converter.mbrola);
+    if let Err(e) = writer.write(&word, &results).and_then(|_| writer.finish()) {
+        log::failed("Failed to write result", &e);
+        std::process::exit(1);
+    }
+    if results.is_err() {
+        std::process::exit(1);
+    }
+}
+
+/// How the phonemes of the model are converted for the output.
+struct Converter<'a> {
+    mapper: Option<&'a PhonemeMapper>,
+    espeak: Option<&'a EspeakPhonemes>,
+    /// Table the converted phonemes must have IDs in.
+    phoneme_ids: Option<&'a PhonemeIdMap>,
+    /// Durations of the pho format.
+    mbrola: MbrolaPhonemes,
+}
+
+/// Phonemize a word, producing `nbest` pronunciations if more than one is requested, and
+/// convert the phonemes with `converter`. Words are looked up in `disk_cache` first, if given.
+fn phonemize(
+    phonemizer: &PhonetisaurusModel,
+    disk_cache: Option<&DiskCachedModel>,
+    word: &str,
+    nbest: usize,
+    options: &PhonemizeOptions,
+    converter: &Converter<'_>,
+) -> Result<Vec<PhonetizationResult>> {
+    let results = match disk_cache {
+        Some(cached) => cached.phonemize_word_nbest_with_options(word, nbest, options)?,
+        None => phonemizer.phonemize_word_nbest_with_options(word, nbest, options)?,
+    };
+    convert(results, options, converter)
+}
+
+/// Phonemize words on up to `jobs` threads like [`phonemize`] does one, keeping their order.
+/// Empty words, of rows without one, keep their place with no pronunciations.
+fn phonemize_words(
+    phonemizer: &PhonetisaurusModel,
+    disk_cache: Option<&DiskCachedModel>,
+    words: &[&str],
+    jobs: usize,
+    nbest: usize,
+    options: &PhonemizeOptions,
+    converter: &Converter<'_>,
+) -> Vec<Result<Vec<PhonetizationResult>>> {
+    let non_empty: Vec<&str> = words.iter().copied().filter(|word| !word.is_empty()).collect();
+    let decoded = match disk_cache {
+        Some(cached) => cached.phonemize_words_nbest_with_threads(&non_empty, nbest, options, jobs),
+        None => phonemizer.phonemize_words_nbest_with_threads(&non_empty, nbest, options, jobs),
+    };
+    let mut decoded = decoded.into_iter();
+    words
+        .iter()
+        .map(|word| match *word {
+            "" => Ok(Vec::new()),
+            _ => decoded.next().expect("one result per word").and_then(|results| convert(results, options, converter)),
+        })
+        .collect()
+}
+
+/// Convert the phonemes of the results of a word with `converter`.
+fn convert(
+    mut results: Vec<PhonetizationResult>,
+    options: &PhonemizeOptions,
+    converter: &Converter<'_>,
+) -> Result<Vec<PhonetizationResult>> {
+    if let Some(mapper) = converter.mapper {
+        results.iter_mut().for_each(|result| mapper.map_result(result, &options.separators));
+    }
+    if let Some(espeak) = converter.espeak {
+        results.iter_mut().for_each(|result| espeak.map_result(result));
+    }
+    if let Some(phoneme_ids) = converter.phoneme_ids {
+        // fail the word here, so that the output only has IDs of convertible results
+        for result in &results {
+            phoneme_ids.result_ids(result)?;
+        }
+    }
+    Ok(results)
+}
+
+/// How the words of a list are processed.
+struct ListProcessing {
+    /// Threads decoding the words.
+    jobs: usize,
+    /// Decode each distinct word once.
+    dedup: bool,
+    /// Write the results of each word before reading the next.
+    unbuffered: bool,
+    /// Words to skip, as their results were written by an earlier run.
+    done_words: HashSet<String>,
+    /// Where the words are in rows of delimited input, `None` for a word per line.
+    columns: Option<Columns>,
+    progress: Option<Progress>,
+}
+
+/// The column of the words in rows of delimited input.
+struct Columns {
+    input_format: InputFormat,
+    /// Counted from 0.
+    word_column: usize,
+    /// Whether the first row is a header.
+    header: bool,
+}
+
+/// Phonemize every non-empty line of `reader` as set by `processing`, writing the results to
+/// `out` in input order. With columns, every row is written back with its phonemes.
+///
+/// Words are read and decoded in batches, so memory use does not grow with the input unless
+/// distinct words are kept to decode them once. Words that fail do not stop the processing.
+/// Returns whether all words succeeded.
+#[allow(clippy::too_many_arguments)]
+fn phonemize_lines(
+    phonemizer: &PhonetisaurusModel,
+    disk_cache: Option<&DiskCachedModel>,
+    reader: impl BufRead,
+    out: impl Write,
+    format: OutputFormat,
+    nbest: usize,
+    options: &PhonemizeOptions,
+    converter: &Converter<'_>,
+    mut processing: ListProcessing,
+) -> io::Result<bool> {
+    let mut writer = ResultWriter::new(BufWriter::new(out), format)
+        .with_normalized_scores(options.length_normalization != LengthNormalization::None)
+        .with_phoneme_separator(&options.separators.phoneme_separator)
+        .with_phoneme_ids(converter.phoneme_ids.cloned())
+        .with_mbrola(converter.mbrola.clone());
+    let mut all_ok = true;
+    let jobs = processing.jobs;
+    let batch_size = if jobs > 1 && !processing.unbuffered { jobs * BATCH_WORDS_PER_JOB } else { 1 };
+    let decode = |words: &[&str]| phonemize_words(phonemizer, disk_cache, words, jobs, nbest, options, converter);
+    // the pronunciations of every distinct word so far, with --dedup
+    let mut decoded: HashMap<String, Result<Vec<PhonetizationResult>>> = HashMap::new();
+    let mut lines = reader.lines().enumerate();
+    if let Some(columns) = processing.columns.as_ref().filter(|columns| columns.header)
+        && let Some((_, row)) = lines.next()
+    {
+        writer.write_header_row(row?.trim_end_matches('\r'), columns.input_format)?;
+    }
+    // words with their rows if read from columns
+    let mut batch: Vec<(String, Option<String>)> = Vec::with_capacity(batch_size);
+
+    loop {
+        batch.clear();
+        let mut skipped = 0;
+        for (line_number, line) in lines.by_ref() {
+            let line = line?;
+            match &processing.columns {
+                Some(columns) => {
+                    let row = line.trim_end_matches('\r');
+                    let word = columns.input_format.field(row, columns.word_column).unwrap_or_else(|| {
+                        let message = format!("Line {} has no column {}", line_number + 1, columns.word_column + 1);
+                        log::warn(&message, [("line", (line_number + 1).into())]);
+                        String::new()
+                    });
+                    batch.push((word.trim().to_string(), Some(row.to_string())));
+                }
+                None => {
+                    let word = line.trim();
+                    if processing.done_words.contains(word) {
+                        skipped += 1;
+                        continue;
+                    }
+                    if word.is_empty() {
+                        continue;
+                    }
+                    batch.push((word.to_string(), None));
+                }
+            }
+            if batch.len() == batch_size {
+                break;
+            }
+        }
+        if let Some(progress) = &mut processing.progress {
+            progress.skip(skipped);
+        }
+        if batch.is_empty() {
+            break;
+        }
+        if processing.dedup {
+            let mut seen = HashSet::new();
+            let new_words: Vec<String> = batch
+                .iter()
+                .map(|(word, _)| word)
+                .filter(|word| !decoded.contains_key(*word) && seen.insert(word.as_str()))
+                .cloned()
+                .collect();
+            let results = decode(&new_words.iter().map(String::as_str).collect::<Vec<_>>());
+            decoded.extend(new_words.into_iter().zip(results));
+            for (word, row) in &batch {
+                let results = &decoded[word];
+                all_ok &= results.is_ok();
+                write_list_result(&mut writer, processing.columns.as_ref(), word, row.as_deref(), results)?;
+            }
+        } else {
+            let results = decode(&batch.iter().map(|(word, _)| word.as_str()).collect::<Vec<_>>());
+            for ((word, row), results) in batch.iter().zip(&results) {
+                all_ok &= results.is_ok();
+                write_list_result(&mut writer, processing.columns.as_ref(), word, row.as_deref(), results)?;
+            }
+        }
+        if processing.unbuffered {
+            writer.flush()?;
+        }
+        if let Some(progress) = &mut processing.progress {
+            progress.advance(batch.len());
+        }
+    }
+    writer.finish()?;
+    if let Some(progress) = processing.progress {
+        progress.finish();
+    }
+
+    Ok(all_ok)
+}
+
+/// Write the results of a word of a list, or its row if it was read from columns.
+fn write_list_result(
+    writer: &mut ResultWriter<impl Write>,
+    columns: Option<&Columns>,
+    word: &str,
+    row: Option<&str>,
+    results: &Result<Vec<PhonetizationResult>>,
+) -> io::Result<()> {
+    match (columns, row) {
+        (Some(columns), Some(row)) => writer.write_row(row, columns.input_format, word, results),
+        _ => writer.write(word, results),
+    }
+}
+
+/// Words read per thread before a batch is decoded, enough to keep the threads busy while
+/// the slowest words of the batch finish.
+const BATCH_WORDS_PER_JOB: usize = 256;
+
+/// Number of non-empty lines of a file, `None` if it cannot be read.
+fn count_words(path: &Path) -> Option<usize> {
+    let mut count = 0;
+    for line in BufReader::new(File::open(path).ok()?).lines() {
+        count += usize::from(!line.ok()?.trim().is_empty());
+    }
+    Some(count)
+}
+
+/// Compact the cache file if needed, which would not happen on exit through
+/// `std::process::exit`.
+fn flush_cache(disk_cache: Option<&DiskCachedModel>) {
+    if let Some(Err(e)) = disk_cache.map(|cached| cached.cache().flush()) {
+        log::failed("Failed to write cache", &e);
+    }
+}
+
+/// Read `phoneme<TAB>milliseconds` lines, skipping empty lines and lines starting with `#`.
+fn read_durations(path: &Path) -> io::Result<Vec<(String, u32)>> {
+    let mut durations = Vec::new();
+    for (line_number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next().map(str::parse), fields.next()) {
+            (Some(phoneme), Some(Ok(duration)), None) => durations.push((phoneme.to_string(), duration)),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Line {} is not a phoneme and its duration: {}", line_number + 1, line),
+                ));
+            }
+        }
+    }
+    Ok(durations)
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,66 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A fixed number of threads running jobs from a shared queue, so that requests decoding many
+/// words at once do not start more threads than there are cores.
+pub struct WorkerPool {
+    jobs: Mutex<Sender<Job>>,
+}
+
+impl WorkerPool {
+    pub fn new(threads: usize) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        for _ in 0..threads.max(1) {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || {
+                loop {
+                    // the lock is only held while waiting for the next job
+                    let job = queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).recv();
+                    let Ok(job) = job else {
+                        return;
+                    };
+                    // a job that panics loses its result, not the worker
+                    let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                }
+            });
+        }
+        WorkerPool { jobs: Mutex::new(jobs) }
+    }
+
+    /// Apply `f` to every item on the workers, keeping the input order.
+    ///
+    /// Returns `None` if `f` panicked for an item.
+    pub fn map<T, R, F>(&self, items: Vec<T>, f: F) -> Option<Vec<R>>
+    where
+        T: Send + 'static,
+        R: Send + 'static,
+        F: Fn(T) -> R + Send + Sync + 'static,
+    {
+        let count = items.len();
+        let f = Arc::new(f);
+        let (results, received) = mpsc::channel();
+        {
+            let jobs = self.jobs.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            for (index, item) in items.into_iter().enumerate() {
+                let f = Arc::clone(&f);
+                let results = results.clone();
+                let job: Job = Box::new(move || {
+                    let _ = results.send((index, f(item)));
+                });
+                jobs.send(job).expect("the workers run as long as the pool");
+            }
+        }
+        // the senders of jobs that panicked are dropped, which ends the iteration early
+        drop(results);
+        let mut ordered: Vec<Option<R>> = (0..count).map(|_| None).collect();
+        for (index, result) in received {
+            ordered[index] = Some(result);
+        }
+        ordered.into_iter().collect()
+    }
+}
@@ -0,0 +1,95 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Time between two redraws of the progress line.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Width of the bar in characters.
+const BAR_WIDTH: usize = 30;
+
+/// A progress line on stderr for long word lists, redrawn in place as words are done, like
+/// `[#######          ]  24% 480000/2000000 words, 9120 words/s, 2m46s left`. Without a
+/// known total, only the count and the rate are shown.
+pub struct Progress {
+    total: Option<usize>,
+    done: usize,
+    /// Words counted as done without being decoded, left out of the rate.
+    skipped: usize,
+    start: Instant,
+    drawn: Option<Instant>,
+}
+
+impl Progress {
+    pub fn new(total: Option<usize>) -> Self {
+        Progress {
+            total,
+            done: 0,
+            skipped: 0,
+            start: Instant::now(),
+            drawn: None,
+        }
+    }
+
+    /// Count `words` more words as done, redrawing the line if it is due.
+    pub fn advance(&mut self, words: usize) {
+        self.done += words;
+        if self.drawn.is_none_or(|drawn| drawn.elapsed() >= REDRAW_INTERVAL) {
+            self.draw();
+        }
+    }
+
+    /// Count `words` more words as done that were not decoded, e.g. as an earlier run did.
+    pub fn skip(&mut self, words: usize) {
+        self.skipped += words;
+        self.advance(words);
+    }
+
+    /// Draw the final state and end the line.
+    pub fn finish(mut self) {
+        self.draw();
+        eprintln!();
+    }
+
+    fn draw(&mut self) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let decoded = self.done - self.skipped;
+        let rate = if elapsed > 0.0 { decoded as f64 / elapsed } else { 0.0 };
+        let line = match self.total {
+            Some(total) => {
+                let fraction = if total == 0 { 1.0 } else { (self.done as f64 / total as f64).min(1.0) };
+                let filled = (fraction * BAR_WIDTH as f64) as usize;
+                let left = match total.saturating_sub(self.done) {
+                    0 => String::new(),
+                    _ if rate == 0.0 => String::new(),
+                    remaining => format!(", {} left", format_duration(remaining as f64 / rate)),
+                };
+                format!(
+                    "[{}{}] {:3.0}% {}/{} words, {:.0} words/s{}",
+                    "#".repeat(filled),
+                    " ".repeat(BAR_WIDTH - filled),
+                    fraction * 100.0,
+                    self.done,
+                    total,
+                    rate,
+                    left
+                )
+            }
+            None => format!("{} words, {:.0} words/s", self.done, rate),
+        };
+        // the escape clears what is left of a longer previous line
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r{}\x1b[K", line);
+        let _ = stderr.flush();
+        self.drawn = Some(Instant::now());
+    }
+}
+
+/// Seconds as `1h02m`, `2m46s` or `12s`.
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, seconds) => format!("{}s", seconds),
+        (0, minutes, seconds) => format!("{}m{:02}s", minutes, seconds),
+        (hours, minutes, _) => format!("{}h{:02}m", hours, minutes),
+    }
+}
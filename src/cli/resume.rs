@@ -0,0 +1,62 @@
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use super::json::Value;
+use super::output::OutputFormat;
+
+/// Open the output file of an interrupted run to append to, with the words it has results for.
+///
+/// Only the formats with the word on every line can be resumed: `tsv` and `jsonl`. The file
+/// is cut after its last complete word, which drops a line cut short by the interruption and,
+/// for `tsv`, all lines of the last word, as some of its pronunciations may be missing. A
+/// missing file is created.
+pub fn open_resumed(path: &Path, format: OutputFormat) -> io::Result<(File, HashSet<String>)> {
+    let key: fn(&str) -> Option<String> = match format {
+        OutputFormat::Tsv => |line| line.split_once('\t').map(|(word, _)| word.to_string()),
+        OutputFormat::Jsonl => |line| Value::parse(line).ok()?.get("word")?.as_str().map(str::to_string),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only the tsv and jsonl formats can be resumed",
+            ));
+        }
+    };
+    let file = OpenOptions::new().read(true).append(true).create(true).open(path)?;
+
+    let mut words = HashSet::new();
+    // end of the complete words, and the word of the last lines for tsv
+    let mut end = 0;
+    let mut last_word: Option<String> = None;
+    let mut offset = 0;
+    let mut reader = BufReader::new(&file);
+    let mut line = String::new();
+    for line_number in 1.. {
+        line.clear();
+        let read = reader.read_line(&mut line)? as u64;
+        if read == 0 || !line.ends_with('\n') {
+            break;
+        }
+        let start = offset;
+        offset += read;
+        let Some(word) = key(line.trim_end_matches(['\n', '\r'])) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Line {} has no word to resume after", line_number),
+            ));
+        };
+        if format == OutputFormat::Jsonl {
+            words.insert(word);
+            end = offset;
+        } else if last_word.as_ref() != Some(&word)
+            && let Some(done) = last_word.replace(word)
+        {
+            words.insert(done);
+            end = start;
+        }
+    }
+    file.set_len(end)?;
+
+    Ok((file, words))
+}
@@ -0,0 +1,190 @@
+use clap::Args;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use super::load_model;
+use phonetisaurus_g2p_py::{Lexicon, PhonetizationResult};
+
+#[derive(Args)]
+pub struct ReviewArgs {
+    /// Path to the Phonetisaurus FST model file [default: $PHONETISAURUS_MODEL, or model_path
+    /// in the config file]
+    model_path: Option<String>,
+
+    /// File with the words to review, one per line
+    #[arg(long)]
+    wordlist: PathBuf,
+
+    /// Exception lexicon (CMUdict format) to append the reviewed entries to. Words it already
+    /// has are not shown again, so a review can be continued later
+    #[arg(long)]
+    out: PathBuf,
+
+    /// Number of pronunciations to choose from per word
+    #[arg(long, default_value_t = 3)]
+    nbest: usize,
+}
+
+/// What the reviewer decided for a word.
+enum Decision {
+    /// Write these pronunciations, as phonemes separated by spaces.
+    Accept(Vec<String>),
+    Skip,
+    Quit,
+}
+
+/// Walk through a word list in the terminal, showing the n best pronunciations of each word
+/// with their scores and alignments, and append the accepted or corrected ones to an
+/// exception lexicon.
+///
+/// Entries are written as soon as they are decided, so quitting loses nothing.
+pub fn run(args: ReviewArgs) {
+    let model = load_model(args.model_path.as_deref());
+    let words = match read_words(&args) {
+        Ok(words) => words,
+        Err(e) => {
+            eprintln!("Failed to read word list '{}': {}", args.wordlist.display(), e);
+            std::process::exit(1);
+        }
+    };
+    let mut out = match OpenOptions::new().append(true).create(true).open(&args.out) {
+        Ok(out) => out,
+        Err(e) => {
+            eprintln!("Failed to open lexicon '{}': {}", args.out.display(), e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut input = io::stdin().lock();
+    let mut reviewed = 0;
+    for (i, word) in words.iter().enumerate() {
+        println!("\n[{}/{}] {}", i + 1, words.len(), word);
+        let results = model.phonemize_word_nbest(word, args.nbest);
+        let results = match results {
+            Ok(results) => results,
+            Err(e) => {
+                println!("  failed: {}", e);
+                Vec::new()
+            }
+        };
+        for (n, result) in results.iter().enumerate() {
+            println!(
+                "  {}) {:<30} score {:7.3}  posterior {:.3}  {}",
+                n + 1,
+                result.phonemes,
+                result.neg_log_score,
+                result.posterior,
+                alignment_text(word, result)
+            );
+        }
+        let decision = match ask(&mut input, &results) {
+            Ok(decision) => decision,
+            Err(e) => {
+                eprintln!("Failed to read the answer: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let pronunciations = match decision {
+            Decision::Accept(pronunciations) => pronunciations,
+            Decision::Skip => continue,
+            Decision::Quit => break,
+        };
+        if let Err(e) = write_entry(&mut out, word, &pronunciations) {
+            eprintln!("Failed to write lexicon '{}': {}", args.out.display(), e);
+            std::process::exit(1);
+        }
+        reviewed += 1;
+    }
+    println!("\nWrote {} entries to {}", reviewed, args.out.display());
+}
+
+/// The distinct words of the word list that the output lexicon does not have yet.
+fn read_words(args: &ReviewArgs) -> io::Result<Vec<String>> {
+    let done = match args.out.exists() {
+        true => Lexicon::try_from(args.out.as_path())?,
+        false => Lexicon::new(),
+    };
+    let mut seen = HashSet::new();
+    let mut words = Vec::new();
+    for line in BufReader::new(File::open(&args.wordlist)?).lines() {
+        let line = line?;
+        let word = line.trim();
+        if !word.is_empty() && done.get(word).is_none() && seen.insert(word.to_string()) {
+            words.push(word.to_string());
+        }
+    }
+    Ok(words)
+}
+
+/// Ask for a decision until the answer is understood. The end of the input quits.
+///
+/// Answers are the numbers of the pronunciations to accept, separated by commas or spaces,
+/// `s` to skip, `q` to quit, or a pronunciation typed out to replace the proposed ones.
+/// An empty answer accepts the best pronunciation.
+fn ask(input: &mut impl BufRead, results: &[PhonetizationResult]) -> io::Result<Decision> {
+    loop {
+        match results.len() {
+            0 => print!("Type the phonemes, s to skip or q to quit: "),
+            n => print!("Accept 1-{} [1], type the phonemes, s to skip or q to quit: ", n),
+        }
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 {
+            return Ok(Decision::Quit);
+        }
+        let answer = answer.trim();
+        let choices: Vec<&str> = answer.split([',', ' ']).filter(|choice| !choice.is_empty()).collect();
+        match answer {
+            "" if results.is_empty() => continue,
+            "" => return Ok(Decision::Accept(vec![results[0].phonemes.clone()])),
+            "s" => return Ok(Decision::Skip),
+            "q" => return Ok(Decision::Quit),
+            _ if choices.iter().all(|choice| choice.bytes().all(|b| b.is_ascii_digit())) => {
+                let chosen: Option<Vec<String>> = choices
+                    .iter()
+                    .map(|choice| {
+                        let n: usize = choice.parse().ok()?;
+                        results.get(n.checked_sub(1)?).map(|result| result.phonemes.clone())
+                    })
+                    .collect();
+                match chosen {
+                    Some(chosen) => return Ok(Decision::Accept(chosen)),
+                    None => println!("There are only {} pronunciations", results.len()),
+                }
+            }
+            phonemes => return Ok(Decision::Accept(vec![phonemes.split_whitespace().collect::<Vec<_>>().join(" ")])),
+        }
+    }
+}
+
+/// The graphemes of each aligned segment with their phonemes, like `r:R ea:IY d:D`, with `_`
+/// for silent graphemes.
+fn alignment_text(word: &str, result: &PhonetizationResult) -> String {
+    let chars: Vec<char> = word.chars().collect();
+    let segments: Vec<String> = result
+        .alignment
+        .iter()
+        .map(|segment| {
+            let graphemes: String = chars.get(segment.graphemes.clone()).unwrap_or_default().iter().collect();
+            let phonemes = match segment.phonemes.is_empty() {
+                true => "_".to_string(),
+                false => segment.phonemes.join(" "),
+            };
+            format!("{}:{}", graphemes, phonemes)
+        })
+        .collect();
+    segments.join(" ")
+}
+
+/// Append the entry of a word in CMUdict format, with variant numbers for the alternatives.
+fn write_entry(out: &mut File, word: &str, pronunciations: &[String]) -> io::Result<()> {
+    for (i, phonemes) in pronunciations.iter().enumerate() {
+        match i {
+            0 => writeln!(out, "{} {}", word, phonemes)?,
+            _ => writeln!(out, "{}({}) {}", word, i + 1, phonemes)?,
+        }
+    }
+    out.flush()
+}
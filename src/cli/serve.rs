@@ -0,0 +1,801 @@
+use clap::Args;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::access::AccessPolicy;
+use super::http::{self, HttpError, Request};
+use super::json::Value;
+use super::limit::ConcurrencyLimit;
+use super::log::{self, Level, LogArgs};
+use super::metrics::Metrics;
+use super::options::parse_options;
+use super::pool::WorkerPool;
+use super::output::to_json;
+use phonetisaurus_g2p_py::*;
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Path to the Phonetisaurus FST model file answering requests without "lang" [default:
+    /// $PHONETISAURUS_MODEL, or model_path in the config file]
+    model_path: Option<String>,
+
+    /// Also serve the model at PATH for requests with this "lang" tag. Repeat for more
+    /// languages; if only one model is served, it also answers requests without "lang"
+    #[arg(long = "model", value_name = "LANG=PATH", value_parser = parse_model_arg)]
+    models: Vec<(String, String)>,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port to listen on
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+
+    /// Exception lexicon (CMUdict format) whose pronunciations override the model given
+    /// without a language
+    #[arg(long)]
+    lexicon: Option<PathBuf>,
+
+    /// Convert the models to a compact representation that takes less memory and decodes faster
+    #[arg(long)]
+    optimize: bool,
+
+    /// Cache the pronunciations of this many words per model, for requests without "nbest"
+    /// and "options"
+    #[arg(long, value_name = "WORDS")]
+    cache_size: Option<usize>,
+
+    /// Page in the models before serving, and decode the words of this file, one per line,
+    /// into the caches of `--cache-size`, so that the first requests are not slower than later
+    /// ones. Done again on every reload
+    #[arg(long, value_name = "PATH")]
+    warm_up: Option<PathBuf>,
+
+    /// Pick the model for words of requests without "lang" by their script and by how many
+    /// of their characters each model knows, and report the chosen "lang" per word
+    #[arg(long)]
+    detect_language: bool,
+
+    /// Reload the models and the lexicon when one of their files changes, checking every
+    /// SECONDS. `POST /reload` reloads them on demand either way
+    #[arg(long, value_name = "SECONDS", num_args = 0..=1, default_missing_value = "2")]
+    watch: Option<f64>,
+
+    /// Decode at most this many phonemize requests at once, to bound the memory used
+    #[arg(long, value_name = "REQUESTS", value_parser = clap::value_parser!(u64).range(1..))]
+    max_concurrency: Option<u64>,
+
+    /// With --max-concurrency, let this many requests wait for their turn; further requests
+    /// are answered with 429 Too Many Requests
+    #[arg(long, value_name = "REQUESTS", default_value_t = 0, requires = "max_concurrency")]
+    max_queue: usize,
+
+    /// Most connections handled at once, each on its own thread; further connections are
+    /// answered with 503 Service Unavailable
+    #[arg(long, value_name = "CONNECTIONS", default_value_t = 256, value_parser = clap::value_parser!(u64).range(1..))]
+    max_connections: u64,
+
+    /// Most words accepted by one POST /phonemize/batch request
+    #[arg(long, value_name = "WORDS", default_value_t = 1000)]
+    max_batch_size: usize,
+
+    /// Most pronunciations a request may ask for per word with "nbest"; requests asking for
+    /// more are answered with 400 Bad Request
+    #[arg(long, value_name = "N", default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..))]
+    max_nbest: u64,
+
+    /// Threads decoding the words of batch requests [default: number of CPUs]
+    #[arg(long, value_name = "THREADS", value_parser = clap::value_parser!(u64).range(1..))]
+    batch_workers: Option<u64>,
+
+    /// Require this API key, sent as "Authorization: Bearer KEY" or "X-API-Key: KEY", on all
+    /// endpoints but /health. Repeat to accept several keys
+    #[arg(long, value_name = "KEY")]
+    api_key: Vec<String>,
+
+    /// Require one of the API keys in this file, one per line, like --api-key but without
+    /// showing them in the process list
+    #[arg(long, value_name = "PATH")]
+    api_key_file: Option<PathBuf>,
+
+    /// Allow browser pages from this origin, e.g. https://example.com, to call the server.
+    /// Repeat for more origins, or use "*" for any
+    #[arg(long, value_name = "ORIGIN")]
+    cors_origin: Vec<String>,
+
+    /// Listen on this Unix socket with newline-delimited JSON instead of HTTP
+    #[cfg(unix)]
+    #[arg(long, value_name = "PATH")]
+    unix_socket: Option<PathBuf>,
+
+    #[command(flatten)]
+    log: LogArgs,
+}
+
+/// Language tag of the model given without `--model`, "undetermined" in BCP 47.
+const DEFAULT_LANG: &str = "und";
+
+/// Time a client has to send each part of its request and to take the response, before its
+/// connection is closed. Unix socket clients also have it between their requests.
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// State shared by the connection threads.
+struct Server {
+    /// The models currently served, replaced as a whole when they are reloaded.
+    loaded: RwLock<Arc<LoadedModels>>,
+    /// Serialize reloads, so that a slow one is not overtaken by an older one.
+    reloading: std::sync::Mutex<()>,
+    args: ServeArgs,
+    access: AccessPolicy,
+    limit: ConcurrencyLimit,
+    /// Number of connections being handled, up to `--max-connections`.
+    connections: AtomicUsize,
+    pool: WorkerPool,
+    metrics: Metrics,
+}
+
+/// Models loaded from the files given on the command line.
+struct LoadedModels {
+    models: ModelRegistry,
+    /// Language of the model answering requests without "lang", if there is one.
+    default_lang: Option<String>,
+    /// Cache of each model, by normalized language tag, with `--cache-size`.
+    caches: BTreeMap<String, CachedPhonetisaurusModel>,
+    /// `(language, path)` of each model, the language normalized.
+    paths: Vec<(String, String)>,
+}
+
+impl LoadedModels {
+    fn load(args: &ServeArgs) -> Result<Self, String> {
+        let load = |model_path: &str| {
+            let model_path = locate::model_path(Path::new(model_path));
+            PhonetisaurusModel::try_from(model_path.as_path())
+                .map_err(|e| format!("Failed to load model from '{}': {}", model_path.display(), e))
+        };
+        let mut models = ModelRegistry::new();
+        let mut paths = Vec::new();
+        if let Some(model_path) = &args.model_path {
+            let mut model = load(model_path)?;
+            if let Some(lexicon_path) = &args.lexicon {
+                let lexicon = Lexicon::try_from(lexicon_path.as_path())
+                    .map_err(|e| format!("Failed to load lexicon from '{}': {}", lexicon_path.display(), e))?;
+                model = model.with_lexicon(lexicon);
+            }
+            models.insert(DEFAULT_LANG, model);
+            paths.push((DEFAULT_LANG.to_string(), model_path.clone()));
+        }
+        for (lang, model_path) in &args.models {
+            if models.insert(lang, load(model_path)?).is_some() {
+                return Err(format!("More than one model for language '{}'", lang));
+            }
+            let lang = models.resolve(lang).expect("the language was registered");
+            paths.push((lang.to_string(), model_path.clone()));
+        }
+        if args.optimize {
+            models = models.iter().map(|(lang, model)| (lang.to_string(), model.clone().optimize())).collect();
+        }
+
+        let default_lang = match (&args.model_path, models.languages().collect::<Vec<_>>().as_slice()) {
+            (Some(_), _) => Some(DEFAULT_LANG.to_string()),
+            (None, [lang]) => Some(lang.to_string()),
+            (None, _) => None,
+        };
+        let caches = match args.cache_size {
+            Some(capacity) => models.iter().map(|(lang, model)| (lang.to_string(), model.clone().with_cache(capacity))).collect(),
+            None => BTreeMap::new(),
+        };
+        if let Some(path) = &args.warm_up {
+            let words = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read warm-up words from '{}': {}", path.display(), e))?;
+            let words: Vec<&str> = words.lines().map(str::trim).filter(|word| !word.is_empty()).collect();
+            for (lang, model) in models.iter() {
+                match caches.get(lang) {
+                    Some(cache) => {
+                        cache.warm_up(&words);
+                    }
+                    None => model.warm_up(),
+                }
+            }
+        }
+        Ok(LoadedModels {
+            models,
+            default_lang,
+            caches,
+            paths,
+        })
+    }
+
+    /// `(language, path, model)` of each model, as [`Metrics`] describes them.
+    fn described(&self) -> impl Iterator<Item = (&str, &str, &PhonetisaurusModel)> {
+        self.paths.iter().map(|(lang, path)| {
+            (lang.as_str(), path.as_str(), self.models.get(lang).expect("every language was registered"))
+        })
+    }
+}
+
+impl Server {
+    /// The models to answer a request with. A request keeps the models it started with even
+    /// if they are reloaded meanwhile.
+    fn loaded(&self) -> Arc<LoadedModels> {
+        Arc::clone(&self.loaded.read().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+
+    /// Load the models again from their files and swap them in, keeping the current ones if
+    /// any of them fails to load.
+    fn reload(&self) -> Result<Arc<LoadedModels>, String> {
+        let _reloading = self.reloading.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let loaded = LoadedModels::load(&self.args);
+        self.metrics.record_reload(loaded.is_ok());
+        let loaded = Arc::new(loaded?);
+        self.metrics.set_models(loaded.described());
+        *self.loaded.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::clone(&loaded);
+        Ok(loaded)
+    }
+
+    /// Count a request that started at `start` in the metrics, and log it at debug level.
+    fn record_request(
+        &self,
+        method: Option<&str>,
+        endpoint: &'static str,
+        status: u16,
+        start: Instant,
+        error: Option<&str>,
+    ) {
+        let elapsed = start.elapsed();
+        self.metrics.record_request(endpoint, status, elapsed);
+        if !log::enabled(Level::Debug) {
+            return;
+        }
+        let latency_ms = (elapsed.as_secs_f64() * 1000.0) as f32;
+        let method = method.unwrap_or_default();
+        let message = format!("{} {} {} in {:.3} ms", method, endpoint, status, latency_ms);
+        let mut fields = vec![
+            ("method", Value::from(method)),
+            ("endpoint", endpoint.into()),
+            ("status", usize::from(status).into()),
+            ("latency_ms", latency_ms.into()),
+        ];
+        fields.extend(error.map(|error| ("error", error.into())));
+        log::log(Level::Debug, message.trim_start(), fields);
+    }
+
+    /// The files the models are loaded from.
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        let models = self.args.model_path.iter().chain(self.args.models.iter().map(|(_, path)| path));
+        models.map(|path| locate::model_path(Path::new(path))).chain(self.args.lexicon.clone()).collect()
+    }
+}
+
+/// Serve the models over HTTP until the process is killed.
+///
+/// `POST /phonemize` takes `{"word": "..."}` or `{"words": [...]}`, optionally with a
+/// `"lang"` tag, `"nbest"` and an `"options"` object, and answers with the JSON objects of
+/// the jsonl output format. `GET /health` answers `{"status":"ok"}` and `GET /metrics`
+/// reports request counts and latencies, word errors, cache usage and the model sizes in the
+/// Prometheus text format. `POST /reload` loads the models again, see [`Server::reload`].
+/// Each connection is handled on its own thread and closed after one request.
+///
+/// With `--unix-socket`, see [`serve_unix`] instead.
+pub fn run(mut args: ServeArgs) {
+    args.log.init();
+    // the model for requests without "lang" is needed unless --model gives the models
+    args.model_path = match args.models.is_empty() {
+        true => Some(super::require_model_path(args.model_path.as_deref())),
+        false => args.model_path.take().or_else(super::default_model_path),
+    };
+    let loaded = match LoadedModels::load(&args) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            log::error(&e, []);
+            std::process::exit(1);
+        }
+    };
+    let metrics = Metrics::new(loaded.described());
+    let limit = ConcurrencyLimit::new(args.max_concurrency.map(|n| n as usize), args.max_queue);
+    let mut api_keys = args.api_key.clone();
+    if let Some(path) = &args.api_key_file {
+        match AccessPolicy::read_api_keys(path) {
+            Ok(keys) if !keys.is_empty() => api_keys.extend(keys),
+            Ok(_) => {
+                let message = format!("No API keys in '{}'", path.display());
+                log::error(&message, [("path", path.display().to_string().into())]);
+                std::process::exit(1);
+            }
+            Err(e) => {
+                log::failed_on("Failed to read API keys from", path, &e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let access = AccessPolicy::new(api_keys, args.cors_origin.clone());
+    let workers = match args.batch_workers {
+        Some(workers) => workers as usize,
+        None => std::thread::available_parallelism().map_or(1, usize::from),
+    };
+    let server = Arc::new(Server {
+        loaded: RwLock::new(Arc::new(loaded)),
+        reloading: Default::default(),
+        args,
+        access,
+        limit,
+        connections: AtomicUsize::new(0),
+        pool: WorkerPool::new(workers),
+        metrics,
+    });
+
+    if let Some(interval) = server.args.watch {
+        if !(interval > 0.0 && interval.is_finite()) {
+            log::error("--watch must be a positive number of seconds", []);
+            std::process::exit(2);
+        }
+        let server = Arc::clone(&server);
+        std::thread::spawn(move || watch(&server, Duration::from_secs_f64(interval)));
+    }
+
+    #[cfg(unix)]
+    if let Some(socket_path) = &server.args.unix_socket {
+        return serve_unix(Arc::clone(&server), socket_path);
+    }
+    let args = &server.args;
+
+    let address = format!("{}:{}", args.host, args.port);
+    let listener = match TcpListener::bind((args.host.as_str(), args.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            let message = format!("Failed to listen on {}: {}", address, e);
+            log::error(&message, [("address", address.into()), ("error", e.to_string().into())]);
+            std::process::exit(1);
+        }
+    };
+    log::info(&format!("Listening on http://{}", address), [("address", address.into())]);
+
+    accept_connections(&server, listener.incoming(), handle_connection, reject_connection);
+}
+
+/// Handle each connection on its own thread, with at most `--max-connections` of them at
+/// once. Connections beyond those, and all connections while the queue of `--max-queue` is
+/// full, are answered with an error by `reject` on the accepting thread, before their request
+/// is read.
+fn accept_connections<S: Send + 'static>(
+    server: &Arc<Server>,
+    incoming: impl Iterator<Item = io::Result<S>>,
+    handle: fn(&Server, S) -> io::Result<()>,
+    reject: fn(&Server, S, HttpError) -> io::Result<()>,
+) {
+    for stream in incoming {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::failed("Failed to accept connection", &e);
+                continue;
+            }
+        };
+        let connection = OpenConnection::new(server);
+        let error = if server.connections.load(Ordering::SeqCst) > server.args.max_connections as usize {
+            Some(HttpError::new(503, "Too many connections, try again later"))
+        } else if server.limit.is_full() {
+            Some(too_many_requests())
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            drop(connection);
+            if let Err(e) = reject(server, stream, error) {
+                log::failed("Failed to reject connection", &e);
+            }
+            continue;
+        }
+        std::thread::spawn(move || {
+            if let Err(e) = handle(&connection.0, stream) {
+                log::failed("Failed to handle connection", &e);
+            }
+        });
+    }
+}
+
+/// A connection counted in [`Server::connections`] until it is dropped.
+struct OpenConnection(Arc<Server>);
+
+impl OpenConnection {
+    fn new(server: &Arc<Server>) -> Self {
+        server.connections.fetch_add(1, Ordering::SeqCst);
+        OpenConnection(Arc::clone(server))
+    }
+}
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        self.0.connections.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Answer a connection that is not handled with its error.
+fn reject_connection(server: &Server, stream: TcpStream, error: HttpError) -> io::Result<()> {
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    server.record_request(None, "other", error.status, Instant::now(), Some(&error.message));
+    http::write_response(&mut &stream, error.status, &[], &Value::object([("error", error.message.into())]))
+}
+
+fn too_many_requests() -> HttpError {
+    HttpError::new(429, "Too many requests, try again later")
+}
+
+/// Reload the models whenever the modification time of one of their files changes.
+///
+/// A file that is being written may fail to load, in which case the old models are kept and
+/// loading is tried again on the next change. Replacing the files by renaming avoids this.
+fn watch(server: &Server, interval: Duration) {
+    let paths = server.watched_paths();
+    let modified = || -> Vec<Option<SystemTime>> {
+        paths.iter().map(|path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()).collect()
+    };
+    let mut last_modified = modified();
+    loop {
+        std::thread::sleep(interval);
+        let now_modified = modified();
+        if now_modified == last_modified {
+            continue;
+        }
+        last_modified = now_modified;
+        match server.reload() {
+            Ok(_) => log::info("Reloaded the models", []),
+            Err(e) => log::failed("Failed to reload the models, keeping the old ones", &e),
+        }
+    }
+}
+
+fn handle_connection(server: &Server, stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let Some(request) = http::read_request(&mut reader)? else {
+        return Ok(());
+    };
+    let start = Instant::now();
+    let mut request = match request {
+        Ok(request) => request,
+        Err(e) => {
+            server.record_request(None, "other", e.status, start, Some(&e.message));
+            return http::write_response(&mut &stream, e.status, &[], &Value::object([("error", e.message.into())]));
+        }
+    };
+    let endpoint = match request.path.as_str() {
+        "/phonemize" => "/phonemize",
+        "/phonemize/batch" => "/phonemize/batch",
+        "/health" => "/health",
+        "/metrics" => "/metrics",
+        "/reload" => "/reload",
+        _ => "other",
+    };
+    let mut headers = server.access.cors_headers(&request);
+
+    // browsers ask before sending requests with an API key or a JSON body, without the key
+    if request.method == "OPTIONS" {
+        headers.extend(server.access.preflight_headers());
+        server.record_request(Some(&request.method), endpoint, 204, start, None);
+        return http::write_text_response(&mut &stream, 204, "text/plain", &headers, "");
+    }
+    let authorized = match endpoint {
+        "/health" => Ok(()),
+        _ => server.access.authorize(&request),
+    };
+    if (request.method.as_str(), endpoint) == ("GET", "/metrics") && authorized.is_ok() {
+        let text = server.metrics.render(&server.loaded().caches, server.limit.load());
+        server.record_request(Some(&request.method), endpoint, 200, start, None);
+        return http::write_text_response(&mut &stream, 200, "text/plain; version=0.0.4", &headers, &text);
+    }
+    // phonemize requests wait for their turn before their body is read
+    let limited = request.method == "POST" && matches!(endpoint, "/phonemize" | "/phonemize/batch");
+    let permit = match limited && authorized.is_ok() {
+        true => server.limit.acquire().ok_or_else(too_many_requests).map(Some),
+        false => Ok(None),
+    };
+    if permit.is_ok() {
+        http::read_body(&mut reader, &mut request)?;
+    }
+    let (status, body) = match authorized.and(permit).and_then(|_permit| route(server, &request)) {
+        Ok(body) => (200, body),
+        Err(e) => (e.status, Value::object([("error", e.message.into())])),
+    };
+    let error = body.get("error").and_then(Value::as_str);
+    server.record_request(Some(&request.method), endpoint, status, start, error);
+    http::write_response(&mut &stream, status, &headers, &body)
+}
+
+/// Serve the model over a Unix socket until the process is killed.
+///
+/// Each line sent by a client is one JSON request like the body of `POST /phonemize`, and is
+/// answered with one line of JSON, `{"error": "..."}` if the request failed. Connections stay
+/// open for any number of requests until they are idle for [`IO_TIMEOUT`], and are handled on
+/// their own threads. A stale socket file left by an earlier server is replaced.
+#[cfg(unix)]
+fn serve_unix(server: Arc<Server>, socket_path: &std::path::Path) {
+    use std::os::unix::fs::FileTypeExt;
+
+    if std::fs::symlink_metadata(socket_path).is_ok_and(|metadata| metadata.file_type().is_socket())
+        && UnixStream::connect(socket_path).is_err()
+    {
+        let _ = std::fs::remove_file(socket_path);
+    }
+    let listener = match UnixListener::bind(socket_path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            let path = socket_path.display().to_string();
+            let message = format!("Failed to listen on {}: {}", path, e);
+            log::error(&message, [("path", path.into()), ("error", e.to_string().into())]);
+            std::process::exit(1);
+        }
+    };
+    let path = socket_path.display().to_string();
+    log::info(&format!("Listening on {}", path), [("path", path.into())]);
+
+    accept_connections(&server, listener.incoming(), handle_unix_connection, reject_unix_connection);
+}
+
+/// Answer a connection that is not handled with an error line.
+#[cfg(unix)]
+fn reject_unix_connection(server: &Server, mut stream: UnixStream, error: HttpError) -> io::Result<()> {
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    server.record_request(None, "unix", error.status, Instant::now(), Some(&error.message));
+    writeln!(stream, "{}", Value::object([("error", error.message.into())]))
+}
+
+#[cfg(unix)]
+fn handle_unix_connection(server: &Server, stream: UnixStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = io::BufWriter::new(stream);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let read = reader.by_ref().take(http::MAX_BODY_SIZE as u64 + 1).read_until(b'\n', &mut line);
+        // a client idle between requests is disconnected without an error
+        let timed_out = |e: &io::Error| matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut);
+        if line.is_empty() && read.as_ref().is_err_and(timed_out) {
+            return Ok(());
+        }
+        let read = read?;
+        if read == 0 {
+            return Ok(());
+        }
+        if line.len() > http::MAX_BODY_SIZE {
+            let error = Value::object([("error", "Request too large".into())]);
+            writeln!(writer, "{}", error)?;
+            return writer.flush();
+        }
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let start = Instant::now();
+        let response = server.limit.acquire().ok_or_else(too_many_requests).and_then(|_permit| phonemize_body(server, &line));
+        let (status, response) = match response {
+            Ok(response) => (200, response),
+            Err(e) => (e.status, Value::object([("error", e.message.into())])),
+        };
+        server.record_request(None, "unix", status, start, response.get("error").and_then(Value::as_str));
+        writeln!(writer, "{}", response)?;
+        writer.flush()?;
+    }
+}
+
+fn route(server: &Server, request: &Request) -> Result<Value, HttpError> {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/phonemize") => phonemize_body(server, &request.body),
+        ("POST", "/phonemize/batch") => phonemize_batch(server, &request.body),
+        ("GET", "/health") => Ok(Value::object([("status", "ok".into())])),
+        ("POST", "/reload") => {
+            let loaded = server.reload().map_err(|e| HttpError::new(500, e))?;
+            let languages = loaded.models.languages().map(Value::from).collect();
+            Ok(Value::object([("status", "reloaded".into()), ("models", Value::Array(languages))]))
+        }
+        (_, "/phonemize" | "/phonemize/batch" | "/health" | "/metrics" | "/reload") => Err(HttpError::new(405, "Method not allowed")),
+        _ => Err(HttpError::new(404, "Not found")),
+    }
+}
+
+/// Answer a phonemize request, the JSON body of `POST /phonemize`. The caller waits for its
+/// turn with `--max-concurrency` first.
+fn phonemize_body(server: &Server, body: &[u8]) -> Result<Value, HttpError> {
+    let body = parse_body(body)?;
+    let decoder = WordDecoder::new(server, &body)?;
+
+    if let Some(word) = body.get("word") {
+        let word = word.as_str().ok_or_else(|| HttpError::new(400, "\"word\" must be a string"))?;
+        let (lang, results) = decoder.decode(word);
+        return Ok(decoder.to_json(server, word, &lang, &results));
+    }
+    let words = body
+        .get("words")
+        .and_then(Value::as_array)
+        .ok_or_else(|| HttpError::new(400, "Expected a \"word\" string or a \"words\" array"))?;
+    let results = words
+        .iter()
+        .map(|word| {
+            let word = word.as_str()?;
+            let (lang, results) = decoder.decode(word);
+            Some(decoder.to_json(server, word, &lang, &results))
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| HttpError::new(400, "\"words\" must only contain strings"))?;
+
+    Ok(Value::object([("results", Value::Array(results))]))
+}
+
+/// Answer `POST /phonemize/batch`, which takes up to `--max-batch-size` words like
+/// `POST /phonemize` and decodes them in parallel on the worker pool.
+///
+/// Words repeated in a batch are decoded once. Words that fail have an `"error"` in their
+/// result, and are counted in `"failed"`.
+fn phonemize_batch(server: &Server, body: &[u8]) -> Result<Value, HttpError> {
+    let body = parse_body(body)?;
+    let decoder = WordDecoder::new(server, &body)?;
+    let words = body
+        .get("words")
+        .and_then(Value::as_array)
+        .ok_or_else(|| HttpError::new(400, "Expected a \"words\" array"))?;
+    if words.len() > server.args.max_batch_size {
+        let message = format!("At most {} words per batch", server.args.max_batch_size);
+        return Err(HttpError::new(413, message));
+    }
+    let words = words
+        .iter()
+        .map(|word| word.as_str().map(String::from))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| HttpError::new(400, "\"words\" must only contain strings"))?;
+
+    let mut distinct = BTreeMap::new();
+    for word in &words {
+        let next = distinct.len();
+        distinct.entry(word.as_str()).or_insert(next);
+    }
+    let mut unique = vec![String::new(); distinct.len()];
+    for (&word, &index) in &distinct {
+        unique[index] = word.to_string();
+    }
+    let decoded = {
+        let decoder = decoder.clone();
+        server.pool.map(unique, move |word| decoder.decode(&word))
+    };
+    let decoded = decoded.ok_or_else(|| HttpError::new(500, "Decoding failed"))?;
+    let decoded: Vec<_> = words.iter().map(|word| &decoded[distinct[word.as_str()]]).collect();
+    let failed = decoded.iter().filter(|(_, results)| results.is_err()).count();
+    let results = words
+        .iter()
+        .zip(decoded)
+        .map(|(word, (lang, results))| decoder.to_json(server, word, lang, results))
+        .collect();
+
+    Ok(Value::object([("results", Value::Array(results)), ("failed", failed.into())]))
+}
+
+fn parse_body(body: &[u8]) -> Result<Value, HttpError> {
+    let text = std::str::from_utf8(body).map_err(|_| HttpError::new(400, "Body is not UTF-8"))?;
+    Value::parse(text).map_err(|e| HttpError::new(400, format!("Invalid JSON: {}", e)))
+}
+
+/// How the words of a request are decoded, from its `"lang"`, `"nbest"` and `"options"`.
+#[derive(Clone)]
+struct WordDecoder {
+    loaded: Arc<LoadedModels>,
+    /// Normalized language of the model, `None` if the language is detected per word.
+    lang: Option<String>,
+    nbest: usize,
+    options: PhonemizeOptions,
+    /// Whether the caches may answer, which hold results of the default options only.
+    cacheable: bool,
+}
+
+impl WordDecoder {
+    fn new(server: &Server, body: &Value) -> Result<Self, HttpError> {
+        let loaded = server.loaded();
+        let bad_request = |message: &str| HttpError::new(400, message);
+        let max_nbest = server.args.max_nbest;
+        let nbest = match body.get("nbest") {
+            None => 1,
+            Some(n) => match n.as_f64() {
+                Some(n) if n >= 1.0 && n.fract() == 0.0 && n <= max_nbest as f64 => n as usize,
+                Some(n) if n > max_nbest as f64 => {
+                    return Err(bad_request(&format!("\"nbest\" must be at most {}", max_nbest)));
+                }
+                _ => return Err(bad_request("\"nbest\" must be a positive integer")),
+            },
+        };
+        let options = match body.get("options") {
+            None => PhonemizeOptions::default(),
+            Some(options) => parse_options(options).map_err(|e| HttpError::new(400, e))?,
+        };
+        let lang = match body.get("lang") {
+            Some(lang) => {
+                let lang = lang.as_str().ok_or_else(|| bad_request("\"lang\" must be a string"))?;
+                let resolved = loaded.models.resolve(lang);
+                Some(resolved.ok_or_else(|| bad_request(&PhonetisaurusError::UnknownLanguage(lang.to_string()).to_string()))?)
+            }
+            None if server.args.detect_language => None,
+            None => Some(
+                loaded
+                    .default_lang
+                    .as_deref()
+                    .ok_or_else(|| bad_request("\"lang\" is required when several models are served"))?,
+            ),
+        };
+        Ok(WordDecoder {
+            lang: lang.map(String::from),
+            nbest,
+            options,
+            cacheable: nbest == 1 && body.get("options").is_none(),
+            loaded,
+        })
+    }
+
+    /// Decode a word, with the language of the model that did.
+    fn decode(&self, word: &str) -> (String, Result<Vec<PhonetizationResult>, PhonetisaurusError>) {
+        let word_lang = self.lang.clone().unwrap_or_else(|| {
+            let detected = self.loaded.models.detect_language_with_options(word, &self.options);
+            detected.expect("at least one model is served").to_string()
+        });
+        let start = Instant::now();
+        let results = match self.loaded.caches.get(&word_lang).filter(|_| self.cacheable) {
+            Some(cache) => cache.phonemize_word(word).map(|result| vec![result]),
+            None => {
+                let model = self.loaded.models.get(&word_lang).expect("the language was resolved");
+                model.phonemize_word_nbest_with_options(word, self.nbest, &self.options)
+            }
+        };
+        if log::enabled(Level::Trace) {
+            log_word(word, &word_lang, start, &results);
+        }
+        (word_lang, results)
+    }
+
+    /// The JSON result of a decoded word, counted in the metrics.
+    fn to_json(
+        &self,
+        server: &Server,
+        word: &str,
+        word_lang: &str,
+        results: &Result<Vec<PhonetizationResult>, PhonetisaurusError>,
+    ) -> Value {
+        server.metrics.record_word(results);
+        let normalized_scores = self.options.length_normalization != LengthNormalization::None;
+        let mut json = to_json(word, results, normalized_scores, None);
+        if let (None, Value::Object(members)) = (&self.lang, &mut json) {
+            members.insert(1, ("lang".to_string(), word_lang.into()));
+        }
+        json
+    }
+}
+
+/// Log a decoded word at trace level, with its length but not the word itself.
+fn log_word(word: &str, lang: &str, start: Instant, results: &Result<Vec<PhonetizationResult>, PhonetisaurusError>) {
+    let latency_ms = (start.elapsed().as_secs_f64() * 1000.0) as f32;
+    let word_length = word.chars().count();
+    let outcome = if results.is_ok() { "ok" } else { "error" };
+    let message = format!("Decoded a word of {} chars in {:.3} ms: {}", word_length, latency_ms, outcome);
+    let mut fields = vec![
+        ("word_length", Value::from(word_length)),
+        ("lang", lang.into()),
+        ("latency_ms", latency_ms.into()),
+        ("outcome", outcome.into()),
+    ];
+    if let Err(e) = results {
+        fields.push(("error", e.to_string().into()));
+    }
+    log::log(Level::Trace, &message, fields);
+}
+
+/// Parse a `--model LANG=PATH` argument.
+fn parse_model_arg(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((lang, path)) if !lang.is_empty() && !path.is_empty() => Ok((lang.to_string(), path.to_string())),
+        _ => Err(format!("expected LANG=PATH, got '{}'", arg)),
+    }
+}
@@ -0,0 +1,106 @@
+use clap::{Args, ValueEnum};
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::phonemize::ModelPhonemes;
+use super::{load_lexicon, load_model};
+use phonetisaurus_g2p_py::*;
+
+#[derive(Args)]
+pub struct SsmlArgs {
+    /// Path to the Phonetisaurus FST model file [default: $PHONETISAURUS_MODEL, or model_path
+    /// in the config file]
+    model_path: Option<String>,
+
+    /// Text to write as SSML. If omitted, the text is read from stdin or --input
+    text: Option<String>,
+
+    /// File to read the text from
+    #[arg(long, conflicts_with = "text")]
+    input: Option<PathBuf>,
+
+    /// Exception lexicon (CMUdict format) whose pronunciations override the model
+    #[arg(long)]
+    lexicon: Option<PathBuf>,
+
+    /// Phoneme inventory of the model, for the built-in tables and its stress digits
+    #[arg(long, value_enum, default_value_t = ModelPhonemes::Nofabet)]
+    model_phonemes: ModelPhonemes,
+
+    /// Phoneme set of the `ph` attributes
+    #[arg(long, value_enum, default_value_t = SsmlAlphabet::Ipa)]
+    alphabet: SsmlAlphabet,
+
+    /// Convert phonemes with this table instead of the built-in one (TSV, or JSON if the name
+    /// ends in .json)
+    #[arg(long, value_name = "PATH")]
+    phoneme_map: Option<PathBuf>,
+
+    /// Read numerals as English words, like "42" as "forty two"
+    #[arg(long)]
+    english_numbers: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+/// Phoneme sets of the SSML `alphabet` attribute.
+enum SsmlAlphabet {
+    Ipa,
+    XSampa,
+}
+
+/// Phonemize a text and write it as an SSML document with a `<phoneme>` tag per word.
+pub fn run(args: SsmlArgs) {
+    let mut model = load_model(args.model_path.as_deref());
+    if let Some(lexicon_path) = &args.lexicon {
+        model = model.with_lexicon(load_lexicon(lexicon_path));
+    }
+
+    let text = match (&args.text, &args.input) {
+        (Some(text), _) => text.clone(),
+        (None, Some(path)) => std::fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("Failed to read input file '{}': {}", path.display(), e);
+            std::process::exit(1);
+        }),
+        (None, None) => {
+            let mut text = String::new();
+            if let Err(e) = io::stdin().read_to_string(&mut text) {
+                eprintln!("Failed to read text: {}", e);
+                std::process::exit(1);
+            }
+            text
+        }
+    };
+
+    let alphabet = args.model_phonemes.alphabet();
+    let mapper = match (&args.phoneme_map, args.alphabet) {
+        (Some(path), _) => PhonemeMapper::try_from(path.as_path()).unwrap_or_else(|e| {
+            eprintln!("Failed to load phoneme map from '{}': {}", path.display(), e);
+            std::process::exit(1);
+        }),
+        (None, SsmlAlphabet::Ipa) => PhonemeMapper::to_ipa(alphabet),
+        (None, SsmlAlphabet::XSampa) => PhonemeMapper::to_xsampa(alphabet),
+    };
+    let stress = match alphabet {
+        PhonemeAlphabet::Arpabet => StressConvention::Arpabet,
+        PhonemeAlphabet::Nofabet => StressConvention::Nofabet,
+    };
+    let ssml = SsmlPhonemes::with_mapper(mapper, Some(stress)).with_alphabet(match args.alphabet {
+        SsmlAlphabet::Ipa => "ipa",
+        SsmlAlphabet::XSampa => "x-sampa",
+    });
+
+    let options = SentenceOptions {
+        verbalizer: args.english_numbers.then(|| Arc::new(EnglishNumbers) as Arc<dyn Verbalizer>),
+        // the case and normalization the model was trained with, if it comes in a bundle
+        phonemize: model.manifest().map(ModelManifest::options).unwrap_or_default(),
+        ..SentenceOptions::default()
+    };
+    match model.phonemize_ssml_with_options(text.trim_end(), &ssml, &options) {
+        Ok(ssml) => println!("{}", ssml),
+        Err(e) => {
+            eprintln!("Failed to phonemize text: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use clap::Args;
+
+use super::json::Value;
+use super::load_model;
+
+#[derive(Args)]
+pub struct ValidateArgs {
+    /// Path to the Phonetisaurus FST model file [default: $PHONETISAURUS_MODEL, or model_path
+    /// in the config file]
+    model_path: Option<String>,
+
+    /// Print the report as a JSON object
+    #[arg(long)]
+    json: bool,
+}
+
+/// Check the structure of a model, exiting with status 1 if problems are found.
+pub fn run(args: ValidateArgs) {
+    let model = load_model(args.model_path.as_deref());
+    let report = model.validate();
+
+    if args.json {
+        let issues: Vec<String> = report.issues.iter().map(ToString::to_string).collect();
+        let info = Value::object([
+            ("valid", report.is_valid().into()),
+            ("num_states", report.num_states.into()),
+            ("num_arcs", report.num_arcs.into()),
+            ("num_final_states", report.num_final_states.into()),
+            ("issues", issues.into()),
+        ]);
+        println!("{}", info);
+    } else {
+        println!("States: {}", report.num_states);
+        println!("Arcs: {}", report.num_arcs);
+        println!("Final states: {}", report.num_final_states);
+        for issue in &report.issues {
+            println!("Problem: {}", issue);
+        }
+        if report.is_valid() {
+            println!("The model is valid.");
+        }
+    }
+
+    if !report.is_valid() {
+        std::process::exit(1);
+    }
+}
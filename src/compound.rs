@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+use std::io::{self, BufRead};
+use std::ops::Range;
+
+use crate::lexicon::Lexicon;
+
+#[derive(Clone, Debug)]
+/// Splits compounds into known words, for a [`G2pPipeline`](crate::G2pPipeline) to phonemize
+/// part by part what the model gets wrong as a whole, like German and Scandinavian compounds.
+///
+/// Words are matched case-insensitively. A split uses as few parts as possible, at least two,
+/// each at least [`Self::with_min_part_len`] chars long and optionally followed by a linking
+/// element such as the German `s` in `Arbeitszimmer`, which is phonemized on its own.
+pub struct CompoundSplitter {
+    /// The known words, lowercased.
+    words: HashSet<String>,
+    /// Shortest part, in chars, without its linking element.
+    min_part_len: usize,
+    /// Lowercased elements that may follow a part.
+    linking_elements: Vec<String>,
+    /// Split words whose model score per grapheme is worse than this, besides failed ones.
+    max_score_per_grapheme: Option<f32>,
+}
+
+impl CompoundSplitter {
+    /// Create a splitter knowing the given words.
+    pub fn new<S: AsRef<str>>(words: impl IntoIterator<Item = S>) -> Self {
+        CompoundSplitter {
+            words: words.into_iter().map(|word| word.as_ref().to_lowercase()).collect(),
+            min_part_len: 3,
+            linking_elements: Vec::new(),
+            max_score_per_grapheme: None,
+        }
+    }
+
+    /// Create a splitter knowing the words of a lexicon.
+    pub fn from_lexicon(lexicon: &Lexicon) -> Self {
+        CompoundSplitter::new(lexicon.iter().map(|(word, _)| word))
+    }
+
+    /// Read the known words, the first field of every line, so that word lists and
+    /// pronunciation lexicons can be used alike. Empty lines and lines starting with `#` or
+    /// `;;;` are ignored.
+    pub fn from_reader(reader: impl BufRead) -> io::Result<Self> {
+        let mut words = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            match line.split_whitespace().next() {
+                Some(word) if !word.starts_with('#') && !word.starts_with(";;;") => words.push(word.to_string()),
+                _ => {}
+            }
+        }
+        Ok(CompoundSplitter::new(words))
+    }
+
+    /// Only split off parts of at least this many chars, 3 by default. Shorter parts make
+    /// spurious splits into short words likely.
+    pub fn with_min_part_len(mut self, min_part_len: usize) -> Self {
+        self.min_part_len = min_part_len.max(1);
+        self
+    }
+
+    /// Allow these elements between parts, e.g. `["s", "es", "n", "en"]` for German.
+    pub fn with_linking_elements<S: AsRef<str>>(mut self, elements: impl IntoIterator<Item = S>) -> Self {
+        self.linking_elements = elements.into_iter().map(|element| element.as_ref().to_lowercase()).collect();
+        self
+    }
+
+    /// Also split words the model phonemizes with a score per grapheme worse than this, not
+    /// only words it fails on.
+    pub fn with_max_score_per_grapheme(mut self, max_score: f32) -> Self {
+        self.max_score_per_grapheme = Some(max_score);
+        self
+    }
+
+    /// Whether a word phonemized with `neg_log_score` should be split anyway.
+    pub(crate) fn is_poor_score(&self, neg_log_score: f32, word: &str) -> bool {
+        self.max_score_per_grapheme
+            .is_some_and(|max_score| neg_log_score / word.chars().count().max(1) as f32 > max_score)
+    }
+
+    /// Split a word into known words, as ranges of chars in word order, with linking elements
+    /// in ranges of their own. `None` if the word is not a compound of known words.
+    pub fn split(&self, word: &str) -> Option<Vec<Range<usize>>> {
+        // lowercase char by char, so that positions stay the same
+        let chars: Vec<char> = word
+            .chars()
+            .map(|c| {
+                let mut lower = c.to_lowercase();
+                match (lower.next(), lower.next()) {
+                    (Some(lower), None) => lower,
+                    _ => c,
+                }
+            })
+            .collect();
+        let len = chars.len();
+
+        // fewest[end]: fewest parts covering chars[..end], with the start of the last part and
+        // the end of its word before any linking element
+        let mut fewest: Vec<Option<(usize, usize, usize)>> = vec![None; len + 1];
+        fewest[0] = Some((0, 0, 0));
+        for end in 1..=len {
+            fewest[end] = (0..end)
+                .filter_map(|start| Some((start, start + self.word_len(&chars[start..end])?)))
+                .filter_map(|(start, word_end)| fewest[start].map(|(parts, _, _)| (parts + 1, start, word_end)))
+                .min_by_key(|&(parts, _, _)| parts);
+        }
+
+        let (parts, _, _) = fewest[len]?;
+        if parts < 2 {
+            return None;
+        }
+        let mut ranges = Vec::with_capacity(2 * parts);
+        let mut end = len;
+        while end > 0 {
+            let (_, start, word_end) = fewest[end]?;
+            if word_end < end {
+                ranges.push(word_end..end);
+            }
+            ranges.push(start..word_end);
+            end = start;
+        }
+        ranges.reverse();
+        Some(ranges)
+    }
+
+    /// Length of the known word a part starts with, if the part is a known word optionally
+    /// followed by a linking element.
+    fn word_len(&self, chars: &[char]) -> Option<usize> {
+        let part: String = chars.iter().collect();
+        let is_word = |word: &str| word.chars().count() >= self.min_part_len && self.words.contains(word);
+        if is_word(&part) {
+            return Some(chars.len());
+        }
+        self.linking_elements
+            .iter()
+            .filter(|element| part.strip_suffix(element.as_str()).is_some_and(is_word))
+            .map(|element| chars.len() - element.chars().count())
+            .next()
+    }
+}
@@ -0,0 +1,631 @@
+//! Phonemization results kept in a file, so that they survive the process.
+//!
+//! The file is a log with one line per cached word, appended to as words are decoded, which
+//! makes inserting cheap and loses at most the line being written if the process dies. Lines
+//! of words that were evicted or expired are dropped when the file is compacted, which
+//! happens when a cache that inserted words is flushed or dropped while such lines outnumber
+//! the live ones. Several processes may share a file for reading, but only one should write
+//! to it at a time.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::alignment::AlignedSegment;
+use crate::error::{PhonetisaurusError, Result};
+use crate::options::PhonemizeOptions;
+use crate::parallel::par_map;
+use crate::phonetisaurus::{PhonetisaurusModel, PhonetizationResult};
+use crate::stress::Stress;
+use crate::trace::{DecodeDiagnostics, ResultSource};
+
+/// First line of a cache file, with the version of the format.
+const HEADER: &str = "phonetisaurus-g2p cache 1";
+
+/// Results of words, by key, kept in a file. See the [module](self) documentation.
+///
+/// Keys are opaque strings; [`DiskCachedModel`] derives them from the model, the options and
+/// the word.
+pub struct DiskCache {
+    path: PathBuf,
+    ttl: Option<Duration>,
+    max_entries: Option<usize>,
+    state: Mutex<CacheState>,
+}
+
+struct CacheState {
+    /// Results by key, with the time they were written in seconds since the epoch.
+    entries: HashMap<String, (u64, Vec<PhonetizationResult>)>,
+    /// Number of entry lines in the file, including superseded ones.
+    lines: usize,
+    /// The file, opened for appending on the first insertion.
+    file: Option<File>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Contents of a [`DiskCache`].
+pub struct DiskCacheStats {
+    /// Number of cached words, including expired ones.
+    pub entries: usize,
+    /// Number of cached words older than the time to live.
+    pub expired: usize,
+    /// Size of the file in bytes.
+    pub file_size: u64,
+    /// When the oldest and the newest word was cached, if there is one.
+    pub oldest: Option<SystemTime>,
+    pub newest: Option<SystemTime>,
+}
+
+impl DiskCache {
+    /// Open the cache file at `path`, which is created when the first word is inserted.
+    ///
+    /// Lines that cannot be read, like the last one of a process that was killed while
+    /// writing it, are skipped.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut entries = HashMap::new();
+        let mut lines = 0;
+        match File::open(&path) {
+            Ok(file) => {
+                let mut reader = BufReader::new(file).lines();
+                match reader.next().transpose().map_err(cache_error)? {
+                    Some(header) if header == HEADER => {}
+                    None => {}
+                    Some(_) => {
+                        let message = format!("{} is not a phonemization cache", path.display());
+                        return Err(cache_error(io::Error::new(io::ErrorKind::InvalidData, message)));
+                    }
+                }
+                for line in reader {
+                    let line = line.map_err(cache_error)?;
+                    if let Some((key, written, results)) = decode_entry(&line) {
+                        entries.insert(key, (written, results));
+                        lines += 1;
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(cache_error(e)),
+        }
+        Ok(DiskCache {
+            path,
+            ttl: None,
+            max_entries: None,
+            state: Mutex::new(CacheState {
+                entries,
+                lines,
+                file: None,
+            }),
+        })
+    }
+
+    /// Treat words cached longer ago than `ttl` as missing, and drop them from the file.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Keep at most `max_entries` words, evicting the ones cached longest ago.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        let mut state = self.lock();
+        evict(&mut state.entries, max_entries);
+        drop(state);
+        self
+    }
+
+    /// The results cached under `key`, unless they expired.
+    pub fn get(&self, key: &str) -> Option<Vec<PhonetizationResult>> {
+        let state = self.lock();
+        let (written, results) = state.entries.get(key)?;
+        if self.is_expired(*written, now()) {
+            return None;
+        }
+        Some(results.clone())
+    }
+
+    /// Cache results under `key`, appending them to the file.
+    pub fn insert(&self, key: &str, results: &[PhonetizationResult]) -> Result<()> {
+        let written = now();
+        let line = encode_entry(key, written, results);
+        let mut state = self.lock();
+        if state.file.is_none() {
+            state.file = Some(self.open_for_append().map_err(cache_error)?);
+        }
+        let file = state.file.as_mut().expect("the file was opened");
+        // written at once, so that a line is never split by a concurrent writer
+        file.write_all(line.as_bytes()).map_err(cache_error)?;
+        state.entries.insert(key.to_string(), (written, results.to_vec()));
+        state.lines += 1;
+        if let Some(max_entries) = self.max_entries {
+            evict(&mut state.entries, max_entries);
+        }
+        Ok(())
+    }
+
+    /// Number of cached words, including expired ones.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Whether no word is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// What the cache holds.
+    pub fn stats(&self) -> DiskCacheStats {
+        let state = self.lock();
+        let now = now();
+        let times = || state.entries.values().map(|(written, _)| *written);
+        let to_time = |seconds: u64| UNIX_EPOCH + Duration::from_secs(seconds);
+        DiskCacheStats {
+            entries: state.entries.len(),
+            expired: times().filter(|&written| self.is_expired(written, now)).count(),
+            file_size: fs::metadata(&self.path).map_or(0, |metadata| metadata.len()),
+            oldest: times().min().map(to_time),
+            newest: times().max().map(to_time),
+        }
+    }
+
+    /// Forget all cached words, and empty the file.
+    pub fn clear(&self) -> Result<()> {
+        let mut state = self.lock();
+        state.entries.clear();
+        self.rewrite(&mut state)
+    }
+
+    /// Rewrite the file with only the words that are still cached.
+    pub fn compact(&self) -> Result<()> {
+        let mut state = self.lock();
+        self.rewrite(&mut state)
+    }
+
+    /// Make sure everything inserted is in the file, and compact the file if words were
+    /// inserted and most of its lines are of words no longer cached.
+    pub fn flush(&self) -> Result<()> {
+        let mut state = self.lock();
+        let Some(file) = &mut state.file else {
+            return Ok(());
+        };
+        file.flush().map_err(cache_error)?;
+        let now = now();
+        let live = state.entries.values().filter(|(written, _)| !self.is_expired(*written, now)).count();
+        if state.lines > 2 * live {
+            self.rewrite(&mut state)?;
+        }
+        Ok(())
+    }
+
+    /// Write the live entries to a new file and move it over the old one.
+    fn rewrite(&self, state: &mut CacheState) -> Result<()> {
+        let now = now();
+        state.entries.retain(|_, (written, _)| !self.is_expired(*written, now));
+        let mut entries: Vec<_> = state.entries.iter().collect();
+        entries.sort_by(|a, b| (a.1.0, a.0).cmp(&(b.1.0, b.0)));
+        let mut contents = format!("{}\n", HEADER);
+        for (key, (written, results)) in entries {
+            contents.push_str(&encode_entry(key, *written, results));
+        }
+
+        let partial_path = self.path.with_extension(format!("part-{}", std::process::id()));
+        let written = fs::write(&partial_path, contents).and_then(|_| fs::rename(&partial_path, &self.path));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&partial_path);
+            return Err(cache_error(e));
+        }
+        // the old file was replaced, appends go to the new one
+        state.file = None;
+        state.lines = state.entries.len();
+        Ok(())
+    }
+
+    fn open_for_append(&self) -> io::Result<File> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).read(true).append(true).open(&self.path)?;
+        let len = file.metadata()?.len();
+        if len == 0 {
+            writeln!(file, "{}", HEADER)?;
+            return Ok(file);
+        }
+        // a process killed while writing leaves a partial line, which the next one must not extend
+        let mut last = [0];
+        file.seek(SeekFrom::Start(len - 1))?;
+        file.read_exact(&mut last)?;
+        if last != *b"\n" {
+            writeln!(file)?;
+        }
+        Ok(file)
+    }
+
+    fn is_expired(&self, written: u64, now: u64) -> bool {
+        self.ttl.is_some_and(|ttl| now.saturating_sub(written) > ttl.as_secs())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        // the state is consistent between statements, so a poisoned one is intact
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for DiskCache {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Phonemizer that keeps its results in a [`DiskCache`], created by
+/// [`PhonetisaurusModel::with_disk_cache`].
+///
+/// Results are cached per model, options and number of pronunciations, so one file can serve
+/// several models and configurations. Failed words are not cached.
+pub struct DiskCachedModel {
+    model: PhonetisaurusModel,
+    cache: DiskCache,
+    /// Hash of the model, its lexicon and its post-transducers, in hex.
+    fingerprint: String,
+}
+
+impl DiskCachedModel {
+    /// Phonemize a word, answering from the cache if it was phonemized before.
+    pub fn phonemize_word(&self, word: &str) -> Result<PhonetizationResult> {
+        let mut results = self.phonemize_word_nbest_with_options(word, 1, &PhonemizeOptions::default())?;
+        Ok(results.remove(0))
+    }
+
+    /// Phonemize a word with up to `n` distinct pronunciations, using the given options,
+    /// answering from the cache if it was phonemized before with the same options.
+    pub fn phonemize_word_nbest_with_options(
+        &self,
+        word: &str,
+        n: usize,
+        options: &PhonemizeOptions,
+    ) -> Result<Vec<PhonetizationResult>> {
+        let mut context = fnv1a(FNV_OFFSET, self.fingerprint.as_bytes());
+        context = fnv1a(context, &n.to_le_bytes());
+        context = fnv1a(context, options.cache_key().as_bytes());
+        let key = format!("{:016x} {}", context, word);
+        if let Some(mut results) = self.cache.get(&key) {
+            if options.diagnostics {
+                for result in &mut results {
+                    result.diagnostics = Some(DecodeDiagnostics {
+                        source: ResultSource::Cache,
+                        ..DecodeDiagnostics::default()
+                    });
+                }
+            }
+            return Ok(results);
+        }
+        let results = self.model.phonemize_word_nbest_with_options(word, n, options)?;
+        self.cache.insert(&key, &results)?;
+        Ok(results)
+    }
+
+    /// Phonemize many words in parallel on up to `threads` threads, one result per word in
+    /// input order, answering from the cache like [`Self::phonemize_word_nbest_with_options`].
+    pub fn phonemize_words_nbest_with_threads(
+        &self,
+        words: &[&str],
+        n: usize,
+        options: &PhonemizeOptions,
+        threads: usize,
+    ) -> Vec<Result<Vec<PhonetizationResult>>> {
+        par_map(words, threads, |word| self.phonemize_word_nbest_with_options(word, n, options))
+    }
+
+    /// The wrapped model, for calls that bypass the cache.
+    pub fn model(&self) -> &PhonetisaurusModel {
+        &self.model
+    }
+
+    /// The cache the results are kept in.
+    pub fn cache(&self) -> &DiskCache {
+        &self.cache
+    }
+}
+
+impl PhonetisaurusModel {
+    /// Keep the results of the model in a file, see [`DiskCachedModel`].
+    pub fn with_disk_cache(self, cache: DiskCache) -> Result<DiskCachedModel> {
+        let fingerprint = fnv1a(FNV_OFFSET, &self.content_bytes()?);
+        Ok(DiskCachedModel {
+            model: self,
+            cache,
+            fingerprint: format!("{:016x}", fingerprint),
+        })
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// The 64-bit FNV-1a hash of `bytes`, continuing from `hash`.
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Drop the entries written longest ago until at most `max_entries` are left, and a tenth
+/// less if there are too many, so that evicting is not needed on every insertion.
+fn evict(entries: &mut HashMap<String, (u64, Vec<PhonetizationResult>)>, max_entries: usize) {
+    if entries.len() <= max_entries {
+        return;
+    }
+    let keep = max_entries - max_entries / 10;
+    let mut by_age: Vec<_> = entries.iter().map(|(key, (written, _))| (*written, key.clone())).collect();
+    by_age.sort_unstable();
+    for (_, key) in &by_age[..entries.len() - keep] {
+        entries.remove(key);
+    }
+}
+
+fn cache_error(e: io::Error) -> PhonetisaurusError {
+    PhonetisaurusError::CacheError(e)
+}
+
+/// A line of the file: the key, the time it was written and the results, as fields
+/// separated by spaces, with lists preceded by their length.
+fn encode_entry(key: &str, written: u64, results: &[PhonetizationResult]) -> String {
+    let mut fields = vec![escape(key), written.to_string(), results.len().to_string()];
+    for result in results {
+        fields.push(escape(&result.phonemes));
+        push_strings(&mut fields, &result.phoneme_tokens);
+        fields.extend([result.neg_log_score, result.posterior, result.normalized_score].map(|score| score.to_string()));
+        fields.push(result.alignment.len().to_string());
+        for segment in &result.alignment {
+            fields.push(segment.graphemes.start.to_string());
+            fields.push(segment.graphemes.end.to_string());
+            push_strings(&mut fields, &segment.phonemes);
+        }
+        fields.push(result.syllables.len().to_string());
+        for syllable in &result.syllables {
+            push_strings(&mut fields, syllable);
+        }
+        fields.push(result.stress.len().to_string());
+        for (position, stress) in &result.stress {
+            fields.push(position.to_string());
+            fields.push(match stress {
+                Stress::Primary => "1",
+                Stress::Secondary => "2",
+            }.to_string());
+        }
+        fields.push(result.phoneme_posteriors.len().to_string());
+        fields.extend(result.phoneme_posteriors.iter().map(f32::to_string));
+    }
+    let mut line = fields.join(" ");
+    line.push('\n');
+    line
+}
+
+fn push_strings(fields: &mut Vec<String>, strings: &[String]) {
+    fields.push(strings.len().to_string());
+    fields.extend(strings.iter().map(|string| escape(string)));
+}
+
+/// Read a line written by [`encode_entry`], `None` if it is malformed.
+fn decode_entry(line: &str) -> Option<(String, u64, Vec<PhonetizationResult>)> {
+    let mut fields = line.split(' ');
+    let mut next = || fields.next();
+    let key = unescape(next()?)?;
+    let written = next()?.parse().ok()?;
+    let count: usize = next()?.parse().ok()?;
+    let mut results = Vec::new();
+    for _ in 0..count {
+        let phonemes = unescape(next()?)?;
+        let phoneme_tokens = read_strings(&mut next)?;
+        let neg_log_score = next()?.parse().ok()?;
+        let posterior = next()?.parse().ok()?;
+        let normalized_score = next()?.parse().ok()?;
+        let segments: usize = next()?.parse().ok()?;
+        let mut alignment = Vec::new();
+        for _ in 0..segments {
+            let start = next()?.parse().ok()?;
+            let end = next()?.parse().ok()?;
+            let phonemes = read_strings(&mut next)?;
+            alignment.push(AlignedSegment {
+                graphemes: start..end,
+                phonemes,
+            });
+        }
+        let count: usize = next()?.parse().ok()?;
+        let syllables = (0..count).map(|_| read_strings(&mut next)).collect::<Option<_>>()?;
+        let count: usize = next()?.parse().ok()?;
+        let mut stress = Vec::new();
+        for _ in 0..count {
+            let position = next()?.parse().ok()?;
+            stress.push((position, match next()? {
+                "1" => Stress::Primary,
+                "2" => Stress::Secondary,
+                _ => return None,
+            }));
+        }
+        let count: usize = next()?.parse().ok()?;
+        let phoneme_posteriors = (0..count).map(|_| next()?.parse().ok()).collect::<Option<_>>()?;
+        results.push(PhonetizationResult {
+            phonemes,
+            phoneme_tokens,
+            neg_log_score,
+            posterior,
+            normalized_score,
+            alignment,
+            syllables,
+            stress,
+            phoneme_posteriors,
+            diagnostics: None,
+        });
+    }
+    if next().is_some() {
+        return None;
+    }
+    Some((key, written, results))
+}
+
+fn read_strings<'a>(next: &mut impl FnMut() -> Option<&'a str>) -> Option<Vec<String>> {
+    let count: usize = next()?.parse().ok()?;
+    (0..count).map(|_| unescape(next()?)).collect()
+}
+
+/// Escape the characters that separate fields and lines.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ' ' => escaped.push_str("\\s"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(field: &str) -> Option<String> {
+    let mut text = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        text.push(match chars.next()? {
+            '\\' => '\\',
+            's' => ' ',
+            'n' => '\n',
+            'r' => '\r',
+            _ => return None,
+        });
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> PhonetizationResult {
+        PhonetizationResult {
+            phonemes: "HH AH0 L OW1".to_string(),
+            phoneme_tokens: ["HH", "AH0", "L", "OW1"].map(String::from).to_vec(),
+            neg_log_score: 3.25,
+            posterior: 0.75,
+            normalized_score: 0.8125,
+            alignment: vec![
+                AlignedSegment {
+                    graphemes: 0..1,
+                    phonemes: vec!["HH".to_string()],
+                },
+                AlignedSegment {
+                    graphemes: 1..5,
+                    phonemes: ["AH0", "L", "OW1"].map(String::from).to_vec(),
+                },
+            ],
+            syllables: vec![vec!["HH".to_string(), "AH0".to_string()], vec!["L".to_string(), "OW1".to_string()]],
+            stress: vec![(3, Stress::Primary)],
+            phoneme_posteriors: vec![1.0, 0.5, 1.0, 0.25],
+            diagnostics: None,
+        }
+    }
+
+    /// A file in the temporary directory, removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("disk-cache-{}-{}", std::process::id(), name));
+            let _ = fs::remove_file(&path);
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn escapes_separators() {
+        let text = "a b\\c\nd\re\\s";
+        let escaped = escape(text);
+        assert!(!escaped.contains([' ', '\n', '\r']));
+        assert_eq!(unescape(&escaped).as_deref(), Some(text));
+        assert_eq!(escape("a b\\"), "a\\sb\\\\");
+    }
+
+    #[test]
+    fn rejects_invalid_escapes() {
+        assert_eq!(unescape("a\\x"), None);
+        assert_eq!(unescape("a\\"), None);
+    }
+
+    #[test]
+    fn round_trips_entries() {
+        let key = "0123456789abcdef new york\\city";
+        let line = encode_entry(key, 1700000000, &[result(), result()]);
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+
+        let (decoded_key, written, results) = decode_entry(line.trim_end_matches('\n')).expect("a valid line");
+        assert_eq!(decoded_key, key);
+        assert_eq!(written, 1700000000);
+        assert_eq!(results.len(), 2);
+        let (expected, decoded) = (result(), &results[0]);
+        assert_eq!(decoded.phonemes, expected.phonemes);
+        assert_eq!(decoded.phoneme_tokens, expected.phoneme_tokens);
+        assert_eq!(decoded.neg_log_score, expected.neg_log_score);
+        assert_eq!(decoded.posterior, expected.posterior);
+        assert_eq!(decoded.normalized_score, expected.normalized_score);
+        assert_eq!(decoded.alignment, expected.alignment);
+        assert_eq!(decoded.syllables, expected.syllables);
+        assert_eq!(decoded.stress, expected.stress);
+        assert_eq!(decoded.phoneme_posteriors, expected.phoneme_posteriors);
+    }
+
+    #[test]
+    fn rejects_truncated_lines() {
+        let line = encode_entry("key", 1, &[result()]);
+        let line = line.trim_end_matches('\n');
+        // cut at every field boundary, where the prefix still parses field by field
+        for (cut, _) in line.match_indices(' ') {
+            assert!(decode_entry(&line[..cut]).is_none(), "accepted {:?}", &line[..cut]);
+        }
+        assert!(decode_entry(&format!("{} 1", line)).is_none());
+    }
+
+    #[test]
+    fn appends_after_a_truncated_line() {
+        let file = TempFile::new("truncated");
+        let cache = DiskCache::open(&file.0).unwrap();
+        cache.insert("first", &[result()]).unwrap();
+        drop(cache);
+        // a process killed in the middle of writing a line
+        let partial = encode_entry("partial", 1, &[result()]);
+        OpenOptions::new()
+            .append(true)
+            .open(&file.0)
+            .and_then(|mut f| f.write_all(&partial.as_bytes()[..partial.len() / 2]))
+            .unwrap();
+
+        let cache = DiskCache::open(&file.0).unwrap();
+        assert_eq!(cache.len(), 1);
+        cache.insert("second", &[result()]).unwrap();
+        drop(cache);
+
+        let cache = DiskCache::open(&file.0).unwrap();
+        assert!(cache.get("first").is_some());
+        assert!(cache.get("second").is_some());
+        assert!(cache.get("partial").is_none());
+    }
+}
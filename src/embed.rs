@@ -0,0 +1,66 @@
+/// Embed a model in the binary and parse it on first use.
+///
+/// `phonetisaurus_model!(pub static MODEL = "../models/en.fst");` declares a `static` that
+/// derefs to the model, and `phonetisaurus_model!("../models/en.fst")` is an expression of
+/// type `&'static PhonetisaurusModel`. The file is included with `include_bytes!`, so its
+/// path is relative to the file the macro is used in. The model is parsed the first time it
+/// is accessed, which panics if the embedded file is not a valid model.
+///
+/// Gzip- and zstd-compressed models are decompressed when parsed, so `en.fst.gz` and
+/// `en.fst.zst` can be embedded as they are. Other formats can be embedded along with a
+/// function decompressing them, e.g. with `xz2`:
+/// `phonetisaurus_model!(static MODEL = "en.fst.xz", decompress = unxz)`, where `unxz` takes
+/// the embedded `&[u8]` and returns the model as a `Vec<u8>`, or a `Result` of it.
+#[macro_export]
+macro_rules! phonetisaurus_model {
+    ($vis:vis static $name:ident = $path:literal $(, decompress = $decompress:expr)? $(,)?) => {
+        $vis static $name: ::std::sync::LazyLock<$crate::PhonetisaurusModel> =
+            ::std::sync::LazyLock::new(|| $crate::phonetisaurus_model!(@parse $path $(, $decompress)?));
+    };
+    ($path:literal $(, decompress = $decompress:expr)? $(,)?) => {{
+        static MODEL: ::std::sync::LazyLock<$crate::PhonetisaurusModel> =
+            ::std::sync::LazyLock::new(|| $crate::phonetisaurus_model!(@parse $path $(, $decompress)?));
+        &*MODEL
+    }};
+    (@parse $path:literal) => {
+        $crate::embedded_model(include_bytes!($path), $path)
+    };
+    (@parse $path:literal, $decompress:expr) => {
+        $crate::embedded_model(
+            $crate::EmbeddedBytes::into_bytes(($decompress)(&include_bytes!($path)[..]), $path).as_ref(),
+            $path,
+        )
+    };
+}
+
+/// Parse a model embedded by [`phonetisaurus_model!`], panicking if it is invalid.
+#[doc(hidden)]
+pub fn embedded_model(model_binary: &[u8], path: &str) -> crate::PhonetisaurusModel {
+    crate::PhonetisaurusModel::try_from(model_binary)
+        .unwrap_or_else(|e| panic!("Invalid model embedded from {}: {}", path, e))
+}
+
+/// What the decompressing function of [`phonetisaurus_model!`] may return: the bytes of the
+/// model, or a `Result` of them.
+#[doc(hidden)]
+pub trait EmbeddedBytes {
+    type Bytes: AsRef<[u8]>;
+
+    fn into_bytes(self, path: &str) -> Self::Bytes;
+}
+
+impl EmbeddedBytes for Vec<u8> {
+    type Bytes = Vec<u8>;
+
+    fn into_bytes(self, _path: &str) -> Vec<u8> {
+        self
+    }
+}
+
+impl<T: AsRef<[u8]>, E: std::fmt::Display> EmbeddedBytes for Result<T, E> {
+    type Bytes = T;
+
+    fn into_bytes(self, path: &str) -> T {
+        self.unwrap_or_else(|e| panic!("Can't decompress the model embedded from {}: {}", path, e))
+    }
+}
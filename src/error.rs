@@ -0,0 +1,157 @@
+use rustfst::Label;
+use std::fmt;
+
+/// Result type used throughout the crate.
+pub type Result<T, E = PhonetisaurusError> = std::result::Result<T, E>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Which side of the model a symbol table belongs to.
+pub enum SymbolTableKind {
+    /// Graphemes, read by the model.
+    Input,
+    /// Phonemes, produced by the model.
+    Output,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+/// Errors produced while loading a model or phonemizing with it.
+pub enum PhonetisaurusError {
+    /// The model could not be read or parsed as an FST.
+    ModelLoadError(anyhow::Error),
+    /// The model has no symbol table on the given side, but one is needed.
+    MissingSymbolTable(SymbolTableKind),
+    /// A character of the word is not in the input symbol table.
+    UnknownSymbol {
+        /// The offending character.
+        ch: char,
+        /// Position of the character in the word, counted in chars, after case folding and
+        /// normalization.
+        position: usize,
+    },
+    /// A label produced by the model is not in the output symbol table.
+    UnknownLabel(Label),
+    /// A phoneme given to the model is not in the output symbol table.
+    UnknownPhoneme(String),
+    /// The composed FST contains no path, so the word cannot be transcribed.
+    NoPathFound,
+    /// The model is compressed with the named format, which this build cannot decompress.
+    UnsupportedCompression(&'static str),
+    /// An FST operation (construction, composition, search) failed.
+    FstError(anyhow::Error),
+    /// Decoding took longer than [`PhonemizeOptions::timeout`](crate::PhonemizeOptions::timeout).
+    Timeout,
+    /// Decoding was aborted through a [`CancellationToken`](crate::CancellationToken).
+    Cancelled,
+    /// The word is longer than [`PhonemizeOptions::max_word_length`](crate::PhonemizeOptions::max_word_length).
+    InputTooLong {
+        /// Length of the word in chars.
+        length: usize,
+        /// The maximum length in chars that was exceeded.
+        limit: usize,
+    },
+    /// A decoding lattice grew beyond
+    /// [`PhonemizeOptions::max_lattice_states`](crate::PhonemizeOptions::max_lattice_states).
+    SearchSpaceExceeded {
+        /// Number of states of the lattice.
+        states: usize,
+        /// The maximum number of states that was exceeded.
+        limit: usize,
+    },
+    /// No model was registered for the language tag, see [`ModelRegistry`](crate::ModelRegistry).
+    /// The tag is empty if no model was registered at all.
+    UnknownLanguage(String),
+    /// The [`global`](crate::global) model was used before it was initialized.
+    NotInitialized,
+    /// The [`global`](crate::global) model was initialized a second time.
+    AlreadyInitialized,
+    /// A model could not be downloaded, see [`fetch`](crate::fetch).
+    DownloadError(anyhow::Error),
+    /// A downloaded model does not have the expected SHA-256 checksum.
+    ChecksumMismatch {
+        /// The checksum the model should have, in hex.
+        expected: String,
+        /// The checksum of the downloaded file, in hex.
+        actual: String,
+    },
+    /// The file of a [`DiskCache`](crate::DiskCache) could not be read or written.
+    CacheError(std::io::Error),
+    /// A phoneme has no ID in the [`PhonemeIdMap`](crate::PhonemeIdMap) it was converted with.
+    NoPhonemeId(String),
+}
+
+impl fmt::Display for PhonetisaurusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PhonetisaurusError::ModelLoadError(e) => write!(f, "{:#}", e),
+            PhonetisaurusError::MissingSymbolTable(kind) => write!(
+                f,
+                "No {} symbol table found in loaded FST model, but one is needed.",
+                kind
+            ),
+            PhonetisaurusError::UnknownSymbol { ch, position } => write!(
+                f,
+                "Symbol {} at position {} not found in symbol table. Most likely, the FST was not trained with this symbol.",
+                ch, position
+            ),
+            PhonetisaurusError::UnknownLabel(label) => write!(
+                f,
+                "Symbol for label {} not found in output symbol table",
+                label
+            ),
+            PhonetisaurusError::UnknownPhoneme(phoneme) => write!(
+                f,
+                "Phoneme {} not found in output symbol table",
+                phoneme
+            ),
+            PhonetisaurusError::NoPathFound => write!(
+                f,
+                "Transcription failed: No path found in FST for the given word."
+            ),
+            PhonetisaurusError::UnsupportedCompression(format) => write!(
+                f,
+                "Model is {}-compressed, decompress it before loading.",
+                format
+            ),
+            PhonetisaurusError::FstError(e) => write!(f, "FST operation failed: {:#}", e),
+            PhonetisaurusError::Timeout => write!(f, "Transcription timed out."),
+            PhonetisaurusError::Cancelled => write!(f, "Transcription was cancelled."),
+            PhonetisaurusError::InputTooLong { length, limit } => write!(
+                f,
+                "Word is {} characters long, the limit is {}.",
+                length, limit
+            ),
+            PhonetisaurusError::SearchSpaceExceeded { states, limit } => write!(
+                f,
+                "Decoding lattice has {} states, the limit is {}.",
+                states, limit
+            ),
+            PhonetisaurusError::UnknownLanguage(lang) if lang.is_empty() => write!(f, "No model was registered."),
+            PhonetisaurusError::UnknownLanguage(lang) => write!(f, "No model for language '{}'.", lang),
+            PhonetisaurusError::NotInitialized => write!(f, "The global model was not initialized."),
+            PhonetisaurusError::AlreadyInitialized => write!(f, "The global model was already initialized."),
+            PhonetisaurusError::DownloadError(e) => write!(f, "{:#}", e),
+            PhonetisaurusError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "Downloaded model has SHA-256 checksum {}, expected {}.",
+                actual, expected
+            ),
+            PhonetisaurusError::CacheError(e) => write!(f, "Phonemization cache failed: {}", e),
+            PhonetisaurusError::NoPhonemeId(phoneme) => write!(f, "Phoneme {} has no ID in the phoneme ID map", phoneme),
+        }
+    }
+}
+
+impl fmt::Display for SymbolTableKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolTableKind::Input => write!(f, "input"),
+            SymbolTableKind::Output => write!(f, "output"),
+        }
+    }
+}
+
+// the messages of wrapped errors are part of the message, so there is no source that would
+// repeat them when the chain of sources is printed
+impl std::error::Error for PhonetisaurusError {}
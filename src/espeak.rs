@@ -0,0 +1,93 @@
+use crate::phoneme_map::{PhonemeAlphabet, PhonemeMapper};
+use crate::phonetisaurus::PhonetizationResult;
+use crate::sentence::SentenceResult;
+use crate::stress::StressConvention;
+
+/// Writes pronunciations in the phoneme mnemonics of eSpeak-ng, the way `espeak-ng -x` does,
+/// like `h@l'oU` for "hello": the phonemes are joined without separator and stressed ones are
+/// preceded by `'` (primary) or `,` (secondary).
+///
+/// With [`Self::with_brackets`], pronunciations are written as `[[h@l'oU]]`, the notation
+/// eSpeak-ng reads phonemes in, so that they can be passed on to it as text.
+#[derive(Clone, Debug)]
+pub struct EspeakPhonemes {
+    mapper: PhonemeMapper,
+    /// How the model marks stress, `None` if it does not.
+    stress: Option<StressConvention>,
+    bracketed: bool,
+}
+
+impl EspeakPhonemes {
+    /// Convert the symbols of `alphabet` with the built-in table of
+    /// [`PhonemeMapper::to_espeak`], reading the stress from their digits.
+    pub fn new(alphabet: PhonemeAlphabet) -> Self {
+        let stress = match alphabet {
+            PhonemeAlphabet::Arpabet => StressConvention::Arpabet,
+            PhonemeAlphabet::Nofabet => StressConvention::Nofabet,
+        };
+        EspeakPhonemes::with_mapper(PhonemeMapper::to_espeak(alphabet), Some(stress))
+    }
+
+    /// Convert symbols with another table, for models of other phoneme sets. `stress` says how
+    /// the model marks stress, `None` if it does not.
+    pub fn with_mapper(mapper: PhonemeMapper, stress: Option<StressConvention>) -> Self {
+        EspeakPhonemes {
+            mapper,
+            stress,
+            bracketed: false,
+        }
+    }
+
+    /// Write pronunciations in `[[` and `]]`.
+    pub fn with_brackets(mut self, bracketed: bool) -> Self {
+        self.bracketed = bracketed;
+        self
+    }
+
+    /// Mnemonics of the phoneme tokens, each preceded by its stress marker. Tokens that are
+    /// only stress markers are dropped.
+    pub fn map_tokens<S: AsRef<str>>(&self, tokens: &[S]) -> Vec<String> {
+        self.mapper.map_marked(tokens, self.stress, ["'", ","])
+    }
+
+    /// The pronunciation of a word given by its phoneme tokens.
+    pub fn phonemes<S: AsRef<str>>(&self, tokens: &[S]) -> String {
+        self.wrap(self.map_tokens(tokens).concat())
+    }
+
+    /// The pronunciation of a result.
+    pub fn result_phonemes(&self, result: &PhonetizationResult) -> String {
+        self.phonemes(&result.phoneme_tokens)
+    }
+
+    /// The pronunciation of a sentence, with spaces between words.
+    pub fn sentence_phonemes(&self, sentence: &SentenceResult) -> String {
+        let words: Vec<String> = sentence
+            .words
+            .iter()
+            .map(|word| self.map_tokens(&word.result.phoneme_tokens).concat())
+            .collect();
+        self.wrap(words.join(" "))
+    }
+
+    /// Convert the phonemes of a result, including those of its alignment and syllables.
+    /// `phonemes` becomes the eSpeak-ng pronunciation.
+    pub fn map_result(&self, result: &mut PhonetizationResult) {
+        result.phonemes = self.result_phonemes(result);
+        result.phoneme_tokens = self.map_tokens(&result.phoneme_tokens);
+        for segment in &mut result.alignment {
+            segment.phonemes = self.map_tokens(&segment.phonemes);
+        }
+        for syllable in &mut result.syllables {
+            *syllable = self.map_tokens(syllable);
+        }
+    }
+
+    fn wrap(&self, phonemes: String) -> String {
+        if self.bracketed {
+            format!("[[{}]]", phonemes)
+        } else {
+            phonemes
+        }
+    }
+}
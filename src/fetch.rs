@@ -0,0 +1,185 @@
+//! Downloading models into a local cache, so that applications can fetch the model they need
+//! on first use instead of shipping it.
+//!
+//! Models are cached under `$XDG_CACHE_HOME/phonetisaurus-g2p`, or `~/.cache/phonetisaurus-g2p`,
+//! and verified against a SHA-256 checksum if one is given. Downloads are made with the
+//! `curl` program, which must be installed, so that this crate does not depend on a TLS
+//! implementation.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::anyhow;
+
+use crate::error::{PhonetisaurusError, Result};
+use crate::phonetisaurus::PhonetisaurusModel;
+use crate::sha256::{Sha256, sha256_hex};
+
+/// Name of the cache directory of the crate, inside the cache directory of the user.
+const CACHE_NAME: &str = "phonetisaurus-g2p";
+
+#[derive(Clone, Debug)]
+/// A model to download from an HTTPS URL, see the [module](self) documentation.
+pub struct ModelDownload {
+    url: String,
+    sha256: Option<String>,
+    cache_dir: Option<PathBuf>,
+    /// HTTP headers sent with the request, as `name: value` lines.
+    headers: Vec<String>,
+}
+
+impl ModelDownload {
+    pub fn new(url: &str) -> Self {
+        ModelDownload {
+            url: url.to_string(),
+            sha256: None,
+            cache_dir: None,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Verify the downloaded file against this SHA-256 checksum, given in hex.
+    ///
+    /// A cached file that does not match is downloaded again.
+    pub fn with_sha256(mut self, sha256: &str) -> Self {
+        self.sha256 = Some(sha256.trim().to_ascii_lowercase());
+        self
+    }
+
+    /// Cache the model in this directory instead of the default one, see [`cache_dir`].
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// Send an HTTP header with the request, e.g. `Authorization` for private models. Headers
+    /// are passed to `curl` on its standard input, so they do not show in the process list.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push(format!("{}: {}", name, value));
+        self
+    }
+
+    /// Where the model is cached.
+    ///
+    /// Each URL gets a directory of its own, named after the hash of the URL, which keeps the
+    /// file name of the URL.
+    pub fn cached_path(&self) -> Result<PathBuf> {
+        let cache_dir = match &self.cache_dir {
+            Some(cache_dir) => cache_dir.clone(),
+            None => cache_dir().ok_or_else(|| {
+                PhonetisaurusError::DownloadError(anyhow!("No cache directory, neither XDG_CACHE_HOME nor HOME is set"))
+            })?,
+        };
+        let mut url_hash = Sha256::new();
+        url_hash.update(self.url.as_bytes());
+        let file_name = self
+            .url
+            .split(['?', '#'])
+            .next()
+            .and_then(|path| path.rsplit('/').next())
+            .filter(|name| !name.is_empty() && *name != "." && *name != "..")
+            .unwrap_or("model.fst");
+        Ok(cache_dir.join(&url_hash.finish_hex()[..16]).join(file_name))
+    }
+
+    /// Download the model unless it is cached already, and return the path of the cached file.
+    pub fn fetch(&self) -> Result<PathBuf> {
+        if !self.url.starts_with("https://") {
+            return Err(PhonetisaurusError::DownloadError(anyhow!(
+                "Models are only downloaded over HTTPS, not from {}",
+                self.url
+            )));
+        }
+        let path = self.cached_path()?;
+        if path.is_file() && self.verify(&path).is_ok() {
+            return Ok(path);
+        }
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir).map_err(|e| download_error(e.into(), &self.url))?;
+        // downloaded next to the cached file and moved there once verified, so that the cache
+        // never holds a partial file
+        let partial_path = path.with_extension(format!("part-{}", std::process::id()));
+        let downloaded = self.download(&partial_path).and_then(|_| self.verify(&partial_path));
+        if let Err(e) = downloaded.and_then(|_| {
+            fs::rename(&partial_path, &path).map_err(|e| download_error(e.into(), &self.url))
+        }) {
+            let _ = fs::remove_file(&partial_path);
+            return Err(e);
+        }
+        Ok(path)
+    }
+
+    /// Fetch the model and load it.
+    pub fn load(&self) -> Result<PhonetisaurusModel> {
+        PhonetisaurusModel::try_from(self.fetch()?.as_path())
+    }
+
+    fn download(&self, path: &Path) -> Result<()> {
+        let run = || -> std::io::Result<std::process::Output> {
+            let mut curl = Command::new("curl")
+                .args(["--fail", "--location", "--silent", "--show-error", "--proto", "=https"])
+                .args(["--header", "@-", "--output"])
+                .arg(path)
+                .arg(&self.url)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()?;
+            if let Some(mut stdin) = curl.stdin.take() {
+                for header in &self.headers {
+                    writeln!(stdin, "{}", header)?;
+                }
+            }
+            curl.wait_with_output()
+        };
+        let output = run().map_err(|e| download_error(anyhow::Error::from(e).context("Can't run curl"), &self.url))?;
+        if !output.status.success() {
+            let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Err(download_error(anyhow!(message), &self.url));
+        }
+        Ok(())
+    }
+
+    /// Check a file against the checksum, if there is one.
+    fn verify(&self, path: &Path) -> Result<()> {
+        let Some(expected) = &self.sha256 else {
+            return Ok(());
+        };
+        let file = File::open(path).map_err(|e| download_error(e.into(), &self.url))?;
+        let actual = sha256_hex(file).map_err(|e| download_error(e.into(), &self.url))?;
+        if actual != *expected {
+            return Err(PhonetisaurusError::ChecksumMismatch {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Download a model from an HTTPS URL into the cache, verify it against a SHA-256 checksum if
+/// one is given, and load it.
+pub fn fetch_model(url: &str, sha256: Option<&str>) -> Result<PhonetisaurusModel> {
+    let download = ModelDownload::new(url);
+    match sha256 {
+        Some(sha256) => download.with_sha256(sha256),
+        None => download,
+    }
+    .load()
+}
+
+/// The directory models are cached in by default: `$XDG_CACHE_HOME/phonetisaurus-g2p`, or
+/// `$HOME/.cache/phonetisaurus-g2p` if `XDG_CACHE_HOME` is not set.
+pub fn cache_dir() -> Option<PathBuf> {
+    let non_empty = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    non_empty("XDG_CACHE_HOME")
+        .or_else(|| non_empty("HOME").map(|home| home.join(".cache")))
+        .map(|cache| cache.join(CACHE_NAME))
+}
+
+fn download_error(error: anyhow::Error, url: &str) -> PhonetisaurusError {
+    PhonetisaurusError::DownloadError(error.context(format!("Can't download {}", url)))
+}
@@ -0,0 +1,217 @@
+//! JSON objects of string values, like phoneme maps and bundle manifests, and phoneme ID
+//! tables, read and written without a JSON library.
+
+use std::fmt::Write;
+
+/// Read a JSON object whose values are all strings, keeping the members in order.
+pub(crate) fn parse_string_object(json: &str) -> Result<Vec<(String, String)>, String> {
+    let mut chars = json.chars().peekable();
+    let mut entries = Vec::new();
+
+    skip_whitespace(&mut chars);
+    if chars.next() != Some('{') {
+        return Err("expected a JSON object".to_string());
+    }
+    skip_whitespace(&mut chars);
+    if chars.peek() == Some(&'}') {
+        chars.next();
+    } else {
+        loop {
+            skip_whitespace(&mut chars);
+            let key = json_string(&mut chars).ok_or("expected a string key")?;
+            skip_whitespace(&mut chars);
+            if chars.next() != Some(':') {
+                return Err("expected ':'".to_string());
+            }
+            skip_whitespace(&mut chars);
+            let value = json_string(&mut chars).ok_or("expected a string value")?;
+            entries.push((key, value));
+            skip_whitespace(&mut chars);
+            match chars.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                _ => return Err("expected ',' or '}'".to_string()),
+            }
+        }
+    }
+    skip_whitespace(&mut chars);
+    if chars.next().is_some() {
+        return Err("trailing characters after the object".to_string());
+    }
+
+    Ok(entries)
+}
+
+/// Write a JSON object of string values, one member per line.
+pub(crate) fn write_string_object<K: AsRef<str>, V: AsRef<str>>(entries: impl IntoIterator<Item = (K, V)>) -> String {
+    let mut json = String::from("{");
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        json.push_str(if i == 0 { "\n  " } else { ",\n  " });
+        write_string(&mut json, key.as_ref());
+        json.push_str(": ");
+        write_string(&mut json, value.as_ref());
+    }
+    json.push_str("\n}\n");
+    json
+}
+
+/// Read a table of phoneme IDs, the `phoneme_id_map` member of a piper voice config or an
+/// object mapping symbols to arrays of integers like `{"a": [14]}`, keeping the members in
+/// order.
+pub(crate) fn parse_id_object(json: &str) -> Result<Vec<(String, Vec<i64>)>, String> {
+    let mut chars = json.chars().peekable();
+    let IdJson::Object(mut members) = id_json(&mut chars)? else {
+        return Err("expected a JSON object".to_string());
+    };
+    skip_whitespace(&mut chars);
+    if chars.next().is_some() {
+        return Err("trailing characters after the object".to_string());
+    }
+
+    if let Some(index) = members.iter().position(|(key, _)| key == "phoneme_id_map") {
+        let IdJson::Object(map) = members.swap_remove(index).1 else {
+            return Err("phoneme_id_map is not an object".to_string());
+        };
+        members = map;
+    }
+    members
+        .into_iter()
+        .map(|(key, value)| match value {
+            IdJson::Ids(ids) => Ok((key, ids)),
+            IdJson::Int(id) => Ok((key, vec![id])),
+            _ => Err(format!("the IDs of \"{}\" are not an array of integers", key)),
+        })
+        .collect()
+}
+
+/// A JSON value, as far as tables of phoneme IDs need to tell them apart.
+enum IdJson {
+    Int(i64),
+    /// An array of integers.
+    Ids(Vec<i64>),
+    Object(Vec<(String, IdJson)>),
+    Other,
+}
+
+fn id_json(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Result<IdJson, String> {
+    skip_whitespace(chars);
+    match chars.peek() {
+        Some('{') => {
+            chars.next();
+            let mut members = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if_eq(&'}').is_some() {
+                return Ok(IdJson::Object(members));
+            }
+            loop {
+                skip_whitespace(chars);
+                let key = json_string(chars).ok_or("expected a string key")?;
+                skip_whitespace(chars);
+                if chars.next() != Some(':') {
+                    return Err("expected ':'".to_string());
+                }
+                members.push((key, id_json(chars)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(IdJson::Object(members)),
+                    _ => return Err("expected ',' or '}'".to_string()),
+                }
+            }
+        }
+        Some('[') => {
+            chars.next();
+            let mut ids = Some(Vec::new());
+            skip_whitespace(chars);
+            if chars.next_if_eq(&']').is_some() {
+                return Ok(IdJson::Ids(Vec::new()));
+            }
+            loop {
+                match id_json(chars)? {
+                    IdJson::Int(id) => ids.iter_mut().for_each(|ids| ids.push(id)),
+                    _ => ids = None,
+                }
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Ok(ids.map_or(IdJson::Other, IdJson::Ids)),
+                    _ => return Err("expected ',' or ']'".to_string()),
+                }
+            }
+        }
+        Some('"') => json_string(chars).map(|_| IdJson::Other).ok_or_else(|| "malformed string".to_string()),
+        _ => {
+            let mut literal = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.')) {
+                literal.push(c);
+            }
+            if let Ok(id) = literal.parse() {
+                Ok(IdJson::Int(id))
+            } else if matches!(literal.as_str(), "true" | "false" | "null") || literal.parse::<f64>().is_ok() {
+                Ok(IdJson::Other)
+            } else {
+                Err("expected a JSON value".to_string())
+            }
+        }
+    }
+}
+
+fn write_string(json: &mut String, s: &str) {
+    json.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+/// Read a JSON string literal, `None` if there is none or it is malformed.
+fn json_string(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<String> {
+    if chars.next()? != '"' {
+        return None;
+    }
+    let mut s = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(s),
+            '\\' => match chars.next()? {
+                'n' => s.push('\n'),
+                't' => s.push('\t'),
+                'r' => s.push('\r'),
+                'b' => s.push('\u{8}'),
+                'f' => s.push('\u{c}'),
+                'u' => {
+                    let mut code = hex4(chars)?;
+                    // surrogate pair
+                    if (0xd800..0xdc00).contains(&code) {
+                        if chars.next()? != '\\' || chars.next()? != 'u' {
+                            return None;
+                        }
+                        let low = hex4(chars)?;
+                        code = 0x10000 + ((code - 0xd800) << 10) + low.checked_sub(0xdc00)?;
+                    }
+                    s.push(char::from_u32(code)?);
+                }
+                c => s.push(c),
+            },
+            c => s.push(c),
+        }
+    }
+}
+
+fn hex4(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<u32> {
+    (0..4).try_fold(0, |code, _| Some(code * 16 + chars.next()?.to_digit(16)?))
+}
@@ -0,0 +1,99 @@
+use rustfst::prelude::*;
+use std::fmt::Write;
+
+/// Write an FST in the AT&T text format of `fstprint`, one transition per line as
+/// `source target input output [weight]`, then one `state [weight]` line per final state.
+///
+/// The start state comes first. Labels are written as symbols if the table has them, and
+/// weights of 0 are left out.
+pub(crate) fn fst_to_text(
+    fst: &VectorFst<TropicalWeight>,
+    isyms: Option<&SymbolTable>,
+    osyms: Option<&SymbolTable>,
+) -> String {
+    let mut text = String::new();
+    for state in states_start_first(fst) {
+        for tr in fst.get_trs(state).map(|trs| trs.trs().to_vec()).unwrap_or_default() {
+            let _ = write!(
+                text,
+                "{}\t{}\t{}\t{}",
+                state,
+                tr.nextstate,
+                symbol(isyms, tr.ilabel),
+                symbol(osyms, tr.olabel)
+            );
+            if !tr.weight.is_one() {
+                let _ = write!(text, "\t{}", tr.weight.value());
+            }
+            text.push('\n');
+        }
+        if let Ok(Some(weight)) = fst.final_weight(state) {
+            let _ = write!(text, "{}", state);
+            if !weight.is_one() {
+                let _ = write!(text, "\t{}", weight.value());
+            }
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// Draw an FST in the DOT language of Graphviz, laid out like `fstdraw` does, e.g. for
+/// `dot -Tsvg`.
+///
+/// Transitions are labelled `input:output/weight` with symbols where the tables have them,
+/// final states are drawn as double circles labelled with their final weight, and the start
+/// state in bold.
+pub(crate) fn fst_to_dot(
+    fst: &VectorFst<TropicalWeight>,
+    isyms: Option<&SymbolTable>,
+    osyms: Option<&SymbolTable>,
+) -> String {
+    let mut dot = String::from(
+        "digraph FST {\nrankdir = LR;\nsize = \"8.5,11\";\ncenter = 1;\norientation = Portrait;\nranksep = \"0.4\";\nnodesep = \"0.25\";\n",
+    );
+    for state in states_start_first(fst) {
+        let (label, shape) = match fst.final_weight(state) {
+            Ok(Some(weight)) => (format!("{}/{}", state, weight.value()), "doublecircle"),
+            _ => (state.to_string(), "circle"),
+        };
+        let style = if fst.start() == Some(state) { "bold" } else { "solid" };
+        let _ = writeln!(
+            dot,
+            "{} [label = \"{}\", shape = {}, style = {}, fontsize = 14]",
+            state, label, shape, style
+        );
+        for tr in fst.get_trs(state).map(|trs| trs.trs().to_vec()).unwrap_or_default() {
+            let _ = writeln!(
+                dot,
+                "\t{} -> {} [label = \"{}:{}/{}\", fontsize = 14];",
+                state,
+                tr.nextstate,
+                escape(&symbol(isyms, tr.ilabel)),
+                escape(&symbol(osyms, tr.olabel)),
+                tr.weight.value()
+            );
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// The states of an FST, the start state first.
+fn states_start_first(fst: &VectorFst<TropicalWeight>) -> impl Iterator<Item = StateId> + '_ {
+    let start = fst.start();
+    start.into_iter().chain(fst.states_iter().filter(move |&state| Some(state) != start))
+}
+
+/// The symbol of a label, or the label itself if there is no symbol for it.
+fn symbol(symbols: Option<&SymbolTable>, label: Label) -> String {
+    match symbols.and_then(|symbols| symbols.get_symbol(label)) {
+        Some(symbol) => symbol.to_string(),
+        None => label.to_string(),
+    }
+}
+
+/// Escape a symbol for a quoted DOT string.
+fn escape(symbol: &str) -> String {
+    symbol.replace('\\', "\\\\").replace('"', "\\\"")
+}
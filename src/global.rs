@@ -0,0 +1,59 @@
+//! A process-wide model, for applications that only ever need one and would rather not pass
+//! it around.
+//!
+//! The model is set once with [`init`] or [`set`], after which [`phonemize`] and [`model`] can
+//! be used from anywhere, including other threads. Code that needs several models, or to
+//! replace one, should hold [`PhonetisaurusModel`]s or a [`ModelRegistry`](crate::ModelRegistry)
+//! instead.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::error::{PhonetisaurusError, Result};
+use crate::options::PhonemizeOptions;
+use crate::phonetisaurus::{PhonetisaurusModel, PhonetizationResult};
+
+static MODEL: OnceLock<PhonetisaurusModel> = OnceLock::new();
+
+/// Load the global model from a file.
+///
+/// Fails with [`PhonetisaurusError::AlreadyInitialized`] if there already is one, in which
+/// case the file is not read.
+pub fn init(model_path: impl AsRef<Path>) -> Result<()> {
+    if MODEL.get().is_some() {
+        return Err(PhonetisaurusError::AlreadyInitialized);
+    }
+    set(PhonetisaurusModel::try_from(model_path.as_ref())?)
+}
+
+/// Make a loaded model the global one, e.g. one built with the `with_*` methods.
+///
+/// Fails with [`PhonetisaurusError::AlreadyInitialized`] if there already is one.
+pub fn set(model: PhonetisaurusModel) -> Result<()> {
+    MODEL.set(model).map_err(|_| PhonetisaurusError::AlreadyInitialized)
+}
+
+/// The global model, or [`PhonetisaurusError::NotInitialized`].
+pub fn model() -> Result<&'static PhonetisaurusModel> {
+    MODEL.get().ok_or(PhonetisaurusError::NotInitialized)
+}
+
+/// Whether the global model was initialized.
+pub fn is_initialized() -> bool {
+    MODEL.get().is_some()
+}
+
+/// Phonemize a word with the global model, see [`PhonetisaurusModel::phonemize_word`].
+pub fn phonemize(word: &str) -> Result<PhonetizationResult> {
+    model()?.phonemize_word(word)
+}
+
+/// Phonemize a word with the global model, using the given options.
+pub fn phonemize_with_options(word: &str, options: &PhonemizeOptions) -> Result<PhonetizationResult> {
+    model()?.phonemize_word_with_options(word, options)
+}
+
+/// Phonemize a word with the global model and return up to `n` distinct pronunciations.
+pub fn phonemize_nbest(word: &str, n: usize) -> Result<Vec<PhonetizationResult>> {
+    model()?.phonemize_word_nbest(word, n)
+}
@@ -0,0 +1,345 @@
+use std::io;
+
+/// Base lengths of the length codes 257 to 285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+
+/// Extra bits of the length codes 257 to 285.
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+/// Base distances of the distance codes.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+
+/// Extra bits of the distance codes.
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// Order in which the code lengths of the code length alphabet are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+const MAX_BITS: usize = 15;
+
+/// Flags of the gzip header telling which optional fields follow it.
+const FLAG_HEADER_CRC: u8 = 0x02;
+const FLAG_EXTRA: u8 = 0x04;
+const FLAG_NAME: u8 = 0x08;
+const FLAG_COMMENT: u8 = 0x10;
+
+/// Whether data starts like a gzip file.
+pub(crate) fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&[0x1f, 0x8b])
+}
+
+/// Decompress a gzip file (RFC 1952), checking the length and the CRC-32 of its data.
+///
+/// Files of several members, as written by concatenating gzip files, are decompressed as a
+/// whole. Only the deflate method (RFC 1951) is defined for gzip, so that is the only one read.
+pub(crate) fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len().saturating_mul(4));
+    let mut rest = data;
+    loop {
+        let start = out.len();
+        let mut reader = BitReader::new(&rest[header_len(rest)?..]);
+        inflate(&mut reader, &mut out, start)?;
+        let trailer = reader.rest();
+        if trailer.len() < 8 {
+            return Err(invalid("the gzip file is truncated"));
+        }
+        let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        if crc32(&out[start..]) != crc || (out.len() - start) as u32 != size {
+            return Err(invalid("the gzip file is corrupt, its checksum does not match"));
+        }
+        rest = &trailer[8..];
+        if !is_gzip(rest) {
+            return Ok(out);
+        }
+    }
+}
+
+/// Length of the header of a gzip member, with its optional fields.
+fn header_len(data: &[u8]) -> io::Result<usize> {
+    let truncated = || invalid("the gzip header is truncated");
+    if data.len() < 10 || !is_gzip(data) {
+        return Err(truncated());
+    }
+    if data[2] != 8 {
+        return Err(invalid("the gzip file uses an unknown compression method"));
+    }
+    let flags = data[3];
+    let mut len = 10;
+    if flags & FLAG_EXTRA != 0 {
+        let extra = data.get(len..len + 2).ok_or_else(truncated)?;
+        len += 2 + u16::from_le_bytes([extra[0], extra[1]]) as usize;
+    }
+    for flag in [FLAG_NAME, FLAG_COMMENT] {
+        if flags & flag != 0 {
+            let field = data.get(len..).ok_or_else(truncated)?;
+            len += field.iter().position(|&byte| byte == 0).ok_or_else(truncated)? + 1;
+        }
+    }
+    if flags & FLAG_HEADER_CRC != 0 {
+        len += 2;
+    }
+    match len <= data.len() {
+        true => Ok(len),
+        false => Err(truncated()),
+    }
+}
+
+/// Decompress the deflate blocks at `reader` onto `out`, whose data from `start` on is the
+/// data of this stream, the only data that distances may refer back to.
+fn inflate(reader: &mut BitReader, out: &mut Vec<u8>, start: usize) -> io::Result<()> {
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => stored_block(reader, out)?,
+            1 => {
+                let (lengths, distances) = fixed_codes();
+                compressed_block(reader, out, start, &lengths, &distances)?;
+            }
+            2 => {
+                let (lengths, distances) = dynamic_codes(reader)?;
+                compressed_block(reader, out, start, &lengths, &distances)?;
+            }
+            _ => return Err(invalid("the deflate data has an invalid block type")),
+        }
+        if last {
+            return Ok(());
+        }
+    }
+}
+
+fn stored_block(reader: &mut BitReader, out: &mut Vec<u8>) -> io::Result<()> {
+    reader.align();
+    let len = reader.bits(16)?;
+    if reader.bits(16)? != !len & 0xffff {
+        return Err(invalid("the deflate data has a corrupt stored block"));
+    }
+    out.extend_from_slice(reader.bytes(len as usize)?);
+    Ok(())
+}
+
+fn compressed_block(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    start: usize,
+    lengths: &Huffman,
+    distances: &Huffman,
+) -> io::Result<()> {
+    loop {
+        let symbol = lengths.decode(reader)? as usize;
+        if symbol < 256 {
+            out.push(symbol as u8);
+            continue;
+        }
+        if symbol == 256 {
+            return Ok(());
+        }
+        let code = symbol - 257;
+        if code >= LENGTH_BASE.len() {
+            return Err(invalid("the deflate data has an invalid length code"));
+        }
+        let len = LENGTH_BASE[code] as usize + reader.bits(LENGTH_EXTRA[code] as u32)? as usize;
+        let code = distances.decode(reader)? as usize;
+        if code >= DISTANCE_BASE.len() {
+            return Err(invalid("the deflate data has an invalid distance code"));
+        }
+        let distance = DISTANCE_BASE[code] as usize + reader.bits(DISTANCE_EXTRA[code] as u32)? as usize;
+        if distance > out.len() - start {
+            return Err(invalid("the deflate data refers back before its start"));
+        }
+        // the copy may overlap the bytes it adds, repeating them
+        for _ in 0..len {
+            out.push(out[out.len() - distance]);
+        }
+    }
+}
+
+/// The codes of blocks compressed with fixed Huffman codes.
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    let lengths = Huffman::new(&lengths).expect("the fixed codes are complete");
+    let distances = Huffman::new(&[5; 30]).expect("the fixed codes are complete");
+    (lengths, distances)
+}
+
+/// Read the codes of a block compressed with dynamic Huffman codes.
+fn dynamic_codes(reader: &mut BitReader) -> io::Result<(Huffman, Huffman)> {
+    let num_lengths = reader.bits(5)? as usize + 257;
+    let num_distances = reader.bits(5)? as usize + 1;
+    let num_code_lengths = reader.bits(4)? as usize + 4;
+    if num_lengths > 286 || num_distances > 30 {
+        return Err(invalid("the deflate data has too many codes"));
+    }
+
+    let mut code_lengths = [0; 19];
+    for &i in &CODE_LENGTH_ORDER[..num_code_lengths] {
+        code_lengths[i] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0; num_lengths + num_distances];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = code_lengths.decode(reader)?;
+        let (len, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => match i.checked_sub(1) {
+                Some(previous) => (lengths[previous], 3 + reader.bits(2)? as usize),
+                None => return Err(invalid("the deflate data repeats a code length before the first")),
+            },
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        if i + repeat > lengths.len() {
+            return Err(invalid("the deflate data has too many code lengths"));
+        }
+        lengths[i..i + repeat].fill(len);
+        i += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(invalid("the deflate data has no end-of-block code"));
+    }
+
+    Ok((Huffman::new(&lengths[..num_lengths])?, Huffman::new(&lengths[num_lengths..])?))
+}
+
+/// A canonical Huffman code, decoded a bit at a time.
+struct Huffman {
+    /// Number of codes of each length.
+    counts: [u16; MAX_BITS + 1],
+    /// Symbols ordered by their codes.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// The code of symbols with the given code lengths, 0 for symbols not used. Incomplete
+    /// codes are accepted, as deflate uses them for a single distance code.
+    fn new(lengths: &[u8]) -> io::Result<Self> {
+        let mut counts = [0; MAX_BITS + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = 2 * left - count as i32;
+            if left < 0 {
+                return Err(invalid("the deflate data has an over-subscribed code"));
+            }
+        }
+
+        let mut offsets = [0; MAX_BITS + 1];
+        for len in 1..MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> io::Result<u16> {
+        // codes of each length follow the codes of the shorter lengths, shifted left
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as usize;
+            let count = count as usize;
+            if code < first + count {
+                return Ok(self.symbols[index + code - first]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("the deflate data has an invalid code"))
+    }
+}
+
+/// Reads deflate data, whose values are packed starting at the least significant bit.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Bits read from the data but not consumed yet, less than a byte between calls.
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader {
+            data,
+            pos: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    /// The next `n` bits, at most 16.
+    fn bits(&mut self, n: u32) -> io::Result<u32> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or_else(|| invalid("the gzip file is truncated"))?;
+            self.buffer |= (byte as u32) << self.count;
+            self.count += 8;
+            self.pos += 1;
+        }
+        let value = self.buffer & ((1 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Skip to the start of the next byte.
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+
+    /// The next `len` bytes, after [`Self::align`].
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos + len).ok_or_else(|| invalid("the gzip file is truncated"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// The bytes after the last one read from.
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+}
+
+/// CRC-32 of the gzip trailer, with the polynomial of IEEE 802.3.
+fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 == 1 { 0xedb88320 ^ (crc >> 1) } else { crc >> 1 };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+    !data.iter().fold(!0, |crc, &byte| TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
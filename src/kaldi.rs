@@ -0,0 +1,222 @@
+use rustfst::algorithms::tr_sort;
+use rustfst::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Word symbol that passes the backoff disambiguation symbol of a grammar through the lexicon.
+const WORD_DISAMBIG: &str = "#0";
+
+/// A pronunciation lexicon for Kaldi: `lexicon.txt`, and the lexicon FST `L.fst` built from it
+/// the way Kaldi's `prepare_lang.sh` does.
+///
+/// Pronunciations that are a prefix of another one, or shared by several words, get the
+/// disambiguation symbols `#1`, `#2`, ... appended in `lexicon_disambig.txt` and
+/// `L_disambig.fst`, so that the latter can be composed with a grammar and determinized.
+#[derive(Clone, Debug, Default)]
+pub struct KaldiLexicon {
+    /// Words and their phonemes, in insertion order.
+    entries: Vec<(String, Vec<String>)>,
+    /// Silence phone optionally inserted between words, and its probability.
+    silence: Option<(String, f32)>,
+}
+
+impl KaldiLexicon {
+    /// Create an empty lexicon without optional silence.
+    pub fn new() -> Self {
+        KaldiLexicon::default()
+    }
+
+    /// Insert `phone` between words with probability `probability`, and at the start of
+    /// utterances, like `prepare_lang.sh --sil-prob`.
+    pub fn with_optional_silence(mut self, phone: impl Into<String>, probability: f32) -> Self {
+        self.silence = Some((phone.into(), probability.clamp(0.0, 1.0)));
+        self
+    }
+
+    /// Add a pronunciation of a word. Words with several pronunciations are added once per
+    /// pronunciation.
+    pub fn push(&mut self, word: impl Into<String>, phonemes: Vec<String>) {
+        self.entries.push((word.into(), phonemes));
+    }
+
+    /// The words and their phonemes, in insertion order.
+    pub fn entries(&self) -> &[(String, Vec<String>)] {
+        &self.entries
+    }
+
+    /// Write `lexicon.txt`: one `word phonemes...` line per pronunciation.
+    pub fn write_lexicon(&self, out: impl Write) -> io::Result<()> {
+        write_entries(out, &self.entries)
+    }
+
+    /// The entries with disambiguation symbols, and the number of disambiguation symbols
+    /// used, `#0` and the one of the silence included.
+    ///
+    /// Like Kaldi's `add_lex_disambig.pl`, the pronunciations that are a proper prefix of
+    /// another or occur more than once are numbered `#1`, `#2`, ... per pronunciation.
+    pub fn disambiguated(&self) -> (Vec<(String, Vec<String>)>, usize) {
+        let mut counts: HashMap<&[String], usize> = HashMap::new();
+        let mut prefixes: HashSet<&[String]> = HashSet::new();
+        for (_, phonemes) in &self.entries {
+            *counts.entry(phonemes).or_default() += 1;
+            for end in 0..phonemes.len() {
+                prefixes.insert(&phonemes[..end]);
+            }
+        }
+
+        let mut last_used: HashMap<&[String], usize> = HashMap::new();
+        let mut max_disambig = 0;
+        let entries = self
+            .entries
+            .iter()
+            .map(|(word, phonemes)| {
+                let mut disambiguated = phonemes.clone();
+                if prefixes.contains(phonemes.as_slice()) || counts[phonemes.as_slice()] > 1 {
+                    let used = last_used.entry(phonemes).or_default();
+                    *used += 1;
+                    max_disambig = max_disambig.max(*used);
+                    disambiguated.push(format!("#{}", used));
+                }
+                (word.clone(), disambiguated)
+            })
+            .collect();
+        // one more for the silence, as `prepare_lang.sh` reserves it either way
+        (entries, max_disambig + 2)
+    }
+
+    /// Write the files of a Kaldi `lang` directory that come from the lexicon:
+    /// `lexicon.txt`, `lexicon_disambig.txt`, `phones.txt`, `words.txt`, `L.fst` and
+    /// `L_disambig.fst`.
+    ///
+    /// The FSTs are in the binary format of OpenFst, sorted by output label, with the phones
+    /// as input and the words as output symbols.
+    pub fn write_lang(&self, dir: &Path) -> io::Result<()> {
+        let (disambiguated, num_disambig) = self.disambiguated();
+
+        let mut phones = SymbolTable::new();
+        let mut phone_inventory: Vec<&str> =
+            self.entries.iter().flat_map(|(_, phonemes)| phonemes.iter().map(String::as_str)).collect();
+        phone_inventory.extend(self.silence.as_ref().map(|(phone, _)| phone.as_str()));
+        phone_inventory.sort_unstable();
+        phone_inventory.dedup();
+        phones.add_symbols(phone_inventory);
+        phones.add_symbols((0..num_disambig).map(|i| format!("#{}", i)));
+
+        let mut words = SymbolTable::new();
+        let mut word_inventory: Vec<&str> = self.entries.iter().map(|(word, _)| word.as_str()).collect();
+        word_inventory.sort_unstable();
+        word_inventory.dedup();
+        words.add_symbols(word_inventory);
+        words.add_symbols([WORD_DISAMBIG, "<s>", "</s>"]);
+
+        let phones = Arc::new(phones);
+        let words = Arc::new(words);
+        let silence_disambig = format!("#{}", num_disambig - 1);
+        let lexicon_fst = self.build_fst(&self.entries, &phones, &words, None).map_err(io::Error::other)?;
+        let disambig_fst = self
+            .build_fst(&disambiguated, &phones, &words, Some(&silence_disambig))
+            .map_err(io::Error::other)?;
+
+        let mut lexicon = BufWriter::new(File::create(dir.join("lexicon.txt"))?);
+        self.write_lexicon(&mut lexicon)?;
+        lexicon.flush()?;
+        let mut lexicon_disambig = BufWriter::new(File::create(dir.join("lexicon_disambig.txt"))?);
+        write_entries(&mut lexicon_disambig, &disambiguated)?;
+        lexicon_disambig.flush()?;
+        phones.write_text(dir.join("phones.txt")).map_err(io::Error::other)?;
+        words.write_text(dir.join("words.txt")).map_err(io::Error::other)?;
+        lexicon_fst.write(dir.join("L.fst")).map_err(io::Error::other)?;
+        disambig_fst.write(dir.join("L_disambig.fst")).map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    /// Build the lexicon FST of `entries` like Kaldi's `make_lexicon_fst.py`: a loop state
+    /// that every pronunciation starts and ends in, emitting its word on the first phone.
+    ///
+    /// With `silence_disambig`, the entries already carry their disambiguation symbols, the
+    /// silence is followed by that symbol and the loop state gets a `#0:#0` self-loop, like
+    /// `fstaddselfloops` adds in `prepare_lang.sh`.
+    fn build_fst(
+        &self,
+        entries: &[(String, Vec<String>)],
+        phones: &Arc<SymbolTable>,
+        words: &Arc<SymbolTable>,
+        silence_disambig: Option<&str>,
+    ) -> anyhow::Result<VectorFst<TropicalWeight>> {
+        let phone = |symbol: &str| {
+            phones.get_label(symbol).ok_or_else(|| anyhow::anyhow!("Phone {} is not in the phone table", symbol))
+        };
+        let word = |symbol: &str| {
+            words.get_label(symbol).ok_or_else(|| anyhow::anyhow!("Word {} is not in the word table", symbol))
+        };
+
+        let mut fst = VectorFst::<TropicalWeight>::new();
+        let start = fst.add_state();
+        fst.set_start(start)?;
+        // words end in the loop state, or in the silence state that leads back to it
+        let (loop_state, ends) = match &self.silence {
+            Some((silence, probability)) => {
+                let loop_state = fst.add_state();
+                let silence_state = fst.add_state();
+                let silence_cost = -probability.ln();
+                let no_silence_cost = -(1.0 - probability).ln();
+                let silence = phone(silence)?;
+                fst.add_tr(start, Tr::new(EPS_LABEL, EPS_LABEL, no_silence_cost, loop_state))?;
+                fst.add_tr(start, Tr::new(silence, EPS_LABEL, silence_cost, loop_state))?;
+                match silence_disambig {
+                    Some(symbol) => {
+                        let disambig_state = fst.add_state();
+                        fst.add_tr(silence_state, Tr::new(silence, EPS_LABEL, 0.0, disambig_state))?;
+                        fst.add_tr(disambig_state, Tr::new(phone(symbol)?, EPS_LABEL, 0.0, loop_state))?;
+                    }
+                    None => fst.add_tr(silence_state, Tr::new(silence, EPS_LABEL, 0.0, loop_state))?,
+                }
+                (loop_state, vec![(loop_state, no_silence_cost), (silence_state, silence_cost)])
+            }
+            None => (start, vec![(start, 0.0)]),
+        };
+        fst.set_final(loop_state, 0.0)?;
+
+        for (entry_word, phonemes) in entries {
+            let mut state = loop_state;
+            let mut output = word(entry_word)?;
+            for phoneme in phonemes.iter().take(phonemes.len().saturating_sub(1)) {
+                let next = fst.add_state();
+                fst.add_tr(state, Tr::new(phone(phoneme)?, output, 0.0, next))?;
+                state = next;
+                output = EPS_LABEL;
+            }
+            let last = match phonemes.last() {
+                Some(phoneme) => phone(phoneme)?,
+                None => EPS_LABEL,
+            };
+            for &(end, cost) in &ends {
+                fst.add_tr(state, Tr::new(last, output, cost, end))?;
+            }
+        }
+
+        if silence_disambig.is_some() {
+            let disambig_phone = phone("#0")?;
+            let disambig_word = word(WORD_DISAMBIG)?;
+            fst.add_tr(loop_state, Tr::new(disambig_phone, disambig_word, 0.0, loop_state))?;
+        }
+
+        fst.set_input_symbols(Arc::clone(phones));
+        fst.set_output_symbols(Arc::clone(words));
+        tr_sort(&mut fst, OLabelCompare {});
+        Ok(fst)
+    }
+}
+
+fn write_entries(mut out: impl Write, entries: &[(String, Vec<String>)]) -> io::Result<()> {
+    for (word, phonemes) in entries {
+        match phonemes.is_empty() {
+            true => writeln!(out, "{}", word)?,
+            false => writeln!(out, "{} {}", word, phonemes.join(" "))?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,289 @@
+use rustfst::prelude::*;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::alignment::{AlignedSegment, align};
+use crate::error::{PhonetisaurusError, Result};
+use crate::fst_export::{fst_to_dot, fst_to_text};
+use crate::log_semiring::to_log;
+use crate::options::PhonemeSeparators;
+use crate::phonetisaurus::nshortest_with_ties;
+
+#[derive(Clone, Debug)]
+/// All alignments of a word with pronunciations the model allows, as returned by
+/// [`PhonetisaurusModel::phonemize_word_lattice`](crate::PhonetisaurusModel::phonemize_word_lattice),
+/// for rescoring outside the library, e.g. with a neural language model.
+///
+/// The lattice is the word acceptor composed with the model: its input labels are the
+/// graphemes of the word, its output labels the phonemes, and its weights the negative log
+/// likelihoods of the model. Unlike the pronunciations of `phonemize_word_nbest`, its paths
+/// are alignments, so a pronunciation appears once per way of aligning it with the word.
+pub struct PronunciationLattice {
+    word: String,
+    fst: VectorFst<TropicalWeight>,
+    /// Char positions in the word of every input label on a path, in order.
+    spans: Vec<Range<usize>>,
+    isyms: Arc<SymbolTable>,
+    osyms: Arc<SymbolTable>,
+    separators: PhonemeSeparators,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// One path through a [`PronunciationLattice`].
+pub struct LatticePath {
+    /// The phoneme tokens of the path, without skip symbols and with ties removed.
+    pub phoneme_tokens: Vec<String>,
+    /// Negative log likelihood of the path, lower is better.
+    pub neg_log_score: f32,
+    /// Which graphemes of the word produced which phoneme tokens on this path.
+    pub alignment: Vec<AlignedSegment>,
+}
+
+impl PronunciationLattice {
+    pub(crate) fn new(
+        word: &str,
+        mut fst: VectorFst<TropicalWeight>,
+        spans: Vec<Range<usize>>,
+        isyms: Arc<SymbolTable>,
+        osyms: Arc<SymbolTable>,
+        separators: PhonemeSeparators,
+    ) -> Self {
+        fst.set_input_symbols(isyms.clone());
+        fst.set_output_symbols(osyms.clone());
+        PronunciationLattice {
+            word: word.to_string(),
+            fst,
+            spans,
+            isyms,
+            osyms,
+            separators,
+        }
+    }
+
+    /// The word, after case folding and normalization.
+    pub fn word(&self) -> &str {
+        &self.word
+    }
+
+    /// The lattice FST, with the symbol tables of the model.
+    pub fn fst(&self) -> &VectorFst<TropicalWeight> {
+        &self.fst
+    }
+
+    /// Take the lattice FST.
+    pub fn into_fst(self) -> VectorFst<TropicalWeight> {
+        self.fst
+    }
+
+    /// Number of states of the lattice.
+    pub fn num_states(&self) -> usize {
+        self.fst.num_states()
+    }
+
+    /// Number of transitions of the lattice.
+    pub fn num_arcs(&self) -> usize {
+        self.fst.states_iter().filter_map(|state| self.fst.num_trs(state).ok()).sum()
+    }
+
+    /// Iterate over all paths of the lattice, in no particular order.
+    ///
+    /// The number of paths grows exponentially with the length of the word, so for long words
+    /// prune the lattice with the options, or take the [`Self::best_paths`] instead.
+    pub fn paths(&self) -> LatticePaths<'_> {
+        LatticePaths::new(self, &self.fst)
+    }
+
+    /// The `n` best paths of the lattice, best first. Paths with the same score are ordered
+    /// by their phoneme tokens, then by their alignment.
+    pub fn best_paths(&self, n: usize) -> Result<Vec<LatticePath>> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let (nshortest_fst, _) = nshortest_with_ties(&self.fst, n)?;
+        let mut paths = LatticePaths::new(self, &nshortest_fst).collect::<Result<Vec<_>>>()?;
+        let alignment_key = |path: &LatticePath| -> Vec<(usize, usize, Vec<String>)> {
+            path.alignment
+                .iter()
+                .map(|segment| (segment.graphemes.start, segment.graphemes.end, segment.phonemes.clone()))
+                .collect()
+        };
+        paths.sort_by(|a, b| {
+            a.neg_log_score
+                .total_cmp(&b.neg_log_score)
+                .then_with(|| a.phoneme_tokens.cmp(&b.phoneme_tokens))
+                .then_with(|| alignment_key(a).cmp(&alignment_key(b)))
+        });
+        paths.truncate(n);
+        Ok(paths)
+    }
+
+    /// Draw `k` paths at random, each with a probability proportional to
+    /// `exp(-neg_log_score / temperature)`, e.g. to augment a lexicon with plausible variants.
+    ///
+    /// A temperature of 1 samples by the probabilities of the model, lower ones favor the
+    /// best paths and higher ones flatten the distribution. Temperatures are at least
+    /// [`MIN_TEMPERATURE`]. The same seed draws the same paths.
+    pub fn sample(&self, k: usize, temperature: f32, seed: u64) -> Result<Vec<LatticePath>> {
+        let Some(start) = self.fst.start() else {
+            return Err(PhonetisaurusError::NoPathFound);
+        };
+        let temperature = temperature.max(MIN_TEMPERATURE);
+        let scaled = |weight: &TropicalWeight| weight.value() / temperature;
+
+        // -log of the summed probabilities of all paths from each state to a final state
+        let log_fst = to_log(&self.fst, temperature)?;
+        let beta: Vec<f32> = shortest_distance(&log_fst, true)
+            .map_err(PhonetisaurusError::FstError)?
+            .iter()
+            .map(|weight| *weight.value())
+            .collect();
+        let beta = |state: StateId| beta.get(state as usize).copied().unwrap_or(f32::INFINITY);
+        if !beta(start).is_finite() {
+            return Err(PhonetisaurusError::NoPathFound);
+        }
+
+        let mut random = SplitMix64(seed);
+        let mut paths = Vec::with_capacity(k);
+        for _ in 0..k {
+            let mut state = start;
+            let mut arcs = Vec::new();
+            let mut score = 0.0;
+            loop {
+                let final_weight = self.fst.final_weight(state).map_err(PhonetisaurusError::FstError)?;
+                let trs = self.fst.get_trs(state).map_err(PhonetisaurusError::FstError)?;
+                // the costs of ending here and of each transition, the probabilities of which
+                // sum to exp(-beta(state))
+                let choices = final_weight
+                    .iter()
+                    .map(|weight| (scaled(weight), None))
+                    .chain(trs.trs().iter().map(|tr| (scaled(&tr.weight) + beta(tr.nextstate), Some(tr))))
+                    .filter(|(cost, _)| cost.is_finite());
+                let mut remaining = random.next_f32();
+                let mut chosen = None;
+                for (cost, tr) in choices {
+                    chosen = Some(tr);
+                    remaining -= (beta(state) - cost).exp();
+                    if remaining < 0.0 {
+                        break;
+                    }
+                }
+                match chosen.ok_or(PhonetisaurusError::NoPathFound)? {
+                    Some(tr) => {
+                        arcs.push((tr.ilabel, tr.olabel));
+                        score += tr.weight.value();
+                        state = tr.nextstate;
+                    }
+                    None => {
+                        score += final_weight.map_or(0.0, |weight| *weight.value());
+                        break;
+                    }
+                }
+            }
+            paths.push(self.path(&arcs, score)?);
+        }
+        Ok(paths)
+    }
+
+    /// The lattice in the AT&T text format of OpenFst's `fstprint`, with symbols, which
+    /// `fstcompile` reads back given the symbol tables of the model.
+    pub fn to_text(&self) -> String {
+        fst_to_text(&self.fst, Some(&self.isyms), Some(&self.osyms))
+    }
+
+    /// The lattice in the DOT language of Graphviz, drawn like OpenFst's `fstdraw` does.
+    pub fn to_dot(&self) -> String {
+        fst_to_dot(&self.fst, Some(&self.isyms), Some(&self.osyms))
+    }
+
+    /// Build a path from its transitions.
+    fn path(&self, arcs: &[(Label, Label)], neg_log_score: f32) -> Result<LatticePath> {
+        let alignment = align(arcs, &self.spans, &self.osyms, &self.separators)?;
+        Ok(LatticePath {
+            phoneme_tokens: alignment.iter().flat_map(|segment| segment.phonemes.iter().cloned()).collect(),
+            neg_log_score,
+            alignment,
+        })
+    }
+}
+
+/// Lowest temperature of [`PronunciationLattice::sample`].
+pub const MIN_TEMPERATURE: f32 = 0.01;
+
+/// The SplitMix64 generator, good enough to draw samples and reproducible from its seed.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    /// A number in `[0, 1)`.
+    fn next_f32(&mut self) -> f32 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Iterator over the paths of a [`PronunciationLattice`], see [`PronunciationLattice::paths`].
+///
+/// The lattice is walked depth first, so only the current path is kept in memory.
+pub struct LatticePaths<'a> {
+    lattice: &'a PronunciationLattice,
+    fst: &'a VectorFst<TropicalWeight>,
+    /// The states of the current path, each with the number of steps already taken from it:
+    /// 0 if its final weight was not checked yet, otherwise one more than the transitions
+    /// already followed.
+    stack: Vec<(StateId, usize)>,
+    /// The transitions of the current path, with their weights.
+    arcs: Vec<(Label, Label, f32)>,
+}
+
+impl<'a> LatticePaths<'a> {
+    fn new(lattice: &'a PronunciationLattice, fst: &'a VectorFst<TropicalWeight>) -> Self {
+        LatticePaths {
+            lattice,
+            fst,
+            stack: fst.start().map(|start| (start, 0)).into_iter().collect(),
+            arcs: Vec::new(),
+        }
+    }
+}
+
+impl Iterator for LatticePaths<'_> {
+    type Item = Result<LatticePath>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (state, steps) = self.stack.last_mut()?;
+            let state = *state;
+            if *steps == 0 {
+                *steps = 1;
+                match self.fst.final_weight(state) {
+                    Ok(Some(final_weight)) => {
+                        let pairs: Vec<(Label, Label)> = self.arcs.iter().map(|&(i, o, _)| (i, o)).collect();
+                        let score = self.arcs.iter().map(|&(_, _, weight)| weight).sum::<f32>() + final_weight.value();
+                        return Some(self.lattice.path(&pairs, score));
+                    }
+                    Ok(None) => continue,
+                    Err(e) => return Some(Err(PhonetisaurusError::FstError(e))),
+                }
+            }
+            let tr = match self.fst.get_trs(state) {
+                Ok(trs) => trs.trs().get(*steps - 1).cloned(),
+                Err(e) => return Some(Err(PhonetisaurusError::FstError(e))),
+            };
+            match tr {
+                Some(tr) => {
+                    *steps += 1;
+                    self.arcs.push((tr.ilabel, tr.olabel, *tr.weight.value()));
+                    self.stack.push((tr.nextstate, 0));
+                }
+                None => {
+                    self.stack.pop();
+                    self.arcs.pop();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+#[derive(Clone, Debug, Default)]
+/// Pronunciation dictionary with fixed pronunciations for exact word matches.
+///
+/// Attached to a model with [`PhonetisaurusModel::with_lexicon`](crate::PhonetisaurusModel::with_lexicon),
+/// it takes precedence over FST decoding, which pins pronunciations of brand names, place
+/// names and other words the model gets wrong.
+pub struct Lexicon {
+    /// Pronunciations of each word, as phoneme tokens, in file order.
+    entries: HashMap<String, Vec<Vec<String>>>,
+}
+
+impl TryFrom<&Path> for Lexicon {
+    type Error = io::Error;
+
+    /// Load a lexicon from a CMUdict-style file.
+    fn try_from(lexicon_path: &Path) -> std::result::Result<Self, Self::Error> {
+        Lexicon::from_reader(BufReader::new(File::open(lexicon_path)?))
+    }
+}
+
+impl Lexicon {
+    /// Create an empty lexicon.
+    pub fn new() -> Self {
+        Lexicon::default()
+    }
+
+    /// Read a lexicon in CMUdict or Kaldi format.
+    ///
+    /// Every line holds a word followed by its whitespace-separated phonemes. Alternative
+    /// pronunciations repeat the word, optionally with a variant number (`WORD(2)`). Empty
+    /// lines and lines starting with `;;;` or `#` are ignored. Pronunciations that only apply
+    /// with a tag are entered as `WORD#TAG`.
+    pub fn from_reader(reader: impl BufRead) -> io::Result<Self> {
+        let mut lexicon = Lexicon::new();
+
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(";;;") || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let word = fields.next().map(strip_variant_number).unwrap_or_default();
+            let phonemes: Vec<String> = fields.map(str::to_string).collect();
+            if phonemes.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Lexicon line {} has no phonemes: {}", line_number + 1, line),
+                ));
+            }
+            lexicon.insert(word, phonemes);
+        }
+
+        Ok(lexicon)
+    }
+
+    /// Add a pronunciation of a word, after the ones it already has.
+    pub fn insert(&mut self, word: impl Into<String>, phonemes: Vec<String>) {
+        let pronunciations = self.entries.entry(word.into()).or_default();
+        if !pronunciations.contains(&phonemes) {
+            pronunciations.push(phonemes);
+        }
+    }
+
+    /// Pronunciations of a word, preferred first, if the word is in the lexicon.
+    pub fn get(&self, word: &str) -> Option<&[Vec<String>]> {
+        self.entries.get(word).map(Vec::as_slice)
+    }
+
+    /// Pronunciations of a word with a POS or sense tag, entered as `word#TAG`, if the lexicon
+    /// has any for this tag.
+    pub fn get_with_tag(&self, word: &str, tag: &str) -> Option<&[Vec<String>]> {
+        self.get(&format!("{}#{}", word, tag))
+    }
+
+    /// Iterate over the words and their pronunciations, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[Vec<String>])> {
+        self.entries
+            .iter()
+            .map(|(word, pronunciations)| (word.as_str(), pronunciations.as_slice()))
+    }
+
+    /// Number of words in the lexicon.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the lexicon has no words.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Remove the CMUdict variant number from a headword: `WORD(2)` becomes `WORD`.
+fn strip_variant_number(word: &str) -> &str {
+    match word.strip_suffix(')').and_then(|rest| rest.rsplit_once('(')) {
+        Some((stem, number)) if !stem.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => stem,
+        _ => word,
+    }
+}
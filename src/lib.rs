@@ -1,63 +1,758 @@
+// pyo3 0.22's generated glue predates the Rust 2024 unsafe and conversion lints.
+#![cfg_attr(feature = "python", allow(unsafe_op_in_unsafe_fn, clippy::useless_conversion))]
+
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
 #[cfg(feature = "python")]
-use pyo3::exceptions::PyValueError;
-use std::path::Path;
+use pyo3::buffer::PyBuffer;
+#[cfg(feature = "python")]
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+#[cfg(feature = "python")]
+use pyo3::types::{PyBytes, PyCFunction, PyDict};
+#[cfg(feature = "python")]
+use std::collections::HashMap;
+#[cfg(feature = "python")]
+use std::hash::{DefaultHasher, Hash, Hasher};
+#[cfg(feature = "python")]
+use std::path::PathBuf;
+#[cfg(feature = "python")]
+use std::sync::{Arc, Mutex};
 
 // Re-export the main types from main.rs
+mod acronym;
+mod alignment;
+mod bundle;
+mod cache;
+mod compound;
+mod disk_cache;
+mod embed;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod error;
+mod espeak;
+#[cfg(feature = "download")]
+pub mod fetch;
+mod flat_json;
+mod fst_export;
+pub mod global;
+mod gzip;
+mod kaldi;
+mod lattice;
+mod lexicon;
+pub mod locate;
+mod log_semiring;
+mod lru;
+mod mbrola;
+mod model_fst;
+#[cfg(feature = "napi")]
+pub mod napi;
+mod normalize;
+mod options;
+mod parallel;
+mod phoneme_ids;
+mod phoneme_map;
 mod phonetisaurus;
+mod pipeline;
+mod post_fst;
+mod prune;
+mod registry;
+mod script;
+mod segmentation;
+mod sentence;
+mod session;
+#[cfg(feature = "download")]
+mod sha256;
+mod ssml;
+mod stress;
+mod syllabify;
+mod trace;
+mod validate;
+mod verbalize;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod zstd;
+pub use acronym::AcronymSpeller;
+pub use alignment::AlignedSegment;
+pub use bundle::{ModelManifest, write_bundle};
+pub use cache::{CacheStats, CachedPhonetisaurusModel};
+pub use compound::CompoundSplitter;
+pub use disk_cache::{DiskCache, DiskCacheStats, DiskCachedModel};
+#[doc(hidden)]
+pub use embed::{EmbeddedBytes, embedded_model};
+pub use error::*;
+pub use espeak::EspeakPhonemes;
+pub use kaldi::KaldiLexicon;
+pub use lattice::{LatticePath, LatticePaths, MIN_TEMPERATURE, PronunciationLattice};
+pub use lexicon::Lexicon;
+pub use mbrola::MbrolaPhonemes;
+pub use normalize::{CaseFold, Normalization};
+pub use options::*;
+pub use phoneme_ids::PhonemeIdMap;
+pub use phoneme_map::{PhonemeAlphabet, PhonemeMapper};
 pub use phonetisaurus::*;
+pub use pipeline::{FallbackRules, G2pPipeline, GraphemeRules, PipelineResult, PipelineStage};
+pub use registry::ModelRegistry;
+pub use segmentation::Segmentation;
+pub use sentence::*;
+pub use session::PhonemizerSession;
+pub use ssml::SsmlPhonemes;
+pub use stress::{Stress, StressConvention, StressMarking};
+pub use syllabify::{PhonemeClass, Syllabifier};
+pub use trace::{DecodeDiagnostics, DecodeStage, DecodeTracer, ResultSource, StageDiagnostics, StageTrace, Tracer};
+pub use validate::{ValidationIssue, ValidationReport};
+pub use verbalize::{EnglishNumbers, Verbalizer};
 
 #[cfg(feature = "python")]
 /// Python class wrapping the Rust PhonetisaurusModel
-#[pyclass]
+#[pyclass(module = "phonetisaurus_g2p_py.phonetisaurus_g2p_py")]
 pub struct PyPhonetisaurusModel {
-    inner: PhonetisaurusModel,
+    inner: Arc<PhonetisaurusModel>,
 }
 
 #[cfg(feature = "python")]
 /// Python class wrapping the Rust PhonetizationResult
+///
+/// Results are equal if all their attributes are, and hashable, e.g. to collect distinct
+/// pronunciations in a set.
 #[pyclass]
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct PyPhonetizationResult {
     /// Phonemes produced during phonemization
     #[pyo3(get)]
     pub phonemes: String,
+    /// The phoneme symbols that make up `phonemes`, as a list
+    #[pyo3(get)]
+    pub tokens: Vec<String>,
     /// Negative log likelihood of phonemes, lower is better
     #[pyo3(get)]
     pub neg_log_score: f32,
+    /// Probability of the pronunciation among the n-best list it was returned in
+    #[pyo3(get)]
+    pub posterior: f32,
+    /// Score divided by the length selected in the options, the raw score by default
+    #[pyo3(get)]
+    pub normalized_score: f32,
+    /// Which graphemes produced which tokens, as (start, end, tokens) with the char positions
+    /// of the graphemes in the case-folded word; empty for pronunciations from a lexicon
+    #[pyo3(get)]
+    pub alignment: Vec<(usize, usize, Vec<String>)>,
+    /// Probability of each token at its place in the word over all pronunciations, empty
+    /// unless decoded with semiring="log"
+    #[pyo3(get)]
+    pub phoneme_scores: Vec<f32>,
 }
 
 #[cfg(feature = "python")]
 #[pymethods]
 impl PyPhonetisaurusModel {
-    /// Create a new phonemizer from a phonetisaurus model file path
+    /// Create a new phonemizer from a phonetisaurus model file path (str or os.PathLike)
+    ///
+    /// Without a path, the model named by the PHONETISAURUS_MODEL environment variable is
+    /// loaded. Names and relative paths that do not exist are looked up in the directory
+    /// PHONETISAURUS_MODEL_DIR, also with `.fst` appended.
+    ///
+    /// With `optimize`, the model is converted to a compact representation that takes less
+    /// memory and decodes faster.
     #[new]
-    fn new(model_path: &str) -> PyResult<Self> {
-        let model = PhonetisaurusModel::try_from(Path::new(model_path))
-            .map_err(|e| PyValueError::new_err(format!("Failed to load model: {}", e)))?;
+    #[pyo3(signature = (model_path = None, *, optimize = false))]
+    fn new(model_path: Option<PathBuf>, optimize: bool) -> PyResult<Self> {
+        let model_path = match model_path {
+            Some(model_path) => locate::model_path(&model_path),
+            None => locate::env_model_path().ok_or_else(|| {
+                exceptions::ModelLoadError::new_err(format!("No model path given and {} is not set", locate::MODEL_ENV))
+            })?,
+        };
+        let model = PhonetisaurusModel::try_from(model_path.as_path())
+            .map_err(|e| exceptions::ModelLoadError::new_err(format!("Failed to load model: {}", e)))?;
         
-        Ok(PyPhonetisaurusModel { inner: model })
+        Ok(PyPhonetisaurusModel::wrap(model, optimize))
     }
 
     /// Create a new phonemizer from model bytes
+    ///
+    /// Any object with a byte buffer works, e.g. bytes, bytearray, memoryview, mmap or a
+    /// numpy uint8 array. Contiguous buffers are parsed in place, without a copy. `optimize`
+    /// works like in the constructor.
     #[staticmethod]
-    fn from_bytes(model_bytes: &[u8]) -> PyResult<Self> {
-        let model = PhonetisaurusModel::try_from(model_bytes)
-            .map_err(|e| PyValueError::new_err(format!("Failed to load model from bytes: {}", e)))?;
-        
-        Ok(PyPhonetisaurusModel { inner: model })
+    #[pyo3(signature = (model_bytes, optimize = false))]
+    fn from_bytes(py: Python<'_>, model_bytes: PyBuffer<u8>, optimize: bool) -> PyResult<Self> {
+        let model = if model_bytes.is_c_contiguous() {
+            // SAFETY: the buffer is contiguous and stays alive and exported while `model_bytes`
+            // is held; the GIL is held, so Python code cannot resize it meanwhile
+            let data = unsafe {
+                std::slice::from_raw_parts(model_bytes.buf_ptr().cast::<u8>(), model_bytes.len_bytes())
+            };
+            PhonetisaurusModel::try_from(data)
+        } else {
+            PhonetisaurusModel::try_from(model_bytes.to_vec(py)?.as_slice())
+        }
+        .map_err(|e| exceptions::ModelLoadError::new_err(format!("Failed to load model from bytes: {}", e)))?;
+
+        Ok(PyPhonetisaurusModel::wrap(model, optimize))
+    }
+
+    /// Whether the model was loaded with `optimize`
+    #[getter]
+    fn optimized(&self) -> bool {
+        self.inner.is_optimized()
+    }
+
+    /// Graphemes the model accepts, without epsilon; empty if the model has no input symbol table
+    ///
+    /// Characters of a text missing from it cannot be phonemized, see check_word.
+    #[getter]
+    fn input_symbols(&self) -> Vec<String> {
+        self.inner.input_symbols()
+    }
+
+    /// Phonemes the model can emit, without epsilon; empty if the model has no output symbol table
+    #[getter]
+    fn output_symbols(&self) -> Vec<String> {
+        self.inner.output_symbols()
+    }
+
+    /// Number of states of the model FST
+    #[getter]
+    fn num_states(&self) -> usize {
+        self.inner.num_states()
+    }
+
+    /// Number of arcs of the model FST
+    #[getter]
+    fn num_arcs(&self) -> usize {
+        self.inner.num_arcs()
+    }
+
+    /// Manifest of a model loaded from a bundle, as a dict like `{"language": "en-US",
+    /// "phoneme_set": "arpabet", "version": "1.2"}` with the members it has; None for a plain FST
+    #[getter]
+    fn metadata<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyDict>>> {
+        let Some(manifest) = self.inner.manifest() else {
+            return Ok(None);
+        };
+        let metadata = PyDict::new_bound(py);
+        for (key, value) in manifest.members() {
+            metadata.set_item(key, value)?;
+        }
+
+        Ok(Some(metadata))
+    }
+
+    /// The model in the binary FST format, as accepted by from_bytes
+    fn to_bytes<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let model_bytes = py.allow_threads(|| self.inner.to_bytes())
+            .map_err(|e| exceptions::PhonetisaurusError::new_err(format!("Failed to serialize model: {}", e)))?;
+
+        Ok(PyBytes::new_bound(py, &model_bytes))
+    }
+
+    /// Pickle the model as its FST bytes, so it can be sent to multiprocessing workers
+    /// and cached with joblib. A model with metadata is pickled as a bundle of the FST and
+    /// its manifest, so that the metadata survives
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> PyResult<Reduced<'py>> {
+        let py = slf.py();
+        let from_bytes = slf.get_type().getattr("from_bytes")?;
+        let model = slf.borrow();
+        let inner = &model.inner;
+        let model_bytes = match inner.manifest() {
+            Some(manifest) => {
+                let fst_bytes = py.allow_threads(|| inner.to_bytes())
+                    .map_err(|e| exceptions::PhonetisaurusError::new_err(format!("Failed to serialize model: {}", e)))?;
+                let mut bundle = Vec::new();
+                write_bundle(&mut bundle, &fst_bytes, manifest)
+                    .map_err(|e| exceptions::PhonetisaurusError::new_err(format!("Failed to serialize model: {}", e)))?;
+                PyBytes::new_bound(py, &bundle)
+            }
+            None => model.to_bytes(py)?,
+        };
+
+        Ok((from_bytes, (model_bytes, model.optimized())))
     }
 
     /// Phonemize a word using the loaded model
-    fn phonemize_word(&self, word: &str) -> PyResult<PyPhonetizationResult> {
-        let result = self.inner.phonemize_word(word)
-            .map_err(|e| PyValueError::new_err(format!("Failed to phonemize word: {}", e)))?;
-        
-        Ok(PyPhonetizationResult {
+    ///
+    /// The GIL is released while decoding, so other Python threads can run concurrently.
+    /// With `nbest`, a list of up to that many distinct pronunciations is returned instead
+    /// of a single result. `unknown` is "error", "skip" or a single replacement character
+    /// for characters the model does not know, `lowercase` lower-cases the word first and
+    /// `separator` joins the phonemes. `semiring="log"` scores a pronunciation by all of its
+    /// alignments instead of the best one, and gives the results their phoneme_scores.
+    #[pyo3(signature = (word, *, nbest = None, unknown = "error", lowercase = false, separator = " ", semiring = "tropical"))]
+    #[allow(clippy::too_many_arguments)]
+    fn phonemize_word(
+        &self,
+        py: Python<'_>,
+        word: &str,
+        nbest: Option<usize>,
+        unknown: &str,
+        lowercase: bool,
+        separator: &str,
+        semiring: &str,
+    ) -> PyResult<PyObject> {
+        let options = py_options(unknown, lowercase, separator, semiring)?;
+        let decoded = py.allow_threads(|| decode_word(&self.inner, word, nbest, &options))
+            .map_err(|e| py_err(py, "Failed to phonemize word", e))?;
+
+        Ok(decoded.into_py(py))
+    }
+
+    /// Phonemize a word in the default executor of the event loop, returning an awaitable
+    /// for the result
+    ///
+    /// Takes the same keyword arguments as phonemize_word. Must be called while an asyncio
+    /// event loop is running, which is not blocked by the decoding.
+    #[pyo3(signature = (word, *, nbest = None, unknown = "error", lowercase = false, separator = " ", semiring = "tropical"))]
+    #[allow(clippy::too_many_arguments)]
+    fn phonemize_word_async<'py>(
+        &self,
+        py: Python<'py>,
+        word: String,
+        nbest: Option<usize>,
+        unknown: &str,
+        lowercase: bool,
+        separator: &str,
+        semiring: &str,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let options = py_options(unknown, lowercase, separator, semiring)?;
+        let model = Arc::clone(&self.inner);
+        spawn_future(
+            py,
+            move || decode_word(&model, &word, nbest, &options),
+            |py, decoded| {
+                decoded
+                    .map(|decoded| decoded.into_py(py))
+                    .map_err(|e| py_err(py, "Failed to phonemize word", e))
+            },
+        )
+    }
+
+    /// Phonemize a list of words in parallel, returning results in input order
+    ///
+    /// Takes the same keyword arguments as phonemize_word; with `nbest`, each word gets a list.
+    #[pyo3(signature = (words, *, nbest = None, unknown = "error", lowercase = false, separator = " ", semiring = "tropical"))]
+    #[allow(clippy::too_many_arguments)]
+    fn phonemize_words(
+        &self,
+        py: Python<'_>,
+        words: Vec<String>,
+        nbest: Option<usize>,
+        unknown: &str,
+        lowercase: bool,
+        separator: &str,
+        semiring: &str,
+    ) -> PyResult<Vec<PyObject>> {
+        let options = py_options(unknown, lowercase, separator, semiring)?;
+        let results = py.allow_threads(|| decode_words(&self.inner, &words, nbest, &options));
+        words_into_py(py, &words, results)
+    }
+
+    /// Phonemize a list of words in parallel in the default executor of the event loop,
+    /// returning an awaitable for the results in input order
+    ///
+    /// Takes the same keyword arguments as phonemize_word. Must be called while an asyncio
+    /// event loop is running, which is not blocked by the decoding.
+    #[pyo3(signature = (words, *, nbest = None, unknown = "error", lowercase = false, separator = " ", semiring = "tropical"))]
+    #[allow(clippy::too_many_arguments)]
+    fn phonemize_words_async<'py>(
+        &self,
+        py: Python<'py>,
+        words: Vec<String>,
+        nbest: Option<usize>,
+        unknown: &str,
+        lowercase: bool,
+        separator: &str,
+        semiring: &str,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let options = py_options(unknown, lowercase, separator, semiring)?;
+        let model = Arc::clone(&self.inner);
+        spawn_future(
+            py,
+            move || {
+                let results = decode_words(&model, &words, nbest, &options);
+                (words, results)
+            },
+            |py, (words, results)| words_into_py(py, &words, results).map(|results| results.into_py(py)),
+        )
+    }
+
+    /// Phonemize a column of words, e.g. a pandas Series, and return the results as columns
+    ///
+    /// `words` is any iterable of str, in which None and NaN stand for missing words. Each
+    /// distinct word is decoded once, in parallel and with the GIL released. The columns are
+    /// `word`, `phonemes`, `neg_log_score`, `posterior` and `error`, one row per word in input
+    /// order; a word that fails or is missing has None in `phonemes` and the scores, and a
+    /// failed word has the message in `error`. `output` is "dict" for a dict of lists,
+    /// "arrow" for a pyarrow.Table or "pandas" for a pandas.DataFrame, which need those
+    /// packages. Takes the keyword arguments of phonemize_word except `nbest`.
+    #[pyo3(signature = (words, *, unknown = "error", lowercase = false, separator = " ", semiring = "tropical", output = "dict"))]
+    #[allow(clippy::too_many_arguments)]
+    fn phonemize_series<'py>(
+        &self,
+        py: Python<'py>,
+        words: &Bound<'py, PyAny>,
+        unknown: &str,
+        lowercase: bool,
+        separator: &str,
+        semiring: &str,
+        output: &str,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let options = py_options(unknown, lowercase, separator, semiring)?;
+        let table = match output {
+            "dict" => None,
+            "arrow" => Some(("pyarrow", "table")),
+            "pandas" => Some(("pandas", "DataFrame")),
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "output must be \"dict\", \"arrow\" or \"pandas\", not '{}'",
+                    output
+                )));
+            }
+        };
+        let words: Vec<Option<String>> = words
+            .iter()?
+            .map(|word| {
+                let word = word?;
+                let missing = word.is_none() || word.extract::<f64>().is_ok_and(f64::is_nan);
+                if missing { Ok(None) } else { word.extract().map(Some) }
+            })
+            .collect::<PyResult<_>>()?;
+
+        let columns = py.allow_threads(|| SeriesColumns::decode(&self.inner, &words, &options));
+        let dict = PyDict::new_bound(py);
+        dict.set_item("word", words)?;
+        dict.set_item("phonemes", columns.phonemes)?;
+        dict.set_item("neg_log_score", columns.neg_log_scores)?;
+        dict.set_item("posterior", columns.posteriors)?;
+        dict.set_item("error", columns.errors)?;
+        match table {
+            Some((module, constructor)) => py.import_bound(module)?.call_method1(constructor, (dict,)),
+            None => Ok(dict.into_any()),
+        }
+    }
+
+    /// Phonemize a word and return up to n distinct pronunciations, best first
+    ///
+    /// Takes the same keyword arguments as phonemize_word.
+    #[pyo3(signature = (word, n, *, unknown = "error", lowercase = false, separator = " ", semiring = "tropical"))]
+    #[allow(clippy::too_many_arguments)]
+    fn phonemize_word_nbest(
+        &self,
+        py: Python<'_>,
+        word: &str,
+        n: usize,
+        unknown: &str,
+        lowercase: bool,
+        separator: &str,
+        semiring: &str,
+    ) -> PyResult<Vec<PyPhonetizationResult>> {
+        let options = py_options(unknown, lowercase, separator, semiring)?;
+        let results = py.allow_threads(|| self.inner.phonemize_word_nbest_with_options(word, n, &options))
+            .map_err(|e| py_err(py, "Failed to phonemize word", e))?;
+
+        Ok(results.into_iter().map(Into::into).collect())
+    }
+
+    /// Phonemize a word with a POS or sense tag, like "VBD" for the past tense of "read"
+    ///
+    /// Pronunciations of `word#TAG` in the exception lexicon come first, and models trained
+    /// with tags read the tag after the word. Takes the same keyword arguments as
+    /// phonemize_word.
+    #[pyo3(signature = (word, tag, *, nbest = None, unknown = "error", lowercase = false, separator = " ", semiring = "tropical"))]
+    #[allow(clippy::too_many_arguments)]
+    fn phonemize_word_with_tag(
+        &self,
+        py: Python<'_>,
+        word: &str,
+        tag: &str,
+        nbest: Option<usize>,
+        unknown: &str,
+        lowercase: bool,
+        separator: &str,
+        semiring: &str,
+    ) -> PyResult<PyObject> {
+        let options = py_options(unknown, lowercase, separator, semiring)?;
+        let results = py
+            .allow_threads(|| self.inner.phonemize_word_with_tag_nbest_with_options(word, tag, nbest.unwrap_or(1), &options))
+            .map_err(|e| py_err(py, "Failed to phonemize word", e))?;
+        let decoded = match nbest {
+            Some(_) => Decoded::NBest(results),
+            None => results
+                .into_iter()
+                .next()
+                .map(Decoded::Best)
+                .ok_or_else(|| py_err(py, "Failed to phonemize word", PhonetisaurusError::NoPathFound))?,
+        };
+
+        Ok(decoded.into_py(py))
+    }
+
+    /// All characters of a word the model does not know, as (position, char) pairs
+    ///
+    /// The list is empty if the word can be phonemized. `lowercase` lower-cases the word first.
+    #[pyo3(signature = (word, *, lowercase = false))]
+    fn check_word(&self, word: &str, lowercase: bool) -> Vec<(usize, char)> {
+        let options = PhonemizeOptions {
+            case_fold: if lowercase { CaseFold::Lower } else { CaseFold::None },
+            ..PhonemizeOptions::default()
+        };
+        self.inner.check_word_with_options(word, &options).err().unwrap_or_default()
+    }
+
+    /// Negative log score of the given pronunciation (a list of phonemes) of a word
+    fn score_pronunciation(&self, py: Python<'_>, word: &str, phonemes: Vec<String>) -> PyResult<f32> {
+        let phonemes: Vec<&str> = phonemes.iter().map(String::as_str).collect();
+        py.allow_threads(|| self.inner.score_pronunciation(word, &phonemes))
+            .map_err(|e| py_err(py, "Failed to score pronunciation", e))
+    }
+
+    /// Spell a list of phonemes, returning up to n (spelling, neg_log_score) pairs, best first
+    #[pyo3(signature = (phonemes, n = 1))]
+    fn spell_phonemes(&self, py: Python<'_>, phonemes: Vec<String>, n: usize) -> PyResult<Vec<(String, f32)>> {
+        let phonemes: Vec<&str> = phonemes.iter().map(String::as_str).collect();
+        let results = py.allow_threads(|| self.inner.spell_phonemes_nbest(&phonemes, n))
+            .map_err(|e| py_err(py, "Failed to spell phonemes", e))?;
+
+        Ok(results.into_iter().map(|result| (result.spelling, result.neg_log_score)).collect())
+    }
+}
+
+#[cfg(feature = "python")]
+/// Callable and arguments recreating a pickled model
+type Reduced<'py> = (Bound<'py, PyAny>, (Bound<'py, PyBytes>, bool));
+
+#[cfg(feature = "python")]
+impl PyPhonetisaurusModel {
+    fn wrap(model: PhonetisaurusModel, optimize: bool) -> Self {
+        let model = if optimize { model.optimize() } else { model };
+        PyPhonetisaurusModel { inner: Arc::new(model) }
+    }
+}
+
+#[cfg(feature = "python")]
+/// Exceptions raised by the Python module
+///
+/// All of them derive from PhonetisaurusError, which derives from ValueError.
+// create_exception! checks pyo3's `gil-refs` feature in this crate's cfg namespace
+#[allow(unexpected_cfgs)]
+mod exceptions {
+    use pyo3::create_exception;
+    use pyo3::exceptions::PyValueError;
+
+    create_exception!(phonetisaurus_g2p_py, PhonetisaurusError, PyValueError, "Base class of the errors of phonetisaurus_g2p_py.");
+    create_exception!(phonetisaurus_g2p_py, ModelLoadError, PhonetisaurusError, "The model could not be loaded.");
+    create_exception!(
+        phonetisaurus_g2p_py,
+        UnknownSymbolError,
+        PhonetisaurusError,
+        "A character of the word is not known to the model. Its `char` and `position` attributes hold the character and its index in the word."
+    );
+    create_exception!(phonetisaurus_g2p_py, DecodeError, PhonetisaurusError, "Decoding failed, e.g. because no path was found.");
+}
+
+#[cfg(feature = "python")]
+/// Python exception for an error while decoding, with `context` prefixed to the message
+fn py_err(py: Python<'_>, context: &str, e: PhonetisaurusError) -> PyErr {
+    let message = format!("{}: {}", context, e);
+    match e {
+        PhonetisaurusError::UnknownSymbol { ch, position } => {
+            let err = exceptions::UnknownSymbolError::new_err(message);
+            let value = err.value_bound(py);
+            // setting attributes on a fresh exception instance cannot fail
+            let _ = value.setattr("char", ch.to_string());
+            let _ = value.setattr("position", position);
+            err
+        }
+        _ => exceptions::DecodeError::new_err(message),
+    }
+}
+
+#[cfg(feature = "python")]
+/// Pronunciations of a word, a single one or an n-best list
+enum Decoded {
+    Best(PhonetizationResult),
+    NBest(Vec<PhonetizationResult>),
+}
+
+#[cfg(feature = "python")]
+impl IntoPy<PyObject> for Decoded {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        match self {
+            Decoded::Best(result) => PyPhonetizationResult::from(result).into_py(py),
+            Decoded::NBest(results) => {
+                results.into_iter().map(PyPhonetizationResult::from).collect::<Vec<_>>().into_py(py)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "python")]
+/// Phonemize a word, with the n-best list if `nbest` is given
+fn decode_word(
+    model: &PhonetisaurusModel,
+    word: &str,
+    nbest: Option<usize>,
+    options: &PhonemizeOptions,
+) -> Result<Decoded> {
+    match nbest {
+        Some(n) => model.phonemize_word_nbest_with_options(word, n, options).map(Decoded::NBest),
+        None => model.phonemize_word_with_options(word, options).map(Decoded::Best),
+    }
+}
+
+#[cfg(feature = "python")]
+/// Phonemize words in parallel, with the n-best lists if `nbest` is given
+fn decode_words(
+    model: &PhonetisaurusModel,
+    words: &[String],
+    nbest: Option<usize>,
+    options: &PhonemizeOptions,
+) -> Vec<Result<Decoded>> {
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    match nbest {
+        Some(n) => model
+            .phonemize_words_nbest_with_options(&words, n, options)
+            .into_iter()
+            .map(|results| results.map(Decoded::NBest))
+            .collect(),
+        None => model
+            .phonemize_words_with_options(&words, options)
+            .into_iter()
+            .map(|result| result.map(Decoded::Best))
+            .collect(),
+    }
+}
+
+#[cfg(feature = "python")]
+/// Python results of decode_words, failing with the error of the first failed word
+fn words_into_py(py: Python<'_>, words: &[String], results: Vec<Result<Decoded>>) -> PyResult<Vec<PyObject>> {
+    results
+        .into_iter()
+        .zip(words)
+        .map(|(result, word)| {
+            result
+                .map(|decoded| decoded.into_py(py))
+                .map_err(|e| py_err(py, &format!("Failed to phonemize word '{}'", word), e))
+        })
+        .collect()
+}
+
+#[cfg(feature = "python")]
+#[derive(Default)]
+/// Columns of the results of phonemize_series, one row per word
+struct SeriesColumns {
+    phonemes: Vec<Option<String>>,
+    neg_log_scores: Vec<Option<f32>>,
+    posteriors: Vec<Option<f32>>,
+    errors: Vec<Option<String>>,
+}
+
+#[cfg(feature = "python")]
+impl SeriesColumns {
+    /// Phonemize the distinct words in parallel and repeat their results where they recur
+    fn decode(model: &PhonetisaurusModel, words: &[Option<String>], options: &PhonemizeOptions) -> Self {
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        let mut distinct = Vec::new();
+        for word in words.iter().flatten() {
+            index.entry(word.as_str()).or_insert_with(|| {
+                distinct.push(word.as_str());
+                distinct.len() - 1
+            });
+        }
+        let results = model.phonemize_words_with_options(&distinct, options);
+
+        let mut columns = SeriesColumns::default();
+        for word in words {
+            let result = word.as_deref().map(|word| &results[index[word]]);
+            let (phonemes, neg_log_score, posterior, error) = match result {
+                Some(Ok(result)) => (Some(result.phonemes.clone()), Some(result.neg_log_score), Some(result.posterior), None),
+                Some(Err(e)) => (None, None, None, Some(e.to_string())),
+                None => (None, None, None, None),
+            };
+            columns.phonemes.push(phonemes);
+            columns.neg_log_scores.push(neg_log_score);
+            columns.posteriors.push(posterior);
+            columns.errors.push(error);
+        }
+        columns
+    }
+}
+
+#[cfg(feature = "python")]
+/// Run `work` in the default executor of the running asyncio event loop and return the
+/// future of its result, converted by `finish`
+///
+/// The GIL is released during `work`. Executor threads are joined when the event loop shuts
+/// down, so no decoding outlives the interpreter.
+fn spawn_future<'py, T, W, F>(py: Python<'py>, work: W, finish: F) -> PyResult<Bound<'py, PyAny>>
+where
+    T: Send,
+    W: FnOnce() -> T + Send + 'static,
+    F: FnOnce(Python<'_>, T) -> PyResult<PyObject> + Send + 'static,
+{
+    let event_loop = py.import_bound("asyncio")?.call_method0("get_running_loop")?;
+    // the callable must be Fn, so the job is taken out on its single call
+    let job = Mutex::new(Some((work, finish)));
+    let run = PyCFunction::new_closure_bound(py, None, None, move |args, _| -> PyResult<PyObject> {
+        let py = args.py();
+        let Some((work, finish)) = job.lock().ok().and_then(|mut job| job.take()) else {
+            return Err(PyRuntimeError::new_err("The decoding job already ran"));
+        };
+        let value = py.allow_threads(work);
+        finish(py, value)
+    })?;
+
+    event_loop.call_method1("run_in_executor", (py.None(), run))
+}
+
+#[cfg(feature = "python")]
+/// PhonemizeOptions from the keyword arguments of the phonemize methods
+fn py_options(unknown: &str, lowercase: bool, separator: &str, semiring: &str) -> PyResult<PhonemizeOptions> {
+    let mut chars = unknown.chars();
+    let unknown_char_policy = match (unknown, chars.next(), chars.next()) {
+        ("error", _, _) => UnknownCharPolicy::Error,
+        ("skip", _, _) => UnknownCharPolicy::Skip,
+        (_, Some(ch), None) => UnknownCharPolicy::Substitute(ch),
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "unknown must be \"error\", \"skip\" or a single character, not '{}'",
+                unknown
+            )));
+        }
+    };
+    let semiring = match semiring {
+        "tropical" => ScoreSemiring::Tropical,
+        "log" => ScoreSemiring::Log,
+        _ => return Err(PyValueError::new_err(format!("semiring must be \"tropical\" or \"log\", not '{}'", semiring))),
+    };
+
+    Ok(PhonemizeOptions {
+        unknown_char_policy,
+        semiring,
+        case_fold: if lowercase { CaseFold::Lower } else { CaseFold::None },
+        separators: PhonemeSeparators {
+            phoneme_separator: separator.to_string(),
+            ..PhonemeSeparators::default()
+        },
+        ..PhonemizeOptions::default()
+    })
+}
+
+#[cfg(feature = "python")]
+impl From<PhonetizationResult> for PyPhonetizationResult {
+    fn from(result: PhonetizationResult) -> Self {
+        PyPhonetizationResult {
             phonemes: result.phonemes,
+            tokens: result.phoneme_tokens,
             neg_log_score: result.neg_log_score,
-        })
+            posterior: result.posterior,
+            normalized_score: result.normalized_score,
+            alignment: result
+                .alignment
+                .into_iter()
+                .map(|segment| (segment.graphemes.start, segment.graphemes.end, segment.phonemes))
+                .collect(),
+            phoneme_scores: result.phoneme_posteriors,
+        }
     }
 }
 
@@ -65,13 +760,38 @@ impl PyPhonetisaurusModel {
 #[pymethods]
 impl PyPhonetizationResult {
     fn __repr__(&self) -> String {
-        format!("PhonetizationResult(phonemes='{}', neg_log_score={})", 
-                self.phonemes, self.neg_log_score)
+        format!("PhonetizationResult(phonemes='{}', neg_log_score={}, posterior={})", 
+                self.phonemes, self.neg_log_score, self.posterior)
     }
     
     fn __str__(&self) -> String {
         self.phonemes.clone()
     }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self == other
+    }
+
+    // the scores are left out, so that results equal as floats hash alike
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (&self.phonemes, &self.tokens, &self.alignment).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The attributes as a dict, e.g. to build a DataFrame or write JSON
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("phonemes", &self.phonemes)?;
+        dict.set_item("tokens", &self.tokens)?;
+        dict.set_item("neg_log_score", self.neg_log_score)?;
+        dict.set_item("posterior", self.posterior)?;
+        dict.set_item("normalized_score", self.normalized_score)?;
+        dict.set_item("alignment", self.alignment.clone())?;
+        dict.set_item("phoneme_scores", &self.phoneme_scores)?;
+
+        Ok(dict)
+    }
 }
 
 #[cfg(feature = "python")]
@@ -80,5 +800,9 @@ impl PyPhonetizationResult {
 fn phonetisaurus_g2p_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyPhonetisaurusModel>()?;
     m.add_class::<PyPhonetizationResult>()?;
+    m.add("PhonetisaurusError", m.py().get_type_bound::<exceptions::PhonetisaurusError>())?;
+    m.add("ModelLoadError", m.py().get_type_bound::<exceptions::ModelLoadError>())?;
+    m.add("UnknownSymbolError", m.py().get_type_bound::<exceptions::UnknownSymbolError>())?;
+    m.add("DecodeError", m.py().get_type_bound::<exceptions::DecodeError>())?;
     Ok(())
 }
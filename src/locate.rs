@@ -0,0 +1,53 @@
+//! Finding models through environment variables, so that deployments like containers can
+//! configure the model without passing paths around.
+//!
+//! [`MODEL_ENV`] (`PHONETISAURUS_MODEL`) names the model to use when none is given.
+//! [`MODEL_DIR_ENV`] (`PHONETISAURUS_MODEL_DIR`) is a directory that model names and
+//! relative paths are looked up in when they do not exist from the current directory, so
+//! that `en` finds `$PHONETISAURUS_MODEL_DIR/en.fst`.
+//!
+//! A path given explicitly takes precedence over `PHONETISAURUS_MODEL`. Empty variables are
+//! ignored.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Environment variable with the model to use when none is given, a path or a name to look up
+/// in [`MODEL_DIR_ENV`].
+pub const MODEL_ENV: &str = "PHONETISAURUS_MODEL";
+
+/// Environment variable with the directory to look up model names and relative paths in.
+pub const MODEL_DIR_ENV: &str = "PHONETISAURUS_MODEL_DIR";
+
+/// Extension tried after a model name that is not found in [`MODEL_DIR_ENV`] as it is.
+const MODEL_EXTENSION: &str = "fst";
+
+/// The model from [`MODEL_ENV`], if the variable is set, located with [`model_path`].
+pub fn env_model_path() -> Option<PathBuf> {
+    non_empty_var(MODEL_ENV).map(|model| model_path(Path::new(&model)))
+}
+
+/// Where to find the model `path`: the path itself if it exists or is absolute, otherwise the
+/// path or the path with `.fst` appended in [`MODEL_DIR_ENV`] if one of them exists there.
+///
+/// If the model is found nowhere, `path` is returned unchanged, so that loading it reports the
+/// path as given.
+pub fn model_path(path: &Path) -> PathBuf {
+    if path.exists() || path.is_absolute() {
+        return path.to_path_buf();
+    }
+    let Some(dir) = non_empty_var(MODEL_DIR_ENV).map(PathBuf::from) else {
+        return path.to_path_buf();
+    };
+    let mut with_extension = path.as_os_str().to_owned();
+    with_extension.push(".");
+    with_extension.push(MODEL_EXTENSION);
+    [dir.join(path), dir.join(with_extension)]
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+fn non_empty_var(name: &str) -> Option<OsString> {
+    std::env::var_os(name).filter(|value| !value.is_empty())
+}
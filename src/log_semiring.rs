@@ -0,0 +1,135 @@
+use rustfst::algorithms::determinize::determinize;
+use rustfst::algorithms::rm_epsilon::rm_epsilon;
+use rustfst::prelude::*;
+use std::collections::HashMap;
+
+use crate::error::{PhonetisaurusError, Result};
+use crate::options::PhonemeSeparators;
+use crate::prune::input_positions;
+
+/// Copy an FST into the log semiring, with its weights divided by `temperature`.
+///
+/// The weights of phonetisaurus models are negative log probabilities, so they read the same
+/// in both semirings, but paths are summed instead of taking the best one.
+pub(crate) fn to_log(fst: &VectorFst<TropicalWeight>, temperature: f32) -> Result<VectorFst<LogWeight>> {
+    convert(fst, |weight| weight / temperature)
+}
+
+/// Copy an FST in the log semiring back into the tropical one.
+pub(crate) fn to_tropical(fst: &VectorFst<LogWeight>) -> Result<VectorFst<TropicalWeight>> {
+    convert(fst, |weight| weight)
+}
+
+fn convert<W1: Semiring<Type = f32>, W2: Semiring<Type = f32>>(
+    fst: &VectorFst<W1>,
+    scale: impl Fn(f32) -> f32,
+) -> Result<VectorFst<W2>> {
+    let mut converted: VectorFst<W2> = VectorFst::new();
+    converted.add_states(fst.num_states());
+    if let Some(start) = fst.start() {
+        converted.set_start(start).map_err(PhonetisaurusError::FstError)?;
+    }
+    for state in fst.states_iter() {
+        if let Some(weight) = fst.final_weight(state).map_err(PhonetisaurusError::FstError)? {
+            converted
+                .set_final(state, W2::new(scale(*weight.value())))
+                .map_err(PhonetisaurusError::FstError)?;
+        }
+        for tr in fst.get_trs(state).map_err(PhonetisaurusError::FstError)?.trs() {
+            let converted_tr = Tr::new(tr.ilabel, tr.olabel, W2::new(scale(*tr.weight.value())), tr.nextstate);
+            converted.add_tr(state, converted_tr).map_err(PhonetisaurusError::FstError)?;
+        }
+    }
+    if let Some(isyms) = fst.input_symbols() {
+        converted.set_input_symbols(isyms.clone());
+    }
+    if let Some(osyms) = fst.output_symbols() {
+        converted.set_output_symbols(osyms.clone());
+    }
+    Ok(converted)
+}
+
+/// Remove the epsilons of a projected lattice and determinize it in the log semiring, so
+/// that each phoneme sequence is left with the summed probability of all its alignments.
+pub(crate) fn sum_alignments(lattice: &VectorFst<TropicalWeight>) -> Result<VectorFst<TropicalWeight>> {
+    let mut log_lattice = to_log(lattice, 1.0)?;
+    rm_epsilon(&mut log_lattice).map_err(PhonetisaurusError::FstError)?;
+    let deterministic_fst: VectorFst<LogWeight> = determinize(&log_lattice).map_err(PhonetisaurusError::FstError)?;
+    to_tropical(&deterministic_fst)
+}
+
+/// Marginal probabilities of the phonemes of a composed lattice, from the forward and
+/// backward probabilities of its states in the log semiring.
+pub(crate) struct PhonemePosteriors {
+    /// Probability of producing a phoneme token by a transition leaving a state after the
+    /// given number of input symbols, keyed by that number, whether the transition reads
+    /// an input symbol, and the token.
+    by_position: HashMap<(usize, bool, String), f64>,
+}
+
+impl PhonemePosteriors {
+    pub(crate) fn new(
+        lattice: &VectorFst<TropicalWeight>,
+        osyms: &SymbolTable,
+        separators: &PhonemeSeparators,
+    ) -> Result<Self> {
+        let Some(start) = lattice.start() else {
+            return Err(PhonetisaurusError::NoPathFound);
+        };
+        let log_lattice = to_log(lattice, 1.0)?;
+        let distances = |reverse| -> Result<Vec<f32>> {
+            Ok(shortest_distance(&log_lattice, reverse)
+                .map_err(PhonetisaurusError::FstError)?
+                .iter()
+                .map(|weight| *weight.value())
+                .collect())
+        };
+        let (alpha, beta) = (distances(false)?, distances(true)?);
+        let distance = |d: &[f32], state: StateId| d.get(state as usize).copied().unwrap_or(f32::INFINITY);
+        let total = distance(&beta, start);
+
+        let positions = input_positions(lattice, start)?;
+        let mut by_position: HashMap<(usize, bool, String), f64> = HashMap::new();
+        for state in lattice.states_iter() {
+            let Some(position) = positions[state as usize] else {
+                continue;
+            };
+            for tr in lattice.get_trs(state).map_err(PhonetisaurusError::FstError)?.trs() {
+                let Some(symbol) = osyms.get_symbol(tr.olabel) else {
+                    continue;
+                };
+                if tr.olabel == EPS_LABEL || separators.is_skip(symbol) {
+                    continue;
+                }
+                let cost = distance(&alpha, state) + tr.weight.value() + distance(&beta, tr.nextstate) - total;
+                let key = (position, tr.ilabel != EPS_LABEL, separators.token(symbol));
+                *by_position.entry(key).or_default() += (-(cost as f64)).exp();
+            }
+        }
+        Ok(PhonemePosteriors { by_position })
+    }
+
+    /// The marginal probability of each phoneme token produced along a path of the lattice,
+    /// given as its `(ilabel, olabel)` pairs.
+    pub(crate) fn of_path(
+        &self,
+        arcs: &[(Label, Label)],
+        osyms: &SymbolTable,
+        separators: &PhonemeSeparators,
+    ) -> Vec<f32> {
+        let mut position = 0;
+        let mut posteriors = Vec::new();
+        for &(ilabel, olabel) in arcs {
+            let reads = ilabel != EPS_LABEL;
+            if let Some(symbol) = osyms.get_symbol(olabel)
+                && olabel != EPS_LABEL
+                && !separators.is_skip(symbol)
+            {
+                let key = (position, reads, separators.token(symbol));
+                posteriors.push(self.by_position.get(&key).map_or(0.0, |&p| p.min(1.0) as f32));
+            }
+            position += usize::from(reads);
+        }
+        posteriors
+    }
+}
@@ -0,0 +1,139 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Sentinel index marking the ends of the recency list.
+const NONE: usize = usize::MAX;
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    /// Next more recently used entry.
+    newer: usize,
+    /// Next less recently used entry.
+    older: usize,
+}
+
+/// Least-recently-used cache with constant time lookup, insertion and eviction.
+///
+/// Entries live in a vector and form a doubly linked list through their indices, ordered
+/// from most to least recently used. The map points from keys to vector indices.
+pub(crate) struct LruCache<K, V> {
+    capacity: usize,
+    map: HashMap<K, usize>,
+    entries: Vec<Entry<K, V>>,
+    newest: usize,
+    oldest: usize,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    /// Create a cache holding at most `capacity` entries. A capacity of 0 disables caching.
+    pub(crate) fn new(capacity: usize) -> Self {
+        LruCache {
+            capacity,
+            map: HashMap::new(),
+            entries: Vec::new(),
+            newest: NONE,
+            oldest: NONE,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Look up a value, marking it as most recently used.
+    pub(crate) fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = *self.map.get(key)?;
+        self.unlink(index);
+        self.push_front(index);
+        Some(&self.entries[index].value)
+    }
+
+    /// Whether a key is cached, without marking it as used.
+    pub(crate) fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(key)
+    }
+
+    /// Insert or replace a value, evicting the least recently used entry if the cache is full.
+    pub(crate) fn put(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Some(&index) = self.map.get(&key) {
+            self.entries[index].value = value;
+            self.unlink(index);
+            self.push_front(index);
+            return;
+        }
+
+        let index = if self.entries.len() < self.capacity {
+            self.entries.push(Entry {
+                key: key.clone(),
+                value,
+                newer: NONE,
+                older: NONE,
+            });
+            self.entries.len() - 1
+        } else {
+            // reuse the slot of the evicted entry
+            let index = self.oldest;
+            self.unlink(index);
+            let evicted = std::mem::replace(
+                &mut self.entries[index],
+                Entry {
+                    key: key.clone(),
+                    value,
+                    newer: NONE,
+                    older: NONE,
+                },
+            );
+            self.map.remove(&evicted.key);
+            index
+        };
+        self.map.insert(key, index);
+        self.push_front(index);
+    }
+
+    /// Remove all entries.
+    pub(crate) fn clear(&mut self) {
+        self.map.clear();
+        self.entries.clear();
+        self.newest = NONE;
+        self.oldest = NONE;
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let (newer, older) = (self.entries[index].newer, self.entries[index].older);
+        match newer {
+            NONE => self.newest = older,
+            newer => self.entries[newer].older = older,
+        }
+        match older {
+            NONE => self.oldest = newer,
+            older => self.entries[older].newer = newer,
+        }
+    }
+
+    fn push_front(&mut self, index: usize) {
+        self.entries[index].newer = NONE;
+        self.entries[index].older = self.newest;
+        match self.newest {
+            NONE => self.oldest = index,
+            newest => self.entries[newest].newer = index,
+        }
+        self.newest = index;
+    }
+}
@@ -1,42 +1,116 @@
-use anyhow::Result;
-use clap::Parser;
-use std::path::Path;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 
-mod phonetisaurus;
-use phonetisaurus::*;
+mod cli;
+use cli::bench::{self, BenchArgs};
+use cli::cache::{self, CacheArgs};
+use cli::config::{self, Config};
+use cli::debug::{self, DebugArgs};
+use cli::dict::{self, DictArgs};
+use cli::evaluate::{self, EvaluateArgs};
+#[cfg(feature = "download")]
+use cli::fetch::{self, FetchArgs};
+use cli::inspect::{self, InspectArgs};
+use cli::kaldi::{self, KaldiArgs};
+use cli::pack::{self, PackArgs};
+use cli::phonemize::{self, PhonemizeArgs};
+use cli::review::{self, ReviewArgs};
+#[cfg(feature = "server")]
+use cli::serve::{self, ServeArgs};
+use cli::ssml::{self, SsmlArgs};
+use cli::validate::{self, ValidateArgs};
 
 /// A command-line tool for phonemizing words using Phonetisaurus FST models
+///
+/// Invoking the tool without a subcommand behaves like `phonemize`. Defaults of the arguments
+/// are read from `g2p.toml` in the current directory, or the file given by a leading
+/// `--config PATH`. `PHONETISAURUS_MODEL` sets the model path used when none is given,
+/// overriding the config file, and models that are not found are looked up in
+/// `PHONETISAURUS_MODEL_DIR`.
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
-    /// Path to the Phonetisaurus FST model file
-    model_path: String,
-    
-    /// Word to phonemize
-    word: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    phonemize: Option<PhonemizeArgs>,
 }
 
-fn main() {
-    // Parse command-line arguments using clap
-    let cli = Cli::parse();
+#[derive(Subcommand)]
+enum Command {
+    /// Phonemize a word, or a list of words read from stdin or a file
+    Phonemize(Box<PhonemizeArgs>),
+    /// Show the size and the symbol inventories of a model
+    Inspect(InspectArgs),
+    /// Write a text as SSML, with a `<phoneme>` tag giving the pronunciation of each word
+    Ssml(SsmlArgs),
+    /// Bundle a model with a manifest of its language, phoneme set, casing and version
+    Pack(PackArgs),
+    /// Compare the model against a reference lexicon, reporting phoneme and word error rates
+    Evaluate(EvaluateArgs),
+    /// Write a pronunciation lexicon (CMUdict/Kaldi format) for a list of words
+    Dict(DictArgs),
+    /// Write a Kaldi lexicon for a list of words, and optionally L.fst with disambiguation symbols
+    Kaldi(KaldiArgs),
+    /// Review the pronunciations of a list of words and append the accepted ones to an exception lexicon
+    Review(ReviewArgs),
+    /// Measure throughput, latency and memory use on a word list
+    Bench(BenchArgs),
+    /// Serve the model over HTTP or a Unix socket with a JSON API
+    #[cfg(feature = "server")]
+    Serve(ServeArgs),
+    /// Download a model into the local cache and print its path
+    #[cfg(feature = "download")]
+    Fetch(FetchArgs),
+    /// Show the statistics of a phonemization cache file, or clear it
+    Cache(CacheArgs),
+    /// Decode a word and show the decoding stages, optionally drawing their FSTs
+    Debug(DebugArgs),
+    /// Check that a model is well-formed: start and final states, symbol tables, no unreachable states
+    Validate(ValidateArgs),
+}
 
-    // Load the model from the specified path
-    let phonemizer: PhonetisaurusModel = match PhonetisaurusModel::try_from(Path::new(&cli.model_path)) {
-        Ok(m) => m,
-        Err(e) => {
-            eprintln!("Failed to load model from '{}': {}", cli.model_path, e);
-            std::process::exit(1);
-        }
-    };
+fn main() {
+    // Parse command-line arguments using clap, with the defaults of the config file
+    let mut args: Vec<_> = std::env::args_os().collect();
+    let mut command = Cli::command();
+    let mut config = None;
+    if let Some(path) = config::take_config_path(&mut args) {
+        (command, config) = match Config::read(&path).and_then(|config| Ok((config.apply(command)?, config))) {
+            Ok((command, config)) => (command, Some(config)),
+            Err(e) => {
+                eprintln!("Failed to read config '{}': {}", path.display(), e);
+                std::process::exit(2);
+            }
+        };
+    }
+    let matches = command.get_matches_from(args);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let command_name = matches.subcommand_name().unwrap_or("phonemize");
+    if let Some(model_path) = config.as_ref().and_then(|config| config.model_path(command_name)) {
+        cli::set_config_model_path(model_path.to_string());
+    }
 
-    // Phonemize the word
-    match phonemizer.phonemize_word(&cli.word) {
-        Ok(result) => {
-            println!("Nofabet: {}", result.phonemes);
-        }
-        Err(e) => {
-            eprintln!("Failed to phonemize word: {}", e);
-            std::process::exit(1);
-        }
+    match (cli.command, cli.phonemize) {
+        (Some(Command::Phonemize(args)), _) => phonemize::run(*args),
+        (None, Some(args)) => phonemize::run(args),
+        (Some(Command::Inspect(args)), _) => inspect::run(args),
+        (Some(Command::Pack(args)), _) => pack::run(args),
+        (Some(Command::Ssml(args)), _) => ssml::run(args),
+        (Some(Command::Evaluate(args)), _) => evaluate::run(args),
+        (Some(Command::Dict(args)), _) => dict::run(args),
+        (Some(Command::Kaldi(args)), _) => kaldi::run(args),
+        (Some(Command::Review(args)), _) => review::run(args),
+        (Some(Command::Bench(args)), _) => bench::run(args),
+        #[cfg(feature = "server")]
+        (Some(Command::Serve(args)), _) => serve::run(args),
+        (Some(Command::Validate(args)), _) => validate::run(args),
+        (Some(Command::Debug(args)), _) => debug::run(args),
+        (Some(Command::Cache(args)), _) => cache::run(args),
+        #[cfg(feature = "download")]
+        (Some(Command::Fetch(args)), _) => fetch::run(args),
+        // no subcommand and no phonemize arguments but defaults, as the model path is optional
+        (None, None) => phonemize::run(PhonemizeArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())),
     }
 }
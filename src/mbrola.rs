@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::phoneme_map::PhonemeMapper;
+use crate::phonetisaurus::PhonetizationResult;
+use crate::sentence::SentenceResult;
+use crate::stress::{StressConvention, StressMarking, apply_stress};
+
+/// Writes pronunciations as the `.pho` input of MBROLA voices: one `phoneme duration` line per
+/// phoneme, durations in milliseconds, with the silence `_` before and after each utterance.
+///
+/// The lines have no pitch targets, and every phoneme gets a default duration unless one is set
+/// for it, so that the output can be fed to a voice as is or refined by a prosody model.
+#[derive(Clone, Debug)]
+pub struct MbrolaPhonemes {
+    mapper: Option<PhonemeMapper>,
+    /// How the model marks stress, which MBROLA phonemes do not carry.
+    stress: Option<StressConvention>,
+    default_duration: u32,
+    durations: HashMap<String, u32>,
+    pause: u32,
+}
+
+impl Default for MbrolaPhonemes {
+    fn default() -> Self {
+        MbrolaPhonemes::new()
+    }
+}
+
+impl MbrolaPhonemes {
+    /// Write the phoneme tokens as they are, 80 ms each, with pauses of 100 ms.
+    pub fn new() -> Self {
+        MbrolaPhonemes {
+            mapper: None,
+            stress: None,
+            default_duration: 80,
+            durations: HashMap::new(),
+            pause: 100,
+        }
+    }
+
+    /// Convert the tokens to the phonemes of the voice with a table, like
+    /// [`PhonemeMapper::to_xsampa`] for the SAMPA names most voices use. `stress` says how the
+    /// model marks stress, so that the markers are removed.
+    pub fn with_mapper(mut self, mapper: PhonemeMapper, stress: Option<StressConvention>) -> Self {
+        self.mapper = Some(mapper);
+        self.stress = stress;
+        self
+    }
+
+    /// Remove the stress markers of this convention from the tokens, without converting them.
+    pub fn with_stress_convention(mut self, stress: Option<StressConvention>) -> Self {
+        self.stress = stress;
+        self
+    }
+
+    /// Duration of the phonemes without one of their own, in milliseconds.
+    pub fn with_default_duration(mut self, milliseconds: u32) -> Self {
+        self.default_duration = milliseconds;
+        self
+    }
+
+    /// Durations of phonemes of the voice, in milliseconds.
+    pub fn with_durations(mut self, durations: impl IntoIterator<Item = (String, u32)>) -> Self {
+        self.durations.extend(durations);
+        self
+    }
+
+    /// Duration of the silence before and after utterances and between the words of a
+    /// sentence, in milliseconds. 0 leaves the silences out.
+    pub fn with_pause(mut self, milliseconds: u32) -> Self {
+        self.pause = milliseconds;
+        self
+    }
+
+    /// The phonemes of the voice for phoneme tokens, without stress markers.
+    pub fn map_tokens<S: AsRef<str>>(&self, tokens: &[S]) -> Vec<String> {
+        match (&self.mapper, self.stress) {
+            (Some(mapper), stress) => mapper.map_marked(tokens, stress, ["", ""]),
+            (None, Some(convention)) => apply_stress(tokens, convention, StressMarking::Strip).0,
+            (None, None) => tokens.iter().map(|token| token.as_ref().to_string()).collect(),
+        }
+    }
+
+    /// The `.pho` lines of an utterance given by its phoneme tokens.
+    pub fn pho<S: AsRef<str>>(&self, tokens: &[S]) -> String {
+        self.utterance([tokens])
+    }
+
+    /// The `.pho` lines of a result.
+    pub fn result_pho(&self, result: &PhonetizationResult) -> String {
+        self.pho(&result.phoneme_tokens)
+    }
+
+    /// The `.pho` lines of a sentence, with a pause between words.
+    pub fn sentence_pho(&self, sentence: &SentenceResult) -> String {
+        self.utterance(sentence.words.iter().map(|word| &word.result.phoneme_tokens))
+    }
+
+    fn utterance<S: AsRef<str>, W: AsRef<[S]>>(&self, words: impl IntoIterator<Item = W>) -> String {
+        let mut pho = String::new();
+        self.push_pause(&mut pho);
+        for (i, tokens) in words.into_iter().enumerate() {
+            if i > 0 {
+                self.push_pause(&mut pho);
+            }
+            for phoneme in self.map_tokens(tokens.as_ref()).into_iter().filter(|phoneme| !phoneme.is_empty()) {
+                let duration = self.durations.get(&phoneme).copied().unwrap_or(self.default_duration);
+                let _ = writeln!(pho, "{} {}", phoneme, duration);
+            }
+        }
+        self.push_pause(&mut pho);
+        pho
+    }
+
+    fn push_pause(&self, pho: &mut String) {
+        if self.pause > 0 {
+            let _ = writeln!(pho, "_ {}", self.pause);
+        }
+    }
+}
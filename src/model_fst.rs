@@ -0,0 +1,175 @@
+use rustfst::algorithms::compose;
+use rustfst::algorithms::fst_convert_from_ref;
+use rustfst::prelude::*;
+use std::sync::Arc;
+
+use crate::error::{PhonetisaurusError, Result};
+use crate::validate::{ValidationReport, validate_fst};
+
+type W = TropicalWeight;
+
+#[derive(Clone, Debug)]
+/// The trained FST of a model, in the mutable representation it is loaded in or in the
+/// compact immutable one of [`PhonetisaurusModel::optimize`](crate::PhonetisaurusModel::optimize).
+///
+/// Both are wrapped inside an Arc, so that composition can borrow the FST through a cheap
+/// pointer clone instead of copying it for each new phonemization.
+pub(crate) enum ModelFst {
+    Vector(Arc<VectorFst<W>>),
+    /// All transitions in a single array, which takes less memory and is read with better
+    /// cache locality during composition.
+    Const(Arc<ConstFst<W>>),
+}
+
+impl ModelFst {
+    /// Convert to the compact immutable representation.
+    pub(crate) fn into_const(self) -> Self {
+        match self {
+            ModelFst::Vector(fst) => {
+                let fst = Arc::try_unwrap(fst).unwrap_or_else(|fst| fst.as_ref().clone());
+                ModelFst::Const(Arc::new(ConstFst::from(fst)))
+            }
+            const_fst => const_fst,
+        }
+    }
+
+    pub(crate) fn is_const(&self) -> bool {
+        matches!(self, ModelFst::Const(_))
+    }
+
+    pub(crate) fn input_symbols(&self) -> Option<&Arc<SymbolTable>> {
+        match self {
+            ModelFst::Vector(fst) => fst.input_symbols(),
+            ModelFst::Const(fst) => fst.input_symbols(),
+        }
+    }
+
+    pub(crate) fn output_symbols(&self) -> Option<&Arc<SymbolTable>> {
+        match self {
+            ModelFst::Vector(fst) => fst.output_symbols(),
+            ModelFst::Const(fst) => fst.output_symbols(),
+        }
+    }
+
+    pub(crate) fn num_states(&self) -> usize {
+        match self {
+            ModelFst::Vector(fst) => fst.num_states(),
+            ModelFst::Const(fst) => fst.num_states(),
+        }
+    }
+
+    pub(crate) fn num_arcs(&self) -> usize {
+        fn count<F: ExpandedFst<W>>(fst: &F) -> usize {
+            fst.states_iter().filter_map(|state| fst.num_trs(state).ok()).sum()
+        }
+        match self {
+            ModelFst::Vector(fst) => count(fst.as_ref()),
+            ModelFst::Const(fst) => count(fst.as_ref()),
+        }
+    }
+
+    /// Read every transition, so that the memory of the FST is resident.
+    pub(crate) fn touch(&self) {
+        fn touch<F: ExpandedFst<W>>(fst: &F) {
+            let mut checksum: Label = 0;
+            for state in fst.states_iter() {
+                if let Ok(trs) = fst.get_trs(state) {
+                    for tr in trs.trs() {
+                        checksum = checksum.wrapping_add(tr.ilabel ^ tr.olabel ^ tr.nextstate as Label);
+                    }
+                }
+            }
+            // keep the reads from being optimized away
+            std::hint::black_box(checksum);
+        }
+        match self {
+            ModelFst::Vector(fst) => touch(fst.as_ref()),
+            ModelFst::Const(fst) => touch(fst.as_ref()),
+        }
+    }
+
+    pub(crate) fn start(&self) -> Option<StateId> {
+        match self {
+            ModelFst::Vector(fst) => fst.start(),
+            ModelFst::Const(fst) => fst.start(),
+        }
+    }
+
+    pub(crate) fn final_weight(&self, state: StateId) -> Result<Option<W>> {
+        match self {
+            ModelFst::Vector(fst) => fst.final_weight(state),
+            ModelFst::Const(fst) => fst.final_weight(state),
+        }
+        .map_err(PhonetisaurusError::FstError)
+    }
+
+    /// The transitions of a state reading `ilabel`, found by binary search since the
+    /// transitions of the model are sorted by input label.
+    pub(crate) fn trs_reading(&self, state: StateId, ilabel: Label) -> Result<Vec<Tr<W>>> {
+        fn reading<F: Fst<W>>(fst: &F, state: StateId, ilabel: Label) -> Result<Vec<Tr<W>>> {
+            let trs = fst.get_trs(state).map_err(PhonetisaurusError::FstError)?;
+            let trs = trs.trs();
+            let first = trs.partition_point(|tr| tr.ilabel < ilabel);
+            Ok(trs[first..].iter().take_while(|tr| tr.ilabel == ilabel).cloned().collect())
+        }
+        match self {
+            ModelFst::Vector(fst) => reading(fst.as_ref(), state, ilabel),
+            ModelFst::Const(fst) => reading(fst.as_ref(), state, ilabel),
+        }
+    }
+
+    pub(crate) fn validate(&self) -> ValidationReport {
+        match self {
+            ModelFst::Vector(fst) => validate_fst(fst.as_ref()),
+            ModelFst::Const(fst) => validate_fst(fst.as_ref()),
+        }
+    }
+
+    /// Serialize in the binary VectorFst format models are loaded from.
+    pub(crate) fn store(&self, model_binary: &mut Vec<u8>) -> Result<()> {
+        match self {
+            ModelFst::Vector(fst) => fst.store(model_binary),
+            ModelFst::Const(fst) => fst_convert_from_ref::<_, _, VectorFst<W>>(fst.as_ref()).store(model_binary),
+        }
+        .map_err(PhonetisaurusError::FstError)
+    }
+
+    /// Compose an FST with the trained FST on the right, e.g. a word acceptor.
+    pub(crate) fn compose_right_of(&self, fst: VectorFst<W>) -> Result<VectorFst<W>> {
+        // NOTE: The weird type annotation is needed, as Rust doesn't know which Borrow<_> impl
+        // to use for the trained FST. The impls for both Arc<_> and the FST itself are possible
+        // (as far as I understand), and we need to use the second one, so the FST type needs to
+        // be specified as F2. For reference, the full type annotation would be:
+        //      W:  TropicalWeight,
+        //      F1: VectorFst<TropicalWeight>,
+        //      F2: VectorFst<TropicalWeight> or ConstFst<TropicalWeight>,
+        //      F3: VectorFst<TropicalWeight>,
+        //      B1: VectorFst<TropicalWeight>,
+        //      B2: Arc<F2>,
+        // Only the Arc is cloned here: composition reads the trained FST through it and never
+        // copies it.
+        match self {
+            ModelFst::Vector(model) => {
+                compose::compose::<_, _, VectorFst<W>, _, _, _>(fst, model.clone())
+            }
+            ModelFst::Const(model) => {
+                compose::compose::<_, _, ConstFst<W>, _, _, _>(fst, model.clone())
+            }
+        }
+        .map_err(PhonetisaurusError::FstError)
+    }
+
+    /// Compose an FST with the trained FST on the left, e.g. a phoneme acceptor.
+    pub(crate) fn compose_left_of(&self, fst: VectorFst<W>) -> Result<VectorFst<W>> {
+        // same type annotation as in compose_right_of, with the trained FST on the left
+        match self {
+            ModelFst::Vector(model) => {
+                compose::compose::<_, VectorFst<W>, VectorFst<W>, _, Arc<VectorFst<W>>, _>(model.clone(), fst)
+            }
+            ModelFst::Const(model) => {
+                compose::compose::<_, ConstFst<W>, VectorFst<W>, _, Arc<ConstFst<W>>, _>(model.clone(), fst)
+            }
+        }
+        .map_err(PhonetisaurusError::FstError)
+    }
+}
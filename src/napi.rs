@@ -0,0 +1,248 @@
+//! Node.js addon, written against the stable N-API C interface of Node.
+//!
+//! The shared library built with the `napi` feature is a native Node module once renamed
+//! to `.node`. It exports a `PhonetisaurusModel` class:
+//!
+//! ```js
+//! const { PhonetisaurusModel } = require("./phonetisaurus_g2p_py.node");
+//! const model = new PhonetisaurusModel("model.fst");
+//! model.phonemizeWord("hello"); // { phonemes: "...", score: 3.6 }
+//! ```
+//!
+//! The N-API symbols are resolved against the Node process when the module is loaded.
+
+use std::ffi::{CString, c_void};
+use std::path::Path;
+use std::ptr;
+
+use crate::PhonetisaurusModel;
+
+#[allow(non_camel_case_types)]
+mod sys {
+    use std::ffi::{c_char, c_int, c_void};
+
+    pub type napi_env = *mut c_void;
+    pub type napi_value = *mut c_void;
+    pub type napi_callback_info = *mut c_void;
+    pub type napi_ref = *mut c_void;
+    pub type napi_status = c_int;
+    pub type napi_callback = Option<unsafe extern "C" fn(napi_env, napi_callback_info) -> napi_value>;
+    pub type napi_finalize = Option<unsafe extern "C" fn(napi_env, *mut c_void, *mut c_void)>;
+
+    pub const NAPI_OK: napi_status = 0;
+    pub const NAPI_AUTO_LENGTH: usize = usize::MAX;
+    pub const NAPI_DEFAULT: c_int = 0;
+
+    #[repr(C)]
+    pub struct napi_property_descriptor {
+        pub utf8name: *const c_char,
+        pub name: napi_value,
+        pub method: napi_callback,
+        pub getter: napi_callback,
+        pub setter: napi_callback,
+        pub value: napi_value,
+        pub attributes: c_int,
+        pub data: *mut c_void,
+    }
+
+    unsafe extern "C" {
+        pub fn napi_define_class(
+            env: napi_env,
+            utf8name: *const c_char,
+            length: usize,
+            constructor: napi_callback,
+            data: *mut c_void,
+            property_count: usize,
+            properties: *const napi_property_descriptor,
+            result: *mut napi_value,
+        ) -> napi_status;
+        pub fn napi_get_cb_info(
+            env: napi_env,
+            cbinfo: napi_callback_info,
+            argc: *mut usize,
+            argv: *mut napi_value,
+            this_arg: *mut napi_value,
+            data: *mut *mut c_void,
+        ) -> napi_status;
+        pub fn napi_get_value_string_utf8(
+            env: napi_env,
+            value: napi_value,
+            buf: *mut c_char,
+            bufsize: usize,
+            result: *mut usize,
+        ) -> napi_status;
+        pub fn napi_wrap(
+            env: napi_env,
+            js_object: napi_value,
+            native_object: *mut c_void,
+            finalize_cb: napi_finalize,
+            finalize_hint: *mut c_void,
+            result: *mut napi_ref,
+        ) -> napi_status;
+        pub fn napi_unwrap(env: napi_env, js_object: napi_value, result: *mut *mut c_void) -> napi_status;
+        pub fn napi_create_object(env: napi_env, result: *mut napi_value) -> napi_status;
+        pub fn napi_create_string_utf8(
+            env: napi_env,
+            str: *const c_char,
+            length: usize,
+            result: *mut napi_value,
+        ) -> napi_status;
+        pub fn napi_create_double(env: napi_env, value: f64, result: *mut napi_value) -> napi_status;
+        pub fn napi_set_named_property(
+            env: napi_env,
+            object: napi_value,
+            utf8name: *const c_char,
+            value: napi_value,
+        ) -> napi_status;
+        pub fn napi_throw_error(env: napi_env, code: *const c_char, msg: *const c_char) -> napi_status;
+    }
+}
+
+use sys::*;
+
+/// Error raised as a JavaScript exception.
+struct JsError(String);
+
+impl<E: std::fmt::Display> From<E> for JsError {
+    fn from(e: E) -> Self {
+        JsError(e.to_string())
+    }
+}
+
+fn check(status: napi_status) -> Result<(), JsError> {
+    if status == NAPI_OK {
+        Ok(())
+    } else {
+        Err(JsError(format!("N-API call failed with status {}", status)))
+    }
+}
+
+/// Run a callback body, turning its error into a thrown exception.
+unsafe fn throw_on_error(env: napi_env, body: impl FnOnce() -> Result<napi_value, JsError>) -> napi_value {
+    match body() {
+        Ok(value) => value,
+        Err(JsError(message)) => {
+            let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+            unsafe { napi_throw_error(env, ptr::null(), message.as_ptr()) };
+            ptr::null_mut()
+        }
+    }
+}
+
+/// The `this` object of a call and its first `N` arguments, failing if fewer were passed.
+unsafe fn call_args<const N: usize>(
+    env: napi_env,
+    info: napi_callback_info,
+) -> Result<(napi_value, [napi_value; N]), JsError> {
+    let mut argc = N;
+    let mut argv = [ptr::null_mut(); N];
+    let mut this = ptr::null_mut();
+    check(unsafe { napi_get_cb_info(env, info, &mut argc, argv.as_mut_ptr(), &mut this, ptr::null_mut()) })?;
+    if argc < N {
+        return Err(JsError(format!("Expected {} argument(s), got {}", N, argc)));
+    }
+    Ok((this, argv))
+}
+
+unsafe fn get_string(env: napi_env, value: napi_value) -> Result<String, JsError> {
+    let mut len = 0;
+    check(unsafe { napi_get_value_string_utf8(env, value, ptr::null_mut(), 0, &mut len) })
+        .map_err(|_| JsError("Expected a string".to_string()))?;
+    let mut buffer = vec![0u8; len + 1];
+    check(unsafe {
+        napi_get_value_string_utf8(env, value, buffer.as_mut_ptr().cast(), buffer.len(), &mut len)
+    })?;
+    buffer.truncate(len);
+    Ok(String::from_utf8(buffer)?)
+}
+
+unsafe fn create_string(env: napi_env, s: &str) -> Result<napi_value, JsError> {
+    let mut value = ptr::null_mut();
+    check(unsafe { napi_create_string_utf8(env, s.as_ptr().cast(), s.len(), &mut value) })?;
+    Ok(value)
+}
+
+unsafe extern "C" fn finalize_model(_env: napi_env, data: *mut c_void, _hint: *mut c_void) {
+    drop(unsafe { Box::from_raw(data.cast::<PhonetisaurusModel>()) });
+}
+
+/// `new PhonetisaurusModel(path)`
+unsafe extern "C" fn model_constructor(env: napi_env, info: napi_callback_info) -> napi_value {
+    unsafe {
+        throw_on_error(env, || {
+            let (this, [path]) = call_args::<1>(env, info)?;
+            let path = get_string(env, path)?;
+            let model = PhonetisaurusModel::try_from(Path::new(&path))
+                .map_err(|e| JsError(format!("Failed to load model: {}", e)))?;
+            let model = Box::into_raw(Box::new(model));
+            if let Err(e) = check(napi_wrap(env, this, model.cast(), Some(finalize_model), ptr::null_mut(), ptr::null_mut())) {
+                drop(Box::from_raw(model));
+                return Err(e);
+            }
+            Ok(this)
+        })
+    }
+}
+
+/// `model.phonemizeWord(word)`, returning `{phonemes, score}`
+unsafe extern "C" fn model_phonemize_word(env: napi_env, info: napi_callback_info) -> napi_value {
+    unsafe {
+        throw_on_error(env, || {
+            let (this, [word]) = call_args::<1>(env, info)?;
+            let mut model: *mut c_void = ptr::null_mut();
+            check(napi_unwrap(env, this, &mut model))?;
+            let model = &*model.cast::<PhonetisaurusModel>();
+            let word = get_string(env, word)?;
+            let result = model
+                .phonemize_word(&word)
+                .map_err(|e| JsError(format!("Failed to phonemize word: {}", e)))?;
+
+            let mut object = ptr::null_mut();
+            check(napi_create_object(env, &mut object))?;
+            let phonemes = create_string(env, &result.phonemes)?;
+            check(napi_set_named_property(env, object, c"phonemes".as_ptr(), phonemes))?;
+            let mut score = ptr::null_mut();
+            // go through the shortest decimal representation, so 3.6f32 becomes 3.6
+            let score_value = result.neg_log_score.to_string().parse().unwrap_or(result.neg_log_score as f64);
+            check(napi_create_double(env, score_value, &mut score))?;
+            check(napi_set_named_property(env, object, c"score".as_ptr(), score))?;
+            Ok(object)
+        })
+    }
+}
+
+/// Module entry point, called by Node when the addon is loaded.
+///
+/// # Safety
+///
+/// Must only be called by Node with a valid environment and exports object.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn napi_register_module_v1(env: napi_env, exports: napi_value) -> napi_value {
+    unsafe {
+        throw_on_error(env, || {
+            let methods = [napi_property_descriptor {
+                utf8name: c"phonemizeWord".as_ptr(),
+                name: ptr::null_mut(),
+                method: Some(model_phonemize_word),
+                getter: None,
+                setter: None,
+                value: ptr::null_mut(),
+                attributes: NAPI_DEFAULT,
+                data: ptr::null_mut(),
+            }];
+            let mut class = ptr::null_mut();
+            check(napi_define_class(
+                env,
+                c"PhonetisaurusModel".as_ptr(),
+                NAPI_AUTO_LENGTH,
+                Some(model_constructor),
+                ptr::null_mut(),
+                methods.len(),
+                methods.as_ptr(),
+                &mut class,
+            ))?;
+            check(napi_set_named_property(env, exports, c"PhonetisaurusModel".as_ptr(), class))?;
+            Ok(exports)
+        })
+    }
+}
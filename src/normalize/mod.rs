@@ -0,0 +1,196 @@
+use std::borrow::Cow;
+
+mod tables;
+use tables::{CANONICAL_COMPOSITIONS, CANONICAL_DECOMPOSITIONS, COMBINING_CLASSES};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Unicode normalization form applied to words before phonemization.
+pub enum Normalization {
+    /// Leave the word as it is.
+    #[default]
+    None,
+    /// Canonical composition, the form most models are trained on.
+    Nfc,
+    /// Canonical decomposition, for models trained on base letters and combining marks.
+    Nfd,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Case mapping applied to words before phonemization.
+pub enum CaseFold {
+    /// Leave the case as it is.
+    #[default]
+    None,
+    /// Map to lower case.
+    Lower,
+    /// Map to upper case.
+    Upper,
+    /// Map to lower case with Turkish and Azerbaijani rules: "I" becomes dotless "ı" and
+    /// "İ" becomes "i".
+    TurkicLower,
+    /// Map to upper case with Turkish and Azerbaijani rules: "i" becomes dotted "İ".
+    TurkicUpper,
+}
+
+impl Normalization {
+    /// Normalize a string to this form.
+    pub fn apply<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        match self {
+            Normalization::None => Cow::Borrowed(s),
+            // ASCII is invariant under both forms
+            _ if s.is_ascii() => Cow::Borrowed(s),
+            Normalization::Nfd => Cow::Owned(decompose(s).into_iter().collect()),
+            Normalization::Nfc => Cow::Owned(compose(decompose(s)).into_iter().collect()),
+        }
+    }
+}
+
+impl CaseFold {
+    /// Map the case of a string.
+    pub fn apply<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        match self {
+            CaseFold::None => Cow::Borrowed(s),
+            CaseFold::Lower => Cow::Owned(s.to_lowercase()),
+            CaseFold::Upper => Cow::Owned(s.to_uppercase()),
+            CaseFold::TurkicLower => Cow::Owned(
+                s.chars()
+                    .flat_map(|ch| match ch {
+                        'I' => vec!['ı'],
+                        'İ' => vec!['i'],
+                        ch => ch.to_lowercase().collect(),
+                    })
+                    .collect(),
+            ),
+            CaseFold::TurkicUpper => Cow::Owned(
+                s.chars()
+                    .flat_map(|ch| match ch {
+                        'i' => vec!['İ'],
+                        ch => ch.to_uppercase().collect(),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+// Hangul syllables are composed from leading consonants (L), vowels (V) and trailing
+// consonants (T) arithmetically, see the Unicode standard, section 3.12.
+const S_BASE: u32 = 0xAC00;
+const L_BASE: u32 = 0x1100;
+const V_BASE: u32 = 0x1161;
+const T_BASE: u32 = 0x11A7;
+const L_COUNT: u32 = 19;
+const V_COUNT: u32 = 21;
+const T_COUNT: u32 = 28;
+const N_COUNT: u32 = V_COUNT * T_COUNT;
+const S_COUNT: u32 = L_COUNT * N_COUNT;
+
+/// Full canonical decomposition in canonical order (NFD).
+fn decompose(s: &str) -> Vec<char> {
+    let mut chars = Vec::with_capacity(s.len());
+    for ch in s.chars() {
+        decompose_char(ch, &mut chars);
+    }
+
+    // canonical ordering: stable sort of every run of non-starters by combining class
+    let mut start = 0;
+    while start < chars.len() {
+        if combining_class(chars[start]) == 0 {
+            start += 1;
+            continue;
+        }
+        let end = chars[start..]
+            .iter()
+            .position(|&ch| combining_class(ch) == 0)
+            .map_or(chars.len(), |offset| start + offset);
+        chars[start..end].sort_by_key(|&ch| combining_class(ch));
+        start = end;
+    }
+
+    chars
+}
+
+fn decompose_char(ch: char, out: &mut Vec<char>) {
+    let code = ch as u32;
+    if (S_BASE..S_BASE + S_COUNT).contains(&code) {
+        let index = code - S_BASE;
+        out.extend(char::from_u32(L_BASE + index / N_COUNT));
+        out.extend(char::from_u32(V_BASE + (index % N_COUNT) / T_COUNT));
+        if !index.is_multiple_of(T_COUNT) {
+            out.extend(char::from_u32(T_BASE + index % T_COUNT));
+        }
+        return;
+    }
+    match CANONICAL_DECOMPOSITIONS.binary_search_by_key(&ch, |&(composite, _, _)| composite) {
+        Ok(i) => {
+            let (_, first, second) = CANONICAL_DECOMPOSITIONS[i];
+            decompose_char(first, out);
+            if second != '\0' {
+                decompose_char(second, out);
+            }
+        }
+        Err(_) => out.push(ch),
+    }
+}
+
+/// Canonical composition of a decomposed string (NFD to NFC).
+fn compose(chars: Vec<char>) -> Vec<char> {
+    let mut result: Vec<char> = Vec::with_capacity(chars.len());
+    let mut starter: Option<usize> = None;
+    // combining class of the last char kept after the starter, None if there is none
+    let mut last_class: Option<u8> = None;
+
+    for ch in chars {
+        let class = combining_class(ch);
+        if let Some(starter) = starter {
+            let blocked = last_class.is_some_and(|last| last == 0 || last >= class);
+            if !blocked && let Some(composite) = compose_pair(result[starter], ch) {
+                result[starter] = composite;
+                continue;
+            }
+        }
+        if class == 0 {
+            starter = Some(result.len());
+            last_class = None;
+        } else {
+            last_class = Some(class);
+        }
+        result.push(ch);
+    }
+
+    result
+}
+
+fn compose_pair(first: char, second: char) -> Option<char> {
+    let (f, s) = (first as u32, second as u32);
+    if (L_BASE..L_BASE + L_COUNT).contains(&f) && (V_BASE..V_BASE + V_COUNT).contains(&s) {
+        return char::from_u32(S_BASE + ((f - L_BASE) * V_COUNT + (s - V_BASE)) * T_COUNT);
+    }
+    if (S_BASE..S_BASE + S_COUNT).contains(&f)
+        && (f - S_BASE).is_multiple_of(T_COUNT)
+        && (T_BASE + 1..T_BASE + T_COUNT).contains(&s)
+    {
+        return char::from_u32(f + (s - T_BASE));
+    }
+    CANONICAL_COMPOSITIONS
+        .binary_search_by_key(&(first, second), |&(a, b, _)| (a, b))
+        .ok()
+        .map(|i| CANONICAL_COMPOSITIONS[i].2)
+}
+
+fn combining_class(ch: char) -> u8 {
+    match COMBINING_CLASSES.binary_search_by(|&(first, last, _)| {
+        if last < ch {
+            std::cmp::Ordering::Less
+        } else if first > ch {
+            std::cmp::Ordering::Greater
+        } else {
+            std::cmp::Ordering::Equal
+        }
+    }) {
+        Ok(i) => COMBINING_CLASSES[i].2,
+        Err(_) => 0,
+    }
+}
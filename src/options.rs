@@ -0,0 +1,242 @@
+use crate::normalize::{CaseFold, Normalization};
+use crate::segmentation::Segmentation;
+use crate::stress::{StressConvention, StressMarking};
+use crate::syllabify::Syllabifier;
+use crate::trace::Tracer;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What to do with input characters that are not in the model's input symbol table.
+pub enum UnknownCharPolicy {
+    /// Fail with [`PhonetisaurusError::UnknownSymbol`](crate::PhonetisaurusError::UnknownSymbol).
+    #[default]
+    Error,
+    /// Drop the character and phonemize the rest of the word.
+    Skip,
+    /// Replace the character with another one, which must be in the symbol table.
+    Substitute(char),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Length by which [`PhonetizationResult::normalized_score`](crate::PhonetizationResult::normalized_score)
+/// is normalized, so that scores of long and short words can be compared.
+pub enum LengthNormalization {
+    /// Report the raw score.
+    #[default]
+    None,
+    /// Divide the score by the number of phonemes of the pronunciation.
+    PerPhoneme,
+    /// Divide the score by the number of chars of the word, after case folding and
+    /// normalization.
+    PerGrapheme,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// How the weights of the alignments of a pronunciation are combined into its score.
+pub enum ScoreSemiring {
+    /// Score a pronunciation by its best alignment, like phonetisaurus does.
+    #[default]
+    Tropical,
+    /// Score a pronunciation by the summed probability of all its alignments, and fill
+    /// [`PhonetizationResult::phoneme_posteriors`](crate::PhonetizationResult::phoneme_posteriors)
+    /// with the marginal probability of each phoneme.
+    Log,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What to do with hyphens and apostrophes in words like "don't" and "state-of-the-art",
+/// which models are often not trained with.
+pub enum WordPunctuation {
+    /// Look them up like letters, so that the unknown char policy applies to those missing
+    /// from the input symbol table.
+    #[default]
+    AsIs,
+    /// Keep those in the input symbol table and drop the others.
+    Keep,
+    /// Drop them.
+    Strip,
+    /// Phonemize the parts between them separately and join the pronunciations of the parts,
+    /// with `joiner` as a phoneme token between them unless it is empty. Only the best
+    /// pronunciation is produced.
+    Split { joiner: String },
+}
+
+/// Hyphens and apostrophes handled by [`WordPunctuation`].
+pub(crate) fn is_word_punctuation(c: char) -> bool {
+    matches!(c, '-' | '\u{2010}' | '\u{2011}' | '\'' | '\u{2019}' | '\u{02BC}')
+}
+
+#[derive(Clone, Debug, Default)]
+/// Flag to abort phonemizations from another thread.
+///
+/// Clones share the flag, so a token can be passed in [`PhonemizeOptions::cancellation`]
+/// and cancelled through a clone.
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make all phonemizations using this token fail with
+    /// [`PhonetisaurusError::Cancelled`](crate::PhonetisaurusError::Cancelled).
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`Self::cancel`] was called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl PartialEq for CancellationToken {
+    /// Tokens are equal if they share the flag.
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+/// Options controlling how a word is phonemized.
+///
+/// The default options reproduce the behavior of [`PhonetisaurusModel::phonemize_word`](crate::PhonetisaurusModel::phonemize_word).
+pub struct PhonemizeOptions {
+    /// Handling of characters missing from the input symbol table.
+    pub unknown_char_policy: UnknownCharPolicy,
+    /// Unicode normalization form the word is brought into before symbol lookup.
+    pub normalize: Normalization,
+    /// Case mapping applied to the word before normalization and symbol lookup.
+    pub case_fold: CaseFold,
+    /// How the word is split into input symbols.
+    pub segmentation: Segmentation,
+    /// Handling of hyphens and apostrophes in the word, after the exception lexicon was
+    /// consulted with the word as it is.
+    pub word_punctuation: WordPunctuation,
+    /// Split words at the last occurrence of this char into the word and a POS or sense tag,
+    /// like `read#VBD`, see [`PhonetisaurusModel::phonemize_word_with_tag`](crate::PhonetisaurusModel::phonemize_word_with_tag).
+    pub tag_separator: Option<char>,
+    /// How the normalized score of the results is computed.
+    pub length_normalization: LengthNormalization,
+    /// Whether pronunciations are scored by their best alignment or by all of them.
+    pub semiring: ScoreSemiring,
+    /// Keep at most this many lattice states per input position, those on the best paths.
+    pub beam: Option<usize>,
+    /// Keep at most this many lattice states in total, those on the best paths.
+    pub max_states: Option<usize>,
+    /// Drop lattice states and transitions whose best path is worse than the best path of
+    /// the lattice by more than this weight.
+    pub prune_weight: Option<f32>,
+    /// Fail with [`PhonetisaurusError::Timeout`](crate::PhonetisaurusError::Timeout) once
+    /// decoding a word took longer than this.
+    ///
+    /// The timeout is best-effort: the time is checked between the decoding stages, which
+    /// cannot be interrupted themselves, so a word can take longer than this. Without any of
+    /// the pruning options, a timeout also prunes the lattice with a `prune_weight` of 20, so
+    /// that determinization works on a bounded lattice; `max_lattice_states` bounds it further.
+    pub timeout: Option<Duration>,
+    /// Token to abort decoding from another thread, checked like `timeout`. Not serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub cancellation: Option<CancellationToken>,
+    /// Reject words longer than this many chars, after case folding and normalization, with
+    /// [`PhonetisaurusError::InputTooLong`](crate::PhonetisaurusError::InputTooLong).
+    pub max_word_length: Option<usize>,
+    /// Fail with [`PhonetisaurusError::SearchSpaceExceeded`](crate::PhonetisaurusError::SearchSpaceExceeded)
+    /// if the composed, the projected or the determinized lattice has more states than this.
+    /// The projected lattice is checked before it is determinized.
+    pub max_lattice_states: Option<usize>,
+    /// How output symbols are turned into phoneme tokens and joined.
+    pub separators: PhonemeSeparators,
+    /// Fill [`PhonetizationResult::syllables`](crate::PhonetizationResult::syllables) with
+    /// this syllabifier.
+    pub syllabifier: Option<Arc<Syllabifier>>,
+    /// How the model marks stress. If set, [`PhonetizationResult::stress`](crate::PhonetizationResult::stress)
+    /// is filled and `stress_marking` is applied.
+    pub stress_convention: Option<StressConvention>,
+    /// Keep, strip or rewrite the stress markers, if `stress_convention` is set.
+    ///
+    /// Pronunciations that only differed in their stress are merged when stripping.
+    pub stress_marking: StressMarking,
+    /// Receiver of the timing and lattice size of every decoding stage. Not serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub tracer: Option<Tracer>,
+    /// Fill [`PhonetizationResult::diagnostics`](crate::PhonetizationResult::diagnostics) with
+    /// the stages, the skipped chars and the source of the results.
+    pub diagnostics: bool,
+}
+
+impl PhonemizeOptions {
+    /// A text that is the same for options producing the same results, and the same from one
+    /// run to the next, to key cached results with.
+    pub(crate) fn cache_key(&self) -> String {
+        // the limits only decide whether a word fails, and failures are not cached; diagnostics
+        // are not cached either
+        let options = PhonemizeOptions {
+            timeout: None,
+            cancellation: None,
+            tracer: None,
+            diagnostics: false,
+            syllabifier: None,
+            ..self.clone()
+        };
+        let syllabifier = self.syllabifier.as_ref().map(|syllabifier| syllabifier.cache_key());
+        format!("{:?} {:?}", options, syllabifier)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+/// Symbol conventions of the output side of a model, and how phoneme tokens are joined.
+///
+/// The defaults follow phonetisaurus alignments: `_` for graphemes without a phoneme, `|`
+/// between the phonemes of a multi-phoneme symbol like `K|S`, and tokens joined by spaces.
+pub struct PhonemeSeparators {
+    /// Output symbols produced for silent graphemes, which are dropped from the results.
+    pub skip_symbols: Vec<String>,
+    /// Tie between the phonemes of a multi-phoneme symbol, removed when the symbol becomes a
+    /// phoneme token. Empty to keep symbols as they are.
+    pub tie: String,
+    /// Separator between the tokens in [`PhonetizationResult::phonemes`](crate::PhonetizationResult::phonemes).
+    pub phoneme_separator: String,
+}
+
+impl Default for PhonemeSeparators {
+    fn default() -> Self {
+        PhonemeSeparators {
+            skip_symbols: vec!["_".to_string()],
+            tie: "|".to_string(),
+            phoneme_separator: " ".to_string(),
+        }
+    }
+}
+
+impl PhonemeSeparators {
+    /// Whether an output symbol is dropped from the results.
+    pub(crate) fn is_skip(&self, symbol: &str) -> bool {
+        self.skip_symbols.iter().any(|skip| skip == symbol)
+    }
+
+    /// Phoneme token of an output symbol.
+    pub(crate) fn token(&self, symbol: &str) -> String {
+        if self.tie.is_empty() {
+            symbol.to_string()
+        } else {
+            symbol.replace(&self.tie, "")
+        }
+    }
+
+    /// Join phoneme tokens with the phoneme separator.
+    pub(crate) fn join<S: AsRef<str>>(&self, tokens: &[S]) -> String {
+        let tokens: Vec<&str> = tokens.iter().map(AsRef::as_ref).collect();
+        tokens.join(&self.phoneme_separator)
+    }
+}
@@ -0,0 +1,64 @@
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Number of worker threads used when the caller does not ask for a specific count.
+pub(crate) fn default_threads() -> usize {
+    thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+}
+
+/// Most items a thread takes at once, so that a thread that got slow items does not hold up
+/// the others with a long run of items still to do.
+const MAX_CHUNK_SIZE: usize = 16;
+
+/// Apply `f` to every item on up to `threads` scoped threads, keeping the input order.
+///
+/// Threads take small chunks of consecutive items from a shared counter as they become free,
+/// so that items taking much longer than others, like long words, do not leave the other
+/// threads idle. The chunks are put back in order after joining the threads.
+pub(crate) fn par_map<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let threads = threads.clamp(1, items.len().max(1));
+    if threads == 1 {
+        return items.iter().map(&f).collect();
+    }
+
+    // several chunks per thread even for short lists, to even out their work
+    let chunk_size = (items.len() / (threads * 4)).clamp(1, MAX_CHUNK_SIZE);
+    let next = AtomicUsize::new(0);
+    let (f, next) = (&f, &next);
+    let mut chunks: Vec<(usize, Vec<R>)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(move || {
+                    let mut chunks = Vec::new();
+                    loop {
+                        let start = next.fetch_add(chunk_size, Ordering::Relaxed);
+                        if start >= items.len() {
+                            return chunks;
+                        }
+                        let chunk = &items[start..(start + chunk_size).min(items.len())];
+                        chunks.push((start, chunk.iter().map(f).collect::<Vec<R>>()));
+                    }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| match handle.join() {
+                Ok(chunks) => chunks,
+                Err(panic) => std::panic::resume_unwind(panic),
+            })
+            .collect()
+    });
+
+    chunks.sort_unstable_by_key(|(start, _)| *start);
+    chunks.into_iter().flat_map(|(_, results)| results).collect()
+}
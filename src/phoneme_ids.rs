@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use crate::error::{PhonetisaurusError, Result};
+use crate::flat_json::parse_id_object;
+use crate::phonetisaurus::{PhonetisaurusModel, PhonetizationResult};
+use crate::sentence::SentenceResult;
+
+/// Converts phoneme tokens to the integer IDs that the acoustic model of a TTS voice takes as
+/// input, with a table like the `phoneme_id_map` of a piper voice.
+///
+/// A token is looked up as is first, and otherwise split into the longest symbols of the
+/// table, so that `aɪ` is converted to the IDs of `a` and `ɪ` with a table of single
+/// characters. Tokens that cannot be converted fail with [`PhonetisaurusError::NoPhonemeId`].
+///
+/// The IDs are framed the way piper frames them: the IDs of the beginning symbol (`^`) come
+/// first, those of the end symbol (`$`) last, the padding (`_`) follows the beginning and
+/// every symbol, and the word separator (` `) comes between words. Each is left out if the
+/// table has no IDs for it, and can be changed with the `with_*` methods.
+#[derive(Clone, Debug)]
+pub struct PhonemeIdMap {
+    table: HashMap<String, Vec<i64>>,
+    /// Length of the longest symbol of the table, in bytes.
+    max_symbol_len: usize,
+    pad: Option<String>,
+    bos: Option<String>,
+    eos: Option<String>,
+    word_separator: Option<String>,
+}
+
+impl TryFrom<&Path> for PhonemeIdMap {
+    type Error = io::Error;
+
+    /// Load a table, as JSON if the file name ends with `.json`, as TSV otherwise. A JSON file
+    /// may be the config of a piper voice.
+    fn try_from(path: &Path) -> std::result::Result<Self, Self::Error> {
+        let mut reader = BufReader::new(File::open(path)?);
+        if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json")) {
+            let mut json = String::new();
+            reader.read_to_string(&mut json)?;
+            PhonemeIdMap::from_json(&json)
+        } else {
+            PhonemeIdMap::from_tsv(reader)
+        }
+    }
+}
+
+impl FromIterator<(String, Vec<i64>)> for PhonemeIdMap {
+    fn from_iter<I: IntoIterator<Item = (String, Vec<i64>)>>(entries: I) -> Self {
+        let table: HashMap<String, Vec<i64>> = entries.into_iter().collect();
+        let max_symbol_len = table.keys().map(String::len).max().unwrap_or_default();
+        PhonemeIdMap {
+            table,
+            max_symbol_len,
+            pad: Some("_".to_string()),
+            bos: Some("^".to_string()),
+            eos: Some("$".to_string()),
+            word_separator: Some(" ".to_string()),
+        }
+    }
+}
+
+impl PhonemeIdMap {
+    /// Read a table from JSON: the config of a piper voice, or its `phoneme_id_map` object
+    /// alone, like `{"_": [0], "^": [1], "$": [2], " ": [3], "a": [14]}`.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let entries = parse_id_object(json)
+            .map_err(|message| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid phoneme ID map: {}", message)))?;
+        Ok(entries.into_iter().collect())
+    }
+
+    /// Read a table with one `symbol<TAB>id` line per symbol, or several IDs separated by
+    /// whitespace.
+    ///
+    /// Empty lines and lines starting with `#` are ignored. Symbols that are whitespace, like
+    /// the word separator of piper, need the JSON format.
+    pub fn from_tsv(reader: impl BufRead) -> io::Result<Self> {
+        let mut entries = Vec::new();
+
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let symbol = fields.next().unwrap_or_default();
+            let ids: Option<Vec<i64>> = fields.map(|id| id.parse().ok()).collect();
+            match ids {
+                Some(ids) if !ids.is_empty() => entries.push((symbol.to_string(), ids)),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Phoneme ID map line {} is not a symbol and its IDs: {}", line_number + 1, line),
+                    ));
+                }
+            }
+        }
+
+        Ok(entries.into_iter().collect())
+    }
+
+    /// Symbol whose IDs follow the beginning and every symbol, `_` by default.
+    pub fn with_pad(mut self, symbol: Option<&str>) -> Self {
+        self.pad = symbol.map(str::to_string);
+        self
+    }
+
+    /// Symbol whose IDs begin the sequence, `^` by default.
+    pub fn with_bos(mut self, symbol: Option<&str>) -> Self {
+        self.bos = symbol.map(str::to_string);
+        self
+    }
+
+    /// Symbol whose IDs end the sequence, `$` by default.
+    pub fn with_eos(mut self, symbol: Option<&str>) -> Self {
+        self.eos = symbol.map(str::to_string);
+        self
+    }
+
+    /// Symbol whose IDs separate words, ` ` by default.
+    pub fn with_word_separator(mut self, symbol: Option<&str>) -> Self {
+        self.word_separator = symbol.map(str::to_string);
+        self
+    }
+
+    /// IDs of a single phoneme token, without padding.
+    pub fn token_ids(&self, token: &str) -> Result<Vec<i64>> {
+        let mut ids = Vec::new();
+        self.push_token(&mut ids, token, false)?;
+        Ok(ids)
+    }
+
+    /// Framed IDs of the phoneme tokens of a word.
+    pub fn ids<S: AsRef<str>>(&self, tokens: &[S]) -> Result<Vec<i64>> {
+        self.framed([tokens])
+    }
+
+    /// Framed IDs of the phonemes of a result.
+    pub fn result_ids(&self, result: &PhonetizationResult) -> Result<Vec<i64>> {
+        self.ids(&result.phoneme_tokens)
+    }
+
+    /// Framed IDs of the phonemes of a sentence, with the word separator between words.
+    pub fn sentence_ids(&self, sentence: &SentenceResult) -> Result<Vec<i64>> {
+        self.framed(sentence.words.iter().map(|word| &word.result.phoneme_tokens))
+    }
+
+    fn framed<S: AsRef<str>, W: AsRef<[S]>>(&self, words: impl IntoIterator<Item = W>) -> Result<Vec<i64>> {
+        let mut ids = Vec::new();
+        self.push_special(&mut ids, &self.bos);
+        self.push_special(&mut ids, &self.pad);
+        for (i, tokens) in words.into_iter().enumerate() {
+            if i > 0 && self.ids_of(&self.word_separator).is_some() {
+                self.push_special(&mut ids, &self.word_separator);
+                self.push_special(&mut ids, &self.pad);
+            }
+            for token in tokens.as_ref() {
+                self.push_token(&mut ids, token.as_ref(), true)?;
+            }
+        }
+        self.push_special(&mut ids, &self.eos);
+        Ok(ids)
+    }
+
+    /// Push the IDs of a token, split into symbols if needed, each followed by the padding if
+    /// `padded`.
+    fn push_token(&self, ids: &mut Vec<i64>, token: &str, padded: bool) -> Result<()> {
+        let unknown = || PhonetisaurusError::NoPhonemeId(token.to_string());
+        let symbols = match self.table.get(token) {
+            Some(symbol_ids) => vec![symbol_ids],
+            None if token.is_empty() => return Err(unknown()),
+            None => self.split(token).ok_or_else(unknown)?,
+        };
+        for symbol_ids in symbols {
+            ids.extend_from_slice(symbol_ids);
+            if padded {
+                self.push_special(ids, &self.pad);
+            }
+        }
+        Ok(())
+    }
+
+    /// IDs of the symbols of a token, matching the longest symbol at each position.
+    fn split(&self, token: &str) -> Option<Vec<&Vec<i64>>> {
+        let mut symbols = Vec::new();
+        let mut rest = token;
+        while !rest.is_empty() {
+            let (len, ids) = (1..=self.max_symbol_len.min(rest.len()))
+                .rev()
+                .filter(|&len| rest.is_char_boundary(len))
+                .find_map(|len| self.table.get(&rest[..len]).map(|ids| (len, ids)))?;
+            symbols.push(ids);
+            rest = &rest[len..];
+        }
+        Some(symbols)
+    }
+
+    fn ids_of(&self, symbol: &Option<String>) -> Option<&Vec<i64>> {
+        self.table.get(symbol.as_deref()?)
+    }
+
+    fn push_special(&self, ids: &mut Vec<i64>, symbol: &Option<String>) {
+        if let Some(symbol_ids) = self.ids_of(symbol) {
+            ids.extend_from_slice(symbol_ids);
+        }
+    }
+}
+
+impl PhonetisaurusModel {
+    /// Phonemize a word and convert its pronunciation to the framed phoneme IDs of a TTS voice.
+    pub fn phonemize_word_ids(&self, word: &str, phoneme_ids: &PhonemeIdMap) -> Result<Vec<i64>> {
+        phoneme_ids.result_ids(&self.phonemize_word(word)?)
+    }
+
+    /// Phonemize a sentence and convert its pronunciation to the framed phoneme IDs of a TTS
+    /// voice, with the word separator between words.
+    pub fn phonemize_sentence_ids(&self, text: &str, phoneme_ids: &PhonemeIdMap) -> Result<Vec<i64>> {
+        phoneme_ids.sentence_ids(&self.phonemize_sentence(text)?)
+    }
+}
@@ -0,0 +1,483 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use crate::flat_json::parse_string_object;
+use crate::options::PhonemeSeparators;
+use crate::phonetisaurus::PhonetizationResult;
+use crate::stress::{Stress, StressConvention, StressMarking, apply_stress};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Phoneme inventories of models, which the built-in tables of [`PhonemeMapper`] convert from.
+pub enum PhonemeAlphabet {
+    /// ARPAbet as used by CMUdict, with stress digits 0 to 2 on vowels.
+    Arpabet,
+    /// Nofabet as used by the NST and NB Uttale lexicons for Norwegian, with stress
+    /// digits 0 to 3 on vowels and syllabic consonants.
+    Nofabet,
+}
+
+/// Converts phoneme tokens from the symbols of a model to another phoneme set, such as IPA.
+///
+/// A token is looked up as is first, then without trailing stress digits, so tables only
+/// need entries with stress digits where the stress changes the sound (ARPAbet `AH0` is
+/// `ə`, other `AH` are `ʌ`). Tokens joined from several symbols (`K|S` becomes `KS`) are
+/// split into the longest symbols of the table. Tokens that cannot be mapped are kept.
+#[derive(Clone, Debug, Default)]
+pub struct PhonemeMapper {
+    table: HashMap<String, String>,
+    /// Length of the longest symbol of the table, in bytes.
+    max_symbol_len: usize,
+}
+
+impl TryFrom<&Path> for PhonemeMapper {
+    type Error = io::Error;
+
+    /// Load a mapping table, as JSON if the file name ends with `.json`, as TSV otherwise.
+    fn try_from(path: &Path) -> std::result::Result<Self, Self::Error> {
+        let mut reader = BufReader::new(File::open(path)?);
+        if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json")) {
+            let mut json = String::new();
+            reader.read_to_string(&mut json)?;
+            PhonemeMapper::from_json(&json)
+        } else {
+            PhonemeMapper::from_tsv(reader)
+        }
+    }
+}
+
+impl FromIterator<(String, String)> for PhonemeMapper {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(entries: I) -> Self {
+        let table: HashMap<String, String> = entries.into_iter().collect();
+        let max_symbol_len = table.keys().map(String::len).max().unwrap_or_default();
+        PhonemeMapper { table, max_symbol_len }
+    }
+}
+
+impl PhonemeMapper {
+    /// Built-in table from the symbols of `alphabet` to IPA.
+    ///
+    /// Stress digits are dropped, except where they select the vowel quality.
+    pub fn to_ipa(alphabet: PhonemeAlphabet) -> Self {
+        let table = match alphabet {
+            PhonemeAlphabet::Arpabet => ARPABET_TO_IPA,
+            PhonemeAlphabet::Nofabet => NOFABET_TO_IPA,
+        };
+        table
+            .iter()
+            .map(|&(symbol, ipa)| (symbol.to_string(), ipa.to_string()))
+            .collect()
+    }
+
+    /// Built-in table from the symbols of `alphabet` to X-SAMPA, derived from [`Self::to_ipa`].
+    pub fn to_xsampa(alphabet: PhonemeAlphabet) -> Self {
+        let ipa = PhonemeMapper::to_ipa(alphabet);
+        ipa.table
+            .into_iter()
+            .map(|(symbol, ipa)| (symbol, ipa_to_xsampa(&ipa)))
+            .collect()
+    }
+
+    /// Built-in table from the symbols of `alphabet` to eSpeak-ng phoneme mnemonics, those of
+    /// its English phonemes for ARPAbet and derived from [`Self::to_ipa`] for Nofabet.
+    ///
+    /// See [`EspeakPhonemes`](crate::EspeakPhonemes) to write whole pronunciations with stress
+    /// the way eSpeak-ng does.
+    pub fn to_espeak(alphabet: PhonemeAlphabet) -> Self {
+        match alphabet {
+            PhonemeAlphabet::Arpabet => ARPABET_TO_ESPEAK
+                .iter()
+                .map(|&(symbol, espeak)| (symbol.to_string(), espeak.to_string()))
+                .collect(),
+            PhonemeAlphabet::Nofabet => {
+                let ipa = PhonemeMapper::to_ipa(alphabet);
+                ipa.table
+                    .into_iter()
+                    .map(|(symbol, ipa)| (symbol, ipa_to_espeak(&ipa)))
+                    .collect()
+            }
+        }
+    }
+
+    /// Read a table with one `symbol<TAB>mapped` pair per line.
+    ///
+    /// Fields may also be separated by other whitespace. Empty lines and lines starting with
+    /// `#` are ignored.
+    pub fn from_tsv(reader: impl BufRead) -> io::Result<Self> {
+        let mut entries = Vec::new();
+
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let (Some(symbol), Some(mapped), None) = (fields.next(), fields.next(), fields.next()) else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Phoneme map line {} is not a symbol and its mapping: {}", line_number + 1, line),
+                ));
+            };
+            entries.push((symbol.to_string(), mapped.to_string()));
+        }
+
+        Ok(entries.into_iter().collect())
+    }
+
+    /// Read a table from a JSON object mapping symbols to strings, like `{"AA": "ɑ"}`.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid phoneme map: {}", message));
+        let entries = parse_string_object(json).map_err(|message| invalid(&message))?;
+        Ok(entries.into_iter().collect())
+    }
+
+    /// Map a single phoneme token.
+    ///
+    /// IPA stress markers (`ˈ`, `ˌ`) at the start of the token are kept in front of the
+    /// mapped phoneme.
+    pub fn map_token(&self, token: &str) -> String {
+        if let Some(mapped) = self.lookup(token) {
+            return mapped.to_string();
+        }
+        let phoneme = token.trim_start_matches(['ˈ', 'ˌ']);
+        let marker = &token[..token.len() - phoneme.len()];
+        match self.lookup(phoneme).map(str::to_string).or_else(|| self.split(phoneme)) {
+            Some(mapped) if !phoneme.is_empty() => format!("{}{}", marker, mapped),
+            _ => token.to_string(),
+        }
+    }
+
+    /// Map phoneme tokens one by one.
+    pub fn map_tokens<S: AsRef<str>>(&self, tokens: &[S]) -> Vec<String> {
+        tokens.iter().map(|token| self.map_token(token.as_ref())).collect()
+    }
+
+    /// Map the phonemes of a result, including those of its alignment and syllables, joining
+    /// them with the phoneme separator the result was produced with.
+    pub fn map_result(&self, result: &mut PhonetizationResult, separators: &PhonemeSeparators) {
+        result.phoneme_tokens = self.map_tokens(&result.phoneme_tokens);
+        result.phonemes = separators.join(&result.phoneme_tokens);
+        for segment in &mut result.alignment {
+            segment.phonemes = self.map_tokens(&segment.phonemes);
+        }
+        for syllable in &mut result.syllables {
+            *syllable = self.map_tokens(syllable);
+        }
+    }
+
+    /// Map phoneme tokens, putting the `markers` of primary and secondary stress in front of the
+    /// stressed ones. The stress is read from the tokens with `stress`, tokens that are only
+    /// stress markers are dropped, and IPA markers are removed from the mapped tokens.
+    pub(crate) fn map_marked<S: AsRef<str>>(
+        &self,
+        tokens: &[S],
+        stress: Option<StressConvention>,
+        markers: [&str; 2],
+    ) -> Vec<String> {
+        let stressed = match stress {
+            // keeping the markers keeps the positions of the stressed tokens
+            Some(convention) => apply_stress(tokens, convention, StressMarking::Keep).1,
+            None => Vec::new(),
+        };
+        tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| !stress.is_some_and(|convention| convention.is_marker(token.as_ref())))
+            .map(|(i, token)| {
+                let mapped = self.map_token(token.as_ref()).replace(['ˈ', 'ˌ'], "");
+                match stressed.iter().find(|(position, _)| *position == i) {
+                    Some((_, Stress::Primary)) => format!("{}{}", markers[0], mapped),
+                    Some((_, Stress::Secondary)) => format!("{}{}", markers[1], mapped),
+                    None => mapped,
+                }
+            })
+            .collect()
+    }
+
+    /// Mapping of a symbol, or of the symbol without its stress digits.
+    fn lookup(&self, symbol: &str) -> Option<&str> {
+        let unstressed = symbol.trim_end_matches(|c: char| c.is_ascii_digit());
+        self.table
+            .get(symbol)
+            .or_else(|| self.table.get(unstressed).filter(|_| !unstressed.is_empty()))
+            .map(String::as_str)
+    }
+
+    /// Map a token made of several symbols, matching the longest symbol at each position.
+    fn split(&self, token: &str) -> Option<String> {
+        let mut mapped = String::new();
+        let mut rest = token;
+        while !rest.is_empty() {
+            let (len, symbol) = (1..=self.max_symbol_len.min(rest.len()))
+                .rev()
+                .filter(|&len| rest.is_char_boundary(len))
+                .find_map(|len| self.lookup_prefix(&rest[..len], &rest[len..]).map(|symbol| (len, symbol)))?;
+            mapped.push_str(symbol);
+            rest = &rest[len..];
+        }
+        Some(mapped)
+    }
+
+    /// Mapping of `prefix`, taking its stress digits at the start of `rest` along.
+    fn lookup_prefix(&self, prefix: &str, rest: &str) -> Option<&str> {
+        // "AH0N" must split into "AH0" and "N", not "AH" and "0N"
+        if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        self.lookup(prefix)
+    }
+}
+
+/// IPA transcriptions of the CMUdict phonemes and the common extensions of ARPAbet.
+const ARPABET_TO_IPA: &[(&str, &str)] = &[
+    ("AA", "ɑ"),
+    ("AE", "æ"),
+    ("AH", "ʌ"),
+    ("AH0", "ə"),
+    ("AO", "ɔ"),
+    ("AW", "aʊ"),
+    ("AX", "ə"),
+    ("AXR", "ɚ"),
+    ("AY", "aɪ"),
+    ("EH", "ɛ"),
+    ("ER", "ɝ"),
+    ("ER0", "ɚ"),
+    ("EY", "eɪ"),
+    ("IH", "ɪ"),
+    ("IX", "ɨ"),
+    ("IY", "i"),
+    ("OW", "oʊ"),
+    ("OY", "ɔɪ"),
+    ("UH", "ʊ"),
+    ("UW", "u"),
+    ("B", "b"),
+    ("CH", "tʃ"),
+    ("D", "d"),
+    ("DH", "ð"),
+    ("DX", "ɾ"),
+    ("EL", "l̩"),
+    ("EM", "m̩"),
+    ("EN", "n̩"),
+    ("F", "f"),
+    ("G", "ɡ"),
+    ("HH", "h"),
+    ("JH", "dʒ"),
+    ("K", "k"),
+    ("L", "l"),
+    ("M", "m"),
+    ("N", "n"),
+    ("NG", "ŋ"),
+    ("P", "p"),
+    ("Q", "ʔ"),
+    ("R", "ɹ"),
+    ("S", "s"),
+    ("SH", "ʃ"),
+    ("T", "t"),
+    ("TH", "θ"),
+    ("V", "v"),
+    ("W", "w"),
+    ("Y", "j"),
+    ("Z", "z"),
+    ("ZH", "ʒ"),
+];
+
+/// eSpeak-ng mnemonics of the CMUdict phonemes, as its English voices name them.
+const ARPABET_TO_ESPEAK: &[(&str, &str)] = &[
+    ("AA", "A:"),
+    ("AE", "a"),
+    ("AH", "V"),
+    ("AH0", "@"),
+    ("AO", "O:"),
+    ("AW", "aU"),
+    ("AX", "@"),
+    ("AXR", "3"),
+    ("AY", "aI"),
+    ("EH", "E"),
+    ("ER", "3:"),
+    ("ER0", "3"),
+    ("EY", "eI"),
+    ("IH", "I"),
+    ("IX", "I2"),
+    ("IY", "i:"),
+    ("OW", "oU"),
+    ("OY", "OI"),
+    ("UH", "U"),
+    ("UW", "u:"),
+    ("B", "b"),
+    ("CH", "tS"),
+    ("D", "d"),
+    ("DH", "D"),
+    ("DX", "*"),
+    ("EL", "@L"),
+    ("EM", "@m"),
+    ("EN", "@n"),
+    ("F", "f"),
+    ("G", "g"),
+    ("HH", "h"),
+    ("JH", "dZ"),
+    ("K", "k"),
+    ("L", "l"),
+    ("M", "m"),
+    ("N", "n"),
+    ("NG", "N"),
+    ("P", "p"),
+    ("Q", "?"),
+    ("R", "r"),
+    ("S", "s"),
+    ("SH", "S"),
+    ("T", "t"),
+    ("TH", "T"),
+    ("V", "v"),
+    ("W", "w"),
+    ("Y", "j"),
+    ("Z", "z"),
+    ("ZH", "Z"),
+];
+
+/// IPA transcriptions of the Nofabet phonemes of Urban East Norwegian.
+const NOFABET_TO_IPA: &[(&str, &str)] = &[
+    ("AA", "ɑː"),
+    ("AH", "ɑ"),
+    ("AE", "æː"),
+    ("AEH", "æ"),
+    ("AEJ", "æɪ"),
+    ("AEW", "æʉ"),
+    ("AJ", "ɑɪ"),
+    ("AX", "ə"),
+    ("EE", "eː"),
+    ("EH", "ɛ"),
+    ("EJ", "ɛɪ"),
+    ("II", "iː"),
+    ("IH", "ɪ"),
+    ("OA", "oː"),
+    ("OAH", "ɔ"),
+    ("OAJ", "ɔʏ"),
+    ("OE", "øː"),
+    ("OEH", "œ"),
+    ("OEJ", "œʏ"),
+    ("OJ", "ɔʏ"),
+    ("OO", "uː"),
+    ("OH", "ʊ"),
+    ("UU", "ʉː"),
+    ("UH", "ʉ"),
+    ("UJ", "ʉɪ"),
+    ("YY", "yː"),
+    ("YH", "ʏ"),
+    ("B", "b"),
+    ("D", "d"),
+    ("F", "f"),
+    ("G", "ɡ"),
+    ("H", "h"),
+    ("J", "j"),
+    ("K", "k"),
+    ("KJ", "ç"),
+    ("L", "l"),
+    ("M", "m"),
+    ("N", "n"),
+    ("NG", "ŋ"),
+    ("P", "p"),
+    ("R", "r"),
+    ("RD", "ɖ"),
+    ("RL", "ɭ"),
+    ("RN", "ɳ"),
+    ("RS", "ʂ"),
+    ("RT", "ʈ"),
+    ("S", "s"),
+    ("SJ", "ʃ"),
+    ("T", "t"),
+    ("V", "ʋ"),
+    ("W", "w"),
+    ("LX", "l̩"),
+    ("MX", "m̩"),
+    ("NX", "n̩"),
+    ("RLX", "ɭ̩"),
+    ("RNX", "ɳ̩"),
+    ("RX", "r̩"),
+    ("SX", "s̩"),
+];
+
+/// X-SAMPA for the IPA characters of the built-in tables that are not plain ASCII letters.
+const IPA_TO_XSAMPA: &[(char, &str)] = &[
+    ('ɑ', "A"),
+    ('æ', "{"),
+    ('ʌ', "V"),
+    ('ɔ', "O"),
+    ('ə', "@"),
+    ('ɚ', "@`"),
+    ('ɛ', "E"),
+    ('ɝ', "3`"),
+    ('ɪ', "I"),
+    ('ɨ', "1"),
+    ('ʊ', "U"),
+    ('ʉ', "}"),
+    ('ø', "2"),
+    ('œ', "9"),
+    ('ʏ', "Y"),
+    ('ð', "D"),
+    ('ɾ', "4"),
+    ('ɡ', "g"),
+    ('ŋ', "N"),
+    ('ʔ', "?"),
+    ('ɹ', "r\\"),
+    ('ʃ', "S"),
+    ('θ', "T"),
+    ('ʒ', "Z"),
+    ('ç', "C"),
+    ('ɖ', "d`"),
+    ('ɭ', "l`"),
+    ('ɳ', "n`"),
+    ('ʂ', "s`"),
+    ('ʈ', "t`"),
+    ('ʋ', "P"),
+    ('ː', ":"),
+    ('\u{0329}', "="),
+];
+
+/// eSpeak-ng mnemonics for the IPA characters of the Nofabet table that are not plain ASCII
+/// letters.
+const IPA_TO_ESPEAK: &[(char, &str)] = &[
+    ('ɑ', "A"),
+    ('æ', "&"),
+    ('ə', "@"),
+    ('ɛ', "E"),
+    ('ɪ', "I"),
+    ('ɔ', "O"),
+    ('ʊ', "U"),
+    ('ʉ', "u\""),
+    ('ø', "Y"),
+    ('œ', "W"),
+    ('ʏ', "y"),
+    ('ɡ', "g"),
+    ('ŋ', "N"),
+    ('ʃ', "S"),
+    ('ç', "C"),
+    ('ɖ', "d."),
+    ('ɭ', "l."),
+    ('ɳ', "n."),
+    ('ʂ', "s."),
+    ('ʈ', "t."),
+    ('ʋ', "v"),
+    ('ː', ":"),
+    ('\u{0329}', "-"),
+];
+
+fn ipa_to_espeak(ipa: &str) -> String {
+    ipa.chars()
+        .map(|c| match IPA_TO_ESPEAK.iter().find(|(ipa, _)| *ipa == c) {
+            Some((_, espeak)) => espeak.to_string(),
+            None => c.to_string(),
+        })
+        .collect()
+}
+
+fn ipa_to_xsampa(ipa: &str) -> String {
+    ipa.chars()
+        .map(|c| match IPA_TO_XSAMPA.iter().find(|(ipa, _)| *ipa == c) {
+            Some((_, xsampa)) => xsampa.to_string(),
+            None => c.to_string(),
+        })
+        .collect()
+}
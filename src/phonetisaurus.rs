@@ -26,19 +26,89 @@ SOFTWARE.
 
 */
 
-use anyhow::{Context, Result, anyhow};
+use crate::alignment::{AlignedSegment, align, linear_path};
+use crate::bundle::{ModelManifest, is_bundle, read_bundle};
+use crate::parallel::{default_threads, par_map};
+use crate::post_fst::{compose_post_fsts, compose_post_fsts_left_of, prepare_post_fst};
+use crate::prune::prune_lattice;
+use crate::error::{PhonetisaurusError, Result, SymbolTableKind};
+use crate::gzip;
+use crate::zstd;
+use crate::lattice::PronunciationLattice;
+use crate::lexicon::Lexicon;
+use crate::log_semiring::{PhonemePosteriors, sum_alignments};
+use crate::options::{
+    CancellationToken, LengthNormalization, PhonemeSeparators, PhonemizeOptions, ScoreSemiring, UnknownCharPolicy,
+    WordPunctuation,
+    is_word_punctuation,
+};
+use crate::segmentation::SymbolClusters;
+use crate::session::PhonemizerSession;
+use crate::stress::{Stress, StressMarking, apply_stress};
+use crate::trace::{DecodeDiagnostics, DecodeStage, ResultSource, StageRecorder, StageTrace, Tracer};
+use crate::model_fst::ModelFst;
+use crate::validate::ValidationReport;
+use anyhow::Context;
 use rustfst::algorithms::compose;
+use rustfst::algorithms::determinize::determinize;
+use rustfst::algorithms::rm_epsilon::rm_epsilon;
 use rustfst::prelude::*;
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+use std::ops::Range;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Result of a phonemization.
 pub struct PhonetizationResult {
     /// Phonemes produced during phonemization.
     pub phonemes: String,
+    /// The individual phoneme symbols that make up `phonemes`.
+    pub phoneme_tokens: Vec<String>,
     /// Negative log likelihood of phonemes, lower is better.
     pub neg_log_score: f32,
+    /// Probability of this pronunciation relative to the others returned for the word, so
+    /// that the posteriors of an n-best list sum to 1. Always 1 for a single result.
+    pub posterior: f32,
+    /// `neg_log_score` divided by the length selected with
+    /// [`PhonemizeOptions::length_normalization`], the raw score by default.
+    pub normalized_score: f32,
+    /// Which graphemes of the word produced which phoneme tokens, in word order.
+    ///
+    /// Empty for pronunciations taken from a [`Lexicon`].
+    pub alignment: Vec<AlignedSegment>,
+    /// `phoneme_tokens` split into syllables, empty unless a
+    /// [`PhonemizeOptions::syllabifier`] is set.
+    pub syllables: Vec<Vec<String>>,
+    /// Positions in `phoneme_tokens` of the stressed phonemes, with their stress level, empty
+    /// unless a [`PhonemizeOptions::stress_convention`] is set.
+    pub stress: Vec<(usize, Stress)>,
+    /// Marginal probability of each token of `phoneme_tokens` at its place in the word, over
+    /// all pronunciations of the lattice, empty unless [`PhonemizeOptions::semiring`] is
+    /// [`ScoreSemiring::Log`](crate::ScoreSemiring::Log).
+    pub phoneme_posteriors: Vec<f32>,
+    /// How the word was phonemized, `None` unless [`PhonemizeOptions::diagnostics`] is set.
+    /// All results of a word share the same diagnostics.
+    pub diagnostics: Option<DecodeDiagnostics>,
+}
+
+/// Maximum number of graphemes without a phoneme ("_") that
+/// [`PhonetisaurusModel::spell_phonemes_nbest`] generates in a row.
+pub const MAX_SILENT_GRAPHEMES: usize = 3;
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Result of spelling a phoneme sequence.
+pub struct SpellingResult {
+    /// The graphemes of the spelling.
+    pub spelling: String,
+    /// Negative log likelihood of the spelling, lower is better.
+    pub neg_log_score: f32,
 }
 
 #[derive(Clone, Debug)]
@@ -46,137 +116,1465 @@ pub struct PhonetizationResult {
 pub struct PhonetisaurusModel {
     /// The trained FST.
     ///
-    /// It is wrapped inside a smart pointer, since the FST needs to be cloned for each new phonemization.
+    /// It is wrapped inside a smart pointer, so that composition can borrow it through a cheap pointer
+    /// clone instead of copying the whole FST for each new phonemization.
     /// Arc instead of Rc is used in order to provide thread safety, so that parallel phonemization is possible.
-    trained_fst: Arc<VectorFst<TropicalWeight>>,
+    trained_fst: ModelFst,
+    /// Exception lexicon consulted before decoding, shared between clones of the model.
+    lexicon: Option<Arc<Lexicon>>,
+    /// Multi-char input symbols, used by [`Segmentation::LongestMatch`](crate::Segmentation::LongestMatch).
+    clusters: Arc<SymbolClusters>,
+    /// Transducers composed after the trained FST, in order, see [`Self::with_post_fst`].
+    post_fsts: Vec<Arc<VectorFst<TropicalWeight>>>,
+    /// Manifest of the bundle the model was loaded from.
+    manifest: Option<Arc<ModelManifest>>,
 }
 
 impl TryFrom<&Path> for PhonetisaurusModel {
-    type Error = anyhow::Error;
+    type Error = PhonetisaurusError;
 
     /// Create a new phonemizer from a phonetisaurus model file.
     fn try_from(model_path: &Path) -> std::result::Result<Self, Self::Error> {
-        Ok(PhonetisaurusModel {
-            trained_fst: Arc::new(VectorFst::<TropicalWeight>::read(model_path)?),
-        })
+        let model_binary = std::fs::read(model_path).map_err(|e| {
+            PhonetisaurusError::ModelLoadError(
+                anyhow::Error::from(e).context(format!("Can't read model file {:?}", model_path)),
+            )
+        })?;
+
+        PhonetisaurusModel::try_from(model_binary.as_slice())
     }
 }
 
 impl TryFrom<&[u8]> for PhonetisaurusModel {
-    type Error = anyhow::Error;
+    type Error = PhonetisaurusError;
 
-    /// Create a new phonemizer from a binary of a phonetisaurus model.
+    /// Create a new phonemizer from a binary of a phonetisaurus model, or of a bundle of
+    /// the model and its manifest, either of them optionally gzip- or zstd-compressed.
     /// Typically, this would be used with the include_bytes! macro.
     fn try_from(model_binary: &[u8]) -> std::result::Result<Self, Self::Error> {
-        Ok(PhonetisaurusModel {
-            trained_fst: Arc::new(VectorFst::<TropicalWeight>::load(model_binary)?),
-        })
+        if is_bundle(model_binary) {
+            let (model_binary, manifest) = read_bundle(model_binary)?;
+            let mut model = PhonetisaurusModel::try_from(model_binary)?;
+            model.manifest = manifest.map(Arc::new);
+            return Ok(model);
+        }
+        let decompressed = if gzip::is_gzip(model_binary) {
+            Some(gzip::decompress(model_binary))
+        } else if zstd::is_zstd(model_binary) {
+            Some(zstd::decompress(model_binary))
+        } else {
+            None
+        };
+        if let Some(decompressed) = decompressed {
+            let model_binary = decompressed.map_err(|e| PhonetisaurusError::ModelLoadError(e.into()))?;
+            return PhonetisaurusModel::try_from(model_binary.as_slice());
+        }
+        if let Some(format) = compression_format(model_binary) {
+            return Err(PhonetisaurusError::UnsupportedCompression(format));
+        }
+        let fst = VectorFst::<TropicalWeight>::load(model_binary)
+            .map_err(PhonetisaurusError::ModelLoadError)?;
+
+        Ok(PhonetisaurusModel::from_fst(fst))
     }
 }
 
 impl PhonetisaurusModel {
+    /// Create a new phonemizer from any reader yielding a binary phonetisaurus model,
+    /// e.g. a network stream, an archive entry or a decompressor.
+    ///
+    /// The FST parser works on a contiguous buffer, so the model is read into memory once
+    /// before parsing. No temporary file or copy on the caller's side is needed.
+    pub fn from_reader(mut reader: impl Read) -> Result<Self> {
+        let mut model_binary = Vec::new();
+        reader
+            .read_to_end(&mut model_binary)
+            .map_err(|e| PhonetisaurusError::ModelLoadError(e.into()))?;
+
+        PhonetisaurusModel::try_from(model_binary.as_slice())
+    }
+
+    /// Load a model file from a repository on the Hugging Face Hub, e.g.
+    /// `from_hf("org/repo", "model.fst")`, downloading it into the cache of the
+    /// [`fetch`](crate::fetch) module on first use.
+    ///
+    /// The file is taken from the `main` branch, and once cached, it is not checked for
+    /// updates; use [`Self::from_hf_revision`] with a commit hash to pin a version. The
+    /// `HF_TOKEN` environment variable is sent as the access token for private and gated
+    /// repositories, and `HF_ENDPOINT` replaces `https://huggingface.co`, e.g. for a mirror.
+    #[cfg(feature = "hf")]
+    pub fn from_hf(repo: &str, filename: &str) -> Result<Self> {
+        Self::from_hf_revision(repo, filename, "main")
+    }
+
+    /// Like [`Self::from_hf`], with the file taken from a branch, tag or commit.
+    #[cfg(feature = "hf")]
+    pub fn from_hf_revision(repo: &str, filename: &str, revision: &str) -> Result<Self> {
+        let endpoint = std::env::var("HF_ENDPOINT").unwrap_or_else(|_| "https://huggingface.co".to_string());
+        let url = format!(
+            "{}/{}/resolve/{}/{}",
+            endpoint.trim_end_matches('/'),
+            repo.trim_matches('/'),
+            revision,
+            filename.trim_start_matches('/')
+        );
+        let mut download = crate::fetch::ModelDownload::new(&url);
+        if let Ok(token) = std::env::var("HF_TOKEN")
+            && !token.is_empty()
+        {
+            download = download.with_header("Authorization", &format!("Bearer {}", token));
+        }
+        download.load()
+    }
+
+    /// Wrap a loaded FST, preparing it for composition.
+    ///
+    /// The transitions are sorted by input label once, so that every composition with a word
+    /// acceptor can look up matching transitions by binary search instead of scanning them.
+    fn from_fst(mut fst: VectorFst<TropicalWeight>) -> Self {
+        tr_sort(&mut fst, ILabelCompare {});
+        let clusters = SymbolClusters::new(fst.input_symbols().map(|symbols| symbols.as_ref()));
+
+        PhonetisaurusModel {
+            trained_fst: ModelFst::Vector(Arc::new(fst)),
+            lexicon: None,
+            clusters: Arc::new(clusters),
+            post_fsts: Vec::new(),
+            manifest: None,
+        }
+    }
+
+    /// The manifest of the bundle the model was loaded from, if it was loaded from one with
+    /// a manifest. [`ModelManifest::options`] gives the options it asks for.
+    pub fn manifest(&self) -> Option<&ModelManifest> {
+        self.manifest.as_deref()
+    }
+
+    /// Attach an exception lexicon. Words found in it are answered from the lexicon with a
+    /// score of 0 instead of being decoded.
+    pub fn with_lexicon(mut self, lexicon: Lexicon) -> Self {
+        self.lexicon = Some(Arc::new(lexicon));
+        self
+    }
+
+    /// Compose the model with another transducer, read from a binary FST file, to rewrite
+    /// the pronunciations it produces, e.g. a phoneme mapping or a dialect adaptation.
+    /// Transducers are applied in the order they are added.
+    ///
+    /// The transducer reads the output symbols of the model, or of the transducer added
+    /// before, matched by name if it has symbol tables, and should pass through every phoneme
+    /// it does not rewrite. Its output symbols become the phonemes of the results, and the
+    /// phonemes given to [`Self::score_pronunciation`] and [`Self::spell_phonemes`]. Skip
+    /// symbols ("_") it does not read pass through unchanged.
+    ///
+    /// The transducers are not included in [`Self::to_bytes`] and not applied by a
+    /// [`PhonemizerSession`].
+    pub fn with_post_fst(mut self, fst_path: impl AsRef<Path>) -> Result<Self> {
+        let fst_path = fst_path.as_ref();
+        let fst = VectorFst::<TropicalWeight>::read(fst_path).map_err(|e| {
+            PhonetisaurusError::ModelLoadError(e.context(format!("Can't read post-transducer file {:?}", fst_path)))
+        })?;
+        let osyms = self.output_symbol_table()?.clone();
+        self.post_fsts.push(Arc::new(prepare_post_fst(fst, &osyms)?));
+        Ok(self)
+    }
+
+    /// The attached exception lexicon, if any.
+    pub fn lexicon(&self) -> Option<&Lexicon> {
+        self.lexicon.as_deref()
+    }
+
+    /// Convert the model FST to a compact immutable representation, which takes less memory
+    /// and is read with better cache locality during decoding. The scores are unchanged, but
+    /// pronunciations with equal scores may come in another order.
+    ///
+    /// The conversion briefly needs memory for both representations. If clones of the model
+    /// exist, the FST is copied first, and only this model is converted.
+    pub fn optimize(mut self) -> Self {
+        self.trained_fst = self.trained_fst.into_const();
+        self
+    }
+
+    /// Whether [`Self::optimize`] was applied.
+    pub fn is_optimized(&self) -> bool {
+        self.trained_fst.is_const()
+    }
+
+    /// Serialize the FST in the binary format models are loaded from, so that
+    /// `PhonetisaurusModel::try_from(bytes.as_slice())` recreates the model.
+    ///
+    /// The exception lexicon and the transducers of [`Self::with_post_fst`] are not included.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut model_binary = Vec::new();
+        self.trained_fst.store(&mut model_binary)?;
+        Ok(model_binary)
+    }
+
+    /// The FST, the lexicon and the post-transducers, everything the results depend on, to
+    /// identify the model with.
+    pub(crate) fn content_bytes(&self) -> Result<Vec<u8>> {
+        let mut content = self.to_bytes()?;
+        if let Some(lexicon) = &self.lexicon {
+            let mut entries: Vec<_> = lexicon.iter().collect();
+            entries.sort_unstable();
+            content.extend_from_slice(format!("{:?}", entries).as_bytes());
+        }
+        for post_fst in &self.post_fsts {
+            post_fst.store(&mut content).map_err(PhonetisaurusError::FstError)?;
+        }
+        Ok(content)
+    }
+
     /// Phonemize a word with the phonetisaurus FST model.
     pub fn phonemize_word(&self, word: &str) -> Result<PhonetizationResult> {
+        self.phonemize_word_with_options(word, &PhonemizeOptions::default())
+    }
+
+    /// Phonemize a word with the phonetisaurus FST model, using the given options.
+    pub fn phonemize_word_with_options(
+        &self,
+        word: &str,
+        options: &PhonemizeOptions,
+    ) -> Result<PhonetizationResult> {
+        // the best pronunciation is searched exactly like the n best ones, which is also what
+        // phonetisaurus does
+        self.phonemize_word_nbest_with_options(word, 1, options)?
+            .into_iter()
+            .next()
+            .ok_or(PhonetisaurusError::NoPathFound)
+    }
+
+    /// Phonemize a word and return up to `n` distinct pronunciations, best first.
+    ///
+    /// Pronunciations that only differ in skipped symbols ("_") are considered identical,
+    /// so the returned list may be shorter than `n` if the lattice has fewer alternatives.
+    ///
+    /// The search follows phonetisaurus: the composed lattice is projected to its phonemes,
+    /// "_" and epsilons are removed, and the n shortest paths of the determinized result are
+    /// taken. Each pronunciation is thus scored with its best alignment, and results match
+    /// those of phonetisaurus-apply for the same model. A single pronunciation is that of the
+    /// best path of the lattice, which is the same without determinizing, so it is taken
+    /// directly unless another path has the same weight.
+    ///
+    /// Pronunciations with the same score are ordered by their phoneme tokens, and all of
+    /// them are considered for the last places of the list, so that the results do not
+    /// depend on how the search happens to break ties.
+    pub fn phonemize_word_nbest(&self, word: &str, n: usize) -> Result<Vec<PhonetizationResult>> {
+        self.phonemize_word_nbest_with_options(word, n, &PhonemizeOptions::default())
+    }
+
+    /// Phonemize a word and return up to `n` distinct pronunciations, using the given options.
+    ///
+    /// With [`ScoreSemiring::Log`], determinization sums the probabilities of the alignments of
+    /// each pronunciation instead of keeping the best one, so pronunciations are ranked and
+    /// scored by their total probability.
+    ///
+    /// With [`PhonemizeOptions::tag_separator`], words like `read#VBD` are phonemized like
+    /// [`Self::phonemize_word_with_tag`] does.
+    pub fn phonemize_word_nbest_with_options(
+        &self,
+        word: &str,
+        n: usize,
+        options: &PhonemizeOptions,
+    ) -> Result<Vec<PhonetizationResult>> {
+        match options.tag_separator.and_then(|separator| split_tag(word, separator)) {
+            Some((word, tag)) => self.phonemize_tagged(word, Some(tag), n, options),
+            None => self.phonemize_tagged(word, None, n, options),
+        }
+    }
+
+    /// Phonemize a word with a POS or sense tag, for heteronyms like "read" or "lead" whose
+    /// pronunciation depends on it.
+    ///
+    /// Pronunciations of `word#TAG` in the exception lexicon take precedence over those of the
+    /// word. The model reads the tag after the graphemes if it has an input symbol for it,
+    /// which models trained on tagged words do, and ignores it otherwise.
+    pub fn phonemize_word_with_tag(&self, word: &str, tag: &str) -> Result<PhonetizationResult> {
+        self.phonemize_tagged(word, Some(tag), 1, &PhonemizeOptions::default())?
+            .into_iter()
+            .next()
+            .ok_or(PhonetisaurusError::NoPathFound)
+    }
+
+    /// Phonemize a word with a tag and return up to `n` distinct pronunciations, using the
+    /// given options. See [`Self::phonemize_word_with_tag`].
+    pub fn phonemize_word_with_tag_nbest_with_options(
+        &self,
+        word: &str,
+        tag: &str,
+        n: usize,
+        options: &PhonemizeOptions,
+    ) -> Result<Vec<PhonetizationResult>> {
+        self.phonemize_tagged(word, Some(tag), n, options)
+    }
+
+    fn phonemize_tagged(
+        &self,
+        word: &str,
+        tag: Option<&str>,
+        n: usize,
+        options: &PhonemizeOptions,
+    ) -> Result<Vec<PhonetizationResult>> {
+        if !options.diagnostics {
+            return self.decode_tagged(word, tag, n, options);
+        }
+        // the stages are recorded by a tracer in front of the one of the options
+        let recorder = Arc::new(StageRecorder::new(options.tracer.clone()));
+        let recording_options = PhonemizeOptions {
+            tracer: Some(Tracer(recorder.clone())),
+            ..options.clone()
+        };
+        let mut results = self.decode_tagged(word, tag, n, &recording_options)?;
+        let stages = recorder.stages();
+        for result in &mut results {
+            result.diagnostics.get_or_insert_with(DecodeDiagnostics::default).stages = stages.clone();
+        }
+        Ok(results)
+    }
+
+    fn decode_tagged(
+        &self,
+        word: &str,
+        tag: Option<&str>,
+        n: usize,
+        options: &PhonemizeOptions,
+    ) -> Result<Vec<PhonetizationResult>> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let folded_word = options.case_fold.apply(word);
+        let word: &str = &options.normalize.apply(&folded_word);
+
+        let pronunciations = self.lexicon.as_ref().and_then(|lexicon| {
+            tag.and_then(|tag| lexicon.get_with_tag(word, tag))
+                .or_else(|| lexicon.get(word))
+        });
+        if let Some(pronunciations) = pronunciations {
+            let mut results = fixed_results(pronunciations, word, n, options);
+            if options.diagnostics {
+                for result in &mut results {
+                    result.diagnostics = Some(DecodeDiagnostics {
+                        source: ResultSource::Lexicon,
+                        ..DecodeDiagnostics::default()
+                    });
+                }
+            }
+            return Ok(results);
+        }
+        if options.word_punctuation != WordPunctuation::AsIs && word.chars().any(is_word_punctuation) {
+            return self.phonemize_punctuated(word, n, options);
+        }
+        let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+        let check_interrupt = || -> Result<()> {
+            if options.cancellation.as_ref().is_some_and(CancellationToken::is_cancelled) {
+                Err(PhonetisaurusError::Cancelled)
+            } else if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                Err(PhonetisaurusError::Timeout)
+            } else {
+                Ok(())
+            }
+        };
+
+        let trace = |stage, start, fst: &VectorFst<TropicalWeight>| {
+            if let Some(tracer) = &options.tracer {
+                tracer.stage_done(word, stage, start, fst);
+            }
+        };
+
+        let (mut composed_fst, spans) = self.compose_word(word, tag, options)?;
+        check_interrupt()?;
+        let start = Instant::now();
+        prune_lattice(&mut composed_fst, options)?;
+        trace(DecodeStage::Prune, start, &composed_fst);
+        check_interrupt()?;
+        let mut results = match (n, options.semiring) {
+            // the best path of the lattice has the best pronunciation with its best alignment,
+            // which is what determinizing finds for a single result, without the cost of it;
+            // unless another path is as good, whose pronunciation may have to be taken instead
+            (1, ScoreSemiring::Tropical) if !best_paths_tie(&composed_fst)? => {
+                let start = Instant::now();
+                let shortest_fst: VectorFst<TropicalWeight> =
+                    shortest_path(&composed_fst).map_err(PhonetisaurusError::FstError)?;
+                trace(DecodeStage::ShortestPath, start, &shortest_fst);
+                vec![self.result_from_path(&shortest_fst, &spans, &options.separators)?]
+            }
+            _ => self.nbest_results(&composed_fst, &spans, n, options, &check_interrupt, &trace)?,
+        };
+        results.sort_by(|a, b| {
+            a.neg_log_score
+                .total_cmp(&b.neg_log_score)
+                .then_with(|| a.phoneme_tokens.cmp(&b.phoneme_tokens))
+        });
+        // removing ties ("|") can still make two label sequences collide, keep the best; the
+        // collisions need not have the same score, so they need not be next to each other
+        let mut seen = Vec::with_capacity(results.len());
+        results.retain(|result| {
+            let new = !seen.contains(&result.phoneme_tokens);
+            if new {
+                seen.push(result.phoneme_tokens.clone());
+            }
+            new
+        });
+        set_stress(&mut results, options);
+        results.truncate(n);
+        set_posteriors(&mut results);
+        set_normalized_scores(&mut results, options.length_normalization, word);
+        set_syllables(&mut results, options);
+        if options.diagnostics {
+            let consumed: usize = spans.iter().map(|span| span.len()).sum();
+            let skipped_chars = word.chars().count().saturating_sub(consumed);
+            for result in &mut results {
+                result.diagnostics = Some(DecodeDiagnostics {
+                    skipped_chars,
+                    ..DecodeDiagnostics::default()
+                });
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// The pronunciations of the `n` shortest paths of the lattice of a word, unsorted, found
+    /// like phonetisaurus does: the lattice is projected to its phonemes, "_" and epsilons are
+    /// removed, and the n shortest paths of the determinized result are taken. The alignment
+    /// of each is recovered by forcing its phonemes on the lattice.
+    fn nbest_results(
+        &self,
+        composed_fst: &VectorFst<TropicalWeight>,
+        spans: &[Range<usize>],
+        n: usize,
+        options: &PhonemizeOptions,
+        check_interrupt: &impl Fn() -> Result<()>,
+        trace: &impl Fn(DecodeStage, Instant, &VectorFst<TropicalWeight>),
+    ) -> Result<Vec<PhonetizationResult>> {
+        let start = Instant::now();
+        let mut lattice = composed_fst.clone();
+
+        // PROJECT TO PHONEMES
+        // Skip symbols ("_") are turned into epsilons, so that determinization merges paths
+        // which only differ in the position of skipped symbols. Determinization then leaves one
+        // path per distinct phoneme sequence, carrying the best weight among the merged paths.
+        project(&mut lattice, ProjectType::ProjectOutput);
+        if let Some(osyms) = lattice.output_symbols().cloned() {
+            lattice.set_input_symbols(osyms);
+        }
+        let skip_labels = self.skip_labels(&options.separators)?;
+        if !skip_labels.is_empty() {
+            let pairs: Vec<(Label, Label)> = skip_labels.iter().map(|&label| (label, EPS_LABEL)).collect();
+            relabel_pairs(&mut lattice, pairs.clone(), pairs).map_err(PhonetisaurusError::FstError)?;
+        }
+        // determinization cannot be interrupted, so the lattice it works on is checked first
+        let deterministic_fst: VectorFst<TropicalWeight> = match options.semiring {
+            ScoreSemiring::Tropical => {
+                rm_epsilon(&mut lattice).map_err(PhonetisaurusError::FstError)?;
+                check_lattice_size(&lattice, options)?;
+                check_interrupt()?;
+                determinize(&lattice).map_err(PhonetisaurusError::FstError)?
+            }
+            // the merged paths carry the sum of their probabilities instead
+            ScoreSemiring::Log => {
+                check_lattice_size(&lattice, options)?;
+                sum_alignments(&lattice)?
+            }
+        };
+        trace(DecodeStage::Determinize, start, &deterministic_fst);
+        check_lattice_size(&deterministic_fst, options)?;
+        check_interrupt()?;
+
+        // ITERATE N SHORTEST PATHS
+        let start = Instant::now();
+        let (nshortest_fst, cutoff) = nshortest_with_ties(&deterministic_fst, n)?;
+        let phoneme_posteriors = match options.semiring {
+            ScoreSemiring::Tropical => None,
+            ScoreSemiring::Log => Some(PhonemePosteriors::new(
+                composed_fst,
+                self.output_symbol_table()?,
+                &options.separators,
+            )?),
+        };
+
+        let mut results: Vec<PhonetizationResult> = Vec::new();
+        for path in nshortest_fst.paths_iter().filter(|path| *path.weight.value() <= cutoff) {
+            check_interrupt()?;
+            // projecting lost the graphemes, so find the best path producing these phonemes again
+            let forced_fst = self.force_phonemes(composed_fst, &path.olabels, &skip_labels)?;
+            let mut result = self.result_from_path(&forced_fst, spans, &options.separators)?;
+            if let Some(phoneme_posteriors) = &phoneme_posteriors {
+                let (arcs, _) = linear_path(&forced_fst)?;
+                result.phoneme_posteriors =
+                    phoneme_posteriors.of_path(&arcs, self.output_symbol_table()?, &options.separators);
+                result.neg_log_score = *path.weight.value();
+            }
+            results.push(result);
+        }
+        trace(DecodeStage::ShortestPath, start, &nshortest_fst);
+        if results.is_empty() {
+            return Err(PhonetisaurusError::NoPathFound);
+        }
+
+        Ok(results)
+    }
+
+    /// Phonemize a case folded and normalized word containing hyphens or apostrophes as
+    /// [`PhonemizeOptions::word_punctuation`] says. The tag of the word, if any, is not used.
+    fn phonemize_punctuated(&self, word: &str, n: usize, options: &PhonemizeOptions) -> Result<Vec<PhonetizationResult>> {
+        let inner_options = PhonemizeOptions {
+            word_punctuation: WordPunctuation::AsIs,
+            tag_separator: None,
+            ..options.clone()
+        };
+        let joiner = match &options.word_punctuation {
+            WordPunctuation::AsIs => return self.phonemize_word_nbest_with_options(word, n, options),
+            WordPunctuation::Keep | WordPunctuation::Strip => {
+                let isyms = self.trained_fst.input_symbols();
+                let keep = |c: char| {
+                    options.word_punctuation == WordPunctuation::Keep
+                        && isyms.is_some_and(|isyms| isyms.get_label(c.to_string()).is_some())
+                };
+                // positions of the kept chars in the word
+                let (positions, stripped): (Vec<usize>, String) = word
+                    .chars()
+                    .enumerate()
+                    .filter(|&(_, c)| !is_word_punctuation(c) || keep(c))
+                    .unzip();
+                let position = |p: usize| positions.get(p).copied().unwrap_or(word.chars().count());
+                let mut results = self
+                    .phonemize_word_nbest_with_options(&stripped, n, &inner_options)
+                    .map_err(|e| move_position(e, position))?;
+                for segment in results.iter_mut().flat_map(|result| result.alignment.iter_mut()) {
+                    let start = position(segment.graphemes.start);
+                    let end = match segment.graphemes.end {
+                        end if end > segment.graphemes.start => position(end - 1) + 1,
+                        _ => start,
+                    };
+                    segment.graphemes = start..end;
+                }
+                return Ok(results);
+            }
+            WordPunctuation::Split { joiner } => joiner,
+        };
+
+        // stress and syllables are determined on the whole word
+        let part_options = PhonemizeOptions {
+            stress_convention: None,
+            syllabifier: None,
+            ..inner_options
+        };
+        let chars: Vec<char> = word.chars().collect();
+        let mut phoneme_tokens: Vec<String> = Vec::new();
+        let mut phoneme_posteriors: Vec<f32> = Vec::new();
+        let mut neg_log_score = 0.0;
+        let mut skipped_chars = 0;
+        let mut alignment = Some(Vec::<AlignedSegment>::new());
+        let mut part_start = 0;
+        for end in 0..=chars.len() {
+            if end < chars.len() && !is_word_punctuation(chars[end]) {
+                continue;
+            }
+            if end > part_start {
+                if !phoneme_tokens.is_empty() && !joiner.is_empty() {
+                    phoneme_tokens.push(joiner.clone());
+                    if options.semiring == ScoreSemiring::Log {
+                        phoneme_posteriors.push(1.0);
+                    }
+                    // the joiner is aligned with the punctuation right before the part
+                    if let Some(segment) = alignment.as_mut().and_then(|alignment| alignment.last_mut()) {
+                        segment.phonemes.push(joiner.clone());
+                    }
+                }
+                let part: String = chars[part_start..end].iter().collect();
+                let mut best = self
+                    .phonemize_word_nbest_with_options(&part, 1, &part_options)
+                    .map_err(|e| move_position(e, |p| p + part_start))?
+                    .into_iter()
+                    .next()
+                    .ok_or(PhonetisaurusError::NoPathFound)?;
+                if best.alignment.is_empty() && !best.phoneme_tokens.is_empty() {
+                    alignment = None;
+                }
+                if let Some(alignment) = &mut alignment {
+                    alignment.extend(best.alignment.drain(..).map(|segment| AlignedSegment {
+                        graphemes: segment.graphemes.start + part_start..segment.graphemes.end + part_start,
+                        phonemes: segment.phonemes,
+                    }));
+                }
+                phoneme_tokens.append(&mut best.phoneme_tokens);
+                phoneme_posteriors.append(&mut best.phoneme_posteriors);
+                neg_log_score += best.neg_log_score;
+                skipped_chars += best.diagnostics.map_or(0, |diagnostics| diagnostics.skipped_chars);
+            }
+            if let (Some(alignment), true) = (&mut alignment, end < chars.len()) {
+                alignment.push(AlignedSegment {
+                    graphemes: end..end + 1,
+                    phonemes: Vec::new(),
+                });
+            }
+            part_start = end + 1;
+        }
+        if phoneme_tokens.is_empty() {
+            return Err(PhonetisaurusError::NoPathFound);
+        }
+
+        let mut results = vec![PhonetizationResult {
+            phonemes: options.separators.join(&phoneme_tokens),
+            phoneme_tokens,
+            neg_log_score,
+            posterior: 1.0,
+            normalized_score: 0.0,
+            alignment: alignment.unwrap_or_default(),
+            syllables: Vec::new(),
+            stress: Vec::new(),
+            phoneme_posteriors,
+            diagnostics: options.diagnostics.then(|| DecodeDiagnostics {
+                skipped_chars,
+                ..DecodeDiagnostics::default()
+            }),
+        }];
+        finish_results(&mut results, word, options);
+        Ok(results)
+    }
+
+    /// The lattice of all alignments of a word with the pronunciations the model allows, see
+    /// [`PronunciationLattice`].
+    pub fn phonemize_word_lattice(&self, word: &str) -> Result<PronunciationLattice> {
+        self.phonemize_word_lattice_with_options(word, &PhonemizeOptions::default())
+    }
+
+    /// The lattice of a word, using the given options. The lattice is pruned with the
+    /// `beam`, `max_states` and `prune_weight` options.
+    ///
+    /// The exception lexicon and the word punctuation policy are not applied, the lattice
+    /// always comes from the model.
+    pub fn phonemize_word_lattice_with_options(
+        &self,
+        word: &str,
+        options: &PhonemizeOptions,
+    ) -> Result<PronunciationLattice> {
+        let folded_word = options.case_fold.apply(word);
+        let word: &str = &options.normalize.apply(&folded_word);
+
+        let (mut composed_fst, spans) = self.compose_word(word, None, options)?;
+        let start = Instant::now();
+        prune_lattice(&mut composed_fst, options)?;
+        if let Some(tracer) = &options.tracer {
+            tracer.stage_done(word, DecodeStage::Prune, start, &composed_fst);
+        }
+
+        let isyms = self
+            .trained_fst
+            .input_symbols()
+            .ok_or(PhonetisaurusError::MissingSymbolTable(SymbolTableKind::Input))?;
+        Ok(PronunciationLattice::new(
+            word,
+            composed_fst,
+            spans,
+            isyms.clone(),
+            self.output_symbol_table()?.clone(),
+            options.separators.clone(),
+        ))
+    }
+
+    /// Draw `k` pronunciations of a word at random from its lattice, each alignment with a
+    /// probability proportional to `exp(-score / temperature)`, see
+    /// [`PronunciationLattice::sample`]. The same pronunciation may be drawn several times.
+    pub fn sample_pronunciations(&self, word: &str, k: usize, temperature: f32) -> Result<Vec<PhonetizationResult>> {
+        let seed = RandomState::new().build_hasher().finish();
+        self.sample_pronunciations_with_options(word, k, temperature, seed, &PhonemizeOptions::default())
+    }
+
+    /// Draw `k` pronunciations of a word at random, reproducibly from `seed`, using the given
+    /// options. The lattice is pruned with the options before sampling.
+    ///
+    /// Each sample is finished on its own, so every result has a posterior of 1.
+    pub fn sample_pronunciations_with_options(
+        &self,
+        word: &str,
+        k: usize,
+        temperature: f32,
+        seed: u64,
+        options: &PhonemizeOptions,
+    ) -> Result<Vec<PhonetizationResult>> {
+        let lattice = self.phonemize_word_lattice_with_options(word, options)?;
+        let mut results = Vec::with_capacity(k);
+        for path in lattice.sample(k, temperature, seed)? {
+            let mut result = vec![PhonetizationResult {
+                phonemes: options.separators.join(&path.phoneme_tokens),
+                phoneme_tokens: path.phoneme_tokens,
+                neg_log_score: path.neg_log_score,
+                posterior: 1.0,
+                normalized_score: path.neg_log_score,
+                alignment: path.alignment,
+                syllables: Vec::new(),
+                stress: Vec::new(),
+                phoneme_posteriors: Vec::new(),
+                diagnostics: None,
+            }];
+            finish_results(&mut result, lattice.word(), options);
+            results.append(&mut result);
+        }
+        Ok(results)
+    }
+
+    /// Phonemize a word and draw the FST of every decoding stage in the DOT language of
+    /// Graphviz, to see why the model produces a pronunciation.
+    ///
+    /// The drawings are returned even if decoding fails, up to the failing stage. Words in
+    /// the exception lexicon are not decoded, so nothing is drawn for them.
+    pub fn draw_decoding(
+        &self,
+        word: &str,
+        n: usize,
+        options: &PhonemizeOptions,
+    ) -> (Result<Vec<PhonetizationResult>>, Vec<(DecodeStage, String)>) {
+        let drawings = Arc::new(Mutex::new(Vec::new()));
+        let options = PhonemizeOptions {
+            tracer: Some(Tracer::new({
+                let drawings = drawings.clone();
+                move |trace: &StageTrace<'_>| {
+                    if let Ok(mut drawings) = drawings.lock() {
+                        drawings.push((trace.stage, trace.to_dot()));
+                    }
+                }
+            })),
+            ..options.clone()
+        };
+        let results = self.phonemize_word_nbest_with_options(word, n, &options);
+        let drawings = drawings.lock().map(|mut drawings| std::mem::take(&mut *drawings)).unwrap_or_default();
+        (results, drawings)
+    }
+
+    /// Start phonemizing a word char by char, see [`PhonemizerSession`].
+    pub fn session(&self) -> Result<PhonemizerSession> {
+        self.session_with_options(PhonemizeOptions::default())
+    }
+
+    /// Start phonemizing a word char by char, using the given options.
+    pub fn session_with_options(&self, options: PhonemizeOptions) -> Result<PhonemizerSession> {
+        PhonemizerSession::new(self.clone(), options)
+    }
+
+    /// Score a given pronunciation of a word with the model.
+    ///
+    /// Returns the negative log score of the best path through the model that reads the word
+    /// and produces exactly the given phoneme tokens, as they appear in
+    /// [`PhonetizationResult::phoneme_tokens`]. The exception lexicon is not consulted.
+    pub fn score_pronunciation(&self, word: &str, phonemes: &[&str]) -> Result<f32> {
+        self.score_pronunciation_with_options(word, phonemes, &PhonemizeOptions::default())
+    }
+
+    /// Score a given pronunciation of a word with the model, using the given options.
+    pub fn score_pronunciation_with_options(
+        &self,
+        word: &str,
+        phonemes: &[&str],
+        options: &PhonemizeOptions,
+    ) -> Result<f32> {
+        let folded_word = options.case_fold.apply(word);
+        let word: &str = &options.normalize.apply(&folded_word);
+
+        let positions = self.phoneme_labels(phonemes, &options.separators)?;
+        let skip_labels = self.skip_labels(&options.separators)?;
+
+        let (composed_fst, _) = self.compose_word(word, None, options)?;
+        let forced_fst = self.restrict_output(&composed_fst, &positions, &skip_labels)?;
+        let (_, weight) = linear_path(&forced_fst)?;
+
+        Ok(*weight.value())
+    }
+
+    /// Spell a phoneme sequence with the model, i.e. decode it the other way round.
+    pub fn spell_phonemes(&self, phonemes: &[&str]) -> Result<SpellingResult> {
+        self.spell_phonemes_nbest(phonemes, 1)?
+            .into_iter()
+            .next()
+            .ok_or(PhonetisaurusError::NoPathFound)
+    }
+
+    /// Spell a phoneme sequence and return up to `n` distinct spellings, best first.
+    ///
+    /// Phonemes are given as tokens, as they appear in [`PhonetizationResult::phoneme_tokens`].
+    /// The model is composed with an acceptor of the phonemes on its output side, which is
+    /// equivalent to decoding with the inverted model without copying it. At most
+    /// [`MAX_SILENT_GRAPHEMES`] graphemes without a phoneme ("_") are generated in a row.
+    pub fn spell_phonemes_nbest(&self, phonemes: &[&str], n: usize) -> Result<Vec<SpellingResult>> {
+        self.spell_phonemes_nbest_with_options(phonemes, n, &PhonemizeOptions::default())
+    }
+
+    /// Spell a phoneme sequence and return up to `n` distinct spellings, using the
+    /// [`PhonemizeOptions::separators`] of the given options.
+    pub fn spell_phonemes_nbest_with_options(
+        &self,
+        phonemes: &[&str],
+        n: usize,
+        options: &PhonemizeOptions,
+    ) -> Result<Vec<SpellingResult>> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        let isyms = self
+            .trained_fst
+            .input_symbols()
+            .ok_or(PhonetisaurusError::MissingSymbolTable(SymbolTableKind::Input))?;
+        let positions = self.phoneme_labels(phonemes, &options.separators)?;
+        let skip_labels = self.skip_labels(&options.separators)?;
+        let output_fst = self.output_acceptor(&positions, &skip_labels, Some(MAX_SILENT_GRAPHEMES))?;
+
+        let output_fst = compose_post_fsts_left_of(output_fst, &self.post_fsts)?;
+        let mut lattice = self.trained_fst.compose_left_of(output_fst)?;
+
+        // kept to score the spellings exactly, as determinization quantizes weights
+        tr_sort(&mut lattice, ILabelCompare {});
+        let composed_fst = lattice.clone();
+
+        // PROJECT TO GRAPHEMES, like phonemize_word_nbest_with_options does for phonemes
+        project(&mut lattice, ProjectType::ProjectInput);
+        rm_epsilon(&mut lattice).map_err(PhonetisaurusError::FstError)?;
+        let deterministic_fst: VectorFst<TropicalWeight> =
+            determinize(&lattice).map_err(PhonetisaurusError::FstError)?;
+        let (nshortest_fst, cutoff) = nshortest_with_ties(&deterministic_fst, n)?;
+
+        let mut results: Vec<SpellingResult> = Vec::new();
+        for path in nshortest_fst.paths_iter().filter(|path| *path.weight.value() <= cutoff) {
+            let spelling = path
+                .ilabels
+                .iter()
+                .map(|&label| {
+                    isyms
+                        .get_symbol(label)
+                        .map(|symbol| symbol.replace('|', ""))
+                        .ok_or(PhonetisaurusError::UnknownLabel(label))
+                })
+                .collect::<Result<String>>()?;
+            // "c|h" and "c" "h" spell the same
+            if results.iter().any(|r| r.spelling == spelling) {
+                continue;
+            }
+            let input_fst = self.create_input_fst(&path.ilabels)?;
+            let forced_fst: VectorFst<TropicalWeight> =
+                compose::compose::<_, VectorFst<TropicalWeight>, VectorFst<TropicalWeight>, _, _, _>(
+                    input_fst,
+                    &composed_fst,
+                )
+                .map_err(PhonetisaurusError::FstError)?;
+            let (_, weight) = linear_path(&shortest_path(&forced_fst).map_err(PhonetisaurusError::FstError)?)?;
+            results.push(SpellingResult {
+                spelling,
+                neg_log_score: *weight.value(),
+            });
+        }
+        if results.is_empty() {
+            return Err(PhonetisaurusError::NoPathFound);
+        }
+        results.sort_by(|a, b| {
+            a.neg_log_score
+                .total_cmp(&b.neg_log_score)
+                .then_with(|| a.spelling.cmp(&b.spelling))
+        });
+        results.truncate(n);
+
+        Ok(results)
+    }
+
+    /// Phonemize many words in parallel, one result per word in input order.
+    ///
+    /// A word that fails to phonemize does not affect the others.
+    pub fn phonemize_words(&self, words: &[&str]) -> Vec<Result<PhonetizationResult>> {
+        self.phonemize_words_with_options(words, &PhonemizeOptions::default())
+    }
+
+    /// Phonemize many words in parallel, using the given options.
+    pub fn phonemize_words_with_options(
+        &self,
+        words: &[&str],
+        options: &PhonemizeOptions,
+    ) -> Vec<Result<PhonetizationResult>> {
+        par_map(words, default_threads(), |word| {
+            self.phonemize_word_with_options(word, options)
+        })
+    }
+
+    /// Phonemize many words in parallel, with up to `n` distinct pronunciations per word.
+    pub fn phonemize_words_nbest(&self, words: &[&str], n: usize) -> Vec<Result<Vec<PhonetizationResult>>> {
+        self.phonemize_words_nbest_with_options(words, n, &PhonemizeOptions::default())
+    }
+
+    /// Phonemize many words in parallel, with up to `n` distinct pronunciations per word,
+    /// using the given options.
+    pub fn phonemize_words_nbest_with_options(
+        &self,
+        words: &[&str],
+        n: usize,
+        options: &PhonemizeOptions,
+    ) -> Vec<Result<Vec<PhonetizationResult>>> {
+        self.phonemize_words_nbest_with_threads(words, n, options, default_threads())
+    }
+
+    /// Phonemize many words in parallel on up to `threads` threads instead of one per CPU,
+    /// with up to `n` distinct pronunciations per word, using the given options.
+    pub fn phonemize_words_nbest_with_threads(
+        &self,
+        words: &[&str],
+        n: usize,
+        options: &PhonemizeOptions,
+        threads: usize,
+    ) -> Vec<Result<Vec<PhonetizationResult>>> {
+        par_map(words, threads, |word| {
+            self.phonemize_word_nbest_with_options(word, n, options)
+        })
+    }
+
+    /// Graphemes the model accepts, i.e. the input symbols without epsilon.
+    ///
+    /// Empty if the model has no input symbol table.
+    pub fn input_symbols(&self) -> Vec<String> {
+        symbols_of(self.trained_fst.input_symbols())
+    }
+
+    /// Phoneme symbols the model can emit, i.e. the output symbols without epsilon, those of
+    /// the last transducer of [`Self::with_post_fst`] if there is one.
+    ///
+    /// Empty if the model has no output symbol table.
+    pub fn output_symbols(&self) -> Vec<String> {
+        symbols_of(self.output_symbol_table().ok())
+    }
+
+    /// Number of states in the model FST.
+    pub fn num_states(&self) -> usize {
+        self.trained_fst.num_states()
+    }
+
+    /// Number of arcs (transitions) in the model FST.
+    pub fn num_arcs(&self) -> usize {
+        self.trained_fst.num_arcs()
+    }
+
+    /// Read the whole model once, so that its memory is paged in before the first word is
+    /// decoded instead of while it is.
+    pub fn warm_up(&self) {
+        self.trained_fst.touch();
+    }
+
+    pub(crate) fn trained_fst(&self) -> &ModelFst {
+        &self.trained_fst
+    }
+
+    /// Find all characters of a word that are missing from the input symbol table, as
+    /// `(position, char)` pairs, instead of failing on the first one like
+    /// [`Self::phonemize_word`].
+    pub fn check_word(&self, word: &str) -> std::result::Result<(), Vec<(usize, char)>> {
+        self.check_word_with_options(word, &PhonemizeOptions::default())
+    }
+
+    /// Find all characters of a word that are missing from the input symbol table, after the
+    /// case folding, normalization and segmentation of the options.
+    ///
+    /// Positions are counted like in [`PhonetisaurusError::UnknownSymbol`]. Words in the
+    /// exception lexicon are always covered. The unknown char policy is ignored, so skipped
+    /// and substituted characters are reported as well.
+    pub fn check_word_with_options(
+        &self,
+        word: &str,
+        options: &PhonemizeOptions,
+    ) -> std::result::Result<(), Vec<(usize, char)>> {
+        let folded_word = options.case_fold.apply(word);
+        let word: &str = &options.normalize.apply(&folded_word);
+        if self.lexicon.as_ref().is_some_and(|lexicon| lexicon.get(word).is_some()) {
+            return Ok(());
+        }
+
+        let isyms = self.trained_fst.input_symbols();
+        let known = |symbol: &str| isyms.is_some_and(|isyms| isyms.get_label(symbol).is_some());
+        let chars: Vec<char> = word.chars().collect();
+        let mut missing = Vec::new();
+        for segment in options.segmentation.split(&chars, &self.clusters) {
+            if segment.len() > 1 {
+                let symbol: String = chars[segment.clone()].iter().collect();
+                if known(&symbol) || self.clusters.get(&symbol).is_some() {
+                    continue;
+                }
+            }
+            missing.extend(
+                segment
+                    .filter(|&position| !known(&chars[position].to_string()))
+                    .map(|position| (position, chars[position])),
+            );
+        }
+
+        if missing.is_empty() { Ok(()) } else { Err(missing) }
+    }
+
+    /// Check that the model FST has a start state, final states, both symbol tables and no
+    /// unreachable or dead-end states, so that a broken model is noticed before decoding.
+    pub fn validate(&self) -> ValidationReport {
+        self.trained_fst.validate()
+    }
+
+    /// Build the acceptor for a word and compose it with the trained FST.
+    ///
+    /// Also returns the char positions in the word of every label of the acceptor.
+    fn compose_word(
+        &self,
+        word: &str,
+        tag: Option<&str>,
+        options: &PhonemizeOptions,
+    ) -> Result<(VectorFst<TropicalWeight>, Vec<Range<usize>>)> {
+        if let Some(limit) = options.max_word_length {
+            let length = word.chars().count();
+            if length > limit {
+                return Err(PhonetisaurusError::InputTooLong { length, limit });
+            }
+        }
+
         // ACCEPTOR
-        let input_sequence: Vec<Label> = self.encode_as_labels(word)?;
+        let start = Instant::now();
+        let (mut input_sequence, mut spans) = self.encode_as_labels(word, options)?;
+        // a tag the model was trained with follows the graphemes without consuming any
+        let tag_label = tag.and_then(|tag| self.trained_fst.input_symbols()?.get_label(tag));
+        if let Some(label) = tag_label {
+            let end = word.chars().count();
+            input_sequence.push(label);
+            spans.push(end..end);
+        }
         let input_fst = self.create_input_fst(&input_sequence)?;
+        if let Some(tracer) = &options.tracer {
+            tracer.stage_done(word, DecodeStage::Acceptor, start, &input_fst);
+        }
 
         // COMPOSE
-        // NOTE: The weird type annotation is needed, as Rust doesn't know which Borrow<_> impl
-        // to use for the second FST. The impls for both Arc<_> and VectorFst<_> are possible
-        // (as far as I understand), and we need to use the second one, so VectorFst<_> needs to
-        // be specified as F2. For reference, the full type annotation would be:
-        //      W:  TropicalWeight,
-        //      F1: VectorFst<TropicalWeight>,
-        //      F2: VectorFst<TropicalWeight>,
-        //      F3: VectorFst<TropicalWeight>,
-        //      B1: VectorFst<TropicalWeight>,
-        //      B2: Arc<VectorFst<TropicalWeight>>,
-        let composed_fst: VectorFst<TropicalWeight> =
-            compose::compose::<_, _, VectorFst<TropicalWeight>, _, _, _>(
-                input_fst,
-                self.trained_fst.clone(),
-            )?;
-
-        // TRANSFORM TO PHONEMES (ITERATE SHORTEST PATH)
-        // WARNING: rustfst's shortest_path does not find the shortest paths, phonetisaurus finds better ones
-        let shortest_fst: VectorFst<_> = shortest_path(&composed_fst)?;
-
-        let shortest_path = shortest_fst.paths_iter().collect::<Vec<_>>();
-        let shortest_path = shortest_path.first().ok_or(anyhow!(
-            "Transcription failed: No shortest path found in FST. This should not be possible."
-        ))?;
-        // only one path should exist, because fst was converted to shortest path fst.
-
-        let osyms = shortest_fst.output_symbols().ok_or(anyhow!(
-            "No output symbol table found in loaded FST model, but one is needed."
-        ))?;
-
-        // "_" symbols need to be skipped
-        // "|" in symbols needs to be removed
-        let phonemes = shortest_path
-            .olabels
-            .iter()
-            .filter_map(|&label| {
-                if let Some(symbol) = osyms.get_symbol(label) {
-                    if symbol == "_" {
-                        return None;
-                    }
+        let start = Instant::now();
+        let composed_fst = self.trained_fst.compose_right_of(input_fst)?;
+        let composed_fst = compose_post_fsts(composed_fst, &self.post_fsts)?;
+        if let Some(tracer) = &options.tracer {
+            tracer.stage_done(word, DecodeStage::Compose, start, &composed_fst);
+        }
+        check_lattice_size(&composed_fst, options)?;
+
+        Ok((composed_fst, spans))
+    }
+
+    /// Restrict a composed FST to the paths producing the given phoneme labels, and return
+    /// the shortest of them.
+    ///
+    /// `olabels` must not contain the `skip_labels`, which may appear anywhere in the
+    /// restricted paths.
+    fn force_phonemes(
+        &self,
+        composed_fst: &VectorFst<TropicalWeight>,
+        olabels: &[Label],
+        skip_labels: &[Label],
+    ) -> Result<VectorFst<TropicalWeight>> {
+        let positions: Vec<Vec<Label>> = olabels.iter().map(|&label| vec![label]).collect();
+        self.restrict_output(composed_fst, &positions, skip_labels)
+    }
+
+    /// Like [`Self::force_phonemes`], but any of the labels given for a position may be
+    /// produced there.
+    fn restrict_output(
+        &self,
+        composed_fst: &VectorFst<TropicalWeight>,
+        positions: &[Vec<Label>],
+        skip_labels: &[Label],
+    ) -> Result<VectorFst<TropicalWeight>> {
+        let output_fst = self.output_acceptor(positions, skip_labels, None)?;
 
-                    Some(Ok(symbol))
+        // the lattice is only borrowed, like the trained FST in compose_word
+        let forced_fst: VectorFst<TropicalWeight> =
+            compose::compose::<_, VectorFst<TropicalWeight>, VectorFst<TropicalWeight>, _, _, _>(
+                composed_fst,
+                output_fst,
+            )
+            .map_err(PhonetisaurusError::FstError)?;
+
+        shortest_path(&forced_fst).map_err(PhonetisaurusError::FstError)
+    }
+
+    /// Build an acceptor of phoneme sequences, reading one of the labels of each position in
+    /// turn, with the `skip_labels` ("_") allowed anywhere.
+    ///
+    /// With `max_skips`, at most that many skip labels are accepted in a row, which keeps the
+    /// acceptor acyclic.
+    fn output_acceptor(
+        &self,
+        positions: &[Vec<Label>],
+        skip_labels: &[Label],
+        max_skips: Option<usize>,
+    ) -> Result<VectorFst<TropicalWeight>> {
+        // state of the acceptor after `position` phonemes and `skips` skip labels in a row
+        let layers = max_skips.map_or(1, |max_skips| max_skips + 1);
+        let state_of = |position: usize, skips: usize| (position * layers + skips) as StateId;
+
+        let mut output_fst: VectorFst<TropicalWeight> = VectorFst::new();
+        output_fst.add_states((positions.len() + 1) * layers);
+        output_fst.set_start(state_of(0, 0)).map_err(PhonetisaurusError::FstError)?;
+        for position in 0..=positions.len() {
+            for skips in 0..layers {
+                let state = state_of(position, skips);
+                if let Some(labels) = positions.get(position) {
+                    for &label in labels {
+                        let tr = Tr::new(label, label, TropicalWeight::one(), state_of(position + 1, 0));
+                        output_fst.add_tr(state, tr).map_err(PhonetisaurusError::FstError)?;
+                    }
                 } else {
-                    Some(Err(anyhow!(
-                        "Symbol for label {} not found in output symbol table",
-                        label
-                    )))
+                    output_fst
+                        .set_final(state, TropicalWeight::one())
+                        .map_err(PhonetisaurusError::FstError)?;
+                }
+                let next_state = match max_skips {
+                    None => state,
+                    Some(_) if skips + 1 < layers => state_of(position, skips + 1),
+                    Some(_) => continue,
+                };
+                for &skip_label in skip_labels {
+                    let tr = Tr::new(skip_label, skip_label, TropicalWeight::one(), next_state);
+                    output_fst.add_tr(state, tr).map_err(PhonetisaurusError::FstError)?;
                 }
+            }
+        }
+        tr_sort(&mut output_fst, ILabelCompare {});
+
+        Ok(output_fst)
+    }
+
+    /// Map phoneme tokens to the output labels that can produce them.
+    ///
+    /// A token can be produced by several symbols, e.g. "KS" by both "KS" and "K|S".
+    fn phoneme_labels(&self, phonemes: &[&str], separators: &PhonemeSeparators) -> Result<Vec<Vec<Label>>> {
+        let mut labels_by_token: HashMap<String, Vec<Label>> = HashMap::new();
+        for (label, symbol) in self.output_symbol_table()?.iter() {
+            if label != EPS_LABEL && !separators.is_skip(symbol) {
+                labels_by_token.entry(separators.token(symbol)).or_default().push(label);
+            }
+        }
+        phonemes
+            .iter()
+            .map(|&phoneme| {
+                labels_by_token
+                    .get(phoneme)
+                    .cloned()
+                    .ok_or_else(|| PhonetisaurusError::UnknownPhoneme(phoneme.to_string()))
             })
-            .collect::<Result<Vec<&str>>>()?
-            .join(" ")
-            .replace("|", "");
+            .collect()
+    }
+
+    /// Output labels of the skip symbols that are in the output symbol table.
+    fn skip_labels(&self, separators: &PhonemeSeparators) -> Result<Vec<Label>> {
+        let osyms = self.output_symbol_table()?;
+        Ok(separators
+            .skip_symbols
+            .iter()
+            .filter_map(|symbol| osyms.get_label(symbol))
+            .filter(|&label| label != EPS_LABEL)
+            .collect())
+    }
+
+    /// Build the result from a single-path FST, given the char positions of its input labels.
+    fn result_from_path(
+        &self,
+        path_fst: &VectorFst<TropicalWeight>,
+        spans: &[Range<usize>],
+        separators: &PhonemeSeparators,
+    ) -> Result<PhonetizationResult> {
+        let (arcs, weight) = linear_path(path_fst)?;
+        let alignment = align(&arcs, spans, self.output_symbol_table()?, separators)?;
+        let phoneme_tokens: Vec<String> = alignment
+            .iter()
+            .flat_map(|segment| segment.phonemes.iter().cloned())
+            .collect();
 
         Ok(PhonetizationResult {
-            phonemes,
-            neg_log_score: *shortest_path.weight.value(),
+            phonemes: separators.join(&phoneme_tokens),
+            phoneme_tokens,
+            neg_log_score: *weight.value(),
+            posterior: 1.0,
+            normalized_score: *weight.value(),
+            alignment,
+            syllables: Vec::new(),
+            stress: Vec::new(),
+            phoneme_posteriors: Vec::new(),
+            diagnostics: None,
         })
     }
 
-    fn encode_as_labels(&self, word: &str) -> Result<Vec<Label>> {
-        let isyms = self.trained_fst.input_symbols().ok_or(anyhow!(
-            "No input symbol table found in loaded FST model, but one is needed."
-        ))?;
+    /// The output symbols of the last post-transducer, or of the model if there is none.
+    pub(crate) fn output_symbol_table(&self) -> Result<&Arc<SymbolTable>> {
+        match self.post_fsts.last() {
+            Some(post_fst) => post_fst.output_symbols(),
+            None => self.trained_fst.output_symbols(),
+        }
+        .ok_or(PhonetisaurusError::MissingSymbolTable(SymbolTableKind::Output))
+    }
+
+    /// Map the chars of a word to input labels, along with the char position of each label.
+    fn encode_as_labels(
+        &self,
+        word: &str,
+        options: &PhonemizeOptions,
+    ) -> Result<(Vec<Label>, Vec<Range<usize>>)> {
+        let isyms = self
+            .trained_fst
+            .input_symbols()
+            .ok_or(PhonetisaurusError::MissingSymbolTable(SymbolTableKind::Input))?;
         let mut input_sequence: Vec<Label> = Vec::new();
+        let mut spans: Vec<Range<usize>> = Vec::new();
 
-        // TODO/WARNING: Inputs are not always ASCII, so this can break!
-        for ch in word.chars() {
-            if let Some(sym) = isyms.get_label(ch.to_string()) {
-                input_sequence.push(sym);
-            } else {
-                return Err(anyhow!(
-                    "Symbol {} not found in symbol table. Most likely, the FST was not trained with this symbol.",
-                    ch
-                ));
+        let chars: Vec<char> = word.chars().collect();
+        for segment in options.segmentation.split(&chars, &self.clusters) {
+            if segment.len() > 1 {
+                let symbol: String = chars[segment.clone()].iter().collect();
+                if let Some(sym) = isyms.get_label(&symbol).or_else(|| self.clusters.get(&symbol)) {
+                    input_sequence.push(sym);
+                    spans.push(segment);
+                    continue;
+                }
+            }
+            // Segments missing from the symbol table are looked up char by char
+            for position in segment {
+                let ch = chars[position];
+                if let Some(sym) = isyms.get_label(ch.to_string()) {
+                    input_sequence.push(sym);
+                    spans.push(position..position + 1);
+                    continue;
+                }
+                match options.unknown_char_policy {
+                    UnknownCharPolicy::Error => {
+                        return Err(PhonetisaurusError::UnknownSymbol { ch, position });
+                    }
+                    UnknownCharPolicy::Skip => {}
+                    UnknownCharPolicy::Substitute(substitute) => {
+                        let sym = isyms
+                            .get_label(substitute.to_string())
+                            .ok_or(PhonetisaurusError::UnknownSymbol { ch, position })?;
+                        input_sequence.push(sym);
+                        spans.push(position..position + 1);
+                    }
+                }
             }
         }
 
-        Ok(input_sequence)
+        Ok((input_sequence, spans))
     }
 
-    fn create_input_fst(&self, input_sequence: &Vec<Label>) -> Result<VectorFst<TropicalWeight>> {
+    fn create_input_fst(&self, input_sequence: &[Label]) -> Result<VectorFst<TropicalWeight>> {
         let mut input_fst: VectorFst<TropicalWeight> = VectorFst::new();
+        // only the Arcs are cloned, so that the stages of decoding can be drawn with symbols
+        if let Some(isyms) = self.trained_fst.input_symbols() {
+            input_fst.set_input_symbols(isyms.clone());
+            input_fst.set_output_symbols(isyms.clone());
+        }
         let mut state = input_fst.add_state();
-        input_fst.set_start(state)?;
+        input_fst
+            .set_start(state)
+            .map_err(PhonetisaurusError::FstError)?;
 
         for &sym in input_sequence {
             let next_state = input_fst.add_state();
             input_fst.add_tr(state, Tr::new(sym, sym, TropicalWeight::one(), next_state))
-                .context("Constructing acceptor FST from input word failed, new transition could not be added.")?;
+                .context("Constructing acceptor FST from input word failed, new transition could not be added.")
+                .map_err(PhonetisaurusError::FstError)?;
             state = next_state;
         }
         input_fst.set_final(state, TropicalWeight::one()).context(
             "Constructing acceptor FST from input word failed, final state could not be set.",
-        )?;
+        )
+        .map_err(PhonetisaurusError::FstError)?;
 
         Ok(input_fst)
     }
 }
+
+/// Find the stressed phonemes of the results and apply the stress marking of the options.
+///
+/// The results must be sorted best first. Later results whose phonemes became identical to
+/// an earlier one are removed.
+fn set_stress(results: &mut Vec<PhonetizationResult>, options: &PhonemizeOptions) {
+    let Some(convention) = options.stress_convention else {
+        return;
+    };
+    for result in results.iter_mut() {
+        if options.stress_marking != StressMarking::Keep {
+            // markers that are tokens of their own are removed, and so are their posteriors
+            let mut markers = result.phoneme_tokens.iter().map(|token| convention.is_marker(token));
+            result.phoneme_posteriors.retain(|_| !markers.next().unwrap_or(false));
+        }
+        let (tokens, stress) = apply_stress(&result.phoneme_tokens, convention, options.stress_marking);
+        result.phonemes = options.separators.join(&tokens);
+        result.phoneme_tokens = tokens;
+        result.stress = stress;
+        for segment in &mut result.alignment {
+            segment.phonemes = apply_stress(&segment.phonemes, convention, options.stress_marking).0;
+        }
+    }
+    let mut seen = Vec::with_capacity(results.len());
+    results.retain(|result| {
+        let new = !seen.contains(&result.phoneme_tokens);
+        if new {
+            seen.push(result.phoneme_tokens.clone());
+        }
+        new
+    });
+}
+
+/// The `n` shortest paths of an FST, extended with the paths as short as the last of them,
+/// along with the weight of that last path.
+///
+/// Which of several equally short paths the search returns first depends on the order of the
+/// states and transitions of the FST. Taking all of them and sorting them by their labels
+/// makes the results the same whatever that order is.
+pub(crate) fn nshortest_with_ties(fst: &VectorFst<TropicalWeight>, n: usize) -> Result<(VectorFst<TropicalWeight>, f32)> {
+    let mut k = n;
+    loop {
+        // one path more than needed tells whether it ties with the last needed one
+        let nshortest_fst: VectorFst<TropicalWeight> =
+            shortest_path_with_config(fst, ShortestPathConfig::default().with_nshortest(k.saturating_add(1)))
+                .map_err(PhonetisaurusError::FstError)?;
+        let mut weights: Vec<f32> = nshortest_fst.paths_iter().map(|path| *path.weight.value()).collect();
+        weights.sort_by(f32::total_cmp);
+        let cutoff = weights.get(n - 1).or(weights.last()).copied().unwrap_or(f32::INFINITY);
+        if weights.len() <= k || weights[k] > cutoff {
+            return Ok((nshortest_fst, cutoff));
+        }
+        k = k.saturating_mul(2);
+    }
+}
+
+/// Whether the two best paths of a lattice have the same weight.
+fn best_paths_tie(fst: &VectorFst<TropicalWeight>) -> Result<bool> {
+    let two_shortest: VectorFst<TropicalWeight> =
+        shortest_path_with_config(fst, ShortestPathConfig::default().with_nshortest(2))
+            .map_err(PhonetisaurusError::FstError)?;
+    let weights: Vec<f32> = two_shortest.paths_iter().map(|path| *path.weight.value()).collect();
+    Ok(matches!(weights.as_slice(), [first, second] if first == second))
+}
+
+/// Results for pronunciations known in advance, e.g. from a [`Lexicon`], with a score of 0
+/// and no alignment. `word` is the case folded and normalized word.
+pub(crate) fn fixed_results(
+    pronunciations: &[Vec<String>],
+    word: &str,
+    n: usize,
+    options: &PhonemizeOptions,
+) -> Vec<PhonetizationResult> {
+    let mut results: Vec<PhonetizationResult> = pronunciations
+        .iter()
+        .take(n)
+        .map(|phoneme_tokens| PhonetizationResult {
+            phonemes: options.separators.join(phoneme_tokens),
+            phoneme_tokens: phoneme_tokens.clone(),
+            neg_log_score: 0.0,
+            posterior: 1.0,
+            normalized_score: 0.0,
+            // the lexicon does not say which graphemes produce which phonemes
+            alignment: Vec::new(),
+            syllables: Vec::new(),
+            stress: Vec::new(),
+            phoneme_posteriors: Vec::new(),
+            diagnostics: None,
+        })
+        .collect();
+    finish_results(&mut results, word, options);
+    results
+}
+
+/// Split a tagged word like `read#VBD` at the last `separator` into the word and the tag, if
+/// neither is empty.
+pub(crate) fn split_tag(word: &str, separator: char) -> Option<(&str, &str)> {
+    word.rsplit_once(separator).filter(|(word, tag)| !word.is_empty() && !tag.is_empty())
+}
+
+/// Move the position of an unknown symbol error from a part of a word to the word.
+fn move_position(error: PhonetisaurusError, position: impl Fn(usize) -> usize) -> PhonetisaurusError {
+    match error {
+        PhonetisaurusError::UnknownSymbol { ch, position: p } => PhonetisaurusError::UnknownSymbol {
+            ch,
+            position: position(p),
+        },
+        error => error,
+    }
+}
+
+/// Apply the stress marking of the options to results built outside of decoding, and set
+/// their posteriors, normalized scores and syllables. `word` is the case folded and
+/// normalized word.
+pub(crate) fn finish_results(results: &mut Vec<PhonetizationResult>, word: &str, options: &PhonemizeOptions) {
+    set_stress(results, options);
+    set_posteriors(results);
+    set_normalized_scores(results, options.length_normalization, word);
+    set_syllables(results, options);
+}
+
+/// Syllabify the results with the syllabifier of the options, if there is one.
+fn set_syllables(results: &mut [PhonetizationResult], options: &PhonemizeOptions) {
+    if let Some(syllabifier) = &options.syllabifier {
+        for result in results {
+            result.syllables = syllabifier.syllabify(&result.phoneme_tokens);
+        }
+    }
+}
+
+/// Fail if a lattice has more states than allowed by the options.
+fn check_lattice_size(fst: &VectorFst<TropicalWeight>, options: &PhonemizeOptions) -> Result<()> {
+    match options.max_lattice_states {
+        Some(limit) if fst.num_states() > limit => Err(PhonetisaurusError::SearchSpaceExceeded {
+            states: fst.num_states(),
+            limit,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Set the posteriors of an n-best list from its scores, normalizing the likelihoods
+/// `exp(-neg_log_score)` over the list.
+fn set_posteriors(results: &mut [PhonetizationResult]) {
+    let Some(best) = results.iter().map(|r| r.neg_log_score).min_by(f32::total_cmp) else {
+        return;
+    };
+    // relative to the best score, so the exponentials cannot all underflow
+    let likelihoods: Vec<f64> = results
+        .iter()
+        .map(|r| (-(r.neg_log_score as f64 - best as f64)).exp())
+        .collect();
+    let total: f64 = likelihoods.iter().sum();
+    for (result, likelihood) in results.iter_mut().zip(likelihoods) {
+        result.posterior = (likelihood / total) as f32;
+    }
+}
+
+/// Set the normalized scores of results for `word`.
+fn set_normalized_scores(results: &mut [PhonetizationResult], normalization: LengthNormalization, word: &str) {
+    for result in results {
+        let length = match normalization {
+            LengthNormalization::None => 1,
+            LengthNormalization::PerPhoneme => result.phoneme_tokens.len(),
+            LengthNormalization::PerGrapheme => word.chars().count(),
+        };
+        result.normalized_score = result.neg_log_score / length.max(1) as f32;
+    }
+}
+
+/// Name of the compression format of `data`, recognized by its magic bytes, for the formats
+/// that are not decompressed when loading.
+///
+/// Compressed models are rejected with a dedicated error instead of a parser failure, as
+/// compressed model files are easily mistaken for plain ones.
+fn compression_format(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        Some("xz")
+    } else if data.starts_with(b"BZh") {
+        Some("bzip2")
+    } else {
+        None
+    }
+}
+
+/// List the symbols of a symbol table in label order, leaving out epsilon.
+fn symbols_of(table: Option<&Arc<SymbolTable>>) -> Vec<String> {
+    table
+        .map(|table| {
+            table
+                .iter()
+                .filter(|&(label, _)| label != EPS_LABEL)
+                .map(|(_, symbol)| symbol.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::sync::Arc;
+
+use crate::acronym::AcronymSpeller;
+use crate::alignment::AlignedSegment;
+use crate::compound::CompoundSplitter;
+use crate::error::Result;
+use crate::lexicon::Lexicon;
+use crate::options::PhonemizeOptions;
+use crate::parallel::{default_threads, par_map};
+use crate::phonetisaurus::{PhonetisaurusModel, PhonetizationResult, finish_results, fixed_results};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The stage of a [`G2pPipeline`] that answered a word, in the order they are tried.
+pub enum PipelineStage {
+    /// The user exception lexicon.
+    UserLexicon,
+    /// The base pronunciation dictionary.
+    Dictionary,
+    /// Spelling an acronym letter by letter with an [`AcronymSpeller`].
+    SpelledOut,
+    /// Decoding with the FST model, including a lexicon attached to the model.
+    Model,
+    /// Splitting the word into known words with a [`CompoundSplitter`] and phonemizing them
+    /// with the stages above, after the model failed or scored poorly.
+    Compound,
+    /// The rule fallback, after the model failed.
+    Rules,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Pronunciations of a word and the pipeline stage they came from.
+pub struct PipelineResult {
+    pub stage: PipelineStage,
+    /// The pronunciations, best first. Those of the lexicons, spelled out acronyms and the
+    /// rules have a score of 0 and no alignment.
+    pub results: Vec<PhonetizationResult>,
+}
+
+/// Last resort for words the model cannot phonemize, e.g. letter-to-sound rules.
+pub trait FallbackRules: Send + Sync {
+    /// Phoneme tokens of a word, after case folding and normalization, or `None` if the
+    /// rules do not cover it either.
+    fn pronounce(&self, word: &str) -> Option<Vec<String>>;
+}
+
+impl<F: Fn(&str) -> Option<Vec<String>> + Send + Sync> FallbackRules for F {
+    fn pronounce(&self, word: &str) -> Option<Vec<String>> {
+        self(word)
+    }
+}
+
+#[derive(Clone)]
+/// The lookup chain of a TTS frontend: a user exception lexicon, then a base pronunciation
+/// dictionary, then optionally spelling out acronyms, then the FST model, then optionally compound splitting and rules for what the
+/// model fails on.
+///
+/// Every stage is optional except the model. Lexicons are looked up with the word after the
+/// case folding and normalization of the options, like a lexicon attached to the model.
+pub struct G2pPipeline {
+    user_lexicon: Option<Arc<Lexicon>>,
+    dictionary: Option<Arc<Lexicon>>,
+    acronyms: Option<Arc<AcronymSpeller>>,
+    model: PhonetisaurusModel,
+    compounds: Option<Arc<CompoundSplitter>>,
+    rules: Option<Arc<dyn FallbackRules>>,
+}
+
+impl G2pPipeline {
+    /// Create a pipeline that only decodes with `model`.
+    pub fn new(model: PhonetisaurusModel) -> Self {
+        G2pPipeline {
+            user_lexicon: None,
+            dictionary: None,
+            acronyms: None,
+            model,
+            compounds: None,
+            rules: None,
+        }
+    }
+
+    /// Consult this lexicon before anything else, for corrections by the user.
+    pub fn with_user_lexicon(mut self, lexicon: Lexicon) -> Self {
+        self.user_lexicon = Some(Arc::new(lexicon));
+        self
+    }
+
+    /// Consult this pronunciation dictionary after the user lexicon, before the model.
+    pub fn with_dictionary(mut self, dictionary: Lexicon) -> Self {
+        self.dictionary = Some(Arc::new(dictionary));
+        self
+    }
+
+    /// Spell words that look like acronyms letter by letter with this speller, instead of
+    /// decoding them. Acronyms pronounced as words, like "NASA", belong in a lexicon, which
+    /// is consulted first.
+    pub fn with_acronym_speller(mut self, speller: AcronymSpeller) -> Self {
+        self.acronyms = Some(Arc::new(speller));
+        self
+    }
+
+    /// Split words the model fails on into known words with this splitter, and phonemize the
+    /// parts separately. The parts are looked up in the lexicons before they are decoded.
+    pub fn with_compound_splitter(mut self, splitter: CompoundSplitter) -> Self {
+        self.compounds = Some(Arc::new(splitter));
+        self
+    }
+
+    /// Fall back to these rules for words the model fails on.
+    pub fn with_rules(mut self, rules: impl FallbackRules + 'static) -> Self {
+        self.rules = Some(Arc::new(rules));
+        self
+    }
+
+    /// The model of the pipeline.
+    pub fn model(&self) -> &PhonetisaurusModel {
+        &self.model
+    }
+
+    /// Phonemize a word with the first stage that knows it.
+    pub fn phonemize_word(&self, word: &str) -> Result<PipelineResult> {
+        self.phonemize_word_nbest_with_options(word, 1, &PhonemizeOptions::default())
+    }
+
+    /// Phonemize a word with the first stage that knows it, with up to `n` pronunciations,
+    /// using the given options. Compounds get a single pronunciation.
+    ///
+    /// If the model fails and neither the compound splitter nor the rules cover the word,
+    /// the model's error is returned.
+    pub fn phonemize_word_nbest_with_options(
+        &self,
+        word: &str,
+        n: usize,
+        options: &PhonemizeOptions,
+    ) -> Result<PipelineResult> {
+        let folded_word = options.case_fold.apply(word);
+        let normalized_word = options.normalize.apply(&folded_word);
+
+        let looked_up = self.look_up(word, &normalized_word, n, options);
+        if let Some(splitter) = &self.compounds {
+            let split = match &looked_up {
+                Ok(result) => {
+                    result.stage == PipelineStage::Model
+                        && result
+                            .results
+                            .first()
+                            .is_some_and(|best| splitter.is_poor_score(best.neg_log_score, &normalized_word))
+                }
+                Err(_) => true,
+            };
+            if let Some(result) = split.then(|| self.phonemize_compound(splitter, &normalized_word, options)).flatten() {
+                return Ok(PipelineResult {
+                    stage: PipelineStage::Compound,
+                    results: vec![result],
+                });
+            }
+        }
+
+        let error = match looked_up {
+            Ok(result) => return Ok(result),
+            Err(e) => e,
+        };
+        match self.rules.as_ref().and_then(|rules| rules.pronounce(&normalized_word)) {
+            Some(phoneme_tokens) => Ok(PipelineResult {
+                stage: PipelineStage::Rules,
+                results: fixed_results(&[phoneme_tokens], &normalized_word, n, options),
+            }),
+            None => Err(error),
+        }
+    }
+
+    /// Phonemize a word with the lexicons, the acronym speller or the model.
+    fn look_up(
+        &self,
+        word: &str,
+        normalized_word: &str,
+        n: usize,
+        options: &PhonemizeOptions,
+    ) -> Result<PipelineResult> {
+        for (stage, lexicon) in [
+            (PipelineStage::UserLexicon, &self.user_lexicon),
+            (PipelineStage::Dictionary, &self.dictionary),
+        ] {
+            if let Some(pronunciations) = lexicon.as_ref().and_then(|lexicon| lexicon.get(normalized_word)) {
+                return Ok(PipelineResult {
+                    stage,
+                    results: fixed_results(pronunciations, normalized_word, n, options),
+                });
+            }
+        }
+        // acronyms are recognized by their case, so before case folding
+        if let Some(phoneme_tokens) = self.acronyms.as_ref().and_then(|speller| speller.spell(word)) {
+            return Ok(PipelineResult {
+                stage: PipelineStage::SpelledOut,
+                results: fixed_results(&[phoneme_tokens], normalized_word, n, options),
+            });
+        }
+        Ok(PipelineResult {
+            stage: PipelineStage::Model,
+            results: self.model.phonemize_word_nbest_with_options(word, n, options)?,
+        })
+    }
+
+    /// Phonemize a compound part by part, or `None` if it cannot be split or a part fails.
+    ///
+    /// The scores of the parts add up. The alignment is kept only if every part has one.
+    fn phonemize_compound(
+        &self,
+        splitter: &CompoundSplitter,
+        word: &str,
+        options: &PhonemizeOptions,
+    ) -> Option<PhonetizationResult> {
+        let parts = splitter.split(word)?;
+        let chars: Vec<char> = word.chars().collect();
+        // stress and syllables are determined on the whole compound
+        let part_options = PhonemizeOptions {
+            stress_convention: None,
+            syllabifier: None,
+            ..options.clone()
+        };
+
+        let mut phoneme_tokens = Vec::new();
+        let mut neg_log_score = 0.0;
+        let mut alignment = Some(Vec::new());
+        for range in parts {
+            let part: String = chars[range.clone()].iter().collect();
+            let mut best = self.look_up(&part, &part, 1, &part_options).ok()?.results.into_iter().next()?;
+            if best.alignment.is_empty() && !best.phoneme_tokens.is_empty() {
+                alignment = None;
+            }
+            if let Some(alignment) = &mut alignment {
+                alignment.extend(best.alignment.drain(..).map(|segment| AlignedSegment {
+                    graphemes: segment.graphemes.start + range.start..segment.graphemes.end + range.start,
+                    phonemes: segment.phonemes,
+                }));
+            }
+            phoneme_tokens.append(&mut best.phoneme_tokens);
+            neg_log_score += best.neg_log_score;
+        }
+
+        let mut results = vec![PhonetizationResult {
+            phonemes: options.separators.join(&phoneme_tokens),
+            phoneme_tokens,
+            neg_log_score,
+            posterior: 1.0,
+            normalized_score: 0.0,
+            alignment: alignment.unwrap_or_default(),
+            syllables: Vec::new(),
+            stress: Vec::new(),
+            phoneme_posteriors: Vec::new(),
+            diagnostics: None,
+        }];
+        finish_results(&mut results, word, options);
+        results.pop()
+    }
+
+    /// Phonemize many words in parallel, one result per word in input order.
+    pub fn phonemize_words(&self, words: &[&str]) -> Vec<Result<PipelineResult>> {
+        self.phonemize_words_nbest_with_options(words, 1, &PhonemizeOptions::default())
+    }
+
+    /// Phonemize many words in parallel with up to `n` pronunciations per word, using the
+    /// given options.
+    pub fn phonemize_words_nbest_with_options(
+        &self,
+        words: &[&str],
+        n: usize,
+        options: &PhonemizeOptions,
+    ) -> Vec<Result<PipelineResult>> {
+        par_map(words, default_threads(), |word| {
+            self.phonemize_word_nbest_with_options(word, n, options)
+        })
+    }
+}
+
+impl fmt::Debug for G2pPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("G2pPipeline")
+            .field("user_lexicon", &self.user_lexicon.as_ref().map(|lexicon| lexicon.len()))
+            .field("dictionary", &self.dictionary.as_ref().map(|lexicon| lexicon.len()))
+            .field("acronyms", &self.acronyms.is_some())
+            .field("compounds", &self.compounds.is_some())
+            .field("rules", &self.rules.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// Letter-to-sound rules mapping grapheme sequences to phonemes, applied by longest match
+/// from the start of the word.
+///
+/// A word is covered if every character is part of a matched sequence; a sequence may map
+/// to no phonemes to make it silent.
+pub struct GraphemeRules {
+    rules: HashMap<String, Vec<String>>,
+    /// Length of the longest grapheme sequence, in chars.
+    max_len: usize,
+}
+
+impl GraphemeRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read rules, one per line: a grapheme sequence followed by its whitespace-separated
+    /// phonemes. Empty lines and lines starting with `#` are ignored.
+    pub fn from_reader(reader: impl BufRead) -> io::Result<Self> {
+        let mut rules = GraphemeRules::new();
+        for line in reader.lines() {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some(graphemes) if !graphemes.starts_with('#') => {
+                    rules.insert(graphemes, fields.map(str::to_string).collect());
+                }
+                _ => {}
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Map a grapheme sequence to phonemes, replacing an earlier rule for it.
+    pub fn insert(&mut self, graphemes: &str, phonemes: Vec<String>) {
+        self.max_len = self.max_len.max(graphemes.chars().count());
+        self.rules.insert(graphemes.to_string(), phonemes);
+    }
+}
+
+impl FallbackRules for GraphemeRules {
+    fn pronounce(&self, word: &str) -> Option<Vec<String>> {
+        let chars: Vec<char> = word.chars().collect();
+        let mut phonemes = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let longest = self.max_len.min(chars.len() - start);
+            let (len, rule) = (1..=longest).rev().find_map(|len| {
+                let graphemes: String = chars[start..start + len].iter().collect();
+                self.rules.get(&graphemes).map(|rule| (len, rule))
+            })?;
+            phonemes.extend(rule.iter().cloned());
+            start += len;
+        }
+        Some(phonemes)
+    }
+}
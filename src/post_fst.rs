@@ -0,0 +1,76 @@
+use rustfst::algorithms::compose;
+use rustfst::prelude::*;
+use std::sync::Arc;
+
+use crate::error::{PhonetisaurusError, Result};
+use crate::options::PhonemeSeparators;
+
+type W = TropicalWeight;
+
+/// Prepare a transducer to be composed after a model whose output symbols are `osyms`, see
+/// [`PhonetisaurusModel::with_post_fst`](crate::PhonetisaurusModel::with_post_fst).
+///
+/// The input labels are renumbered to the labels of the same symbols in `osyms`, dropping
+/// transitions reading symbols the model does not produce. Skip symbols ("_") the transducer
+/// does not read are passed through unchanged at every state, so that the alignments of the
+/// model survive. Without symbol tables, the transducer is assumed to read and write the
+/// labels of `osyms`.
+pub(crate) fn prepare_post_fst(mut fst: VectorFst<W>, osyms: &Arc<SymbolTable>) -> Result<VectorFst<W>> {
+    let (post_isyms, mut post_osyms) = match (fst.input_symbols().cloned(), fst.output_symbols().cloned()) {
+        (Some(isyms), Some(osyms)) => (isyms, osyms),
+        (Some(isyms), None) => (isyms.clone(), isyms),
+        (None, Some(post_osyms)) => (osyms.clone(), post_osyms),
+        (None, None) => (osyms.clone(), osyms.clone()),
+    };
+
+    let mut skip_loops = Vec::new();
+    for symbol in PhonemeSeparators::default().skip_symbols {
+        if let (Some(label), None) = (osyms.get_label(&symbol), post_isyms.get_label(&symbol)) {
+            skip_loops.push((label, Arc::make_mut(&mut post_osyms).add_symbol(&symbol)));
+        }
+    }
+
+    for state in fst.states_iter().collect::<Vec<_>>() {
+        let mut trs: Vec<Tr<W>> = fst
+            .pop_trs(state)
+            .map_err(PhonetisaurusError::FstError)?
+            .into_iter()
+            .filter_map(|mut tr| {
+                if tr.ilabel != EPS_LABEL {
+                    tr.ilabel = osyms.get_label(post_isyms.get_symbol(tr.ilabel)?)?;
+                }
+                Some(tr)
+            })
+            .collect();
+        trs.extend(skip_loops.iter().map(|&(ilabel, olabel)| Tr::new(ilabel, olabel, W::one(), state)));
+        for tr in trs {
+            fst.add_tr(state, tr).map_err(PhonetisaurusError::FstError)?;
+        }
+    }
+    fst.set_input_symbols(osyms.clone());
+    fst.set_output_symbols(post_osyms);
+    tr_sort(&mut fst, ILabelCompare {});
+
+    Ok(fst)
+}
+
+/// Compose an FST with the post-transducers on its right, in order.
+pub(crate) fn compose_post_fsts(mut fst: VectorFst<W>, post_fsts: &[Arc<VectorFst<W>>]) -> Result<VectorFst<W>> {
+    for post_fst in post_fsts {
+        // the post-transducer is borrowed through the Arc, like the trained FST
+        fst = compose::compose::<_, VectorFst<W>, VectorFst<W>, _, _, Arc<VectorFst<W>>>(fst, post_fst.clone())
+            .map_err(PhonetisaurusError::FstError)?;
+    }
+    Ok(fst)
+}
+
+/// Compose an FST with the post-transducers on its left, the last one first, as needed to
+/// compose the model with an acceptor of final phonemes.
+pub(crate) fn compose_post_fsts_left_of(mut fst: VectorFst<W>, post_fsts: &[Arc<VectorFst<W>>]) -> Result<VectorFst<W>> {
+    for post_fst in post_fsts.iter().rev() {
+        fst = compose::compose::<_, VectorFst<W>, VectorFst<W>, _, Arc<VectorFst<W>>, _>(post_fst.clone(), fst)
+            .map_err(PhonetisaurusError::FstError)?;
+        tr_sort(&mut fst, ILabelCompare {});
+    }
+    Ok(fst)
+}
@@ -0,0 +1,149 @@
+use rustfst::algorithms::connect;
+use rustfst::prelude::*;
+use std::collections::VecDeque;
+
+use crate::error::{PhonetisaurusError, Result};
+use crate::options::PhonemizeOptions;
+
+/// Prune weight applied when a `timeout` is given without any of the pruning options, as
+/// determinization cannot be interrupted and is only bounded by the size of the lattice.
+/// Paths this much worse than the best one are less likely by a factor of about 10⁻⁹.
+pub(crate) const TIMEOUT_PRUNE_WEIGHT: f32 = 20.0;
+
+/// Prune the composed lattice of a word according to the `beam`, `max_states` and
+/// `prune_weight` options, or with [`TIMEOUT_PRUNE_WEIGHT`] if only `timeout` is set.
+///
+/// States are ranked by the weight of the best path through them. The states of one best
+/// path of the lattice are always kept, so pruning never makes a word fail.
+pub(crate) fn prune_lattice(fst: &mut VectorFst<TropicalWeight>, options: &PhonemizeOptions) -> Result<()> {
+    let prune_weight = match options.beam.is_none() && options.max_states.is_none() {
+        true => options.prune_weight.or(options.timeout.map(|_| TIMEOUT_PRUNE_WEIGHT)),
+        false => options.prune_weight,
+    };
+    if options.beam.is_none() && options.max_states.is_none() && prune_weight.is_none() {
+        return Ok(());
+    }
+    let Some(start) = fst.start() else {
+        return Ok(());
+    };
+    let alpha = shortest_distance(fst, false).map_err(PhonetisaurusError::FstError)?;
+    let beta = shortest_distance(fst, true).map_err(PhonetisaurusError::FstError)?;
+    let distance = |d: &[TropicalWeight], state: StateId| d.get(state as usize).map_or(f32::INFINITY, |w| *w.value());
+    let best = distance(&beta, start);
+    if !best.is_finite() {
+        return Ok(());
+    }
+
+    let num_states = fst.num_states();
+    // weight of the best path through each state
+    let cost: Vec<f32> = (0..num_states as StateId)
+        .map(|state| distance(&alpha, state) + distance(&beta, state))
+        .collect();
+    let mut keep: Vec<bool> = cost.iter().map(|c| c.is_finite()).collect();
+
+    if let Some(threshold) = prune_weight {
+        for (state, c) in cost.iter().enumerate() {
+            keep[state] &= at_most(*c, best + threshold);
+        }
+    }
+    if let Some(beam) = options.beam {
+        let positions = input_positions(fst, start)?;
+        let mut by_position: Vec<Vec<usize>> = Vec::new();
+        for (state, position) in positions.into_iter().enumerate() {
+            if let Some(position) = position {
+                if by_position.len() <= position {
+                    by_position.resize(position + 1, Vec::new());
+                }
+                by_position[position].push(state);
+            }
+        }
+        for states in &mut by_position {
+            states.sort_by(|&a, &b| cost[a].total_cmp(&cost[b]));
+            for &state in states.iter().skip(beam) {
+                keep[state] = false;
+            }
+        }
+    }
+    if let Some(max_states) = options.max_states {
+        let mut states: Vec<usize> = (0..num_states).filter(|&state| keep[state]).collect();
+        states.sort_by(|&a, &b| cost[a].total_cmp(&cost[b]));
+        for &state in states.iter().skip(max_states) {
+            keep[state] = false;
+        }
+    }
+    for state in best_path_states(fst, start, &beta)? {
+        keep[state as usize] = true;
+    }
+
+    for state in 0..num_states as StateId {
+        let trs = fst.pop_trs(state).map_err(PhonetisaurusError::FstError)?;
+        if !keep[state as usize] {
+            fst.delete_final_weight(state).map_err(PhonetisaurusError::FstError)?;
+            continue;
+        }
+        for tr in trs {
+            let through = distance(&alpha, state) + *tr.weight.value() + distance(&beta, tr.nextstate);
+            let within_threshold = prune_weight.is_none_or(|threshold| at_most(through, best + threshold));
+            if keep[tr.nextstate as usize] && within_threshold {
+                fst.add_tr(state, tr).map_err(PhonetisaurusError::FstError)?;
+            }
+        }
+    }
+    connect(fst).map_err(PhonetisaurusError::FstError)
+}
+
+/// Number of input symbols read before each state, `None` for unreachable states.
+///
+/// The lattice is the composition with a linear acceptor, so all paths to a state have
+/// read the same number of symbols.
+pub(crate) fn input_positions(fst: &VectorFst<TropicalWeight>, start: StateId) -> Result<Vec<Option<usize>>> {
+    let mut positions = vec![None; fst.num_states()];
+    positions[start as usize] = Some(0);
+    let mut queue = VecDeque::from([start]);
+    while let Some(state) = queue.pop_front() {
+        let position = positions[state as usize].unwrap_or_default();
+        for tr in fst.get_trs(state).map_err(PhonetisaurusError::FstError)?.trs() {
+            if positions[tr.nextstate as usize].is_none() {
+                let advance = usize::from(tr.ilabel != EPS_LABEL);
+                positions[tr.nextstate as usize] = Some(position + advance);
+                queue.push_back(tr.nextstate);
+            }
+        }
+    }
+    Ok(positions)
+}
+
+/// `a <= b`, tolerating the rounding errors of summing weights along different paths.
+fn at_most(a: f32, b: f32) -> bool {
+    a <= b + 1e-4 * b.abs().max(1.0)
+}
+
+/// Whether a transition lies on a best path from its source state to a final state.
+fn is_best(beta: &[TropicalWeight], state: StateId, tr: &Tr<TropicalWeight>) -> bool {
+    let (Some(from), Some(to)) = (beta.get(state as usize), beta.get(tr.nextstate as usize)) else {
+        return false;
+    };
+    at_most(tr.weight.value() + to.value(), *from.value())
+}
+
+/// States of one best path from `start` to a final state.
+fn best_path_states(fst: &VectorFst<TropicalWeight>, start: StateId, beta: &[TropicalWeight]) -> Result<Vec<StateId>> {
+    let mut states = vec![start];
+    let mut state = start;
+    // a best path visits every state at most once
+    while states.len() <= fst.num_states() {
+        let final_weight = fst.final_weight(state).map_err(PhonetisaurusError::FstError)?;
+        if let (Some(final_weight), Some(remaining)) = (final_weight, beta.get(state as usize))
+            && at_most(*final_weight.value(), *remaining.value())
+        {
+            break;
+        }
+        let trs = fst.get_trs(state).map_err(PhonetisaurusError::FstError)?;
+        let Some(tr) = trs.trs().iter().find(|tr| is_best(beta, state, tr)) else {
+            break;
+        };
+        state = tr.nextstate;
+        states.push(state);
+    }
+    Ok(states)
+}
@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::error::{PhonetisaurusError, Result};
+use crate::options::PhonemizeOptions;
+use crate::phonetisaurus::{PhonetisaurusModel, PhonetizationResult};
+use crate::script::Script;
+
+#[derive(Clone, Debug, Default)]
+/// Models for several languages, keyed by language tag, so that mixed-language input can be
+/// phonemized through one object.
+///
+/// Tags are compared case-insensitively, with `_` and `-` treated alike. A tag without a
+/// model of its own falls back to its shorter prefixes, so `de-AT` uses the `de` model unless
+/// a `de-AT` model was added. Words without a language tag can be routed with
+/// [`Self::detect_language`]. Models are cheap to clone, so the registry is as well.
+pub struct ModelRegistry {
+    models: BTreeMap<String, Entry>,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    model: PhonetisaurusModel,
+    /// Scripts of the graphemes the model accepts, for language detection.
+    scripts: Vec<Script>,
+}
+
+impl Entry {
+    fn new(model: PhonetisaurusModel) -> Self {
+        let mut scripts: Vec<Script> = model
+            .input_symbols()
+            .iter()
+            .flat_map(|symbol| symbol.chars())
+            .filter_map(Script::of)
+            .collect();
+        scripts.sort_unstable();
+        scripts.dedup();
+        Entry { model, scripts }
+    }
+}
+
+impl ModelRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the model of a language, returning the model it replaces.
+    pub fn insert(&mut self, lang: &str, model: PhonetisaurusModel) -> Option<PhonetisaurusModel> {
+        self.models.insert(normalize_tag(lang), Entry::new(model)).map(|entry| entry.model)
+    }
+
+    /// Load the model of a language from a file, replacing any previous one.
+    pub fn load(&mut self, lang: &str, model_path: impl AsRef<Path>) -> Result<()> {
+        let model = PhonetisaurusModel::try_from(model_path.as_ref())?;
+        self.insert(lang, model);
+        Ok(())
+    }
+
+    /// Remove the model of exactly this language.
+    pub fn remove(&mut self, lang: &str) -> Option<PhonetisaurusModel> {
+        self.models.remove(&normalize_tag(lang)).map(|entry| entry.model)
+    }
+
+    /// The tag of the model used for a language, which is the tag itself or the longest of
+    /// its prefixes that has a model.
+    pub fn resolve(&self, lang: &str) -> Option<&str> {
+        let mut tag = normalize_tag(lang);
+        loop {
+            if let Some((key, _)) = self.models.get_key_value(&tag) {
+                return Some(key);
+            }
+            let end = tag.rfind('-')?;
+            tag.truncate(end);
+        }
+    }
+
+    /// The model used for a language, see [`Self::resolve`].
+    pub fn get(&self, lang: &str) -> Option<&PhonetisaurusModel> {
+        self.resolve(lang).map(|tag| &self.models[tag].model)
+    }
+
+    /// Like [`Self::get`], but failing with [`PhonetisaurusError::UnknownLanguage`].
+    pub fn model(&self, lang: &str) -> Result<&PhonetisaurusModel> {
+        self.get(lang).ok_or_else(|| PhonetisaurusError::UnknownLanguage(lang.to_string()))
+    }
+
+    /// The tags of the languages with a model, normalized and in ascending order.
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.models.keys().map(String::as_str)
+    }
+
+    /// The models with their normalized tags, in ascending order of the tags.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PhonetisaurusModel)> {
+        self.models.iter().map(|(lang, entry)| (lang.as_str(), &entry.model))
+    }
+
+    /// Number of languages with a model.
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    /// Whether no model was added.
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    /// Phonemize a word with the model of a language.
+    pub fn phonemize(&self, lang: &str, word: &str) -> Result<PhonetizationResult> {
+        self.model(lang)?.phonemize_word(word)
+    }
+
+    /// Phonemize a word with the model of a language, using the given options.
+    pub fn phonemize_with_options(
+        &self,
+        lang: &str,
+        word: &str,
+        options: &PhonemizeOptions,
+    ) -> Result<PhonetizationResult> {
+        self.model(lang)?.phonemize_word_with_options(word, options)
+    }
+
+    /// Phonemize a word with up to `n` distinct pronunciations, with the model of a language.
+    pub fn phonemize_nbest_with_options(
+        &self,
+        lang: &str,
+        word: &str,
+        n: usize,
+        options: &PhonemizeOptions,
+    ) -> Result<Vec<PhonetizationResult>> {
+        self.model(lang)?.phonemize_word_nbest_with_options(word, n, options)
+    }
+
+    /// Guess the language of a word without a tag, see [`Self::detect_language_with_options`].
+    pub fn detect_language(&self, word: &str) -> Option<&str> {
+        self.detect_language_with_options(word, &PhonemizeOptions::default())
+    }
+
+    /// Guess the language of a word without a tag, returning the tag of the model that
+    /// handles it best, or `None` if the registry is empty.
+    ///
+    /// Models accepting graphemes of the script most letters of the word are written in are
+    /// preferred. Among them, the models whose input symbols cover the most characters of the
+    /// word, after the case folding, normalization and segmentation of the options, are kept.
+    /// If several remain, the word is decoded with each of them and the model giving the best
+    /// score wins, so ties cost one decoding per model. Equal scores go to the first tag in
+    /// ascending order.
+    pub fn detect_language_with_options(&self, word: &str, options: &PhonemizeOptions) -> Option<&str> {
+        let script = Script::dominant(word);
+        let mut candidates: Vec<(&str, &PhonetisaurusModel)> = self
+            .models
+            .iter()
+            .filter(|(_, entry)| script.is_none_or(|script| entry.scripts.contains(&script)))
+            .map(|(lang, entry)| (lang.as_str(), &entry.model))
+            .collect();
+        if candidates.is_empty() {
+            candidates = self.iter().collect();
+        }
+
+        let missing: Vec<usize> = candidates
+            .iter()
+            .map(|(_, model)| model.check_word_with_options(word, options).map_or_else(|missing| missing.len(), |()| 0))
+            .collect();
+        let fewest_missing = missing.iter().copied().min()?;
+        let candidates: Vec<_> = candidates
+            .into_iter()
+            .zip(missing)
+            .filter(|&(_, missing)| missing == fewest_missing)
+            .map(|(candidate, _)| candidate)
+            .collect();
+        if let [(lang, _)] = candidates.as_slice() {
+            return Some(lang);
+        }
+
+        // failed decodings rank last, and min_by keeps the first of equal scores
+        let score = |model: &PhonetisaurusModel| {
+            model.phonemize_word_with_options(word, options).map_or(f32::INFINITY, |result| result.neg_log_score)
+        };
+        candidates
+            .into_iter()
+            .map(|(lang, model)| (lang, score(model)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(lang, _)| lang)
+    }
+
+    /// Phonemize a word with the model of its detected language, see
+    /// [`Self::detect_language_with_options`], returning the language tag with the results.
+    ///
+    /// Fails with [`PhonetisaurusError::UnknownLanguage`] with an empty tag if the registry
+    /// is empty.
+    pub fn phonemize_detected(
+        &self,
+        word: &str,
+        n: usize,
+        options: &PhonemizeOptions,
+    ) -> Result<(&str, Vec<PhonetizationResult>)> {
+        let lang = self
+            .detect_language_with_options(word, options)
+            .ok_or_else(|| PhonetisaurusError::UnknownLanguage(String::new()))?;
+        let results = self.models[lang].model.phonemize_word_nbest_with_options(word, n, options)?;
+        Ok((lang, results))
+    }
+
+    /// Phonemize many words of one language in parallel, one result per word in input order.
+    pub fn phonemize_words(&self, lang: &str, words: &[&str]) -> Result<Vec<Result<PhonetizationResult>>> {
+        Ok(self.model(lang)?.phonemize_words(words))
+    }
+}
+
+impl FromIterator<(String, PhonetisaurusModel)> for ModelRegistry {
+    fn from_iter<I: IntoIterator<Item = (String, PhonetisaurusModel)>>(iter: I) -> Self {
+        let mut registry = ModelRegistry::new();
+        for (lang, model) in iter {
+            registry.insert(&lang, model);
+        }
+        registry
+    }
+}
+
+/// Lowercase a language tag and use `-` between its subtags.
+fn normalize_tag(lang: &str) -> String {
+    lang.trim().to_ascii_lowercase().replace('_', "-")
+}
@@ -0,0 +1,57 @@
+/// Writing systems told apart by [`ModelRegistry::detect_language`](crate::ModelRegistry::detect_language).
+///
+/// Only the scripts of languages commonly phonemized are distinguished; characters of other
+/// scripts, digits, punctuation and combining marks have no script.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Bengali,
+    Thai,
+    Georgian,
+    Hangul,
+    Kana,
+    Han,
+}
+
+impl Script {
+    /// The script of a character, if it is a letter of one of the known scripts.
+    pub(crate) fn of(c: char) -> Option<Script> {
+        let script = match c as u32 {
+            0x41..=0x5A | 0x61..=0x7A | 0xAA | 0xBA | 0xC0..=0xD6 | 0xD8..=0xF6 | 0xF8..=0x24F => Script::Latin,
+            0x1E00..=0x1EFF | 0x2C60..=0x2C7F | 0xA720..=0xA7FF | 0xFF21..=0xFF3A | 0xFF41..=0xFF5A => Script::Latin,
+            0x370..=0x3FF | 0x1F00..=0x1FFF => Script::Greek,
+            0x400..=0x52F | 0x1C80..=0x1C8F | 0x2DE0..=0x2DFF | 0xA640..=0xA69F => Script::Cyrillic,
+            0x530..=0x58F => Script::Armenian,
+            0x590..=0x5FF | 0xFB1D..=0xFB4F => Script::Hebrew,
+            0x600..=0x6FF | 0x750..=0x77F | 0x8A0..=0x8FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => Script::Arabic,
+            0x900..=0x97F => Script::Devanagari,
+            0x980..=0x9FF => Script::Bengali,
+            0xE00..=0xE7F => Script::Thai,
+            0x10A0..=0x10FF | 0x1C90..=0x1CBF => Script::Georgian,
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+            0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => Script::Kana,
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FA1F => Script::Han,
+            _ => return None,
+        };
+        Some(script)
+    }
+
+    /// The script most letters of a word are written in, the first of them on a tie.
+    pub(crate) fn dominant(word: &str) -> Option<Script> {
+        let mut counts: Vec<(Script, usize)> = Vec::new();
+        for script in word.chars().filter_map(Script::of) {
+            match counts.iter_mut().find(|(seen, _)| *seen == script) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((script, 1)),
+            }
+        }
+        // max_by_key returns the last maximum, so search from the back to prefer the first
+        counts.into_iter().rev().max_by_key(|&(_, count)| count).map(|(script, _)| script)
+    }
+}
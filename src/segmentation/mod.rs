@@ -0,0 +1,181 @@
+use rustfst::prelude::*;
+use std::collections::HashMap;
+use std::ops::Range;
+
+mod tables;
+use tables::{CONTROL, EXTEND};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// How a word is split into input symbols before it is looked up in the model.
+pub enum Segmentation {
+    /// One symbol per `char`, as Phonetisaurus does.
+    #[default]
+    Chars,
+    /// One symbol per extended grapheme cluster, for models trained on multi-codepoint
+    /// graphemes such as Devanagari consonants with vowel signs, Hangul jamo sequences or
+    /// letters with combining diacritics. A cluster missing from the input symbol table is
+    /// looked up `char` by `char` instead.
+    GraphemeClusters,
+    /// Greedy longest match against the multi-char input symbols of the model, such as "sch"
+    /// or Phonetisaurus clusters like "c|h", falling back to single chars.
+    LongestMatch,
+}
+
+impl Segmentation {
+    /// Split the chars of a word into segments, given as char position ranges.
+    pub(crate) fn split(&self, chars: &[char], clusters: &SymbolClusters) -> Vec<Range<usize>> {
+        match self {
+            Segmentation::Chars => (0..chars.len()).map(|i| i..i + 1).collect(),
+            Segmentation::GraphemeClusters => grapheme_clusters(chars),
+            Segmentation::LongestMatch => clusters.longest_match(chars),
+        }
+    }
+}
+
+/// The multi-char input symbols of a model, keyed by their text with the Phonetisaurus
+/// cluster separator "|" removed.
+#[derive(Debug, Default)]
+pub(crate) struct SymbolClusters {
+    labels: HashMap<String, Label>,
+    /// Length of the longest cluster in chars.
+    max_len: usize,
+}
+
+impl SymbolClusters {
+    pub(crate) fn new(symbols: Option<&SymbolTable>) -> Self {
+        let mut clusters = SymbolClusters::default();
+        for (label, symbol) in symbols.into_iter().flat_map(|symbols| symbols.iter()) {
+            if label == EPS_LABEL {
+                continue;
+            }
+            let text: String = symbol.chars().filter(|&ch| ch != '|').collect();
+            let len = text.chars().count();
+            if len > 1 {
+                clusters.max_len = clusters.max_len.max(len);
+                clusters.labels.entry(text).or_insert(label);
+            }
+        }
+        clusters
+    }
+
+    /// Label of the cluster spelled by `text`.
+    pub(crate) fn get(&self, text: &str) -> Option<Label> {
+        self.labels.get(text).copied()
+    }
+
+    fn longest_match(&self, chars: &[char]) -> Vec<Range<usize>> {
+        let mut segments = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let longest = (2..=self.max_len.min(chars.len() - start))
+                .rev()
+                .find(|&len| {
+                    let text: String = chars[start..start + len].iter().collect();
+                    self.labels.contains_key(&text)
+                })
+                .unwrap_or(1);
+            segments.push(start..start + longest);
+            start += longest;
+        }
+        segments
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Break {
+    Cr,
+    Lf,
+    Control,
+    Extend,
+    Zwj,
+    RegionalIndicator,
+    L,
+    V,
+    T,
+    Lv,
+    Lvt,
+    Other,
+}
+
+const ZWJ: char = '\u{200d}';
+
+// Hangul syllables and jamo, see the Unicode standard, section 3.12.
+const S_BASE: u32 = 0xAC00;
+const S_COUNT: u32 = 11172;
+const T_COUNT: u32 = 28;
+
+fn in_ranges(ch: char, ranges: &[(char, char)]) -> bool {
+    ranges
+        .binary_search_by(|&(first, last)| {
+            if last < ch {
+                std::cmp::Ordering::Less
+            } else if first > ch {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+fn break_property(ch: char) -> Break {
+    let cp = ch as u32;
+    match ch {
+        '\r' => Break::Cr,
+        '\n' => Break::Lf,
+        ZWJ => Break::Zwj,
+        '\u{1f1e6}'..='\u{1f1ff}' => Break::RegionalIndicator,
+        '\u{1100}'..='\u{115f}' | '\u{a960}'..='\u{a97c}' => Break::L,
+        '\u{1160}'..='\u{11a7}' | '\u{d7b0}'..='\u{d7c6}' => Break::V,
+        '\u{11a8}'..='\u{11ff}' | '\u{d7cb}'..='\u{d7fb}' => Break::T,
+        _ if (S_BASE..S_BASE + S_COUNT).contains(&cp) => {
+            if (cp - S_BASE).is_multiple_of(T_COUNT) {
+                Break::Lv
+            } else {
+                Break::Lvt
+            }
+        }
+        _ if in_ranges(ch, EXTEND) => Break::Extend,
+        _ if in_ranges(ch, CONTROL) => Break::Control,
+        _ => Break::Other,
+    }
+}
+
+/// Whether there is a cluster boundary between two chars, following the rules of
+/// Unicode Standard Annex #29. `ri_run` is the number of regional indicators directly
+/// preceding the boundary.
+///
+/// Extended_Pictographic is not part of the Unicode Character Database shipped with the
+/// table generator, so any char following a zero width joiner is kept in the cluster
+/// (rule GB11), and prepended concatenation marks start a cluster of their own (rule GB9b).
+fn is_boundary(before: Break, after: Break, ri_run: usize) -> bool {
+    use Break::*;
+    match (before, after) {
+        (Cr, Lf) => false,
+        (Cr | Lf | Control, _) | (_, Cr | Lf | Control) => true,
+        (L, L | V | Lv | Lvt) => false,
+        (Lv | V, V | T) => false,
+        (Lvt | T, T) => false,
+        (_, Extend | Zwj) => false,
+        (Zwj, _) => false,
+        (RegionalIndicator, RegionalIndicator) => ri_run.is_multiple_of(2),
+        _ => true,
+    }
+}
+
+/// Split chars into extended grapheme clusters, given as char position ranges.
+fn grapheme_clusters(chars: &[char]) -> Vec<Range<usize>> {
+    let mut clusters = Vec::new();
+    let mut start = 0;
+    let mut ri_run = 0;
+    for position in 1..=chars.len() {
+        let before = break_property(chars[position - 1]);
+        ri_run = if before == Break::RegionalIndicator { ri_run + 1 } else { 0 };
+        if position == chars.len() || is_boundary(before, break_property(chars[position]), ri_run) {
+            clusters.push(start..position);
+            start = position;
+        }
+    }
+    clusters
+}
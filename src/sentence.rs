@@ -0,0 +1,174 @@
+use crate::error::Result;
+use crate::options::PhonemizeOptions;
+use crate::phonetisaurus::{PhonetisaurusModel, PhonetizationResult};
+use crate::verbalize::Verbalizer;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Splits text into the words to phonemize.
+pub trait Tokenizer: Send + Sync {
+    /// Byte ranges of the words in `text`, in text order.
+    fn tokenize(&self, text: &str) -> Vec<Range<usize>>;
+}
+
+impl<F> Tokenizer for F
+where
+    F: Fn(&str) -> Vec<Range<usize>> + Send + Sync,
+{
+    fn tokenize(&self, text: &str) -> Vec<Range<usize>> {
+        self(text)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+/// Words are runs of letters and digits, which may contain apostrophes and hyphens
+/// between them ("don't", "state-of-the-art"). Everything else is punctuation and ignored.
+pub struct WordTokenizer;
+
+impl Tokenizer for WordTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<Range<usize>> {
+        let mut words = Vec::new();
+        let mut start: Option<usize> = None;
+        let mut chars = text.char_indices().peekable();
+
+        while let Some((i, ch)) = chars.next() {
+            let next_is_alphanumeric = chars.peek().is_some_and(|&(_, next)| next.is_alphanumeric());
+            let is_word_char = ch.is_alphanumeric()
+                || (start.is_some() && matches!(ch, '\'' | '’' | '-') && next_is_alphanumeric);
+            match (is_word_char, start) {
+                (true, None) => start = Some(i),
+                (false, Some(s)) => {
+                    words.push(s..i);
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(s) = start {
+            words.push(s..text.len());
+        }
+
+        words
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+/// Words are separated by whitespace and kept as they are, punctuation included.
+pub struct WhitespaceTokenizer;
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<Range<usize>> {
+        text.split_whitespace()
+            .map(|word| {
+                let start = word.as_ptr() as usize - text.as_ptr() as usize;
+                start..start + word.len()
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+/// Options controlling how a sentence is split and phonemized.
+pub struct SentenceOptions {
+    /// Splits the sentence into words.
+    pub tokenizer: Arc<dyn Tokenizer>,
+    /// Expands tokens like numerals into words before they are phonemized, e.g.
+    /// [`EnglishNumbers`](crate::EnglishNumbers).
+    pub verbalizer: Option<Arc<dyn Verbalizer>>,
+    /// Symbol placed between the phonemes of consecutive words in the joined sequence.
+    pub word_boundary: String,
+    /// Options for phonemizing each word.
+    pub phonemize: PhonemizeOptions,
+}
+
+impl Default for SentenceOptions {
+    fn default() -> Self {
+        SentenceOptions {
+            tokenizer: Arc::new(WordTokenizer),
+            verbalizer: None,
+            word_boundary: "#".to_string(),
+            phonemize: PhonemizeOptions::default(),
+        }
+    }
+}
+
+impl fmt::Debug for SentenceOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SentenceOptions")
+            .field("verbalizer", &self.verbalizer.is_some())
+            .field("word_boundary", &self.word_boundary)
+            .field("phonemize", &self.phonemize)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A word of a sentence and its phonemization.
+pub struct SentenceWord {
+    /// The word as it appears in the sentence, or one of the words a verbalized token was
+    /// expanded into.
+    pub word: String,
+    /// Byte range of the word in the sentence, that of the whole token for all words a
+    /// verbalized token was expanded into.
+    pub span: Range<usize>,
+    /// Phonemization of the word.
+    pub result: PhonetizationResult,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Result of phonemizing a sentence.
+pub struct SentenceResult {
+    /// The words of the sentence, in order.
+    pub words: Vec<SentenceWord>,
+    /// Phonemes of all words, with the word boundary symbol between words.
+    pub phonemes: String,
+}
+
+impl PhonetisaurusModel {
+    /// Split a sentence into words and phonemize each of them.
+    pub fn phonemize_sentence(&self, text: &str) -> Result<SentenceResult> {
+        self.phonemize_sentence_with_options(text, &SentenceOptions::default())
+    }
+
+    /// Split a sentence into words and phonemize each of them, using the given options.
+    ///
+    /// Fails if any word fails; use [`UnknownCharPolicy`](crate::UnknownCharPolicy) to make
+    /// phonemization tolerate unexpected characters.
+    pub fn phonemize_sentence_with_options(
+        &self,
+        text: &str,
+        options: &SentenceOptions,
+    ) -> Result<SentenceResult> {
+        let words = options
+            .tokenizer
+            .tokenize(text)
+            .into_iter()
+            .flat_map(|span| {
+                let token = &text[span.clone()];
+                let words = match options.verbalizer.as_ref().and_then(|verbalizer| verbalizer.verbalize(token)) {
+                    Some(words) => words,
+                    None => vec![token.to_string()],
+                };
+                words.into_iter().map(move |word| (word, span.clone()))
+            })
+            .map(|(word, span)| {
+                Ok(SentenceWord {
+                    result: self.phonemize_word_with_options(&word, &options.phonemize)?,
+                    word,
+                    span,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let phonemes = words
+            .iter()
+            .map(|word| word.result.phonemes.as_str())
+            .filter(|phonemes| !phonemes.is_empty())
+            .collect::<Vec<_>>()
+            .join(&format!(" {} ", options.word_boundary));
+
+        Ok(SentenceResult { words, phonemes })
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::ops::Range;
+
+use rustfst::prelude::*;
+
+use crate::alignment::align;
+use crate::error::{PhonetisaurusError, Result, SymbolTableKind};
+use crate::options::{PhonemizeOptions, UnknownCharPolicy};
+use crate::phonetisaurus::{PhonetisaurusModel, PhonetizationResult, finish_results, fixed_results};
+
+#[derive(Clone, Copy, Debug)]
+/// Best way found to reach a model state after a number of input labels.
+struct Token {
+    cost: f32,
+    /// The previous state and the labels of the transition from it, which read nothing if
+    /// the previous state is in the same layer.
+    back: Option<(StateId, Label, Label)>,
+}
+
+/// Model states reached after reading a number of input labels.
+type Layer = HashMap<StateId, Token>;
+
+#[derive(Clone, Debug)]
+/// Phonemizes a word while it is typed, for live previews.
+///
+/// Chars are appended with [`Self::push_str`] and removed with [`Self::pop`]. The session
+/// keeps the best way to reach every model state after each char, so that a char only costs
+/// one step of the search instead of decoding the whole word again. [`Self::best`] is the
+/// best pronunciation of the word so far, the same as [`PhonetisaurusModel::phonemize_word`]
+/// once the word is complete.
+///
+/// The word is read char by char like with [`Segmentation::Chars`](crate::Segmentation::Chars).
+/// Case folding and normalization are applied to each pushed text on its own, hyphens and
+/// apostrophes are looked up like letters, and [`PhonemizeOptions::beam`] keeps that many
+/// states per char. The other limits and pruning options are ignored.
+pub struct PhonemizerSession {
+    model: PhonetisaurusModel,
+    options: PhonemizeOptions,
+    /// The word so far, case folded and normalized.
+    word: Vec<char>,
+    /// Whether each char of the word was read as an input label, i.e. not skipped.
+    read: Vec<bool>,
+    /// `layers[i]` holds the states reached after `i` input labels.
+    layers: Vec<Layer>,
+    /// Char positions of each input label.
+    spans: Vec<Range<usize>>,
+}
+
+impl PhonemizerSession {
+    pub(crate) fn new(model: PhonetisaurusModel, options: PhonemizeOptions) -> Result<Self> {
+        let start = model.trained_fst().start().ok_or(PhonetisaurusError::NoPathFound)?;
+        let mut session = PhonemizerSession {
+            model,
+            options,
+            word: Vec::new(),
+            read: Vec::new(),
+            layers: Vec::new(),
+            spans: Vec::new(),
+        };
+        let mut layer = Layer::from([(start, Token { cost: 0.0, back: None })]);
+        session.close(&mut layer)?;
+        session.layers.push(layer);
+        Ok(session)
+    }
+
+    /// The word so far, after case folding and normalization.
+    pub fn word(&self) -> String {
+        self.word.iter().collect()
+    }
+
+    /// Append text to the word and return the best pronunciation of the extended word.
+    ///
+    /// If a char is not in the input symbol table and the unknown char policy is
+    /// [`UnknownCharPolicy::Error`], nothing is appended. A word the model cannot read any
+    /// further fails with [`PhonetisaurusError::NoPathFound`] until chars are popped again.
+    pub fn push_str(&mut self, text: &str) -> Result<PhonetizationResult> {
+        let folded_text = self.options.case_fold.apply(text);
+        let text = self.options.normalize.apply(&folded_text);
+        let isyms = self
+            .model
+            .trained_fst()
+            .input_symbols()
+            .ok_or(PhonetisaurusError::MissingSymbolTable(SymbolTableKind::Input))?;
+
+        // look up all chars first, so that an unknown one leaves the session unchanged
+        let mut labels = Vec::new();
+        for (offset, ch) in text.chars().enumerate() {
+            let position = self.word.len() + offset;
+            let label = match (isyms.get_label(ch.to_string()), self.options.unknown_char_policy) {
+                (Some(label), _) => Some(label),
+                (None, UnknownCharPolicy::Error) => return Err(PhonetisaurusError::UnknownSymbol { ch, position }),
+                (None, UnknownCharPolicy::Skip) => None,
+                (None, UnknownCharPolicy::Substitute(substitute)) => Some(
+                    isyms
+                        .get_label(substitute.to_string())
+                        .ok_or(PhonetisaurusError::UnknownSymbol { ch, position })?,
+                ),
+            };
+            labels.push((ch, label));
+        }
+
+        for (ch, label) in labels {
+            if let Some(label) = label {
+                let layer = self.step(label)?;
+                self.layers.push(layer);
+                self.spans.push(self.word.len()..self.word.len() + 1);
+            }
+            self.word.push(ch);
+            self.read.push(label.is_some());
+        }
+        self.best()
+    }
+
+    /// Remove the last char of the word, returning it.
+    pub fn pop(&mut self) -> Option<char> {
+        let ch = self.word.pop()?;
+        if self.read.pop() == Some(true) {
+            self.layers.pop();
+            self.spans.pop();
+        }
+        Some(ch)
+    }
+
+    /// Remove all chars of the word.
+    pub fn clear(&mut self) {
+        self.word.clear();
+        self.read.clear();
+        self.layers.truncate(1);
+        self.spans.clear();
+    }
+
+    /// The best pronunciation of the word so far.
+    ///
+    /// Words in the exception lexicon are answered from it. Otherwise the pronunciation of the
+    /// best complete path is returned, or, while no path of the model ends after the word
+    /// read so far, that of the best path through the word as a preview.
+    pub fn best(&self) -> Result<PhonetizationResult> {
+        let word = self.word();
+        if let Some(pronunciations) = self.model.lexicon().and_then(|lexicon| lexicon.get(&word)) {
+            return fixed_results(pronunciations, &word, 1, &self.options)
+                .into_iter()
+                .next()
+                .ok_or(PhonetisaurusError::NoPathFound);
+        }
+
+        let last = self.layers.len() - 1;
+        let mut best_final: Option<(StateId, f32)> = None;
+        let mut best_prefix: Option<(StateId, f32)> = None;
+        for (&state, token) in &self.layers[last] {
+            // ties go to the lower state, not to the first one the hash map yields
+            let better = |best: Option<(StateId, f32)>, cost: f32| {
+                best.is_none_or(|(best_state, best)| cost < best || (cost == best && state < best_state))
+            };
+            if let Some(final_weight) = self.model.trained_fst().final_weight(state)? {
+                let cost = token.cost + *final_weight.value();
+                if better(best_final, cost) {
+                    best_final = Some((state, cost));
+                }
+            }
+            if better(best_prefix, token.cost) {
+                best_prefix = Some((state, token.cost));
+            }
+        }
+        let (mut state, neg_log_score) = best_final.or(best_prefix).ok_or(PhonetisaurusError::NoPathFound)?;
+
+        // follow the back pointers to the start
+        let mut arcs = Vec::new();
+        let mut layer = last;
+        while let Some((previous, ilabel, olabel)) = self.layers[layer][&state].back {
+            arcs.push((ilabel, olabel));
+            if ilabel != EPS_LABEL {
+                layer -= 1;
+            }
+            state = previous;
+        }
+        arcs.reverse();
+
+        let separators = &self.options.separators;
+        let alignment = align(&arcs, &self.spans, self.model.output_symbol_table()?, separators)?;
+        let phoneme_tokens: Vec<String> = alignment
+            .iter()
+            .flat_map(|segment| segment.phonemes.iter().cloned())
+            .collect();
+        let mut results = vec![PhonetizationResult {
+            phonemes: separators.join(&phoneme_tokens),
+            phoneme_tokens,
+            neg_log_score,
+            posterior: 1.0,
+            normalized_score: neg_log_score,
+            alignment,
+            syllables: Vec::new(),
+            stress: Vec::new(),
+            phoneme_posteriors: Vec::new(),
+            diagnostics: None,
+        }];
+        finish_results(&mut results, &word, &self.options);
+        results.pop().ok_or(PhonetisaurusError::NoPathFound)
+    }
+
+    /// The layer of states reached from the last one by reading `ilabel`.
+    fn step(&self, ilabel: Label) -> Result<Layer> {
+        let fst = self.model.trained_fst();
+        let mut layer = Layer::new();
+        for (&state, token) in self.layers.last().into_iter().flatten() {
+            for tr in fst.trs_reading(state, ilabel)? {
+                relax(&mut layer, tr.nextstate, token.cost + *tr.weight.value(), (state, tr.ilabel, tr.olabel));
+            }
+        }
+        self.close(&mut layer)?;
+
+        if let Some(beam) = self.options.beam
+            && layer.len() > beam
+        {
+            let mut costs: Vec<f32> = layer.values().map(|token| token.cost).collect();
+            costs.select_nth_unstable_by(beam - 1, f32::total_cmp);
+            let threshold = costs[beam - 1];
+            layer.retain(|_, token| token.cost <= threshold);
+        }
+        Ok(layer)
+    }
+
+    /// Add the states reached from those of a layer by transitions reading nothing, like the
+    /// back-off transitions of the model.
+    fn close(&self, layer: &mut Layer) -> Result<()> {
+        let fst = self.model.trained_fst();
+        let mut queue: Vec<StateId> = layer.keys().copied().collect();
+        while let Some(state) = queue.pop() {
+            let cost = layer[&state].cost;
+            for tr in fst.trs_reading(state, EPS_LABEL)? {
+                if relax(layer, tr.nextstate, cost + *tr.weight.value(), (state, tr.ilabel, tr.olabel)) {
+                    queue.push(tr.nextstate);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reach a state with the given cost if that is better than the known way, returning whether
+/// it was. Of two ways with the same cost, the one from the lower state is kept, so that the
+/// order the states are visited in does not matter.
+fn relax(layer: &mut Layer, state: StateId, cost: f32, back: (StateId, Label, Label)) -> bool {
+    let token = Token { cost, back: Some(back) };
+    match layer.entry(state) {
+        Entry::Occupied(mut entry)
+            if cost < entry.get().cost || (cost == entry.get().cost && Some(back) < entry.get().back) =>
+        {
+            entry.insert(token);
+            true
+        }
+        Entry::Occupied(_) => false,
+        Entry::Vacant(entry) => {
+            entry.insert(token);
+            true
+        }
+    }
+}
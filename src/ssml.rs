@@ -0,0 +1,128 @@
+use crate::error::Result;
+use crate::phoneme_map::{PhonemeAlphabet, PhonemeMapper};
+use crate::phonetisaurus::{PhonetisaurusModel, PhonetizationResult};
+use crate::sentence::{SentenceOptions, SentenceResult};
+use crate::stress::StressConvention;
+
+/// Writes pronunciations as SSML `<phoneme>` tags, like
+/// `<phoneme alphabet="ipa" ph="həˈloʊ">hello</phoneme>`, for TTS engines that take SSML.
+///
+/// The phonemes are converted with an IPA table, joined without separator, and stressed ones
+/// are preceded by `ˈ` or `ˌ`. With [`Self::with_alphabet`]`("x-sampa")` and an X-SAMPA table,
+/// stress is written as `"` and `%` instead.
+#[derive(Clone, Debug)]
+pub struct SsmlPhonemes {
+    mapper: PhonemeMapper,
+    /// How the model marks stress, `None` if it does not.
+    stress: Option<StressConvention>,
+    /// Value of the `alphabet` attribute.
+    alphabet: String,
+}
+
+impl SsmlPhonemes {
+    /// Convert the symbols of `alphabet` with the built-in table of [`PhonemeMapper::to_ipa`],
+    /// reading the stress from their digits.
+    pub fn new(alphabet: PhonemeAlphabet) -> Self {
+        let stress = match alphabet {
+            PhonemeAlphabet::Arpabet => StressConvention::Arpabet,
+            PhonemeAlphabet::Nofabet => StressConvention::Nofabet,
+        };
+        SsmlPhonemes::with_mapper(PhonemeMapper::to_ipa(alphabet), Some(stress))
+    }
+
+    /// Convert symbols with another IPA table, for models of other phoneme sets. `stress` says
+    /// how the model marks stress, `None` if it does not.
+    pub fn with_mapper(mapper: PhonemeMapper, stress: Option<StressConvention>) -> Self {
+        SsmlPhonemes {
+            mapper,
+            stress,
+            alphabet: "ipa".to_string(),
+        }
+    }
+
+    /// Name the phoneme set of the table in the `alphabet` attribute, `ipa` by default.
+    pub fn with_alphabet(mut self, alphabet: &str) -> Self {
+        self.alphabet = alphabet.to_string();
+        self
+    }
+
+    /// The `ph` attribute of the pronunciation of a word given by its phoneme tokens.
+    pub fn ph<S: AsRef<str>>(&self, tokens: &[S]) -> String {
+        let markers = match self.alphabet.as_str() {
+            "x-sampa" => ["\"", "%"],
+            _ => ["ˈ", "ˌ"],
+        };
+        self.mapper.map_marked(tokens, self.stress, markers).concat()
+    }
+
+    /// The `<phoneme>` tag of a word and its pronunciation.
+    pub fn phoneme_tag(&self, word: &str, result: &PhonetizationResult) -> String {
+        self.tag(word, &self.ph(&result.phoneme_tokens))
+    }
+
+    /// A `<speak>` document of a text, with a `<phoneme>` tag around each word of the
+    /// sentence it was phonemized into and the rest of the text as it is.
+    ///
+    /// The words a verbalized token was expanded into share one tag, around the token, with
+    /// their pronunciations separated by spaces.
+    pub fn speak(&self, text: &str, sentence: &SentenceResult) -> String {
+        let mut ssml = String::from("<speak>");
+        let mut end = 0;
+        let mut words = sentence.words.iter().peekable();
+        while let Some(word) = words.next() {
+            let span = word.span.clone();
+            let mut ph = vec![self.ph(&word.result.phoneme_tokens)];
+            while let Some(next) = words.next_if(|next| next.span == span) {
+                ph.push(self.ph(&next.result.phoneme_tokens));
+            }
+            ssml.push_str(&escape(&text[end..span.start]));
+            ssml.push_str(&self.tag(&text[span.clone()], &ph.join(" ")));
+            end = span.end;
+        }
+        ssml.push_str(&escape(&text[end..]));
+        ssml.push_str("</speak>");
+        ssml
+    }
+
+    fn tag(&self, word: &str, ph: &str) -> String {
+        format!(
+            "<phoneme alphabet=\"{}\" ph=\"{}\">{}</phoneme>",
+            escape(&self.alphabet),
+            escape(ph),
+            escape(word)
+        )
+    }
+}
+
+impl PhonetisaurusModel {
+    /// Phonemize a text and write it as an SSML `<speak>` document, see [`SsmlPhonemes::speak`].
+    pub fn phonemize_ssml(&self, text: &str, ssml: &SsmlPhonemes) -> Result<String> {
+        self.phonemize_ssml_with_options(text, ssml, &SentenceOptions::default())
+    }
+
+    /// Phonemize a text with the given options and write it as an SSML `<speak>` document.
+    pub fn phonemize_ssml_with_options(
+        &self,
+        text: &str,
+        ssml: &SsmlPhonemes,
+        options: &SentenceOptions,
+    ) -> Result<String> {
+        Ok(ssml.speak(text, &self.phonemize_sentence_with_options(text, options)?))
+    }
+}
+
+/// Escape text for SSML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
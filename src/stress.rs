@@ -0,0 +1,143 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Stress level of a phoneme.
+pub enum Stress {
+    Primary,
+    Secondary,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// How a phoneme set marks stress in its symbols.
+pub enum StressConvention {
+    /// A digit after vowels: 0 unstressed, 1 primary, 2 secondary.
+    Arpabet,
+    /// A digit after vowels and syllabic consonants: 0 unstressed, 1 and 2 primary (with
+    /// the two tonemes), 3 secondary. Normalizing to Nofabet writes primary stress as 1.
+    Nofabet,
+    /// `ˈ` (primary) and `ˌ` (secondary) before the stressed phoneme, attached to it or as a
+    /// token of their own.
+    Ipa,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What happens to the stress markers of the phoneme tokens.
+pub enum StressMarking {
+    /// Leave the tokens as the model produced them.
+    #[default]
+    Keep,
+    /// Remove the markers.
+    Strip,
+    /// Rewrite the markers in another convention.
+    Normalize(StressConvention),
+}
+
+/// Stress information of a single token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mark {
+    /// The token cannot carry stress, or the convention does not say.
+    None,
+    /// A stress-bearing token without stress, like ARPAbet `AH0`.
+    Unstressed,
+    Stressed(Stress),
+}
+
+impl StressConvention {
+    /// Split a token into the phoneme without markers and its stress. An empty phoneme
+    /// means that the token is only a marker.
+    fn split(self, token: &str) -> (String, Mark) {
+        match self {
+            StressConvention::Arpabet | StressConvention::Nofabet => {
+                let mut chars = token.chars();
+                let (Some(digit), phoneme) = (chars.next_back(), chars.as_str()) else {
+                    return (String::new(), Mark::None);
+                };
+                let mark = match (self, digit) {
+                    (_, '0') => Mark::Unstressed,
+                    (_, '1') => Mark::Stressed(Stress::Primary),
+                    (StressConvention::Arpabet, '2') => Mark::Stressed(Stress::Secondary),
+                    (StressConvention::Nofabet, '2') => Mark::Stressed(Stress::Primary),
+                    (StressConvention::Nofabet, '3') => Mark::Stressed(Stress::Secondary),
+                    _ => return (token.to_string(), Mark::None),
+                };
+                if phoneme.is_empty() {
+                    return (token.to_string(), Mark::None);
+                }
+                (phoneme.to_string(), mark)
+            }
+            StressConvention::Ipa => {
+                let mark = token.chars().find_map(|c| match c {
+                    'ˈ' => Some(Mark::Stressed(Stress::Primary)),
+                    'ˌ' => Some(Mark::Stressed(Stress::Secondary)),
+                    _ => None,
+                });
+                let phoneme: String = token.chars().filter(|&c| c != 'ˈ' && c != 'ˌ').collect();
+                (phoneme, mark.unwrap_or(Mark::None))
+            }
+        }
+    }
+
+    /// Whether a token is only a stress marker, applying to the next token.
+    pub(crate) fn is_marker(self, token: &str) -> bool {
+        self.split(token).0.is_empty()
+    }
+
+    /// Write a phoneme with its stress in this convention.
+    fn mark(self, phoneme: &str, mark: Mark) -> String {
+        let marker = match (self, mark) {
+            (_, Mark::None) => "",
+            (StressConvention::Ipa, Mark::Unstressed) => "",
+            (StressConvention::Ipa, Mark::Stressed(Stress::Primary)) => return format!("ˈ{}", phoneme),
+            (StressConvention::Ipa, Mark::Stressed(Stress::Secondary)) => return format!("ˌ{}", phoneme),
+            (_, Mark::Unstressed) => "0",
+            (_, Mark::Stressed(Stress::Primary)) => "1",
+            (StressConvention::Arpabet, Mark::Stressed(Stress::Secondary)) => "2",
+            (StressConvention::Nofabet, Mark::Stressed(Stress::Secondary)) => "3",
+        };
+        format!("{}{}", phoneme, marker)
+    }
+}
+
+/// Find the stressed tokens and apply a [`StressMarking`] to the tokens.
+///
+/// Returns the new tokens together with the positions and levels of the stressed ones,
+/// counted in the new tokens. Markers that are tokens of their own apply to the next token
+/// and are removed unless the marking is [`StressMarking::Keep`].
+pub(crate) fn apply_stress<S: AsRef<str>>(
+    tokens: &[S],
+    convention: StressConvention,
+    marking: StressMarking,
+) -> (Vec<String>, Vec<(usize, Stress)>) {
+    let mut marked = Vec::with_capacity(tokens.len());
+    let mut stress = Vec::new();
+    let mut pending = None;
+
+    for token in tokens {
+        let token = token.as_ref();
+        let (phoneme, own_mark) = convention.split(token);
+        if phoneme.is_empty() {
+            pending = pending.or(Some(own_mark));
+            if marking == StressMarking::Keep {
+                marked.push(token.to_string());
+            }
+            continue;
+        }
+        let mark = match (own_mark, pending.take()) {
+            (Mark::None, Some(pending)) => pending,
+            (mark, _) => mark,
+        };
+        if let Mark::Stressed(level) = mark {
+            stress.push((marked.len(), level));
+        }
+        marked.push(match marking {
+            StressMarking::Keep => token.to_string(),
+            StressMarking::Strip => phoneme,
+            // keeps the tonemes of Nofabet
+            StressMarking::Normalize(target) if target == convention && mark == own_mark => token.to_string(),
+            StressMarking::Normalize(target) => target.mark(&phoneme, mark),
+        });
+    }
+
+    (marked, stress)
+}
@@ -0,0 +1,333 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::phoneme_map::PhonemeAlphabet;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Sound classes of phonemes, in order of increasing sonority.
+pub enum PhonemeClass {
+    /// Plosives and affricates.
+    Stop,
+    Fricative,
+    Nasal,
+    Liquid,
+    /// Glides and other approximants.
+    Glide,
+    /// Vowels, diphthongs and syllabic consonants, which form the nucleus of a syllable.
+    Vowel,
+}
+
+impl PhonemeClass {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "stop" => Some(PhonemeClass::Stop),
+            "fricative" => Some(PhonemeClass::Fricative),
+            "nasal" => Some(PhonemeClass::Nasal),
+            "liquid" => Some(PhonemeClass::Liquid),
+            "glide" => Some(PhonemeClass::Glide),
+            "vowel" => Some(PhonemeClass::Vowel),
+            _ => None,
+        }
+    }
+}
+
+/// Splits phoneme sequences into syllables by the maximum onset principle.
+///
+/// Every vowel is the nucleus of a syllable. The consonants between two nuclei start the
+/// second syllable as far as they form a legal onset, the rest closes the first one. An
+/// onset is legal if it is a single consonant, if its sonority rises strictly after at most
+/// one obstruent, or if it was added with [`Self::with_onsets`], which covers clusters like
+/// English `S T R`.
+///
+/// Phonemes are looked up as they are, then without stress markers (trailing digits or a
+/// leading `ˈ` or `ˌ`). Unknown phonemes are treated as stops.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Syllabifier {
+    classes: HashMap<String, PhonemeClass>,
+    onsets: HashSet<Vec<String>>,
+}
+
+impl TryFrom<&Path> for Syllabifier {
+    type Error = io::Error;
+
+    /// Load a classification table with [`Self::from_tsv`].
+    fn try_from(path: &Path) -> std::result::Result<Self, Self::Error> {
+        Syllabifier::from_tsv(BufReader::new(File::open(path)?))
+    }
+}
+
+impl Syllabifier {
+    /// The classes and onsets in a fixed order, unlike the `Debug` output of their sets.
+    pub(crate) fn cache_key(&self) -> String {
+        let mut classes: Vec<_> = self.classes.iter().collect();
+        classes.sort_unstable();
+        let mut onsets: Vec<_> = self.onsets.iter().collect();
+        onsets.sort_unstable();
+        format!("{:?} {:?}", classes, onsets)
+    }
+
+    /// Create a syllabifier from the class of every phoneme.
+    pub fn new(classes: impl IntoIterator<Item = (String, PhonemeClass)>) -> Self {
+        Syllabifier {
+            classes: classes.into_iter().collect(),
+            onsets: HashSet::new(),
+        }
+    }
+
+    /// Built-in classification of the phonemes of `alphabet`, with the onsets that break the
+    /// sonority rule in its language.
+    pub fn builtin(alphabet: PhonemeAlphabet) -> Self {
+        let (classes, onsets) = match alphabet {
+            PhonemeAlphabet::Arpabet => (ARPABET_CLASSES, ARPABET_ONSETS),
+            PhonemeAlphabet::Nofabet => (NOFABET_CLASSES, NOFABET_ONSETS),
+        };
+        Syllabifier::new(classes.iter().map(|&(phoneme, class)| (phoneme.to_string(), class)))
+            .with_onsets(onsets.iter().map(|onset| onset.iter().map(|p| p.to_string()).collect()))
+    }
+
+    /// Read a table with one `phoneme<TAB>class` pair per line, where the class is one of
+    /// `vowel`, `glide`, `liquid`, `nasal`, `fricative` and `stop`.
+    ///
+    /// Lines starting with `onset` list the phonemes of an extra legal onset instead, like
+    /// `onset S T R`. Empty lines and lines starting with `#` are ignored.
+    pub fn from_tsv(reader: impl BufRead) -> io::Result<Self> {
+        let mut syllabifier = Syllabifier::default();
+
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Syllabification table line {} {}: {}", line_number + 1, message, line),
+                )
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["onset", phonemes @ ..] if !phonemes.is_empty() => {
+                    syllabifier.onsets.insert(phonemes.iter().map(|p| p.to_string()).collect());
+                }
+                &[phoneme, class] => {
+                    let class = PhonemeClass::parse(class).ok_or_else(|| invalid("has an unknown class"))?;
+                    syllabifier.classes.insert(phoneme.to_string(), class);
+                }
+                _ => return Err(invalid("is not a phoneme and its class")),
+            }
+        }
+
+        Ok(syllabifier)
+    }
+
+    /// Allow additional onsets, given as phoneme sequences.
+    pub fn with_onsets(mut self, onsets: impl IntoIterator<Item = Vec<String>>) -> Self {
+        self.onsets.extend(onsets);
+        self
+    }
+
+    /// Class of a phoneme, [`PhonemeClass::Stop`] if it is unknown.
+    pub fn class_of(&self, phoneme: &str) -> PhonemeClass {
+        let unstressed = phoneme
+            .trim_start_matches(['ˈ', 'ˌ'])
+            .trim_end_matches(|c: char| c.is_ascii_digit());
+        self.classes
+            .get(phoneme)
+            .or_else(|| self.classes.get(unstressed))
+            .copied()
+            .unwrap_or(PhonemeClass::Stop)
+    }
+
+    /// Split phonemes into syllables.
+    ///
+    /// A sequence without vowels is returned as a single syllable, an empty one as no
+    /// syllables.
+    pub fn syllabify<S: AsRef<str>>(&self, phonemes: &[S]) -> Vec<Vec<String>> {
+        let phonemes: Vec<&str> = phonemes.iter().map(AsRef::as_ref).collect();
+        let nuclei: Vec<usize> = (0..phonemes.len())
+            .filter(|&i| self.class_of(phonemes[i]) == PhonemeClass::Vowel)
+            .collect();
+        if nuclei.is_empty() {
+            if phonemes.is_empty() {
+                return Vec::new();
+            }
+            return vec![phonemes.iter().map(|p| p.to_string()).collect()];
+        }
+
+        // index of the first phoneme of every syllable but the first
+        let boundaries = nuclei.windows(2).map(|pair| {
+            let consonants = &phonemes[pair[0] + 1..pair[1]];
+            let onset_len = (0..=consonants.len())
+                .rev()
+                .find(|&len| self.is_onset(&consonants[consonants.len() - len..]))
+                .unwrap_or_default();
+            pair[1] - onset_len
+        });
+        let mut syllables = Vec::with_capacity(nuclei.len());
+        let mut start = 0;
+        for boundary in boundaries.chain([phonemes.len()]) {
+            syllables.push(phonemes[start..boundary].iter().map(|p| p.to_string()).collect());
+            start = boundary;
+        }
+        syllables
+    }
+
+    fn is_onset(&self, consonants: &[&str]) -> bool {
+        if consonants.len() <= 1 {
+            return true;
+        }
+        // only the first consonant may be an obstruent, so "K S" is split but "K L" is not
+        let rising = consonants.windows(2).all(|pair| {
+            let (first, second) = (self.class_of(pair[0]), self.class_of(pair[1]));
+            first < second && second >= PhonemeClass::Nasal
+        });
+        rising || self.onsets.iter().any(|onset| onset.iter().eq(consonants.iter()))
+    }
+}
+
+/// Classes of the CMUdict phonemes and the common extensions of ARPAbet.
+const ARPABET_CLASSES: &[(&str, PhonemeClass)] = &[
+    ("AA", PhonemeClass::Vowel),
+    ("AE", PhonemeClass::Vowel),
+    ("AH", PhonemeClass::Vowel),
+    ("AO", PhonemeClass::Vowel),
+    ("AW", PhonemeClass::Vowel),
+    ("AX", PhonemeClass::Vowel),
+    ("AXR", PhonemeClass::Vowel),
+    ("AY", PhonemeClass::Vowel),
+    ("EH", PhonemeClass::Vowel),
+    ("ER", PhonemeClass::Vowel),
+    ("EY", PhonemeClass::Vowel),
+    ("IH", PhonemeClass::Vowel),
+    ("IX", PhonemeClass::Vowel),
+    ("IY", PhonemeClass::Vowel),
+    ("OW", PhonemeClass::Vowel),
+    ("OY", PhonemeClass::Vowel),
+    ("UH", PhonemeClass::Vowel),
+    ("UW", PhonemeClass::Vowel),
+    ("EL", PhonemeClass::Vowel),
+    ("EM", PhonemeClass::Vowel),
+    ("EN", PhonemeClass::Vowel),
+    ("W", PhonemeClass::Glide),
+    ("Y", PhonemeClass::Glide),
+    ("L", PhonemeClass::Liquid),
+    ("R", PhonemeClass::Liquid),
+    ("M", PhonemeClass::Nasal),
+    ("N", PhonemeClass::Nasal),
+    ("NG", PhonemeClass::Nasal),
+    ("DH", PhonemeClass::Fricative),
+    ("F", PhonemeClass::Fricative),
+    ("HH", PhonemeClass::Fricative),
+    ("S", PhonemeClass::Fricative),
+    ("SH", PhonemeClass::Fricative),
+    ("TH", PhonemeClass::Fricative),
+    ("V", PhonemeClass::Fricative),
+    ("Z", PhonemeClass::Fricative),
+    ("ZH", PhonemeClass::Fricative),
+    ("B", PhonemeClass::Stop),
+    ("CH", PhonemeClass::Stop),
+    ("D", PhonemeClass::Stop),
+    ("DX", PhonemeClass::Stop),
+    ("G", PhonemeClass::Stop),
+    ("JH", PhonemeClass::Stop),
+    ("K", PhonemeClass::Stop),
+    ("P", PhonemeClass::Stop),
+    ("Q", PhonemeClass::Stop),
+    ("T", PhonemeClass::Stop),
+];
+
+/// English onsets starting with S before a stop.
+const ARPABET_ONSETS: &[&[&str]] = &[
+    &["S", "P"],
+    &["S", "T"],
+    &["S", "K"],
+    &["S", "P", "L"],
+    &["S", "P", "R"],
+    &["S", "T", "R"],
+    &["S", "K", "L"],
+    &["S", "K", "R"],
+    &["S", "K", "W"],
+    &["S", "P", "Y"],
+    &["S", "K", "Y"],
+];
+
+/// Classes of the Nofabet phonemes.
+const NOFABET_CLASSES: &[(&str, PhonemeClass)] = &[
+    ("AA", PhonemeClass::Vowel),
+    ("AH", PhonemeClass::Vowel),
+    ("AE", PhonemeClass::Vowel),
+    ("AEH", PhonemeClass::Vowel),
+    ("AEJ", PhonemeClass::Vowel),
+    ("AEW", PhonemeClass::Vowel),
+    ("AJ", PhonemeClass::Vowel),
+    ("AX", PhonemeClass::Vowel),
+    ("EE", PhonemeClass::Vowel),
+    ("EH", PhonemeClass::Vowel),
+    ("EJ", PhonemeClass::Vowel),
+    ("II", PhonemeClass::Vowel),
+    ("IH", PhonemeClass::Vowel),
+    ("OA", PhonemeClass::Vowel),
+    ("OAH", PhonemeClass::Vowel),
+    ("OAJ", PhonemeClass::Vowel),
+    ("OE", PhonemeClass::Vowel),
+    ("OEH", PhonemeClass::Vowel),
+    ("OEJ", PhonemeClass::Vowel),
+    ("OJ", PhonemeClass::Vowel),
+    ("OO", PhonemeClass::Vowel),
+    ("OH", PhonemeClass::Vowel),
+    ("UU", PhonemeClass::Vowel),
+    ("UH", PhonemeClass::Vowel),
+    ("UJ", PhonemeClass::Vowel),
+    ("YY", PhonemeClass::Vowel),
+    ("YH", PhonemeClass::Vowel),
+    ("LX", PhonemeClass::Vowel),
+    ("MX", PhonemeClass::Vowel),
+    ("NX", PhonemeClass::Vowel),
+    ("RLX", PhonemeClass::Vowel),
+    ("RNX", PhonemeClass::Vowel),
+    ("RX", PhonemeClass::Vowel),
+    ("SX", PhonemeClass::Vowel),
+    ("J", PhonemeClass::Glide),
+    ("V", PhonemeClass::Glide),
+    ("W", PhonemeClass::Glide),
+    ("L", PhonemeClass::Liquid),
+    ("R", PhonemeClass::Liquid),
+    ("RL", PhonemeClass::Liquid),
+    ("M", PhonemeClass::Nasal),
+    ("N", PhonemeClass::Nasal),
+    ("NG", PhonemeClass::Nasal),
+    ("RN", PhonemeClass::Nasal),
+    ("F", PhonemeClass::Fricative),
+    ("H", PhonemeClass::Fricative),
+    ("KJ", PhonemeClass::Fricative),
+    ("RS", PhonemeClass::Fricative),
+    ("S", PhonemeClass::Fricative),
+    ("SJ", PhonemeClass::Fricative),
+    ("B", PhonemeClass::Stop),
+    ("D", PhonemeClass::Stop),
+    ("G", PhonemeClass::Stop),
+    ("K", PhonemeClass::Stop),
+    ("P", PhonemeClass::Stop),
+    ("RD", PhonemeClass::Stop),
+    ("RT", PhonemeClass::Stop),
+    ("T", PhonemeClass::Stop),
+];
+
+/// Norwegian onsets starting with S before a stop.
+const NOFABET_ONSETS: &[&[&str]] = &[
+    &["S", "P"],
+    &["S", "T"],
+    &["S", "K"],
+    &["S", "P", "L"],
+    &["S", "P", "R"],
+    &["S", "T", "R"],
+    &["S", "K", "L"],
+    &["S", "K", "R"],
+    &["S", "K", "V"],
+    &["S", "P", "J"],
+];
@@ -0,0 +1,228 @@
+use rustfst::prelude::*;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::fst_export::{fst_to_dot, fst_to_text};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A stage of decoding a word, in the order they run.
+pub enum DecodeStage {
+    /// Building the acceptor of the word's input symbols.
+    Acceptor,
+    /// Composing the acceptor with the model.
+    Compose,
+    /// Pruning the composed lattice, see [`PhonemizeOptions::beam`](crate::PhonemizeOptions::beam).
+    Prune,
+    /// Projecting the lattice to phonemes, removing epsilons and determinizing it.
+    Determinize,
+    /// Extracting the n best pronunciations with their alignments.
+    ShortestPath,
+}
+
+#[derive(Clone)]
+/// Timing and size of one decoding stage of a word.
+pub struct StageTrace<'a> {
+    /// The word after case folding and normalization.
+    pub word: &'a str,
+    pub stage: DecodeStage,
+    /// Time the stage took.
+    pub elapsed: Duration,
+    /// Number of states of the FST the stage produced.
+    pub num_states: usize,
+    /// Number of arcs of the FST the stage produced.
+    pub num_arcs: usize,
+    /// The FST the stage produced.
+    fst: &'a VectorFst<TropicalWeight>,
+}
+
+impl StageTrace<'_> {
+    /// The FST the stage produced in the DOT language of Graphviz, with symbol labels, e.g.
+    /// for `dot -Tsvg`.
+    pub fn to_dot(&self) -> String {
+        fst_to_dot(self.fst, self.fst.input_symbols().map(Arc::as_ref), self.fst.output_symbols().map(Arc::as_ref))
+    }
+
+    /// The FST the stage produced in the AT&T text format of OpenFst's `fstprint`.
+    pub fn to_text(&self) -> String {
+        fst_to_text(self.fst, self.fst.input_symbols().map(Arc::as_ref), self.fst.output_symbols().map(Arc::as_ref))
+    }
+}
+
+impl fmt::Debug for StageTrace<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StageTrace")
+            .field("word", &self.word)
+            .field("stage", &self.stage)
+            .field("elapsed", &self.elapsed)
+            .field("num_states", &self.num_states)
+            .field("num_arcs", &self.num_arcs)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What answered a phonemization, see [`DecodeDiagnostics::source`].
+pub enum ResultSource {
+    /// The word was decoded with the model.
+    #[default]
+    Model,
+    /// The word was found in the exception lexicon.
+    Lexicon,
+    /// The results were read from a [`DiskCache`](crate::DiskCache).
+    Cache,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Timing and size of one decoding stage, kept in [`DecodeDiagnostics::stages`].
+pub struct StageDiagnostics {
+    pub stage: DecodeStage,
+    /// Time the stage took.
+    pub elapsed: Duration,
+    /// Number of states of the FST the stage produced.
+    pub num_states: usize,
+    /// Number of arcs of the FST the stage produced.
+    pub num_arcs: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// How a word was phonemized, attached to its results with
+/// [`PhonemizeOptions::diagnostics`](crate::PhonemizeOptions::diagnostics) to find out why a
+/// word is slow or wrong without decoding it again.
+pub struct DecodeDiagnostics {
+    /// What answered the phonemization.
+    pub source: ResultSource,
+    /// The decoding stages in the order they ran, empty unless the model was used. Words
+    /// split at punctuation have the stages of each part.
+    pub stages: Vec<StageDiagnostics>,
+    /// Number of chars missing from the input symbol table that were dropped, see
+    /// [`UnknownCharPolicy::Skip`](crate::UnknownCharPolicy::Skip).
+    pub skipped_chars: usize,
+}
+
+impl DecodeDiagnostics {
+    /// Total time of the decoding stages.
+    pub fn elapsed(&self) -> Duration {
+        self.stages.iter().map(|stage| stage.elapsed).sum()
+    }
+
+    /// Number of states of the composed lattice, summed over the parts of words split at
+    /// punctuation, 0 if the model was not used.
+    pub fn composed_states(&self) -> usize {
+        self.stages
+            .iter()
+            .filter(|stage| stage.stage == DecodeStage::Compose)
+            .map(|stage| stage.num_states)
+            .sum()
+    }
+
+    /// Number of arcs of the composed lattice, summed over the parts of words split at
+    /// punctuation, 0 if the model was not used.
+    pub fn composed_arcs(&self) -> usize {
+        self.stages
+            .iter()
+            .filter(|stage| stage.stage == DecodeStage::Compose)
+            .map(|stage| stage.num_arcs)
+            .sum()
+    }
+}
+
+/// Tracer collecting the stages of one word for its [`DecodeDiagnostics`], passing them on to
+/// the tracer of the options.
+pub(crate) struct StageRecorder {
+    stages: Mutex<Vec<StageDiagnostics>>,
+    inner: Option<Tracer>,
+}
+
+impl StageRecorder {
+    pub(crate) fn new(inner: Option<Tracer>) -> Self {
+        StageRecorder {
+            stages: Mutex::new(Vec::new()),
+            inner,
+        }
+    }
+
+    /// The stages recorded so far.
+    pub(crate) fn stages(&self) -> Vec<StageDiagnostics> {
+        self.stages.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+}
+
+impl DecodeTracer for StageRecorder {
+    fn stage_done(&self, trace: &StageTrace<'_>) {
+        self.stages.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(StageDiagnostics {
+            stage: trace.stage,
+            elapsed: trace.elapsed,
+            num_states: trace.num_states,
+            num_arcs: trace.num_arcs,
+        });
+        if let Some(inner) = &self.inner {
+            inner.0.stage_done(trace);
+        }
+    }
+}
+
+/// Receiver of the stage traces of decoded words, e.g. to log slow words or to feed
+/// metrics. Called on the decoding thread, so it should return quickly.
+pub trait DecodeTracer: Send + Sync {
+    fn stage_done(&self, trace: &StageTrace<'_>);
+}
+
+#[derive(Clone)]
+/// A shared [`DecodeTracer`], to be set in [`PhonemizeOptions::tracer`](crate::PhonemizeOptions::tracer).
+pub struct Tracer(pub Arc<dyn DecodeTracer>);
+
+impl Tracer {
+    pub fn new(tracer: impl DecodeTracer + 'static) -> Self {
+        Tracer(Arc::new(tracer))
+    }
+
+    /// Report a finished stage that started at `start` and produced `fst`.
+    pub(crate) fn stage_done(&self, word: &str, stage: DecodeStage, start: Instant, fst: &VectorFst<TropicalWeight>) {
+        let elapsed = start.elapsed();
+        let num_arcs = fst.states_iter().filter_map(|state| fst.num_trs(state).ok()).sum();
+        self.0.stage_done(&StageTrace {
+            word,
+            stage,
+            elapsed,
+            num_states: fst.num_states(),
+            num_arcs,
+            fst,
+        });
+    }
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Tracer")
+    }
+}
+
+impl PartialEq for Tracer {
+    /// Tracers are equal if they share the receiver.
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<F: Fn(&StageTrace<'_>) + Send + Sync> DecodeTracer for F {
+    fn stage_done(&self, trace: &StageTrace<'_>) {
+        self(trace)
+    }
+}
+
+impl fmt::Display for DecodeStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeStage::Acceptor => write!(f, "acceptor"),
+            DecodeStage::Compose => write!(f, "compose"),
+            DecodeStage::Prune => write!(f, "prune"),
+            DecodeStage::Determinize => write!(f, "determinize"),
+            DecodeStage::ShortestPath => write!(f, "shortest_path"),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use rustfst::prelude::*;
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::error::SymbolTableKind;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A problem found by [`PhonetisaurusModel::validate`](crate::PhonetisaurusModel::validate).
+pub enum ValidationIssue {
+    /// The FST has no start state, so no word can be phonemized.
+    MissingStartState,
+    /// No state of the FST is final, so no word can be phonemized.
+    NoFinalStates,
+    /// The FST has no symbol table on the given side.
+    MissingSymbolTable(SymbolTableKind),
+    /// Transitions use labels that are not in the symbol table of the given side.
+    UnknownLabels {
+        kind: SymbolTableKind,
+        /// The distinct unknown labels, in ascending order.
+        labels: Vec<Label>,
+    },
+    /// States that cannot be reached from the start state.
+    UnreachableStates(usize),
+    /// States from which no final state can be reached.
+    DeadEndStates(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Structure of a model FST and the problems found in it.
+pub struct ValidationReport {
+    pub num_states: usize,
+    pub num_arcs: usize,
+    pub num_final_states: usize,
+    /// Problems found, empty for a valid model.
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Whether no problems were found.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::MissingStartState => write!(f, "The FST has no start state."),
+            ValidationIssue::NoFinalStates => write!(f, "The FST has no final states."),
+            ValidationIssue::MissingSymbolTable(kind) => write!(f, "The FST has no {} symbol table.", kind),
+            ValidationIssue::UnknownLabels { kind, labels } => {
+                let labels: Vec<String> = labels.iter().map(Label::to_string).collect();
+                write!(
+                    f,
+                    "{} {} label(s) are not in the {} symbol table: {}",
+                    labels.len(),
+                    kind,
+                    kind,
+                    labels.join(", ")
+                )
+            }
+            ValidationIssue::UnreachableStates(count) => {
+                write!(f, "{} state(s) cannot be reached from the start state.", count)
+            }
+            ValidationIssue::DeadEndStates(count) => write!(f, "{} state(s) cannot reach a final state.", count),
+        }
+    }
+}
+
+/// Check the structure of a model FST.
+pub(crate) fn validate_fst<F: ExpandedFst<TropicalWeight>>(fst: &F) -> ValidationReport {
+    let num_states = fst.num_states();
+    let mut issues = Vec::new();
+    let mut num_arcs = 0;
+    let mut final_states = Vec::new();
+    // predecessors of each state, to search backwards from the final states
+    let mut predecessors = vec![Vec::new(); num_states];
+    let mut ilabels = Vec::new();
+    let mut olabels = Vec::new();
+
+    for state in fst.states_iter() {
+        if fst.is_final(state).unwrap_or(false) {
+            final_states.push(state);
+        }
+        let Ok(trs) = fst.get_trs(state) else {
+            continue;
+        };
+        for tr in trs.trs() {
+            num_arcs += 1;
+            predecessors[tr.nextstate as usize].push(state);
+            ilabels.push(tr.ilabel);
+            olabels.push(tr.olabel);
+        }
+    }
+
+    let start = fst.start();
+    if start.is_none() {
+        issues.push(ValidationIssue::MissingStartState);
+    }
+    if final_states.is_empty() {
+        issues.push(ValidationIssue::NoFinalStates);
+    }
+    for (kind, table, labels) in [
+        (SymbolTableKind::Input, fst.input_symbols(), ilabels),
+        (SymbolTableKind::Output, fst.output_symbols(), olabels),
+    ] {
+        let Some(table) = table else {
+            issues.push(ValidationIssue::MissingSymbolTable(kind));
+            continue;
+        };
+        let mut unknown: Vec<Label> = labels
+            .into_iter()
+            .filter(|&label| label != EPS_LABEL && table.get_symbol(label).is_none())
+            .collect();
+        unknown.sort_unstable();
+        unknown.dedup();
+        if !unknown.is_empty() {
+            issues.push(ValidationIssue::UnknownLabels { kind, labels: unknown });
+        }
+    }
+
+    if let Some(start) = start {
+        let reachable = search(num_states, [start], |state| {
+            fst.get_trs(state)
+                .map(|trs| trs.trs().iter().map(|tr| tr.nextstate).collect())
+                .unwrap_or_default()
+        });
+        let unreachable = reachable.iter().filter(|&&seen| !seen).count();
+        if unreachable > 0 {
+            issues.push(ValidationIssue::UnreachableStates(unreachable));
+        }
+    }
+    if !final_states.is_empty() {
+        let productive = search(num_states, final_states.iter().copied(), |state| {
+            predecessors[state as usize].clone()
+        });
+        let dead_ends = productive.iter().filter(|&&seen| !seen).count();
+        if dead_ends > 0 {
+            issues.push(ValidationIssue::DeadEndStates(dead_ends));
+        }
+    }
+
+    ValidationReport {
+        num_states,
+        num_arcs,
+        num_final_states: final_states.len(),
+        issues,
+    }
+}
+
+/// States visited by a breadth-first search from `roots` along `next`.
+fn search(
+    num_states: usize,
+    roots: impl IntoIterator<Item = StateId>,
+    next: impl Fn(StateId) -> Vec<StateId>,
+) -> Vec<bool> {
+    let mut seen = vec![false; num_states];
+    let mut queue = VecDeque::new();
+    for root in roots {
+        if !seen[root as usize] {
+            seen[root as usize] = true;
+            queue.push_back(root);
+        }
+    }
+    while let Some(state) = queue.pop_front() {
+        for next_state in next(state) {
+            if !seen[next_state as usize] {
+                seen[next_state as usize] = true;
+                queue.push_back(next_state);
+            }
+        }
+    }
+    seen
+}
@@ -0,0 +1,114 @@
+/// Expands tokens that are not words, like numerals, into the words they are read as, before
+/// a sentence is phonemized. Set in [`SentenceOptions::verbalizer`](crate::SentenceOptions::verbalizer),
+/// with one implementation per language.
+pub trait Verbalizer: Send + Sync {
+    /// The words a token is read as, or `None` to phonemize the token as it is.
+    fn verbalize(&self, token: &str) -> Option<Vec<String>>;
+}
+
+impl<F> Verbalizer for F
+where
+    F: Fn(&str) -> Option<Vec<String>> + Send + Sync,
+{
+    fn verbalize(&self, token: &str) -> Option<Vec<String>> {
+        self(token)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+/// Reads English cardinals ("42" as "forty two") and ordinals ("3rd" as "third").
+///
+/// Numbers with leading zeros or more than 15 digits, like codes and phone numbers, are read
+/// digit by digit. Other tokens are left alone.
+pub struct EnglishNumbers;
+
+const ONES: [&str; 20] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven", "twelve",
+    "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+];
+const TENS: [&str; 10] = ["", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+const SCALES: [&str; 5] = ["", "thousand", "million", "billion", "trillion"];
+
+/// Longest number read as a cardinal, in digits.
+const MAX_CARDINAL_DIGITS: usize = 15;
+
+impl Verbalizer for EnglishNumbers {
+    fn verbalize(&self, token: &str) -> Option<Vec<String>> {
+        let lowercase = token.to_ascii_lowercase();
+        let (digits, ordinal) = match ["st", "nd", "rd", "th"].iter().find_map(|suffix| lowercase.strip_suffix(suffix)) {
+            Some(digits) => (digits, true),
+            None => (lowercase.as_str(), false),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        if digits.len() > MAX_CARDINAL_DIGITS || (digits.len() > 1 && digits.starts_with('0')) {
+            if ordinal {
+                return None;
+            }
+            let words = digits.bytes().map(|b| ONES[usize::from(b - b'0')].to_string()).collect();
+            return Some(words);
+        }
+        let mut words = english_cardinal(digits.parse().ok()?);
+        if ordinal && let Some(last) = words.last_mut() {
+            *last = english_ordinal(last);
+        }
+        Some(words)
+    }
+}
+
+/// The words of a number below 10^15.
+fn english_cardinal(number: u64) -> Vec<String> {
+    if number == 0 {
+        return vec![ONES[0].to_string()];
+    }
+    // groups of three digits, least significant first
+    let groups: Vec<u64> = std::iter::successors(Some(number), |&rest| Some(rest / 1000))
+        .take_while(|&rest| rest > 0)
+        .map(|rest| rest % 1000)
+        .collect();
+
+    let mut words = Vec::new();
+    for (scale, &group) in groups.iter().enumerate().rev() {
+        if group == 0 {
+            continue;
+        }
+        let (hundreds, rest) = (group / 100, (group % 100) as usize);
+        if hundreds > 0 {
+            words.push(ONES[hundreds as usize].to_string());
+            words.push("hundred".to_string());
+        }
+        match rest {
+            0 => {}
+            1..20 => words.push(ONES[rest].to_string()),
+            _ => {
+                words.push(TENS[rest / 10].to_string());
+                if rest % 10 > 0 {
+                    words.push(ONES[rest % 10].to_string());
+                }
+            }
+        }
+        if scale > 0 {
+            words.push(SCALES[scale].to_string());
+        }
+    }
+    words
+}
+
+/// The ordinal of the last word of a cardinal, "three" to "third".
+fn english_ordinal(word: &str) -> String {
+    match word {
+        "one" => "first".to_string(),
+        "two" => "second".to_string(),
+        "three" => "third".to_string(),
+        "five" => "fifth".to_string(),
+        "eight" => "eighth".to_string(),
+        "nine" => "ninth".to_string(),
+        "twelve" => "twelfth".to_string(),
+        _ => match word.strip_suffix('y') {
+            Some(stem) => format!("{}ieth", stem),
+            None => format!("{}th", word),
+        },
+    }
+}
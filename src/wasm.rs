@@ -0,0 +1,105 @@
+//! WebAssembly exports, used through `wasm/phonetisaurus.js`.
+//!
+//! The exports only pass integers and pointers into the linear memory of the module, so
+//! no bindings generator is needed. Strings go in as UTF-8 buffers allocated with
+//! [`phonetisaurus_wasm_alloc`], results come out as buffers holding a little endian `u32`
+//! length followed by that many bytes of JSON, to be released with [`phonetisaurus_wasm_free`].
+
+use crate::{PhonetisaurusModel, PhonetizationResult, Result};
+
+/// Allocate `len` bytes in the linear memory, for the caller to fill.
+#[unsafe(no_mangle)]
+pub extern "C" fn phonetisaurus_wasm_alloc(len: usize) -> *mut u8 {
+    Box::into_raw(vec![0u8; len].into_boxed_slice()).cast()
+}
+
+/// Release a buffer allocated with [`phonetisaurus_wasm_alloc`] or returned by
+/// [`phonetisaurus_wasm_phonemize`], given its full length.
+///
+/// # Safety
+///
+/// `ptr` and `len` must describe a buffer obtained from this module that was not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn phonetisaurus_wasm_free(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        drop(unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)) });
+    }
+}
+
+/// Load a model from the `len` bytes at `ptr`. Returns null if the model is invalid.
+///
+/// # Safety
+///
+/// `ptr` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn phonetisaurus_wasm_load(ptr: *const u8, len: usize) -> *mut PhonetisaurusModel {
+    let data = unsafe { std::slice::from_raw_parts(ptr, len) };
+    match PhonetisaurusModel::try_from(data) {
+        Ok(model) => Box::into_raw(Box::new(model)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Release a model returned by [`phonetisaurus_wasm_load`].
+///
+/// # Safety
+///
+/// `model` must come from [`phonetisaurus_wasm_load`] and must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn phonetisaurus_wasm_model_free(model: *mut PhonetisaurusModel) {
+    if !model.is_null() {
+        drop(unsafe { Box::from_raw(model) });
+    }
+}
+
+/// Phonemize the UTF-8 word of `len` bytes at `ptr`.
+///
+/// Returns a length-prefixed buffer holding `{"phonemes": ..., "score": ...}`, or
+/// `{"error": ...}` if the word could not be phonemized.
+///
+/// # Safety
+///
+/// `model` must be a live model from [`phonetisaurus_wasm_load`], and `ptr` must point to
+/// `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn phonetisaurus_wasm_phonemize(
+    model: *const PhonetisaurusModel,
+    ptr: *const u8,
+    len: usize,
+) -> *mut u8 {
+    let model = unsafe { &*model };
+    let word = String::from_utf8_lossy(unsafe { std::slice::from_raw_parts(ptr, len) });
+    let json = to_json(model.phonemize_word(&word));
+
+    let mut buffer = Vec::with_capacity(4 + json.len());
+    buffer.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(json.as_bytes());
+    Box::into_raw(buffer.into_boxed_slice()).cast()
+}
+
+fn to_json(result: Result<PhonetizationResult>) -> String {
+    match result {
+        Ok(result) if result.neg_log_score.is_finite() => format!(
+            "{{\"phonemes\":{},\"score\":{}}}",
+            json_string(&result.phonemes),
+            result.neg_log_score
+        ),
+        Ok(result) => format!("{{\"phonemes\":{},\"score\":null}}", json_string(&result.phonemes)),
+        Err(e) => format!("{{\"error\":{}}}", json_string(&e.to_string())),
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,733 @@
+use std::io;
+
+/// Magic number starting a zstd frame.
+const MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Baseline and extra bits of each literal length code.
+const LITERAL_LENGTH_CODES: [(u32, u32); 36] = [
+    (0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0), (10, 0), (11, 0), (12, 0), (13, 0),
+    (14, 0), (15, 0), (16, 1), (18, 1), (20, 1), (22, 1), (24, 2), (28, 2), (32, 3), (40, 3), (48, 4), (64, 6),
+    (128, 7), (256, 8), (512, 9), (1024, 10), (2048, 11), (4096, 12), (8192, 13), (16384, 14), (32768, 15), (65536, 16),
+];
+
+/// Baseline and extra bits of each match length code.
+const MATCH_LENGTH_CODES: [(u32, u32); 53] = [
+    (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (8, 0), (9, 0), (10, 0), (11, 0), (12, 0), (13, 0), (14, 0), (15, 0),
+    (16, 0), (17, 0), (18, 0), (19, 0), (20, 0), (21, 0), (22, 0), (23, 0), (24, 0), (25, 0), (26, 0), (27, 0),
+    (28, 0), (29, 0), (30, 0), (31, 0), (32, 0), (33, 0), (34, 0), (35, 1), (37, 1), (39, 1), (41, 1), (43, 2), (47, 2), (51, 3), (59, 3),
+    (67, 4), (83, 4), (99, 5), (131, 7), (259, 8), (515, 9), (1027, 10), (2051, 11), (4099, 12), (8195, 13),
+    (16387, 14), (32771, 15), (65539, 16),
+];
+
+/// The codes of sequences, whose FSE tables are described for each block.
+struct SequenceCode {
+    /// Distribution used in the predefined mode, with -1 for less than one.
+    default: &'static [i16],
+    default_log: u32,
+    max_log: u32,
+    max_symbol: usize,
+}
+
+const LITERAL_LENGTHS: SequenceCode = SequenceCode {
+    default: &[
+        4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1, 1, 1, -1, -1, -1, -1,
+    ],
+    default_log: 6,
+    max_log: 9,
+    max_symbol: 35,
+};
+
+const OFFSETS: SequenceCode = SequenceCode {
+    default: &[1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1],
+    default_log: 5,
+    max_log: 8,
+    max_symbol: 31,
+};
+
+const MATCH_LENGTHS: SequenceCode = SequenceCode {
+    default: &[
+        1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
+    ],
+    default_log: 6,
+    max_log: 9,
+    max_symbol: 52,
+};
+
+/// Most bytes a block holds, compressed or decompressed.
+const MAX_BLOCK_SIZE: usize = 128 << 10;
+
+/// Longest code of the Huffman codes of literals.
+const MAX_HUFFMAN_BITS: u32 = 11;
+
+/// Whether data starts like a zstd file, with a frame or a skippable frame.
+pub(crate) fn is_zstd(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC) || is_skippable(data)
+}
+
+fn is_skippable(data: &[u8]) -> bool {
+    data.len() >= 4 && data[0] & 0xf0 == 0x50 && data[1..4] == [0x2a, 0x4d, 0x18]
+}
+
+/// Decompress a zstd file (RFC 8878), checking the content size and the checksum of its
+/// frames where they are given.
+///
+/// Files of several frames, as written by concatenating zstd files, are decompressed as a
+/// whole, and skippable frames are skipped. Frames compressed with a dictionary are rejected.
+pub(crate) fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len().saturating_mul(4));
+    let mut rest = data;
+    while !rest.is_empty() {
+        if is_skippable(rest) {
+            let size = le(take(&mut &rest[4..], 4)?) as usize;
+            rest = rest.get(8..).and_then(|rest| rest.get(size..)).ok_or_else(truncated)?;
+        } else if rest.starts_with(&MAGIC) {
+            rest = decompress_frame(&rest[MAGIC.len()..], &mut out)?;
+        } else {
+            return Err(invalid("the zstd file has data after its frames"));
+        }
+    }
+    Ok(out)
+}
+
+/// Decompress the frame after its magic number onto `out`, returning the data after it.
+fn decompress_frame<'a>(mut data: &'a [u8], out: &mut Vec<u8>) -> io::Result<&'a [u8]> {
+    let descriptor = take(&mut data, 1)?[0];
+    let single_segment = descriptor & 0x20 != 0;
+    if descriptor & 0x08 != 0 {
+        return Err(invalid("the zstd frame header has a reserved bit set"));
+    }
+    // the window size is not needed, as the whole output is kept
+    if !single_segment {
+        take(&mut data, 1)?;
+    }
+    if le(take(&mut data, [0, 1, 2, 4][descriptor as usize & 3])?) != 0 {
+        return Err(invalid("the zstd frame needs a dictionary"));
+    }
+    let content_size = match (descriptor >> 6, single_segment) {
+        (0, false) => None,
+        (0, true) => Some(le(take(&mut data, 1)?)),
+        (1, _) => Some(le(take(&mut data, 2)?) + 256),
+        (2, _) => Some(le(take(&mut data, 4)?)),
+        _ => Some(le(take(&mut data, 8)?)),
+    };
+
+    let start = out.len();
+    let mut frame = Frame::default();
+    loop {
+        let header = le(take(&mut data, 3)?);
+        let size = (header >> 3) as usize;
+        if size > MAX_BLOCK_SIZE {
+            return Err(invalid("the zstd data has a block that is too large"));
+        }
+        match (header >> 1) & 3 {
+            0 => out.extend_from_slice(take(&mut data, size)?),
+            1 => {
+                let byte = take(&mut data, 1)?[0];
+                out.resize(out.len() + size, byte);
+            }
+            2 => frame.decompress_block(take(&mut data, size)?, out, start)?,
+            _ => return Err(invalid("the zstd data has an invalid block type")),
+        }
+        if header & 1 == 1 {
+            break;
+        }
+    }
+
+    if content_size.is_some_and(|size| size != (out.len() - start) as u64) {
+        return Err(invalid("the zstd frame is corrupt, its size does not match"));
+    }
+    if descriptor & 0x04 != 0 && le(take(&mut data, 4)?) != xxh64(&out[start..]) & 0xffff_ffff {
+        return Err(invalid("the zstd frame is corrupt, its checksum does not match"));
+    }
+    Ok(data)
+}
+
+/// What the compressed blocks of a frame reuse from the blocks before them.
+struct Frame {
+    huffman: Option<HuffmanTable>,
+    literal_lengths: Option<FseTable>,
+    offsets: Option<FseTable>,
+    match_lengths: Option<FseTable>,
+    /// The last three offsets, which sequences can refer to.
+    repeated_offsets: [usize; 3],
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Frame {
+            huffman: None,
+            literal_lengths: None,
+            offsets: None,
+            match_lengths: None,
+            repeated_offsets: [1, 4, 8],
+        }
+    }
+}
+
+impl Frame {
+    /// Decompress a compressed block onto `out`, whose data from `start` on is the data of
+    /// this frame, the only data that matches may refer back to.
+    fn decompress_block(&mut self, block: &[u8], out: &mut Vec<u8>, start: usize) -> io::Result<()> {
+        let (literals, mut data) = self.literals(block)?;
+        let too_large = || invalid("the zstd data has a block that is too large");
+        if literals.len() > MAX_BLOCK_SIZE {
+            return Err(too_large());
+        }
+        let block_start = out.len();
+        let count = match take(&mut data, 1)?[0] as usize {
+            first @ 0..128 => first,
+            first @ 128..255 => ((first - 128) << 8) + take(&mut data, 1)?[0] as usize,
+            _ => le(take(&mut data, 2)?) as usize + 0x7f00,
+        };
+        if count == 0 {
+            out.extend_from_slice(&literals);
+            return Ok(());
+        }
+
+        let modes = take(&mut data, 1)?[0];
+        if modes & 3 != 0 {
+            return Err(invalid("the zstd sequences have a reserved bit set"));
+        }
+        let literal_lengths = update_table(&mut self.literal_lengths, modes >> 6, &mut data, &LITERAL_LENGTHS)?;
+        let offsets = update_table(&mut self.offsets, (modes >> 4) & 3, &mut data, &OFFSETS)?;
+        let match_lengths = update_table(&mut self.match_lengths, (modes >> 2) & 3, &mut data, &MATCH_LENGTHS)?;
+
+        let mut reader = BackwardReader::new(data)?;
+        let mut literal_lengths = FseState::new(literal_lengths, &mut reader);
+        let mut offsets = FseState::new(offsets, &mut reader);
+        let mut match_lengths = FseState::new(match_lengths, &mut reader);
+        let mut literals = literals.as_slice();
+        for i in 0..count {
+            let offset_code = offsets.symbol() as u32;
+            let offset_value = (1 << offset_code) + reader.bits(offset_code);
+            let (base, extra) = MATCH_LENGTH_CODES[match_lengths.symbol() as usize];
+            let match_length = (base + reader.bits(extra) as u32) as usize;
+            let (base, extra) = LITERAL_LENGTH_CODES[literal_lengths.symbol() as usize];
+            let literal_length = (base + reader.bits(extra) as u32) as usize;
+            if i + 1 < count {
+                literal_lengths.update(&mut reader);
+                match_lengths.update(&mut reader);
+                offsets.update(&mut reader);
+            }
+
+            let offset = repeated_offset(&mut self.repeated_offsets, offset_value, literal_length);
+            if literal_length > literals.len() {
+                return Err(invalid("the zstd sequences use more literals than there are"));
+            }
+            if out.len() - block_start + literal_length + match_length > MAX_BLOCK_SIZE {
+                return Err(too_large());
+            }
+            out.extend_from_slice(&literals[..literal_length]);
+            literals = &literals[literal_length..];
+            if offset == 0 || offset > out.len() - start {
+                return Err(invalid("the zstd data refers back before its start"));
+            }
+            let from = out.len() - offset;
+            if offset >= match_length {
+                out.extend_from_within(from..from + match_length);
+            } else {
+                // the copy overlaps the bytes it adds, repeating them
+                for i in from..from + match_length {
+                    out.push(out[i]);
+                }
+            }
+        }
+        if !reader.is_finished() {
+            return Err(invalid("the zstd sequences do not end with their bitstream"));
+        }
+        out.extend_from_slice(literals);
+        Ok(())
+    }
+
+    /// Decode the literals section at the start of a block, returning the literals and the
+    /// rest of the block.
+    fn literals<'a>(&mut self, mut block: &'a [u8]) -> io::Result<(Vec<u8>, &'a [u8])> {
+        let first = *block.first().ok_or_else(truncated)?;
+        let size_format = (first >> 2) & 3;
+        match first & 3 {
+            block_type @ (0 | 1) => {
+                let size = match size_format {
+                    0 | 2 => le(take(&mut block, 1)?) >> 3,
+                    1 => le(take(&mut block, 2)?) >> 4,
+                    _ => le(take(&mut block, 3)?) >> 4,
+                } as usize;
+                let literals = match block_type {
+                    0 => take(&mut block, size)?.to_vec(),
+                    _ => vec![take(&mut block, 1)?[0]; size],
+                };
+                Ok((literals, block))
+            }
+            block_type => {
+                let (header_len, streams, bits) = match size_format {
+                    0 => (3, 1, 10),
+                    1 => (3, 4, 10),
+                    2 => (4, 4, 14),
+                    _ => (5, 4, 18),
+                };
+                let header = le(take(&mut block, header_len)?) as usize;
+                let mask = (1 << bits) - 1;
+                let size = (header >> 4) & mask;
+                let mut compressed = take(&mut block, (header >> (4 + bits)) & mask)?;
+                if block_type == 2 {
+                    self.huffman = Some(HuffmanTable::read(&mut compressed)?);
+                }
+                let huffman = self.huffman.as_ref().ok_or_else(|| invalid("the zstd literals lack a Huffman table"))?;
+                let literals = match streams {
+                    1 => huffman.decode_streams(&[compressed], &[size])?,
+                    _ => {
+                        let jump_table = take(&mut compressed, 6)?;
+                        let segment = size.div_ceil(4);
+                        let last = size.checked_sub(3 * segment);
+                        let last = last.ok_or_else(|| invalid("the zstd literals are too few for four streams"))?;
+                        let mut streams = [&[][..]; 4];
+                        for (i, stream) in streams[..3].iter_mut().enumerate() {
+                            *stream = take(&mut compressed, le(&jump_table[2 * i..2 * i + 2]) as usize)?;
+                        }
+                        streams[3] = compressed;
+                        huffman.decode_streams(&streams, &[segment, segment, segment, last])?
+                    }
+                };
+                Ok((literals, block))
+            }
+        }
+    }
+}
+
+/// The offset of a match from its offset value, updating the repeated offsets.
+fn repeated_offset(repeated: &mut [usize; 3], value: u64, literal_length: usize) -> usize {
+    if value > 3 {
+        let offset = (value - 3) as usize;
+        *repeated = [offset, repeated[0], repeated[1]];
+        return offset;
+    }
+    // without literals, the values 1 to 3 refer to the next repeated offset
+    let index = value as usize - 1 + (literal_length == 0) as usize;
+    let offset = match index {
+        3 => repeated[0].saturating_sub(1),
+        index => repeated[index],
+    };
+    match index {
+        0 => {}
+        1 => *repeated = [offset, repeated[0], repeated[2]],
+        _ => *repeated = [offset, repeated[0], repeated[1]],
+    }
+    offset
+}
+
+/// Set the FSE table of a sequence code as its mode in the block says, returning it.
+fn update_table<'a>(
+    table: &'a mut Option<FseTable>,
+    mode: u8,
+    data: &mut &[u8],
+    code: &SequenceCode,
+) -> io::Result<&'a FseTable> {
+    match mode {
+        0 => *table = Some(FseTable::new(code.default, code.default_log)?),
+        1 => {
+            let symbol = take(data, 1)?[0];
+            if symbol as usize > code.max_symbol {
+                return Err(invalid("the zstd sequences have an invalid code"));
+            }
+            *table = Some(FseTable::rle(symbol));
+        }
+        2 => {
+            let mut reader = ForwardReader::new(data);
+            *table = Some(FseTable::read(&mut reader, code.max_log, code.max_symbol)?);
+            take(data, reader.bytes_read())?;
+        }
+        _ => {}
+    }
+    table.as_ref().ok_or_else(|| invalid("the zstd sequences reuse a table before the first"))
+}
+
+/// A table decoding finite state entropy codes, indexed by state.
+struct FseTable {
+    log: u32,
+    entries: Vec<FseEntry>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct FseEntry {
+    symbol: u8,
+    /// Number of bits read for the next state, which is `baseline` plus them.
+    bits: u32,
+    baseline: usize,
+}
+
+impl FseTable {
+    /// Read the description of a table with an accuracy log of at most `max_log`.
+    fn read(reader: &mut ForwardReader, max_log: u32, max_symbol: usize) -> io::Result<Self> {
+        let log = reader.bits(4) + 5;
+        if log > max_log {
+            return Err(invalid("the zstd data has an FSE table that is too large"));
+        }
+        // probabilities are stored with as few bits as the probability left allows
+        let mut remaining = (1 << log) + 1;
+        let mut threshold = 1 << log;
+        let mut bits = log + 1;
+        let mut counts = Vec::new();
+        while remaining > 1 {
+            if counts.len() > max_symbol {
+                return Err(invalid("the zstd data has an FSE table with too many symbols"));
+            }
+            let max = 2 * threshold - 1 - remaining;
+            let low = reader.peek(bits - 1) as i32;
+            let value = if low < max {
+                reader.consume(bits - 1);
+                low
+            } else {
+                let value = reader.peek(bits) as i32;
+                reader.consume(bits);
+                if value >= threshold { value - max } else { value }
+            };
+            let count = value - 1;
+            remaining -= count.abs();
+            counts.push(count as i16);
+            if count == 0 {
+                loop {
+                    let repeat = reader.bits(2);
+                    counts.resize(counts.len() + repeat as usize, 0);
+                    if repeat != 3 {
+                        break;
+                    }
+                }
+            }
+            while remaining < threshold {
+                bits -= 1;
+                threshold >>= 1;
+            }
+        }
+        if remaining != 1 || counts.len() > max_symbol + 1 || reader.bytes_read() > reader.data.len() {
+            return Err(invalid("the zstd data has a corrupt FSE table"));
+        }
+        FseTable::new(&counts, log)
+    }
+
+    /// The table of a distribution of `1 << log` in total, with -1 for less than one.
+    fn new(counts: &[i16], log: u32) -> io::Result<Self> {
+        let size = 1 << log;
+        let corrupt = || invalid("the zstd data has a corrupt FSE table");
+        let mut symbols = vec![0u8; size];
+        // symbols of less than one take the last states, the others are spread over the rest
+        let mut high = size;
+        for (symbol, _) in counts.iter().enumerate().filter(|&(_, &count)| count == -1) {
+            high = high.checked_sub(1).ok_or_else(corrupt)?;
+            symbols[high] = symbol as u8;
+        }
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut position = 0;
+        for (symbol, &count) in counts.iter().enumerate() {
+            for _ in 0..count.max(0) {
+                symbols[position] = symbol as u8;
+                position = (position + step) & (size - 1);
+                while position >= high {
+                    position = (position + step) & (size - 1);
+                }
+            }
+        }
+        if position != 0 {
+            return Err(corrupt());
+        }
+
+        let mut next: Vec<usize> = counts.iter().map(|&count| count.max(1) as usize).collect();
+        let entries = symbols
+            .iter()
+            .map(|&symbol| {
+                let state = next[symbol as usize];
+                next[symbol as usize] += 1;
+                let bits = log - state.ilog2();
+                FseEntry {
+                    symbol,
+                    bits,
+                    baseline: (state << bits) - size,
+                }
+            })
+            .collect();
+        Ok(FseTable { log, entries })
+    }
+
+    /// The table of a single symbol, read without any bits.
+    fn rle(symbol: u8) -> Self {
+        let entry = FseEntry {
+            symbol,
+            ..Default::default()
+        };
+        FseTable {
+            log: 0,
+            entries: vec![entry],
+        }
+    }
+}
+
+/// The state of an FSE code decoded from a bitstream.
+struct FseState<'a> {
+    table: &'a FseTable,
+    state: usize,
+}
+
+impl<'a> FseState<'a> {
+    fn new(table: &'a FseTable, reader: &mut BackwardReader) -> Self {
+        let state = reader.bits(table.log) as usize;
+        FseState { table, state }
+    }
+
+    fn symbol(&self) -> u8 {
+        self.table.entries[self.state].symbol
+    }
+
+    fn update(&mut self, reader: &mut BackwardReader) {
+        let entry = self.table.entries[self.state];
+        self.state = entry.baseline + reader.bits(entry.bits) as usize;
+    }
+}
+
+/// A Huffman code of literals, decoded by looking up its longest code length of bits.
+struct HuffmanTable {
+    max_bits: u32,
+    /// Symbol and code length of each value of `max_bits` bits.
+    entries: Vec<(u8, u32)>,
+}
+
+impl HuffmanTable {
+    /// Read the description of a table, the weights of the symbols but the last.
+    fn read(data: &mut &[u8]) -> io::Result<Self> {
+        let header = take(data, 1)?[0] as usize;
+        let weights = if header < 128 {
+            fse_weights(take(data, header)?)?
+        } else {
+            let bytes = take(data, (header - 127).div_ceil(2))?;
+            (0..header - 127).map(|i| if i % 2 == 0 { bytes[i / 2] >> 4 } else { bytes[i / 2] & 0xf }).collect()
+        };
+        HuffmanTable::new(weights)
+    }
+
+    fn new(mut weights: Vec<u8>) -> io::Result<Self> {
+        let corrupt = || invalid("the zstd data has a corrupt Huffman table");
+        if weights.iter().any(|&weight| weight as u32 > MAX_HUFFMAN_BITS) {
+            return Err(corrupt());
+        }
+        // the weight of the last symbol completes the sum to a power of two
+        let sum: u32 = weights.iter().filter(|&&weight| weight > 0).map(|&weight| 1 << (weight - 1)).sum();
+        if sum == 0 {
+            return Err(corrupt());
+        }
+        let max_bits = sum.ilog2() + 1;
+        let left = (1 << max_bits) - sum;
+        if max_bits > MAX_HUFFMAN_BITS || !left.is_power_of_two() {
+            return Err(corrupt());
+        }
+        weights.push(left.ilog2() as u8 + 1);
+
+        // codes are assigned by increasing weight, then symbol
+        let mut entries = Vec::with_capacity(1 << max_bits);
+        for weight in 1..=max_bits as u8 {
+            for (symbol, _) in weights.iter().enumerate().filter(|&(_, &w)| w == weight) {
+                let len = 1 << (weight - 1);
+                entries.extend(std::iter::repeat_n((symbol as u8, max_bits + 1 - weight as u32), len));
+            }
+        }
+        Ok(HuffmanTable { max_bits, entries })
+    }
+
+    /// Decode `counts[i]` literals from each of the `streams`.
+    fn decode_streams(&self, streams: &[&[u8]], counts: &[usize]) -> io::Result<Vec<u8>> {
+        let mut literals = Vec::with_capacity(counts.iter().sum());
+        for (stream, &count) in streams.iter().zip(counts) {
+            let mut reader = BackwardReader::new(stream)?;
+            for _ in 0..count {
+                let (symbol, bits) = self.entries[reader.peek(self.max_bits) as usize];
+                reader.consume(bits);
+                literals.push(symbol);
+            }
+            if !reader.is_finished() {
+                return Err(invalid("the zstd literals do not end with their bitstream"));
+            }
+        }
+        Ok(literals)
+    }
+}
+
+/// Decode the weights of a Huffman table, compressed with two interleaved FSE states.
+fn fse_weights(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut reader = ForwardReader::new(data);
+    let table = FseTable::read(&mut reader, 6, 255)?;
+    let mut reader = BackwardReader::new(&data[reader.bytes_read()..])?;
+    let mut states = [FseState::new(&table, &mut reader), FseState::new(&table, &mut reader)];
+    let mut weights = Vec::new();
+    // the states take turns until the bitstream is exhausted, the other one giving the last weight
+    loop {
+        if weights.len() >= 255 {
+            return Err(invalid("the zstd data has a Huffman table with too many symbols"));
+        }
+        weights.push(states[0].symbol());
+        states[0].update(&mut reader);
+        if reader.is_overflowed() {
+            weights.push(states[1].symbol());
+            return Ok(weights);
+        }
+        states.swap(0, 1);
+    }
+}
+
+/// Reads the descriptions of FSE tables, whose values are packed starting at the least
+/// significant bit.
+struct ForwardReader<'a> {
+    data: &'a [u8],
+    /// Number of bits read.
+    pos: usize,
+}
+
+impl<'a> ForwardReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        ForwardReader { data, pos: 0 }
+    }
+
+    /// The next `n` bits, at most 16, with zeros after the end of the data.
+    fn peek(&self, n: u32) -> u32 {
+        let bytes = self.data.iter().skip(self.pos / 8).take(3);
+        let word = bytes.rev().fold(0, |word, &byte| word << 8 | byte as u32);
+        (word >> (self.pos % 8)) & ((1 << n) - 1)
+    }
+
+    fn consume(&mut self, n: u32) {
+        self.pos += n as usize;
+    }
+
+    fn bits(&mut self, n: u32) -> u32 {
+        let value = self.peek(n);
+        self.consume(n);
+        value
+    }
+
+    fn bytes_read(&self) -> usize {
+        self.pos.div_ceil(8)
+    }
+}
+
+/// Reads zstd bitstreams, which start at the highest bit set in their last byte and are read
+/// backwards from there, the most significant bits of each value first.
+struct BackwardReader<'a> {
+    data: &'a [u8],
+    /// Number of bits before the next value, negative once more bits were read than there are.
+    pos: i64,
+}
+
+impl<'a> BackwardReader<'a> {
+    fn new(data: &'a [u8]) -> io::Result<Self> {
+        match data.last() {
+            Some(&last) if last != 0 => Ok(BackwardReader {
+                data,
+                pos: data.len() as i64 * 8 - last.leading_zeros() as i64 - 1,
+            }),
+            _ => Err(invalid("the zstd data has a bitstream without an end mark")),
+        }
+    }
+
+    /// The next `n` bits, at most 32, with zeros before the start of the data.
+    fn peek(&self, n: u32) -> u64 {
+        let start = self.pos - n as i64;
+        let first = start.max(0) as usize / 8;
+        let bytes = self.data.iter().skip(first).take(8);
+        let word = bytes.rev().fold(0, |word, &byte| word << 8 | byte as u64);
+        match start >= 0 {
+            true => (word >> (start % 8)) & ((1 << n) - 1),
+            false if self.pos <= 0 => 0,
+            false => (word & ((1 << self.pos) - 1)) << -start,
+        }
+    }
+
+    fn consume(&mut self, n: u32) {
+        self.pos -= n as i64;
+    }
+
+    fn bits(&mut self, n: u32) -> u64 {
+        let value = self.peek(n);
+        self.consume(n);
+        value
+    }
+
+    /// Whether exactly all bits were read.
+    fn is_finished(&self) -> bool {
+        self.pos == 0
+    }
+
+    /// Whether more bits were read than there are.
+    fn is_overflowed(&self) -> bool {
+        self.pos < 0
+    }
+}
+
+/// Split off the first `len` bytes of the data.
+fn take<'a>(data: &mut &'a [u8], len: usize) -> io::Result<&'a [u8]> {
+    if data.len() < len {
+        return Err(truncated());
+    }
+    let (taken, rest) = data.split_at(len);
+    *data = rest;
+    Ok(taken)
+}
+
+/// A little-endian number of up to 8 bytes.
+fn le(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |value, &byte| value << 8 | byte as u64)
+}
+
+/// XXH64 with a seed of 0, whose low 32 bits are the checksum of a frame.
+fn xxh64(data: &[u8]) -> u64 {
+    const PRIME_1: u64 = 0x9e3779b185ebca87;
+    const PRIME_2: u64 = 0xc2b2ae3d27d4eb4f;
+    const PRIME_3: u64 = 0x165667b19e3779f9;
+    const PRIME_4: u64 = 0x85ebca77c2b2ae63;
+    const PRIME_5: u64 = 0x27d4eb2f165667c5;
+    let round = |acc: u64, lane: u64| {
+        acc.wrapping_add(lane.wrapping_mul(PRIME_2)).rotate_left(31).wrapping_mul(PRIME_1)
+    };
+    let merge = |hash: u64, acc: u64| (hash ^ round(0, acc)).wrapping_mul(PRIME_1).wrapping_add(PRIME_4);
+
+    let stripes = data.chunks_exact(32);
+    let mut rest = stripes.remainder();
+    let mut hash = if data.len() >= 32 {
+        let mut accs = [PRIME_1.wrapping_add(PRIME_2), PRIME_2, 0, PRIME_1.wrapping_neg()];
+        for stripe in stripes {
+            for (acc, bytes) in accs.iter_mut().zip(stripe.chunks_exact(8)) {
+                *acc = round(*acc, le(bytes));
+            }
+        }
+        let [a, b, c, d] = accs;
+        let hash = a.rotate_left(1).wrapping_add(b.rotate_left(7));
+        let hash = hash.wrapping_add(c.rotate_left(12)).wrapping_add(d.rotate_left(18));
+        accs.into_iter().fold(hash, merge)
+    } else {
+        PRIME_5
+    };
+    hash = hash.wrapping_add(data.len() as u64);
+
+    while rest.len() >= 8 {
+        hash = (hash ^ round(0, le(&rest[..8]))).rotate_left(27).wrapping_mul(PRIME_1).wrapping_add(PRIME_4);
+        rest = &rest[8..];
+    }
+    if rest.len() >= 4 {
+        hash = (hash ^ le(&rest[..4]).wrapping_mul(PRIME_1)).rotate_left(23);
+        hash = hash.wrapping_mul(PRIME_2).wrapping_add(PRIME_3);
+        rest = &rest[4..];
+    }
+    for &byte in rest {
+        hash = (hash ^ (byte as u64).wrapping_mul(PRIME_5)).rotate_left(11).wrapping_mul(PRIME_1);
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(PRIME_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(PRIME_3);
+    hash ^ (hash >> 32)
+}
+
+fn truncated() -> io::Error {
+    invalid("the zstd file is truncated")
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
@@ -0,0 +1,76 @@
+// JavaScript wrapper around the WebAssembly build of phonetisaurus-g2p-py.
+//
+// Build the module with
+//   cargo build --release --target wasm32-unknown-unknown --no-default-features --features wasm
+// and pass target/wasm32-unknown-unknown/release/phonetisaurus_g2p_py.wasm to `init`.
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+/**
+ * Instantiate the WebAssembly module from its bytes or a fetch `Response`.
+ */
+export async function init(source) {
+  const { instance } = source instanceof Response
+    ? await WebAssembly.instantiateStreaming(source, {})
+    : await WebAssembly.instantiate(source, {});
+  return instance.exports;
+}
+
+/**
+ * Copy bytes into the module's memory, returning the pointer of the copy.
+ */
+function copyIn(exports, bytes) {
+  const ptr = exports.phonetisaurus_wasm_alloc(bytes.length);
+  new Uint8Array(exports.memory.buffer, ptr, bytes.length).set(bytes);
+  return ptr;
+}
+
+export class PhonetisaurusModel {
+  /**
+   * Load a Phonetisaurus FST model.
+   *
+   * @param exports the exports returned by `init`
+   * @param {Uint8Array} modelBytes contents of the model file
+   */
+  constructor(exports, modelBytes) {
+    const ptr = copyIn(exports, modelBytes);
+    this.exports = exports;
+    this.model = exports.phonetisaurus_wasm_load(ptr, modelBytes.length);
+    exports.phonetisaurus_wasm_free(ptr, modelBytes.length);
+    if (this.model === 0) {
+      throw new Error("Failed to load model");
+    }
+  }
+
+  /**
+   * Phonemize a word.
+   *
+   * @param {string} word
+   * @returns {{phonemes: string, score: number}}
+   */
+  phonemizeWord(word) {
+    const bytes = encoder.encode(word);
+    const ptr = copyIn(this.exports, bytes);
+    const result = this.exports.phonetisaurus_wasm_phonemize(this.model, ptr, bytes.length);
+    this.exports.phonetisaurus_wasm_free(ptr, bytes.length);
+
+    const len = new DataView(this.exports.memory.buffer).getUint32(result, true);
+    const json = decoder.decode(new Uint8Array(this.exports.memory.buffer, result + 4, len));
+    this.exports.phonetisaurus_wasm_free(result, len + 4);
+
+    const parsed = JSON.parse(json);
+    if (parsed.error !== undefined) {
+      throw new Error(parsed.error);
+    }
+    return parsed;
+  }
+
+  /**
+   * Release the model. It must not be used afterwards.
+   */
+  free() {
+    this.exports.phonetisaurus_wasm_model_free(this.model);
+    this.model = 0;
+  }
+}